@@ -0,0 +1,201 @@
+// Copyright 2023 Felix Kahle. All rights reserved.
+
+//! Headless parsing and conversion of the TMS exports.
+//! Runs the same import as the parse_files command of the app, so it can be used in scripts and nightly jobs.
+//!
+//! Exit codes:
+//! * 0: The import succeeded
+//! * 1: The import succeeded, but diagnostics at or above the --fail-on severity were found
+//! * 2: The arguments are invalid
+//! * 3: The input files could not be parsed
+//! * 4: The import profile could not be read
+//! * 5: The output could not be written
+
+use clap::{Parser, ValueEnum};
+use dispo_core::{
+    create_job_rows,
+    export::{self, ExportError, PlannedJob},
+    DispoMode, ImportOptions, JobRow, PlanFile, Severity,
+};
+use std::{
+    fs::File,
+    io::{self, BufWriter, Write},
+    process::ExitCode,
+};
+
+const EXIT_DIAGNOSTICS: u8 = 1;
+const EXIT_INVALID_ARGUMENTS: u8 = 2;
+const EXIT_PARSE_FAILED: u8 = 3;
+const EXIT_PROFILE_FAILED: u8 = 4;
+const EXIT_OUTPUT_FAILED: u8 = 5;
+
+/// The output formats of the CLI.
+/// Plan writes a versioned plan file that can be loaded into the app.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, ValueEnum)]
+enum OutputFormat {
+    Json,
+    Plan,
+    Ndjson,
+    Csv,
+    Xlsx,
+}
+
+/// The lowest severity that makes the CLI exit with a failure
+#[derive(Debug, Copy, Clone, PartialEq, Eq, ValueEnum)]
+enum FailOn {
+    Info,
+    Warning,
+    Error,
+    Never,
+}
+
+impl FailOn {
+    /// Get the lowest severity that fails the run
+    ///
+    /// # Returns
+    /// * The severity or None if no diagnostic fails the run
+    fn severity(&self) -> Option<Severity> {
+        match self {
+            FailOn::Info => Some(Severity::Info),
+            FailOn::Warning => Some(Severity::Warning),
+            FailOn::Error => Some(Severity::Error),
+            FailOn::Never => None,
+        }
+    }
+}
+
+/// Parse the CL View and Shipper Site exports of TMS into jobs
+#[derive(Debug, Parser)]
+#[command(name = "dispo-cli", version)]
+struct Arguments {
+    /// The mode of the jobs: Delivery, Pickup, Transfer, Recovery or Return
+    #[arg(short, long)]
+    mode: String,
+
+    /// The path to the CL View .xls file
+    #[arg(long)]
+    cl_view: String,
+
+    /// The path to the Shipper Site .xls file
+    #[arg(long)]
+    shipper_site: String,
+
+    /// A JSON file with the import options, the defaults of the app are used if omitted.
+    /// Exports with other headers are mapped by its columnHeaders, e.g. {"columnHeaders": {"Load #": "Load Number"}}
+    #[arg(short, long)]
+    profile: Option<String>,
+
+    /// The output format
+    #[arg(short, long, value_enum, default_value_t = OutputFormat::Json)]
+    format: OutputFormat,
+
+    /// The output file, stdout if omitted
+    #[arg(short, long)]
+    output: Option<String>,
+
+    /// The lowest diagnostic severity that makes the run fail
+    #[arg(long, value_enum, default_value_t = FailOn::Error)]
+    fail_on: FailOn,
+}
+
+/// Read the import options of a profile file.
+/// The profile is the JSON of ImportOptions, every field can be left out.
+///
+/// # Arguments
+/// * `path` - The path to the JSON file
+///
+/// # Returns
+/// * Result containing the import options or an error message
+fn read_profile(path: &str) -> Result<ImportOptions, String> {
+    let file = File::open(path).map_err(|error| format!("Could not open profile {}: {}", path, error))?;
+    serde_json::from_reader(io::BufReader::new(file)).map_err(|error| format!("Invalid profile {}: {}", path, error))
+}
+
+/// Write the jobs in the requested format
+///
+/// # Arguments
+/// * `writer` - The writer to write to
+/// * `format` - The output format
+/// * `jobs` - The jobs to write
+///
+/// # Returns
+/// * Result containing nothing or an error
+fn write_jobs<W: Write>(mut writer: W, format: OutputFormat, jobs: &[JobRow]) -> Result<(), ExportError> {
+    match format {
+        OutputFormat::Json => export::write_json(&mut writer, jobs)?,
+        OutputFormat::Plan => PlanFile::new(jobs.iter().cloned().map(PlannedJob::from).collect()).to_writer(&mut writer)?,
+        OutputFormat::Ndjson => export::write_ndjson(&mut writer, jobs)?,
+        OutputFormat::Csv => export::write_csv(&mut writer, jobs)?,
+        OutputFormat::Xlsx => writer.write_all(&export::create_xlsx(jobs)?)?,
+    }
+    writer.flush()?;
+    Ok(())
+}
+
+/// Print the diagnostics of all jobs to stderr
+///
+/// # Arguments
+/// * `jobs` - The jobs to print the diagnostics of
+///
+/// # Returns
+/// * The highest severity found, None if there are no diagnostics
+fn report_diagnostics(jobs: &[JobRow]) -> Option<Severity> {
+    let mut highest: Option<Severity> = None;
+    for job in jobs {
+        for diagnostic in &job.diagnostics {
+            eprintln!("{}: {}", job.job_number, diagnostic);
+            highest = highest.max(Some(diagnostic.severity));
+        }
+    }
+    highest
+}
+
+fn main() -> ExitCode {
+    let arguments = Arguments::parse();
+
+    let mode = match arguments.mode.parse::<DispoMode>() {
+        Ok(mode) => mode,
+        Err(error) => {
+            eprintln!("{}", error);
+            return ExitCode::from(EXIT_INVALID_ARGUMENTS);
+        }
+    };
+
+    let options = match &arguments.profile {
+        Some(path) => match read_profile(path) {
+            Ok(options) => options,
+            Err(error) => {
+                eprintln!("{}", error);
+                return ExitCode::from(EXIT_PROFILE_FAILED);
+            }
+        },
+        None => ImportOptions::default(),
+    };
+
+    let jobs = match create_job_rows(&arguments.cl_view, &arguments.shipper_site, mode, &options) {
+        Ok(jobs) => jobs,
+        Err(error) => {
+            eprintln!("{}", error);
+            return ExitCode::from(EXIT_PARSE_FAILED);
+        }
+    };
+
+    let written = match &arguments.output {
+        Some(path) => File::create(path)
+            .map_err(ExportError::from)
+            .and_then(|file| write_jobs(BufWriter::new(file), arguments.format, &jobs)),
+        None => write_jobs(io::stdout().lock(), arguments.format, &jobs),
+    };
+    if let Err(error) = written {
+        eprintln!("{}", error);
+        return ExitCode::from(EXIT_OUTPUT_FAILED);
+    }
+
+    let highest = report_diagnostics(&jobs);
+    eprintln!("{} jobs imported", jobs.len());
+
+    match (highest, arguments.fail_on.severity()) {
+        (Some(highest), Some(limit)) if highest >= limit => ExitCode::from(EXIT_DIAGNOSTICS),
+        _ => ExitCode::SUCCESS,
+    }
+}
@@ -0,0 +1,199 @@
+// Copyright 2023 Felix Kahle. All rights reserved.
+
+//! Output formats and exit codes of the dispo-cli binary
+
+use calamine::{Reader, Xlsx};
+use dispo_core::{export::EXPORT_COLUMNS, JobRow, PlanFile};
+use std::{
+    path::PathBuf,
+    process::{Command, Output},
+};
+
+/// The loads of the fixtures
+const JOB_NUMBERS: [&str; 3] = ["L1001", "L1002", "L1003"];
+
+/// Get the path of a fixture of the dispo-core crate
+///
+/// # Arguments
+/// * `name` - The file name of the fixture
+///
+/// # Returns
+/// * The path of the fixture
+fn fixture_path(name: &str) -> PathBuf {
+    PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+        .join("..")
+        .join("dispo-core")
+        .join("tests")
+        .join("fixtures")
+        .join(name)
+}
+
+/// Run the CLI on a CL View fixture and the Shipper Site fixture
+///
+/// # Arguments
+/// * `mode` - The mode of the jobs
+/// * `cl_view` - The file name of the CL View fixture
+/// * `arguments` - The additional arguments
+///
+/// # Returns
+/// * The output of the CLI
+fn run(mode: &str, cl_view: &str, arguments: &[&str]) -> Output {
+    Command::new(env!("CARGO_BIN_EXE_dispo-cli"))
+        .arg("--mode")
+        .arg(mode)
+        .arg("--cl-view")
+        .arg(fixture_path(cl_view))
+        .arg("--shipper-site")
+        .arg(fixture_path("shipper_site.xlsx"))
+        .args(arguments)
+        .output()
+        .expect("the CLI can be started")
+}
+
+/// Run the CLI on the default fixtures and get what it wrote to stdout
+///
+/// # Arguments
+/// * `format` - The output format
+///
+/// # Returns
+/// * The output written to stdout
+fn stdout_of(format: &str) -> Vec<u8> {
+    let output = run("Delivery", "cl_view.xlsx", &["--format", format]);
+    assert_eq!(output.status.code(), Some(0), "{}", String::from_utf8_lossy(&output.stderr));
+    output.stdout
+}
+
+/// Get the job numbers of jobs
+///
+/// # Arguments
+/// * `jobs` - The jobs
+///
+/// # Returns
+/// * The job numbers
+fn job_numbers<'a>(jobs: impl IntoIterator<Item = &'a JobRow>) -> Vec<String> {
+    jobs.into_iter().map(|job| job.job_number.clone()).collect()
+}
+
+#[test]
+fn json_is_an_array_of_jobs() {
+    let jobs: Vec<JobRow> = serde_json::from_slice(&stdout_of("json")).expect("the output is JSON");
+    assert_eq!(job_numbers(&jobs), JOB_NUMBERS);
+}
+
+#[test]
+fn plan_is_a_plan_file() {
+    let plan = PlanFile::from_reader(stdout_of("plan").as_slice()).expect("the output is a plan file");
+    assert_eq!(job_numbers(plan.jobs.iter().map(|saved| &saved.job)), JOB_NUMBERS);
+}
+
+#[test]
+fn ndjson_has_one_job_per_line() {
+    let output = String::from_utf8(stdout_of("ndjson")).expect("the output is UTF-8");
+    let jobs: Vec<JobRow> = output
+        .lines()
+        .map(|line| serde_json::from_str(line).expect("every line is a job"))
+        .collect();
+    assert_eq!(job_numbers(&jobs), JOB_NUMBERS);
+}
+
+#[test]
+fn csv_has_the_export_columns() {
+    let output = stdout_of("csv");
+    let mut reader = csv::Reader::from_reader(output.as_slice());
+    let headers = reader.headers().expect("the output has a header row").clone();
+    assert_eq!(headers.iter().collect::<Vec<&str>>(), EXPORT_COLUMNS);
+
+    let job_number = headers
+        .iter()
+        .position(|header| header == "Job Number")
+        .expect("the job number is exported");
+    let rows: Vec<String> = reader
+        .records()
+        .map(|record| record.expect("the output is valid CSV")[job_number].to_string())
+        .collect();
+    assert_eq!(rows, JOB_NUMBERS);
+}
+
+#[test]
+fn xlsx_is_written_to_the_output_file() {
+    let path = std::env::temp_dir().join(format!("dispo-cli-{}-jobs.xlsx", std::process::id()));
+    let output = run(
+        "Delivery",
+        "cl_view.xlsx",
+        &["--format", "xlsx", "--output", path.to_str().expect("the path is valid UTF-8")],
+    );
+    let workbook: Result<Xlsx<_>, _> = calamine::open_workbook(&path);
+    let _ = std::fs::remove_file(&path);
+
+    assert_eq!(output.status.code(), Some(0));
+    assert!(output.stdout.is_empty());
+    let mut workbook = workbook.expect("the output is a workbook");
+    let range = workbook
+        .worksheet_range("Jobs")
+        .expect("the workbook has a Jobs sheet")
+        .expect("the sheet can be read");
+    let rows: Vec<String> = range.rows().skip(1).map(|row| row[1].to_string()).collect();
+    assert_eq!(rows, JOB_NUMBERS);
+}
+
+#[test]
+fn diagnostics_go_to_stderr() {
+    let output = run("Delivery", "cl_view.xlsx", &["--fail-on", "warning"]);
+    assert_eq!(output.status.code(), Some(1));
+
+    // stdout only holds the jobs, so it can be piped into other tools
+    let jobs: Vec<JobRow> = serde_json::from_slice(&output.stdout).expect("stdout is JSON");
+    let stderr = String::from_utf8(output.stderr).expect("stderr is UTF-8");
+    for diagnostic in jobs
+        .iter()
+        .flat_map(|job| job.diagnostics.iter().map(move |diagnostic| (job, diagnostic)))
+    {
+        let (job, diagnostic) = diagnostic;
+        assert!(stderr.contains(&format!("{}: {}", job.job_number, diagnostic)));
+    }
+    assert!(jobs.iter().any(|job| !job.diagnostics.is_empty()));
+    assert!(stderr.contains("3 jobs imported"));
+}
+
+#[test]
+fn profile_maps_renamed_columns() {
+    let path = std::env::temp_dir().join(format!("dispo-cli-{}-profile.json", std::process::id()));
+    // The fixtures have the default headers, so mapping a column to a header they do not have fails the import
+    std::fs::write(&path, r#"{"columnHeaders": {"Load #": "Load Number"}}"#).expect("the profile can be written");
+    let output = run(
+        "Delivery",
+        "cl_view.xlsx",
+        &["--profile", path.to_str().expect("the path is valid UTF-8")],
+    );
+    let _ = std::fs::remove_file(&path);
+
+    assert_eq!(output.status.code(), Some(3));
+    assert!(output.stdout.is_empty());
+}
+
+#[test]
+fn warnings_only_fail_when_asked_for() {
+    assert_eq!(run("Delivery", "cl_view.xlsx", &[]).status.code(), Some(0));
+    assert_eq!(run("Delivery", "cl_view.xlsx", &["--fail-on", "warning"]).status.code(), Some(1));
+}
+
+#[test]
+fn errors_fail_by_default() {
+    assert_eq!(run("Delivery", "cl_view_invalid_window.xlsx", &[]).status.code(), Some(1));
+    assert_eq!(
+        run("Delivery", "cl_view_invalid_window.xlsx", &["--fail-on", "never"])
+            .status
+            .code(),
+        Some(0)
+    );
+}
+
+#[test]
+fn failures_have_their_own_exit_codes() {
+    assert_eq!(run("Teleport", "cl_view.xlsx", &[]).status.code(), Some(2));
+    assert_eq!(run("Delivery", "missing.xls", &[]).status.code(), Some(3));
+    assert_eq!(
+        run("Delivery", "cl_view.xlsx", &["--profile", "missing.json"]).status.code(),
+        Some(4)
+    );
+}
@@ -0,0 +1,74 @@
+// Copyright 2023 Felix Kahle. All rights reserved.
+
+//! Benchmarks of the sheet parsing against generated CL View exports.
+//! The fixtures mimic month-end exports: every mapped column plus many unused ones.
+
+use calamine::{DataType, Range};
+use criterion::{black_box, criterion_group, criterion_main, BenchmarkId, Criterion};
+use dispo_core::{
+    file_parsing::{parse_sheet, JOB_NUMBER_COLUMN_NAME, QUANTITY_COLUMN_NAME},
+    ColumnMapping, DispoMode,
+};
+
+/// The row counts of the generated exports
+const ROW_COUNTS: [u32; 2] = [1_000, 10_000];
+
+/// The number of unused columns added to the generated exports
+const UNUSED_COLUMN_COUNTS: [u32; 2] = [20, 100];
+
+/// Generate a CL View export with all mapped columns and a number of unused columns.
+/// Unused columns alternate between text and numbers, like the real exports.
+///
+/// # Arguments
+/// * `headers` - The mapped columns
+/// * `rows` - The number of rows, without the header row
+/// * `unused_columns` - The number of unused columns
+///
+/// # Returns
+/// * The generated sheet
+fn generate_export(headers: &[&str], rows: u32, unused_columns: u32) -> Range<DataType> {
+    let mut header_names: Vec<String> = headers.iter().map(|header| header.to_string()).collect();
+    header_names.extend((0..unused_columns).map(|index| format!("Unused Column {}", index)));
+
+    let mut range = Range::new((0, 0), (rows, header_names.len() as u32 - 1));
+    for (column, header) in header_names.iter().enumerate() {
+        let column = column as u32;
+        range.set_value((0, column), DataType::String(header.clone()));
+        for row in 1..=rows {
+            let value = match header.as_str() {
+                JOB_NUMBER_COLUMN_NAME => DataType::String(format!("L{:08}", row)),
+                QUANTITY_COLUMN_NAME => DataType::Float((row % 40) as f64),
+                _ if column.is_multiple_of(2) => DataType::String(format!("Value {} {}", row, column)),
+                _ => DataType::Float(row as f64 * 0.5),
+            };
+            range.set_value((row, column), value);
+        }
+    }
+    range
+}
+
+fn bench_parse_sheet(c: &mut Criterion) {
+    let mapping = ColumnMapping::new(DispoMode::Delivery);
+    let mut columns = mapping.cl_view_columns();
+    columns.extend(mapping.optional_cl_view_columns());
+
+    let mut group = c.benchmark_group("parse_sheet");
+    group.sample_size(10);
+    for rows in ROW_COUNTS {
+        for unused_columns in UNUSED_COLUMN_COUNTS {
+            let range = generate_export(&columns, rows, unused_columns);
+            let parameter = format!("{} rows, {} columns", rows, columns.len() as u32 + unused_columns);
+
+            group.bench_with_input(BenchmarkId::new("all columns", &parameter), &range, |b, range| {
+                b.iter(|| parse_sheet(black_box(range), None).unwrap())
+            });
+            group.bench_with_input(BenchmarkId::new("mapped columns", &parameter), &range, |b, range| {
+                b.iter(|| parse_sheet(black_box(range), Some(&columns)).unwrap())
+            });
+        }
+    }
+    group.finish();
+}
+
+criterion_group!(benches, bench_parse_sheet);
+criterion_main!(benches);
@@ -0,0 +1,600 @@
+// Copyright 2023 Felix Kahle. All rights reserved.
+
+use crate::{
+    job_row::DispoMode,
+    lifecycle::{JobStatus, StatusReason},
+    workbook_cache::content_hash,
+};
+use chrono::{NaiveDateTime, Utc};
+use serde_json::Value;
+use std::{
+    collections::HashMap,
+    fmt::{self},
+    fs::{File, OpenOptions},
+    io::{BufRead, BufReader, Read, Seek, SeekFrom, Write},
+    path::{Path, PathBuf},
+    sync::{Mutex, OnceLock},
+};
+
+/// The name of the audit log in the data directory of the app
+pub const AUDIT_LOG_FILE_NAME: &str = "audit_log.jsonl";
+
+/// The header row of the CSV export of the audit log
+pub const AUDIT_LOG_COLUMNS: [&str; 9] = [
+    "Sequence",
+    "Time (UTC)",
+    "User",
+    "Action",
+    "Jobs",
+    "Job",
+    "Field",
+    "Before",
+    "After",
+];
+
+static AUDIT_LOG: OnceLock<AuditLog> = OnceLock::new();
+
+/// This error includes all errors that can occur while writing, reading or exporting the audit log
+///
+/// # Variants
+/// * `Io` - The audit log could not be read or written
+/// * `Json` - An entry could not be written as JSON
+/// * `Csv` - The export could not be written
+/// * `NotConfigured` - There is no audit log, e.g. because the data directory of the app is unknown
+#[derive(Debug)]
+pub enum AuditLogError {
+    Io(std::io::Error),
+    Json(serde_json::Error),
+    Csv(csv::Error),
+    NotConfigured,
+}
+
+impl From<std::io::Error> for AuditLogError {
+    fn from(error: std::io::Error) -> Self {
+        AuditLogError::Io(error)
+    }
+}
+
+impl From<serde_json::Error> for AuditLogError {
+    fn from(error: serde_json::Error) -> Self {
+        AuditLogError::Json(error)
+    }
+}
+
+impl From<csv::Error> for AuditLogError {
+    fn from(error: csv::Error) -> Self {
+        AuditLogError::Csv(error)
+    }
+}
+
+impl fmt::Display for AuditLogError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AuditLogError::Io(error) => write!(f, "IoError: {}", error),
+            AuditLogError::Json(error) => write!(f, "JsonError: {}", error),
+            AuditLogError::Csv(error) => write!(f, "CsvError: {}", error),
+            AuditLogError::NotConfigured => write!(f, "NotConfigured: There is no audit log"),
+        }
+    }
+}
+
+impl std::error::Error for AuditLogError {}
+
+impl serde::Serialize for AuditLogError {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        serializer.serialize_str(&self.to_string())
+    }
+}
+
+/// A file read by an import
+/// * path: The path of the file
+/// * hash: The FNV-1a hash of the content of the file as it was parsed
+#[derive(serde::Serialize, serde::Deserialize, ts_rs::TS, Debug, Clone, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct ImportedFile {
+    pub path: String,
+    pub hash: String,
+}
+
+impl ImportedFile {
+    /// Hash a file read by an import.
+    /// The contents are the bytes that were parsed, the file may have changed since.
+    ///
+    /// # Arguments
+    /// * `path` - The path of the file
+    /// * `contents` - The content of the file
+    ///
+    /// # Returns
+    /// * The ImportedFile
+    pub fn of(path: &str, contents: &[u8]) -> Self {
+        Self {
+            path: path.to_string(),
+            hash: format!("{:016x}", content_hash(contents)),
+        }
+    }
+}
+
+/// The AuditAction enum represents what a dispatcher did
+/// * Import: Jobs were imported from the exports of TMS, watched is true for imports of the watched folder
+/// * JobCreated: A job was created manually
+/// * JobEdited: A job was edited manually
+/// * JobDeleted: A job was deleted
+/// * Assignment: Drivers, vehicles, planned times or notes were changed in the app or by a change set
+/// * StatusChanged: The status of a job was changed
+/// * Export: Jobs were written to a file, kind is the name of the export like "planFile"
+/// * Print: Jobs were printed
+#[derive(serde::Serialize, serde::Deserialize, ts_rs::TS, Debug, Clone, PartialEq, Eq)]
+#[serde(tag = "type", rename_all = "camelCase", rename_all_fields = "camelCase")]
+pub enum AuditAction {
+    Import {
+        mode: DispoMode,
+        files: Vec<ImportedFile>,
+        watched: bool,
+    },
+    JobCreated,
+    JobEdited,
+    JobDeleted,
+    Assignment,
+    StatusChanged {
+        from: JobStatus,
+        to: JobStatus,
+        reason: Option<StatusReason>,
+        note: String,
+    },
+    Export {
+        kind: String,
+        path: String,
+    },
+    Print {
+        printer: String,
+    },
+}
+
+impl fmt::Display for AuditAction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AuditAction::Import { mode, files, watched } => {
+                let files = files
+                    .iter()
+                    .map(|file| format!("{} ({})", file.path, file.hash))
+                    .collect::<Vec<String>>()
+                    .join(", ");
+                match watched {
+                    true => write!(f, "Import of the watched folder, {}: {}", mode, files),
+                    false => write!(f, "Import, {}: {}", mode, files),
+                }
+            }
+            AuditAction::JobCreated => write!(f, "Job created"),
+            AuditAction::JobEdited => write!(f, "Job edited"),
+            AuditAction::JobDeleted => write!(f, "Job deleted"),
+            AuditAction::Assignment => write!(f, "Assignment"),
+            AuditAction::StatusChanged { from, to, reason, note } => {
+                write!(f, "Status changed from {} to {}", from, to)?;
+                if let Some(reason) = reason {
+                    write!(f, " ({})", reason)?;
+                }
+                if !note.is_empty() {
+                    write!(f, ": {}", note)?;
+                }
+                Ok(())
+            }
+            AuditAction::Export { kind, path } => write!(f, "Export {}: {}", kind, path),
+            AuditAction::Print { printer } => write!(f, "Print on {}", printer),
+        }
+    }
+}
+
+/// A changed field of a job
+/// * job_id: The id of the job, see JobRow::id
+/// * field: The name of the field in camelCase
+/// * before: The value before the change, empty for new jobs
+/// * after: The value after the change, empty for deleted jobs
+#[derive(serde::Serialize, serde::Deserialize, ts_rs::TS, Debug, Clone, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct FieldChange {
+    pub job_id: String,
+    pub field: String,
+    pub before: String,
+    pub after: String,
+}
+
+/// One line of the audit log
+/// * sequence: The number of the entry, starting at 1
+/// * at: When the action happened, in UTC
+/// * user: The user of the operating system the app runs as
+/// * action: What was done
+/// * job_ids: The ids of the jobs the action was about
+/// * changes: The fields the action changed
+#[derive(serde::Serialize, serde::Deserialize, ts_rs::TS, Debug, Clone, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct AuditEntry {
+    #[ts(type = "number")]
+    pub sequence: u64,
+    pub at: NaiveDateTime,
+    pub user: String,
+    pub action: AuditAction,
+    pub job_ids: Vec<String>,
+    pub changes: Vec<FieldChange>,
+}
+
+/// A line of the audit log that is not an entry, e.g. the half entry of a crash
+/// * line: The line number, starting at 1
+/// * reason: Why the line could not be read
+#[derive(serde::Serialize, serde::Deserialize, ts_rs::TS, Debug, Clone, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct InvalidLine {
+    pub line: u32,
+    pub reason: String,
+}
+
+/// The entries of the audit log that matched a query
+/// * entries: The matching entries, oldest first
+/// * invalid_lines: The lines that are not entries, they are skipped
+#[derive(serde::Serialize, serde::Deserialize, ts_rs::TS, Debug, Clone, Default, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct AuditEntries {
+    pub entries: Vec<AuditEntry>,
+    pub invalid_lines: Vec<InvalidLine>,
+}
+
+/// Which entries of the audit log are read, every filter that is None matches all entries
+/// * job_id: Only entries about this job
+/// * from: Only entries at or after this time, in UTC
+/// * to: Only entries before this time, in UTC
+#[derive(serde::Serialize, serde::Deserialize, ts_rs::TS, Debug, Clone, Default, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct AuditQuery {
+    pub job_id: Option<String>,
+    pub from: Option<NaiveDateTime>,
+    pub to: Option<NaiveDateTime>,
+}
+
+impl AuditQuery {
+    /// Check whether an entry matches the query
+    ///
+    /// # Arguments
+    /// * `entry` - The entry
+    ///
+    /// # Returns
+    /// * True if the entry matches all filters
+    pub fn matches(&self, entry: &AuditEntry) -> bool {
+        self.job_id.as_ref().is_none_or(|job_id| entry.job_ids.contains(job_id))
+            && self.from.is_none_or(|from| entry.at >= from)
+            && self.to.is_none_or(|to| entry.at < to)
+    }
+}
+
+/// Where the next entry of an audit log is appended
+/// * next_sequence: The sequence of the next entry
+/// * unterminated: Whether the file ends in a line without a newline, e.g. after a crash during an append
+#[derive(Debug)]
+struct AppendPosition {
+    next_sequence: u64,
+    unterminated: bool,
+}
+
+/// The audit log, a file with one JSON entry per line.
+/// Entries are only ever appended, the file is never rewritten.
+/// * path: The path of the file
+/// * user: The user written into new entries
+/// * position: Where the next entry is appended, the lock also keeps concurrent appends apart
+#[derive(Debug)]
+pub struct AuditLog {
+    path: PathBuf,
+    user: String,
+    position: Mutex<AppendPosition>,
+}
+
+impl AuditLog {
+    /// Open an audit log, the file is created with the first entry.
+    /// The numbering continues after the last entry that can be read, broken lines like those of a crash are skipped.
+    /// If the file ends in a broken line without a newline, the first entry starts a new line.
+    ///
+    /// # Arguments
+    /// * `path` - The path of the file
+    /// * `user` - The user written into new entries
+    ///
+    /// # Returns
+    /// * Result containing the audit log or an error if the existing file could not be read
+    pub fn open(path: PathBuf, user: &str) -> Result<Self, AuditLogError> {
+        let mut last_sequence = 0;
+        let mut unterminated = false;
+        match File::open(&path) {
+            Ok(mut file) => {
+                for line in BufReader::new(&file).lines() {
+                    if let Ok(entry) = serde_json::from_str::<AuditEntry>(&line?) {
+                        last_sequence = entry.sequence;
+                    }
+                }
+                unterminated = ends_unterminated(&mut file)?;
+            }
+            Err(error) if error.kind() == std::io::ErrorKind::NotFound => {}
+            Err(error) => return Err(error.into()),
+        }
+        Ok(Self {
+            path,
+            user: user.to_string(),
+            position: Mutex::new(AppendPosition {
+                next_sequence: last_sequence + 1,
+                unterminated,
+            }),
+        })
+    }
+
+    /// Get the path of the audit log
+    ///
+    /// # Returns
+    /// * The path of the file
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Append an entry, stamped with the current time and the user of the log
+    ///
+    /// # Arguments
+    /// * `action` - What was done
+    /// * `job_ids` - The ids of the jobs the action was about
+    /// * `changes` - The fields the action changed
+    ///
+    /// # Returns
+    /// * Result containing the appended entry or an error
+    pub fn append(&self, action: AuditAction, job_ids: Vec<String>, changes: Vec<FieldChange>) -> Result<AuditEntry, AuditLogError> {
+        let mut position = self.position.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+        let entry = AuditEntry {
+            sequence: position.next_sequence,
+            at: Utc::now().naive_utc(),
+            user: self.user.clone(),
+            action,
+            job_ids,
+            changes,
+        };
+
+        // A broken line left by a crash is ended first, so the entry is not appended to it
+        let mut line = match position.unterminated {
+            true => String::from("\n"),
+            false => String::new(),
+        };
+        line.push_str(&serde_json::to_string(&entry)?);
+        line.push('\n');
+        let mut file = OpenOptions::new().create(true).append(true).open(&self.path)?;
+        file.write_all(line.as_bytes())?;
+        file.flush()?;
+
+        position.unterminated = false;
+        position.next_sequence += 1;
+        Ok(entry)
+    }
+
+    /// Read the entries of the audit log.
+    /// Lines that are not entries are skipped like in open, the file is never rewritten, so one broken line must not hide the others.
+    ///
+    /// # Arguments
+    /// * `query` - Which entries are read
+    ///
+    /// # Returns
+    /// * Result containing the matching entries, oldest first, and the skipped lines or an error
+    pub fn entries(&self, query: &AuditQuery) -> Result<AuditEntries, AuditLogError> {
+        let file = match File::open(&self.path) {
+            Ok(file) => file,
+            Err(error) if error.kind() == std::io::ErrorKind::NotFound => return Ok(AuditEntries::default()),
+            Err(error) => return Err(error.into()),
+        };
+
+        let mut result = AuditEntries::default();
+        for (index, line) in BufReader::new(file).lines().enumerate() {
+            let line = line?;
+            if line.trim().is_empty() {
+                continue;
+            }
+            match serde_json::from_str::<AuditEntry>(&line) {
+                Ok(entry) if query.matches(&entry) => result.entries.push(entry),
+                Ok(_) => {}
+                Err(error) => result.invalid_lines.push(InvalidLine {
+                    line: index as u32 + 1,
+                    reason: error.to_string(),
+                }),
+            }
+        }
+        Ok(result)
+    }
+}
+
+/// Check whether a file ends in a line without a newline
+///
+/// # Arguments
+/// * `file` - The file
+///
+/// # Returns
+/// * Result containing true if the file is not empty and its last byte is not a newline, or an error
+fn ends_unterminated(file: &mut File) -> Result<bool, std::io::Error> {
+    if file.metadata()?.len() == 0 {
+        return Ok(false);
+    }
+    let mut last_byte = [0u8; 1];
+    file.seek(SeekFrom::End(-1))?;
+    file.read_exact(&mut last_byte)?;
+    Ok(last_byte[0] != b'\n')
+}
+
+/// Render a JSON value for a FieldChange
+///
+/// # Arguments
+/// * `value` - The value, None if the field does not exist
+///
+/// # Returns
+/// * Text as it is, nothing as an empty string and everything else as JSON
+fn render_value(value: Option<&Value>) -> String {
+    match value {
+        None | Some(Value::Null) => String::new(),
+        Some(Value::String(text)) => text.clone(),
+        Some(value) => value.to_string(),
+    }
+}
+
+/// Compare the fields of a job before and after an action
+///
+/// # Arguments
+/// * `job_id` - The id of the job
+/// * `before` - The job before the action, None if it was created
+/// * `after` - The job after the action, None if it was deleted
+///
+/// # Returns
+/// * The changed fields, in the order of the fields of the job
+pub fn diff<T: serde::Serialize>(job_id: &str, before: Option<&T>, after: Option<&T>) -> Vec<FieldChange> {
+    let fields = |job: Option<&T>| match job.map(serde_json::to_value) {
+        Some(Ok(Value::Object(fields))) => fields,
+        _ => serde_json::Map::new(),
+    };
+    let before = fields(before);
+    let after = fields(after);
+
+    let mut names: Vec<&String> = before.keys().collect();
+    names.extend(after.keys().filter(|name| !before.contains_key(*name)));
+
+    names
+        .into_iter()
+        .filter(|name| before.get(*name) != after.get(*name))
+        .map(|name| FieldChange {
+            job_id: job_id.to_string(),
+            field: name.clone(),
+            before: render_value(before.get(name)),
+            after: render_value(after.get(name)),
+        })
+        .collect()
+}
+
+/// Compare two versions of a list of jobs, matching the jobs by their id
+///
+/// # Arguments
+/// * `before` - The jobs before the action
+/// * `after` - The jobs after the action
+/// * `id` - Get the id of a job
+///
+/// # Returns
+/// * The ids of the created, changed and deleted jobs and their changed fields
+pub fn diff_jobs<T: serde::Serialize>(before: &[T], after: &[T], id: impl Fn(&T) -> String) -> (Vec<String>, Vec<FieldChange>) {
+    let before: Vec<(String, &T)> = before.iter().map(|job| (id(job), job)).collect();
+    let after: Vec<(String, &T)> = after.iter().map(|job| (id(job), job)).collect();
+    let before_by_id: HashMap<&str, &T> = before.iter().map(|(id, job)| (id.as_str(), *job)).collect();
+    let after_by_id: HashMap<&str, &T> = after.iter().map(|(id, job)| (id.as_str(), *job)).collect();
+
+    let mut job_ids = Vec::new();
+    let mut changes = Vec::new();
+    let deleted = before.iter().filter(|(id, _)| !after_by_id.contains_key(id.as_str()));
+    for (id, _) in after.iter().chain(deleted) {
+        let job_changes = diff(id, before_by_id.get(id.as_str()).copied(), after_by_id.get(id.as_str()).copied());
+        if !job_changes.is_empty() {
+            job_ids.push(id.clone());
+            changes.extend(job_changes);
+        }
+    }
+    (job_ids, changes)
+}
+
+/// Write entries of the audit log as CSV, for example for a quality investigation.
+/// Every changed field is a row of its own, entries without changes have a single row.
+/// The columns are AUDIT_LOG_COLUMNS.
+///
+/// # Arguments
+/// * `writer` - The writer to write to
+/// * `entries` - The entries to write
+///
+/// # Returns
+/// * Result containing nothing or an error
+pub fn write_csv<W: Write>(writer: W, entries: &[AuditEntry]) -> Result<(), AuditLogError> {
+    let mut csv_writer = csv::Writer::from_writer(writer);
+    csv_writer.write_record(AUDIT_LOG_COLUMNS)?;
+    for entry in entries {
+        let record = vec![
+            entry.sequence.to_string(),
+            entry.at.format("%Y-%m-%d %H:%M:%S").to_string(),
+            entry.user.clone(),
+            entry.action.to_string(),
+            entry.job_ids.join(", "),
+        ];
+        if entry.changes.is_empty() {
+            let mut row = record.clone();
+            row.extend([String::new(), String::new(), String::new(), String::new()]);
+            csv_writer.write_record(&row)?;
+        }
+        for change in &entry.changes {
+            let mut row = record.clone();
+            row.extend([
+                change.job_id.clone(),
+                change.field.clone(),
+                change.before.clone(),
+                change.after.clone(),
+            ]);
+            csv_writer.write_record(&row)?;
+        }
+    }
+    csv_writer.flush()?;
+    Ok(())
+}
+
+/// Get the name of the user the app runs as
+///
+/// # Returns
+/// * The user name of the operating system, or "unknown"
+pub fn current_user() -> String {
+    std::env::var("USERNAME")
+        .or_else(|_| std::env::var("USER"))
+        .unwrap_or_else(|_| "unknown".to_string())
+}
+
+/// Set the audit log used by record.
+/// Without calling this, for example in the CLI, nothing is recorded.
+/// Only the first call has an effect.
+///
+/// # Arguments
+/// * `path` - The path of the file
+/// * `user` - The user written into new entries
+///
+/// # Returns
+/// * Result containing nothing or an error if the existing file could not be read
+pub fn configure(path: PathBuf, user: &str) -> Result<(), AuditLogError> {
+    let _ = AUDIT_LOG.set(AuditLog::open(path, user)?);
+    Ok(())
+}
+
+/// Get the audit log used by record
+///
+/// # Returns
+/// * The audit log or None if it was not configured
+pub fn global() -> Option<&'static AuditLog> {
+    AUDIT_LOG.get()
+}
+
+/// Append an entry to the configured audit log, nothing happens if there is none
+///
+/// # Arguments
+/// * `action` - What was done
+/// * `job_ids` - The ids of the jobs the action was about
+/// * `changes` - The fields the action changed
+///
+/// # Returns
+/// * Result containing nothing or an error
+pub fn record(action: AuditAction, job_ids: Vec<String>, changes: Vec<FieldChange>) -> Result<(), AuditLogError> {
+    match global() {
+        Some(log) => log.append(action, job_ids, changes).map(|_| ()),
+        None => Ok(()),
+    }
+}
+
+/// Write the matching entries of the configured audit log as CSV to a file.
+/// Lines that are not entries are skipped, read_audit_log reports them.
+///
+/// # Arguments
+/// * `path` - The path of the .csv file
+/// * `query` - Which entries are written
+///
+/// # Returns
+/// * Result containing the number of written entries or an error
+pub fn export_csv(path: &str, query: &AuditQuery) -> Result<usize, AuditLogError> {
+    let entries = global().ok_or(AuditLogError::NotConfigured)?.entries(query)?.entries;
+    write_csv(File::create(path)?, &entries)?;
+    Ok(entries.len())
+}
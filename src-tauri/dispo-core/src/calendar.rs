@@ -0,0 +1,568 @@
+// Copyright 2023 Felix Kahle. All rights reserved.
+
+use chrono::{Datelike, Duration, NaiveDate, NaiveDateTime, NaiveTime, Weekday};
+use std::fmt::{self};
+
+/// The number of days searched in each direction when looking for the nearest valid slot
+pub const MAX_SLOT_SEARCH_DAYS: i64 = 14;
+
+/// The country code of the bundled holiday set
+pub const BUNDLED_HOLIDAY_COUNTRY: &str = "DE";
+
+/// A single public holiday
+/// * date: The date of the holiday
+/// * name: The name of the holiday
+#[derive(serde::Serialize, serde::Deserialize, ts_rs::TS, Debug, Clone, PartialEq, Eq)]
+pub struct Holiday {
+    pub date: NaiveDate,
+    pub name: String,
+}
+
+/// The public holidays of a country or of a region inside a country
+/// * country: The country code, e.g. DE
+/// * aliases: Other spellings of the country as they appear in TMS, e.g. Germany
+/// * region: The region, e.g. HE. If None, the holidays apply to the whole country.
+/// * holidays: The holidays
+#[derive(serde::Serialize, serde::Deserialize, ts_rs::TS, Debug, Clone, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct HolidaySet {
+    pub country: String,
+    #[serde(default)]
+    pub aliases: Vec<String>,
+    #[serde(default)]
+    pub region: Option<String>,
+    pub holidays: Vec<Holiday>,
+}
+
+/// Error type for the calendar subsystem
+///
+/// # Variants
+/// * Io: The holiday file could not be read
+/// * InvalidLine: A line of the holiday file could not be parsed
+#[derive(Debug)]
+pub enum CalendarError {
+    Io(std::io::Error),
+    InvalidLine((usize, String)),
+}
+
+impl From<std::io::Error> for CalendarError {
+    fn from(error: std::io::Error) -> Self {
+        CalendarError::Io(error)
+    }
+}
+
+impl fmt::Display for CalendarError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CalendarError::Io(error) => write!(f, "IoError: {}", error),
+            CalendarError::InvalidLine((line, content)) => write!(f, "Invalid holiday in line {}: {}", line, content),
+        }
+    }
+}
+
+impl serde::Serialize for CalendarError {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        serializer.serialize_str(&self.to_string())
+    }
+}
+
+impl std::error::Error for CalendarError {}
+
+impl HolidaySet {
+    /// Check whether the set applies to a country and region.
+    /// Countries and regions are compared case insensitive.
+    ///
+    /// # Arguments
+    /// * `country` - The country of the job
+    /// * `region` - The region of the job
+    ///
+    /// # Returns
+    /// * True if the set applies
+    pub fn applies_to(&self, country: &str, region: &str) -> bool {
+        let country = country.trim();
+        let country_matches =
+            self.country.eq_ignore_ascii_case(country) || self.aliases.iter().any(|alias| alias.eq_ignore_ascii_case(country));
+
+        match &self.region {
+            Some(own_region) => country_matches && own_region.eq_ignore_ascii_case(region.trim()),
+            None => country_matches,
+        }
+    }
+
+    /// Parse a holiday set from CSV text.
+    /// Every line must have the format `YYYY-MM-DD,Name`.
+    /// Empty lines and lines starting with # are ignored.
+    ///
+    /// # Arguments
+    /// * `country` - The country code the holidays belong to
+    /// * `region` - The region the holidays belong to, or None for the whole country
+    /// * `text` - The CSV text
+    ///
+    /// # Returns
+    /// * Result containing the HolidaySet or an error
+    pub fn from_csv(country: &str, region: Option<&str>, text: &str) -> Result<Self, CalendarError> {
+        let mut holidays = Vec::new();
+
+        for (index, line) in text.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            let (date, name) = line
+                .split_once(',')
+                .ok_or(CalendarError::InvalidLine((index + 1, line.to_string())))?;
+            let date = NaiveDate::parse_from_str(date.trim(), "%Y-%m-%d")
+                .map_err(|_| CalendarError::InvalidLine((index + 1, line.to_string())))?;
+
+            holidays.push(Holiday {
+                date,
+                name: name.trim().to_string(),
+            });
+        }
+
+        Ok(Self {
+            country: country.to_string(),
+            aliases: Vec::new(),
+            region: region.map(|region| region.to_string()),
+            holidays,
+        })
+    }
+
+    /// Read a holiday set from a CSV file
+    ///
+    /// # Arguments
+    /// * `path` - The path of the CSV file
+    /// * `country` - The country code the holidays belong to
+    /// * `region` - The region the holidays belong to, or None for the whole country
+    ///
+    /// # Returns
+    /// * Result containing the HolidaySet or an error
+    pub fn from_csv_file(path: &str, country: &str, region: Option<&str>) -> Result<Self, CalendarError> {
+        let text = std::fs::read_to_string(path)?;
+        Self::from_csv(country, region, &text)
+    }
+
+    /// Create the bundled set of German nationwide public holidays
+    ///
+    /// # Arguments
+    /// * `years` - The years to create the holidays for
+    ///
+    /// # Returns
+    /// * The HolidaySet
+    pub fn bundled_germany(years: std::ops::RangeInclusive<i32>) -> Self {
+        let mut holidays = Vec::new();
+
+        for year in years {
+            let easter = easter_sunday(year);
+            let fixed = [
+                (1, 1, "Neujahr"),
+                (5, 1, "Tag der Arbeit"),
+                (10, 3, "Tag der Deutschen Einheit"),
+                (12, 25, "1. Weihnachtstag"),
+                (12, 26, "2. Weihnachtstag"),
+            ];
+            let movable = [
+                (-2, "Karfreitag"),
+                (1, "Ostermontag"),
+                (39, "Christi Himmelfahrt"),
+                (50, "Pfingstmontag"),
+            ];
+
+            for (month, day, name) in fixed {
+                if let Some(date) = NaiveDate::from_ymd_opt(year, month, day) {
+                    holidays.push(Holiday {
+                        date,
+                        name: name.to_string(),
+                    });
+                }
+            }
+            for (offset, name) in movable {
+                holidays.push(Holiday {
+                    date: easter + Duration::days(offset),
+                    name: name.to_string(),
+                });
+            }
+        }
+
+        holidays.sort_by_key(|holiday| holiday.date);
+
+        Self {
+            country: BUNDLED_HOLIDAY_COUNTRY.to_string(),
+            aliases: vec!["Germany".to_string(), "Deutschland".to_string()],
+            region: None,
+            holidays,
+        }
+    }
+}
+
+/// Calculate the date of Easter Sunday in the gregorian calendar
+///
+/// # Arguments
+/// * `year` - The year
+///
+/// # Returns
+/// * The date of Easter Sunday
+fn easter_sunday(year: i32) -> NaiveDate {
+    // Anonymous gregorian algorithm (Meeus/Jones/Butcher)
+    let a = year % 19;
+    let b = year / 100;
+    let c = year % 100;
+    let d = b / 4;
+    let e = b % 4;
+    let f = (b + 8) / 25;
+    let g = (b - f + 1) / 3;
+    let h = (19 * a + b - d - g + 15) % 30;
+    let i = c / 4;
+    let k = c % 4;
+    let l = (32 + 2 * e + 2 * i - h - k) % 7;
+    let m = (a + 11 * h + 22 * l) / 451;
+    let month = (h + l - 7 * m + 114) / 31;
+    let day = ((h + l - 7 * m + 114) % 31) + 1;
+
+    NaiveDate::from_ymd_opt(year, month as u32, day as u32).unwrap_or_default()
+}
+
+/// The receiving hours of a customer on one weekday
+/// * weekday: The weekday
+/// * open: The time the customer opens
+/// * close: The time the customer closes
+#[derive(serde::Serialize, serde::Deserialize, ts_rs::TS, Debug, Clone, PartialEq, Eq)]
+pub struct OpeningDay {
+    pub weekday: Weekday,
+    pub open: NaiveTime,
+    pub close: NaiveTime,
+}
+
+/// The receiving hours of a customer.
+/// Weekdays without an entry are closed.
+/// * customer: The customer, compared case insensitive
+/// * days: The opening hours per weekday
+#[derive(serde::Serialize, serde::Deserialize, ts_rs::TS, Debug, Clone, PartialEq, Eq)]
+pub struct OpeningHours {
+    pub customer: String,
+    pub days: Vec<OpeningDay>,
+}
+
+/// The reason why a date is not inside working time
+/// * Holiday: The date is a public holiday
+/// * Weekend: The date is on a weekend
+/// * Closed: The customer does not receive at that time
+#[derive(serde::Serialize, serde::Deserialize, ts_rs::TS, Debug, Clone, PartialEq, Eq)]
+#[serde(tag = "type", content = "name", rename_all = "camelCase")]
+pub enum NonWorkingReason {
+    Holiday(String),
+    Weekend,
+    Closed,
+}
+
+impl fmt::Display for NonWorkingReason {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            NonWorkingReason::Holiday(name) => write!(f, "Holiday ({})", name),
+            NonWorkingReason::Weekend => write!(f, "Weekend"),
+            NonWorkingReason::Closed => write!(f, "Closed"),
+        }
+    }
+}
+
+/// A finding of the calendar check of a job
+/// * WindowOutsideWorkingTime: The whole target window lies outside working time
+/// * CalculatedDateOutsideWorkingTime: The calculated date lies outside working time
+#[derive(serde::Serialize, serde::Deserialize, ts_rs::TS, Debug, Clone, PartialEq, Eq)]
+#[serde(tag = "type", content = "reason", rename_all = "camelCase")]
+pub enum CalendarIssue {
+    WindowOutsideWorkingTime(NonWorkingReason),
+    CalculatedDateOutsideWorkingTime(NonWorkingReason),
+}
+
+impl fmt::Display for CalendarIssue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CalendarIssue::WindowOutsideWorkingTime(reason) => write!(f, "Window outside working time: {}", reason),
+            CalendarIssue::CalculatedDateOutsideWorkingTime(reason) => {
+                write!(f, "Calculated date outside working time: {}", reason)
+            }
+        }
+    }
+}
+
+/// The result of the calendar check of a job
+/// * issues: The issues that were found
+/// * suggested_date: The nearest date inside working time, if the calculated date is outside working time
+#[derive(serde::Serialize, serde::Deserialize, ts_rs::TS, Debug, Clone, PartialEq, Eq, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct CalendarCheck {
+    pub issues: Vec<CalendarIssue>,
+    pub suggested_date: Option<NaiveDateTime>,
+}
+
+/// The business calendar used to check the target windows of jobs
+/// * holiday_sets: The known public holidays
+/// * bundled_holidays: Whether the bundled German public holidays apply, they are created for the year of each checked day
+/// * weekend: The weekdays that are not worked
+/// * opening_hours: The receiving hours of customers. Customers without an entry receive all day.
+#[derive(serde::Serialize, serde::Deserialize, ts_rs::TS, Debug, Clone, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct BusinessCalendar {
+    #[serde(default)]
+    pub holiday_sets: Vec<HolidaySet>,
+    #[serde(default = "default_bundled_holidays")]
+    pub bundled_holidays: bool,
+    #[serde(default = "default_weekend")]
+    pub weekend: Vec<Weekday>,
+    #[serde(default)]
+    pub opening_hours: Vec<OpeningHours>,
+}
+
+/// Whether the bundled holidays apply if nothing is configured
+///
+/// # Returns
+/// * True
+fn default_bundled_holidays() -> bool {
+    true
+}
+
+/// The weekend used if none is configured
+///
+/// # Returns
+/// * Saturday and Sunday
+fn default_weekend() -> Vec<Weekday> {
+    vec![Weekday::Sat, Weekday::Sun]
+}
+
+impl Default for BusinessCalendar {
+    fn default() -> Self {
+        Self {
+            holiday_sets: Vec::new(),
+            bundled_holidays: true,
+            weekend: default_weekend(),
+            opening_hours: Vec::new(),
+        }
+    }
+}
+
+impl BusinessCalendar {
+    /// Get the reason why a day is not worked
+    ///
+    /// # Arguments
+    /// * `date` - The day
+    /// * `country` - The country of the job
+    /// * `region` - The region of the job
+    ///
+    /// # Returns
+    /// * The reason, or None if the day is worked
+    fn non_working_day(&self, date: NaiveDate, country: &str, region: &str) -> Option<NonWorkingReason> {
+        // Creating the bundled holidays of one year is cheap, so they are not kept for every year a job could fall into
+        let bundled = self
+            .bundled_holidays
+            .then(|| HolidaySet::bundled_germany(date.year()..=date.year()));
+        let holiday = self
+            .holiday_sets
+            .iter()
+            .chain(bundled.iter())
+            .filter(|set| set.applies_to(country, region))
+            .flat_map(|set| set.holidays.iter())
+            .find(|holiday| holiday.date == date);
+
+        if let Some(holiday) = holiday {
+            return Some(NonWorkingReason::Holiday(holiday.name.clone()));
+        }
+        if self.weekend.contains(&date.weekday()) {
+            return Some(NonWorkingReason::Weekend);
+        }
+        None
+    }
+
+    /// Get the working intervals of a day for a customer.
+    /// Opening hours end at the closing time, which is still worked.
+    /// A customer without opening hours is served all day, up to but not including the next midnight.
+    ///
+    /// # Arguments
+    /// * `date` - The day
+    /// * `customers` - The keys of the customer of the job, see Party::customer_keys
+    /// * `country` - The country of the job
+    /// * `region` - The region of the job
+    ///
+    /// # Returns
+    /// * The working intervals of the day, empty if the day is not worked
+    fn working_intervals(&self, date: NaiveDate, customers: &[&str], country: &str, region: &str) -> Vec<(NaiveDateTime, NaiveDateTime)> {
+        if self.non_working_day(date, country, region).is_some() {
+            return Vec::new();
+        }
+
+        match self.customer_hours(customers) {
+            Some(hours) => hours
+                .days
+                .iter()
+                .filter(|day| day.weekday == date.weekday() && day.open < day.close)
+                .map(|day| (date.and_time(day.open), date.and_time(day.close)))
+                .collect(),
+            None => {
+                let start = date.and_time(NaiveTime::MIN);
+                vec![(start, start + Duration::days(1))]
+            }
+        }
+    }
+
+    /// Get the opening hours of a customer
+    ///
+    /// # Arguments
+    /// * `customers` - The keys of the customer, the first key with opening hours wins
+    ///
+    /// # Returns
+    /// * The opening hours, or None if the customer has none configured
+    fn customer_hours(&self, customers: &[&str]) -> Option<&OpeningHours> {
+        customers.iter().find_map(|customer| {
+            self.opening_hours
+                .iter()
+                .find(|hours| hours.customer.trim().eq_ignore_ascii_case(customer.trim()))
+        })
+    }
+
+    /// Get the reason why a date is not inside working time
+    ///
+    /// # Arguments
+    /// * `date` - The date to check
+    /// * `customers` - The keys of the customer of the job, see Party::customer_keys
+    /// * `country` - The country of the job
+    /// * `region` - The region of the job
+    ///
+    /// # Returns
+    /// * The reason, or None if the date is inside working time
+    pub fn non_working_reason(&self, date: NaiveDateTime, customers: &[&str], country: &str, region: &str) -> Option<NonWorkingReason> {
+        if let Some(reason) = self.non_working_day(date.date(), country, region) {
+            return Some(reason);
+        }
+
+        let inside = self
+            .working_intervals(date.date(), customers, country, region)
+            .iter()
+            .any(|(start, end)| match end.date() == start.date() {
+                true => date >= *start && date <= *end,
+                // The next midnight belongs to the next day
+                false => date >= *start && date < *end,
+            });
+
+        if inside {
+            None
+        } else {
+            Some(NonWorkingReason::Closed)
+        }
+    }
+
+    /// Find the working time closest to a target date.
+    /// If the window contains working time, the closest date inside the window is returned.
+    /// Otherwise the closest working time within MAX_SLOT_SEARCH_DAYS days is returned.
+    ///
+    /// # Arguments
+    /// * `target` - The date to get as close as possible to
+    /// * `window` - The early and late date of the job
+    /// * `customers` - The keys of the customer of the job, see Party::customer_keys
+    /// * `country` - The country of the job
+    /// * `region` - The region of the job
+    ///
+    /// # Returns
+    /// * The closest date inside working time, or None if there is none
+    pub fn nearest_valid_slot(
+        &self,
+        target: NaiveDateTime,
+        window: (NaiveDateTime, NaiveDateTime),
+        customers: &[&str],
+        country: &str,
+        region: &str,
+    ) -> Option<NaiveDateTime> {
+        let (early, late) = if window.0 <= window.1 { window } else { (window.1, window.0) };
+        let mut best_in_window: Option<(i64, NaiveDateTime)> = None;
+        let mut best_overall: Option<(i64, NaiveDateTime)> = None;
+
+        for offset in -MAX_SLOT_SEARCH_DAYS..=MAX_SLOT_SEARCH_DAYS {
+            let date = target.date() + Duration::days(offset);
+
+            for (start, end) in self.working_intervals(date, customers, country, region) {
+                // The next midnight belongs to the next day, which may not be worked
+                let end = match end.date() == start.date() {
+                    true => end,
+                    false => end - Duration::minutes(1),
+                };
+                let candidate = target.clamp(start, end);
+                let distance = (candidate - target).num_minutes().abs();
+                if best_overall.is_none_or(|(best, _)| distance < best) {
+                    best_overall = Some((distance, candidate));
+                }
+
+                // The part of the interval that is inside the window
+                let (window_start, window_end) = (start.max(early), end.min(late));
+                if window_start <= window_end {
+                    let candidate = target.clamp(window_start, window_end);
+                    let distance = (candidate - target).num_minutes().abs();
+                    if best_in_window.is_none_or(|(best, _)| distance < best) {
+                        best_in_window = Some((distance, candidate));
+                    }
+                }
+            }
+        }
+
+        best_in_window.or(best_overall).map(|(_, date)| date)
+    }
+
+    /// Check the target window and the calculated date of a job against the calendar.
+    /// Only the first 2 * MAX_SLOT_SEARCH_DAYS + 1 days of the window are searched for working time,
+    /// a window of a missing or broken target date can span decades.
+    ///
+    /// # Arguments
+    /// * `early_date` - The early date of the job
+    /// * `late_date` - The late date of the job
+    /// * `calculated_date` - The calculated date of the job
+    /// * `customers` - The keys of the customer of the job, see Party::customer_keys
+    /// * `country` - The country of the job
+    /// * `region` - The region of the job
+    ///
+    /// # Returns
+    /// * The result of the check
+    pub fn check(
+        &self,
+        early_date: NaiveDateTime,
+        late_date: NaiveDateTime,
+        calculated_date: NaiveDateTime,
+        customers: &[&str],
+        country: &str,
+        region: &str,
+    ) -> CalendarCheck {
+        let mut check = CalendarCheck::default();
+        let (early, late) = if early_date <= late_date {
+            (early_date, late_date)
+        } else {
+            (late_date, early_date)
+        };
+
+        // The window is fine as soon as any working interval overlaps it
+        let last_date = late.date().min(early.date() + Duration::days(2 * MAX_SLOT_SEARCH_DAYS));
+        let mut window_overlaps = false;
+        let mut date = early.date();
+        while date <= last_date && !window_overlaps {
+            window_overlaps = self
+                .working_intervals(date, customers, country, region)
+                .iter()
+                .any(|(start, end)| *start <= late && *end >= early);
+            date += Duration::days(1);
+        }
+
+        if !window_overlaps {
+            let reason = self
+                .non_working_reason(early, customers, country, region)
+                .unwrap_or(NonWorkingReason::Closed);
+            check.issues.push(CalendarIssue::WindowOutsideWorkingTime(reason));
+        }
+
+        if let Some(reason) = self.non_working_reason(calculated_date, customers, country, region) {
+            check.issues.push(CalendarIssue::CalculatedDateOutsideWorkingTime(reason));
+            check.suggested_date = self.nearest_valid_slot(calculated_date, (early, late), customers, country, region);
+        }
+
+        check
+    }
+}
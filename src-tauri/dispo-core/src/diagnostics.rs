@@ -0,0 +1,172 @@
+// Copyright 2023 Felix Kahle. All rights reserved.
+
+use crate::job_row::TemperatureRange;
+use chrono::NaiveDateTime;
+use std::fmt::{self};
+
+/// The Severity enum represents how serious a diagnostic is
+/// * Info: Something worth knowing, no action needed
+/// * Warning: The job was imported, but should be checked by a dispatcher
+/// * Error: The job was imported, but parts of it are known to be wrong
+#[derive(serde::Serialize, serde::Deserialize, ts_rs::TS, Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Severity {
+    Info,
+    Warning,
+    Error,
+}
+
+impl fmt::Display for Severity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Severity::Info => write!(f, "Info"),
+            Severity::Warning => write!(f, "Warning"),
+            Severity::Error => write!(f, "Error"),
+        }
+    }
+}
+
+/// The DiagnosticKind enum represents the different problems found while importing a job
+/// * UnknownEquipmentCode: An equipment code is not in the equipment catalogue
+/// * InvalidQuantity: A quantity, weight or volume could not be parsed or is not plausible
+/// * CutOffBeforeWindow: The airline cut-off can not be met inside the target window
+/// * MultipleHawbs: The load has more than one HAWB in the Shipper Site, they were merged into one job
+/// * ConflictingTemperatureRanges: The merged HAWBs of the load need different temperature ranges
+/// * DuplicateHawb: The same HAWB is listed more than once for the load
+/// * MissingShipperSiteRow: The load has no row in the Shipper Site
+/// * MissingFlightDetails: A Transfer or Recovery has no MAWB, airline or cut-off, fields names the missing details
+/// * MissingTargetDate: A date of the target window is empty or not a date, field names the column
+/// * InvertedWindow: The early date of the target window is after the late date
+#[derive(serde::Serialize, serde::Deserialize, ts_rs::TS, Debug, Clone, PartialEq, Eq)]
+#[serde(tag = "type", rename_all = "camelCase", rename_all_fields = "camelCase")]
+pub enum DiagnosticKind {
+    UnknownEquipmentCode {
+        code: String,
+    },
+    InvalidQuantity {
+        field: String,
+        value: String,
+        reason: String,
+    },
+    CutOffBeforeWindow {
+        cut_off: NaiveDateTime,
+    },
+    MultipleHawbs {
+        hawbs: Vec<String>,
+    },
+    ConflictingTemperatureRanges {
+        ranges: Vec<TemperatureRange>,
+    },
+    DuplicateHawb {
+        hawb: String,
+    },
+    MissingShipperSiteRow,
+    MissingFlightDetails {
+        fields: Vec<String>,
+    },
+    MissingTargetDate {
+        field: String,
+    },
+    InvertedWindow {
+        early_date: NaiveDateTime,
+        late_date: NaiveDateTime,
+    },
+}
+
+impl fmt::Display for DiagnosticKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DiagnosticKind::UnknownEquipmentCode { code } => write!(f, "Unknown equipment code: {}", code),
+            DiagnosticKind::InvalidQuantity { field, value, reason } => {
+                write!(f, "Invalid {} '{}': {}", field, value, reason)
+            }
+            DiagnosticKind::CutOffBeforeWindow { cut_off } => {
+                write!(f, "The cut-off {} can not be met inside the target window", cut_off)
+            }
+            DiagnosticKind::MultipleHawbs { hawbs } => {
+                write!(
+                    f,
+                    "The load has {} HAWBs, they were merged into one job: {}",
+                    hawbs.len(),
+                    hawbs.join(", ")
+                )
+            }
+            DiagnosticKind::ConflictingTemperatureRanges { ranges } => {
+                let ranges = ranges.iter().map(|range| range.to_string()).collect::<Vec<String>>();
+                write!(f, "The HAWBs of the load need different temperature ranges: {}", ranges.join(", "))
+            }
+            DiagnosticKind::DuplicateHawb { hawb } => write!(f, "The HAWB {} is listed more than once", hawb),
+            DiagnosticKind::MissingShipperSiteRow => write!(f, "The load has no row in the Shipper Site"),
+            DiagnosticKind::MissingFlightDetails { fields } => {
+                write!(f, "The flight details are incomplete: {} missing", fields.join(", "))
+            }
+            DiagnosticKind::MissingTargetDate { field } => write!(f, "The {} is missing or not a date", field),
+            DiagnosticKind::InvertedWindow { early_date, late_date } => {
+                write!(f, "The early date {} is after the late date {}", early_date, late_date)
+            }
+        }
+    }
+}
+
+/// A problem found while importing a job.
+/// Diagnostics never stop an import, they are attached to the job they belong to.
+/// * severity: How serious the problem is
+/// * kind: What the problem is
+#[derive(serde::Serialize, serde::Deserialize, ts_rs::TS, Debug, Clone, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct Diagnostic {
+    pub severity: Severity,
+    pub kind: DiagnosticKind,
+}
+
+impl Diagnostic {
+    /// Create a new Diagnostic
+    ///
+    /// # Arguments
+    /// * `severity` - How serious the problem is
+    /// * `kind` - What the problem is
+    ///
+    /// # Returns
+    /// * A new Diagnostic
+    pub fn new(severity: Severity, kind: DiagnosticKind) -> Self {
+        Self { severity, kind }
+    }
+
+    /// Create a new info
+    ///
+    /// # Arguments
+    /// * `kind` - What the problem is
+    ///
+    /// # Returns
+    /// * A new Diagnostic with the Info severity
+    pub fn info(kind: DiagnosticKind) -> Self {
+        Self::new(Severity::Info, kind)
+    }
+
+    /// Create a new warning
+    ///
+    /// # Arguments
+    /// * `kind` - What the problem is
+    ///
+    /// # Returns
+    /// * A new Diagnostic with the Warning severity
+    pub fn warning(kind: DiagnosticKind) -> Self {
+        Self::new(Severity::Warning, kind)
+    }
+
+    /// Create a new error
+    ///
+    /// # Arguments
+    /// * `kind` - What the problem is
+    ///
+    /// # Returns
+    /// * A new Diagnostic with the Error severity
+    pub fn error(kind: DiagnosticKind) -> Self {
+        Self::new(Severity::Error, kind)
+    }
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {}", self.severity, self.kind)
+    }
+}
@@ -0,0 +1,523 @@
+// Copyright 2023 Felix Kahle. All rights reserved.
+
+use crate::{
+    equipment::{Equipment, EquipmentKind},
+    export::PlannedJob,
+    job_row::{JobRow, TemperatureRange},
+};
+use chrono::{Duration, NaiveDateTime};
+use std::fmt::{self};
+
+// Constants used for the UN1845 dangerous goods declaration
+pub const DRY_ICE_UN_NUMBER: &str = "UN1845";
+pub const DRY_ICE_PROPER_SHIPPING_NAME: &str = "Dry ice";
+pub const DRY_ICE_HAZARD_CLASS: &str = "9";
+pub const DRY_ICE_PACKING_INSTRUCTION: &str = "954";
+
+/// The PackagingType enum represents the different insulated packagings used for dry ice shipments
+/// * EpsBox: Expanded polystyrene box
+/// * PurBox: Polyurethane box
+/// * VacuumInsulated: Vacuum insulated panel box
+#[derive(serde::Serialize, serde::Deserialize, ts_rs::TS, Debug, Copy, Clone, PartialEq, Eq)]
+pub enum PackagingType {
+    EpsBox,
+    PurBox,
+    VacuumInsulated,
+}
+
+impl fmt::Display for PackagingType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PackagingType::EpsBox => write!(f, "EPS Box"),
+            PackagingType::PurBox => write!(f, "PUR Box"),
+            PackagingType::VacuumInsulated => write!(f, "Vacuum Insulated"),
+        }
+    }
+}
+
+impl PackagingType {
+    /// Get the packaging type of a piece of equipment.
+    /// A dry shipper is a vacuum insulated dewar, packaging systems are recognised by their code or description.
+    ///
+    /// # Arguments
+    /// * `item` - The equipment of a job
+    ///
+    /// # Returns
+    /// * The packaging type or None if the equipment is no known dry ice packaging
+    pub fn from_equipment(item: &Equipment) -> Option<PackagingType> {
+        match item.kind {
+            EquipmentKind::DryShipper => Some(PackagingType::VacuumInsulated),
+            EquipmentKind::PackagingSystem => {
+                let name = format!("{} {}", item.code, item.description).to_uppercase();
+                if name.contains("VIP") || name.contains("VACUUM") {
+                    Some(PackagingType::VacuumInsulated)
+                } else if name.contains("PUR") {
+                    Some(PackagingType::PurBox)
+                } else if name.contains("EPS") {
+                    Some(PackagingType::EpsBox)
+                } else {
+                    None
+                }
+            }
+            _ => None,
+        }
+    }
+}
+
+/// Get the dry ice packaging of a job from its equipment
+///
+/// # Arguments
+/// * `job` - The job
+///
+/// # Returns
+/// * The packaging type and the number of packages, None if the job lists no known packaging
+pub fn job_packaging(job: &JobRow) -> Option<(PackagingType, u32)> {
+    job.equipment_items
+        .iter()
+        .find_map(|item| PackagingType::from_equipment(item).map(|packaging| (packaging, item.quantity)))
+}
+
+/// Describes how a packaging type behaves when filled with dry ice
+/// * packaging: The packaging type this profile belongs to
+/// * sublimation_kg_per_day: The amount of dry ice in kilograms one package loses per 24 hours
+/// * capacity_kg: The maximum amount of dry ice in kilograms one package can hold
+#[derive(serde::Serialize, serde::Deserialize, ts_rs::TS, Debug, Copy, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct PackagingProfile {
+    pub packaging: PackagingType,
+    pub sublimation_kg_per_day: f64,
+    pub capacity_kg: f64,
+}
+
+/// Describes when and how dry ice is replenished during transit
+/// * interval_hours: The number of hours after which a package is refilled. A value of 0 disables replenishment.
+/// * safety_margin: The additional fraction of dry ice added on top of the calculated amount, e.g. 0.2 for 20%
+/// * handling_buffer_hours: Hours added to the transit duration to cover packing and handover
+#[derive(serde::Serialize, serde::Deserialize, ts_rs::TS, Debug, Copy, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct ReplenishmentRule {
+    #[ts(type = "number")]
+    pub interval_hours: i64,
+    pub safety_margin: f64,
+    #[ts(type = "number")]
+    pub handling_buffer_hours: i64,
+}
+
+/// The configuration used to estimate dry ice requirements
+/// * profiles: The packaging profiles that are known
+/// * default_packaging: The packaging used if none is specified for a job
+/// * replenishment: The replenishment rule to apply
+/// * vehicle_limit_kg: The maximum net dry ice in kilograms allowed on one vehicle
+/// * rounding_step_kg: Every estimate is rounded up to a multiple of this value
+#[derive(serde::Serialize, serde::Deserialize, ts_rs::TS, Debug, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct DryIceConfig {
+    pub profiles: Vec<PackagingProfile>,
+    pub default_packaging: PackagingType,
+    pub replenishment: ReplenishmentRule,
+    pub vehicle_limit_kg: f64,
+    pub rounding_step_kg: f64,
+}
+
+impl Default for DryIceConfig {
+    fn default() -> Self {
+        Self {
+            profiles: vec![
+                PackagingProfile {
+                    packaging: PackagingType::EpsBox,
+                    sublimation_kg_per_day: 5.0,
+                    capacity_kg: 30.0,
+                },
+                PackagingProfile {
+                    packaging: PackagingType::PurBox,
+                    sublimation_kg_per_day: 3.0,
+                    capacity_kg: 40.0,
+                },
+                PackagingProfile {
+                    packaging: PackagingType::VacuumInsulated,
+                    sublimation_kg_per_day: 1.5,
+                    capacity_kg: 25.0,
+                },
+            ],
+            default_packaging: PackagingType::EpsBox,
+            replenishment: ReplenishmentRule {
+                interval_hours: 72,
+                safety_margin: 0.2,
+                handling_buffer_hours: 4,
+            },
+            vehicle_limit_kg: 200.0,
+            rounding_step_kg: 0.5,
+        }
+    }
+}
+
+impl DryIceConfig {
+    /// Get the profile of a packaging type
+    ///
+    /// # Arguments
+    /// * `packaging` - The packaging type to get the profile for
+    ///
+    /// # Returns
+    /// * Result containing the profile or an error if the packaging is not configured
+    pub fn profile(&self, packaging: PackagingType) -> Result<&PackagingProfile, DryIceError> {
+        self.profiles
+            .iter()
+            .find(|profile| profile.packaging == packaging)
+            .ok_or(DryIceError::UnknownPackaging(packaging))
+    }
+}
+
+/// Error type for the dry ice calculation
+///
+/// # Variants
+/// * NotDryIce: The job does not contain a dry ice temperature range
+/// * InvalidPieceCount: The number of packages of the job is unknown or zero
+/// * UnknownPackaging: The packaging type has no profile in the configuration
+/// * ExceedsPackagingCapacity: The dry ice needed until the next replenishment does not fit into the packaging
+#[derive(Debug, Clone, PartialEq)]
+pub enum DryIceError {
+    NotDryIce(String),
+    InvalidPieceCount(String),
+    UnknownPackaging(PackagingType),
+    ExceedsPackagingCapacity((String, f64, f64)),
+}
+
+impl fmt::Display for DryIceError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DryIceError::NotDryIce(job_number) => write!(f, "Job {} is not a dry ice shipment", job_number),
+            DryIceError::InvalidPieceCount(job_number) => write!(f, "Job {} has no known number of pieces", job_number),
+            DryIceError::UnknownPackaging(packaging) => write!(f, "No packaging profile configured for {}", packaging),
+            DryIceError::ExceedsPackagingCapacity((job_number, needed, capacity)) => write!(
+                f,
+                "Job {} needs {} kg dry ice per package, but the packaging only holds {} kg",
+                job_number, needed, capacity
+            ),
+        }
+    }
+}
+
+impl std::error::Error for DryIceError {}
+
+impl serde::Serialize for DryIceError {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        serializer.serialize_str(&self.to_string())
+    }
+}
+
+/// The estimated dry ice requirement of a single shipment
+/// * job_number: The job number of the shipment
+/// * hawb_number: The hawb number of the shipment
+/// * packaging: The packaging used
+/// * pieces: The number of packages
+/// * transit_hours: The number of hours the shipment has to be kept frozen
+/// * replenishments: The number of times the packages are refilled during transit
+/// * initial_kg_per_piece: The dry ice in kilograms filled into each package before loading
+/// * net_kg: The net dry ice in kilograms loaded on the vehicle for this shipment
+/// * total_kg: The dry ice in kilograms needed over the whole transit, including replenishments
+#[derive(serde::Serialize, ts_rs::TS, Debug, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct DryIceEstimate {
+    pub job_number: String,
+    pub hawb_number: String,
+    pub packaging: PackagingType,
+    pub pieces: u32,
+    #[ts(type = "number")]
+    pub transit_hours: i64,
+    #[ts(type = "number")]
+    pub replenishments: i64,
+    pub initial_kg_per_piece: f64,
+    pub net_kg: f64,
+    pub total_kg: f64,
+}
+
+/// Calculate the number of hours a shipment has to be kept frozen.
+/// The shipment has to last from the earliest of the early date and the planned date
+/// until the latest of the late date and the planned date, plus the handling buffer.
+///
+/// # Arguments
+/// * `early_date` - The early date of the dispo operation
+/// * `late_date` - The late date of the dispo operation
+/// * `calculated_date` - The planned date of the dispo operation
+/// * `handling_buffer_hours` - The hours added for packing and handover
+///
+/// # Returns
+/// * The transit duration in whole hours, rounded up
+pub fn transit_hours(
+    early_date: NaiveDateTime,
+    late_date: NaiveDateTime,
+    calculated_date: NaiveDateTime,
+    handling_buffer_hours: i64,
+) -> i64 {
+    let start = early_date.min(late_date).min(calculated_date);
+    let end = early_date.max(late_date).max(calculated_date);
+    let duration = (end - start) + Duration::hours(handling_buffer_hours.max(0));
+    // Round up partial hours, a shipment that is 90 minutes underway needs ice for 2 hours
+    let minutes = duration.num_minutes();
+    (minutes + 59) / 60
+}
+
+/// Round a value up to the next multiple of a step.
+/// If the step is not positive, the value is returned unchanged.
+///
+/// # Arguments
+/// * `value` - The value to round
+/// * `step` - The step to round to
+///
+/// # Returns
+/// * The rounded value
+fn round_up_to_step(value: f64, step: f64) -> f64 {
+    if step <= 0.0 {
+        return value;
+    }
+    (value / step).ceil() * step
+}
+
+/// Estimate the dry ice needed for a single job.
+/// The packages are the packaging listed in the equipment of the job, or its pieces if it lists none.
+///
+/// # Arguments
+/// * `job` - The job to estimate the dry ice for
+/// * `packaging` - The packaging used. If None, the packaging of the equipment or the default packaging of the configuration is used.
+/// * `config` - The configuration to use
+///
+/// # Returns
+/// * Result containing the estimate or an error
+pub fn estimate_dry_ice(job: &JobRow, packaging: Option<PackagingType>, config: &DryIceConfig) -> Result<DryIceEstimate, DryIceError> {
+    if !job.temperature_ranges.contains(&TemperatureRange::DryIce) {
+        return Err(DryIceError::NotDryIce(job.job_number.clone()));
+    }
+    let listed = job_packaging(job);
+    let pieces = match listed.map(|(_, packages)| packages).or(job.quantity.pieces) {
+        Some(pieces) if pieces > 0 => pieces,
+        _ => return Err(DryIceError::InvalidPieceCount(job.job_number.clone())),
+    };
+
+    let packaging = packaging
+        .or(listed.map(|(packaging, _)| packaging))
+        .unwrap_or(config.default_packaging);
+    let profile = config.profile(packaging)?;
+    let rule = &config.replenishment;
+
+    let hours = transit_hours(job.early_date, job.late_date, job.calculated_date, rule.handling_buffer_hours);
+
+    // Without replenishment the initial charge has to cover the whole transit.
+    // Otherwise it only has to last until the first refill.
+    let (hours_per_charge, replenishments) = if rule.interval_hours > 0 && hours > rule.interval_hours {
+        (rule.interval_hours, (hours - 1) / rule.interval_hours)
+    } else {
+        (hours, 0)
+    };
+
+    let kg_per_charge = profile.sublimation_kg_per_day * (hours_per_charge as f64 / 24.0) * (1.0 + rule.safety_margin);
+    let initial_kg_per_piece = round_up_to_step(kg_per_charge, config.rounding_step_kg);
+
+    if initial_kg_per_piece > profile.capacity_kg {
+        return Err(DryIceError::ExceedsPackagingCapacity((
+            job.job_number.clone(),
+            initial_kg_per_piece,
+            profile.capacity_kg,
+        )));
+    }
+
+    let net_kg = initial_kg_per_piece * pieces as f64;
+    let total_kg = net_kg * (replenishments + 1) as f64;
+
+    Ok(DryIceEstimate {
+        job_number: job.job_number.clone(),
+        hawb_number: job.hawb_number.clone(),
+        packaging,
+        pieces,
+        transit_hours: hours,
+        replenishments,
+        initial_kg_per_piece,
+        net_kg,
+        total_kg,
+    })
+}
+
+/// The net dry ice loaded on one vehicle
+/// * vehicle: The vehicle identifier
+/// * job_numbers: The job numbers of the dry ice shipments on the vehicle
+/// * net_kg: The total net dry ice in kilograms on the vehicle
+/// * limit_kg: The configured limit for one vehicle
+/// * exceeds_limit: True if the net dry ice is above the limit
+#[derive(serde::Serialize, ts_rs::TS, Debug, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct VehicleDryIceTotal {
+    pub vehicle: String,
+    pub job_numbers: Vec<String>,
+    pub net_kg: f64,
+    pub limit_kg: f64,
+    pub exceeds_limit: bool,
+}
+
+/// Sum the net dry ice per vehicle and compare it against the configured limit.
+/// The vehicles are returned in the order they first appear in the input.
+///
+/// # Arguments
+/// * `assignments` - Pairs of vehicle identifier and dry ice estimate
+/// * `config` - The configuration containing the vehicle limit
+///
+/// # Returns
+/// * A vector containing one total per vehicle
+pub fn total_per_vehicle(assignments: &[(String, DryIceEstimate)], config: &DryIceConfig) -> Vec<VehicleDryIceTotal> {
+    let mut totals: Vec<VehicleDryIceTotal> = Vec::new();
+
+    for (vehicle, estimate) in assignments {
+        let index = match totals.iter().position(|total| &total.vehicle == vehicle) {
+            Some(index) => index,
+            None => {
+                totals.push(VehicleDryIceTotal {
+                    vehicle: vehicle.clone(),
+                    job_numbers: Vec::new(),
+                    net_kg: 0.0,
+                    limit_kg: config.vehicle_limit_kg,
+                    exceeds_limit: false,
+                });
+                totals.len() - 1
+            }
+        };
+
+        let total = &mut totals[index];
+        total.job_numbers.push(estimate.job_number.clone());
+        total.net_kg += estimate.net_kg;
+        total.exceeds_limit = total.net_kg > total.limit_kg;
+    }
+
+    totals
+}
+
+/// The data printed on the dangerous goods declaration of a dry ice shipment
+/// * un_number: Always UN1845
+/// * proper_shipping_name: The proper shipping name of dry ice
+/// * hazard_class: The hazard class of dry ice
+/// * packing_instruction: The packing instruction for dry ice
+/// * job_number: The job number of the shipment
+/// * hawb_number: The hawb number of the shipment
+/// * contact_name: The name of the shipper or consignee
+/// * address: The address of the stop
+/// * postal_code: The postal code of the stop
+/// * city: The city of the stop
+/// * country: The country of the stop
+/// * packaging: The packaging used
+/// * number_of_packages: The number of packages containing dry ice
+/// * net_kg_per_package: The net dry ice in kilograms per package
+/// * total_net_kg: The net dry ice in kilograms of the whole shipment
+/// * gross_weight_kg: The gross weight of the shipment, None if unknown
+#[derive(serde::Serialize, ts_rs::TS, Debug, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct DangerousGoodsDeclaration {
+    pub un_number: String,
+    pub proper_shipping_name: String,
+    pub hazard_class: String,
+    pub packing_instruction: String,
+    pub job_number: String,
+    pub hawb_number: String,
+    pub contact_name: String,
+    pub address: String,
+    pub postal_code: String,
+    pub city: String,
+    pub country: String,
+    pub packaging: PackagingType,
+    pub number_of_packages: u32,
+    pub net_kg_per_package: f64,
+    pub total_net_kg: f64,
+    pub gross_weight_kg: Option<f64>,
+}
+
+impl fmt::Display for DangerousGoodsDeclaration {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}, {}, {}, {} x {} kg ({} kg net), PI {}",
+            self.un_number,
+            self.proper_shipping_name,
+            self.hazard_class,
+            self.number_of_packages,
+            self.net_kg_per_package,
+            self.total_net_kg,
+            self.packing_instruction
+        )
+    }
+}
+
+/// Create the dangerous goods declaration for a dry ice shipment
+///
+/// # Arguments
+/// * `job` - The job the declaration is for
+/// * `estimate` - The dry ice estimate of the job
+///
+/// # Returns
+/// * The dangerous goods declaration
+pub fn create_declaration(job: &JobRow, estimate: &DryIceEstimate) -> DangerousGoodsDeclaration {
+    DangerousGoodsDeclaration {
+        un_number: DRY_ICE_UN_NUMBER.to_string(),
+        proper_shipping_name: DRY_ICE_PROPER_SHIPPING_NAME.to_string(),
+        hazard_class: DRY_ICE_HAZARD_CLASS.to_string(),
+        packing_instruction: DRY_ICE_PACKING_INSTRUCTION.to_string(),
+        job_number: job.job_number.clone(),
+        hawb_number: job.hawb_number.clone(),
+        contact_name: job.contact_name.clone(),
+        address: job.address.clone(),
+        postal_code: job.postal_code.clone(),
+        city: job.city.clone(),
+        country: job.country.clone(),
+        packaging: estimate.packaging,
+        number_of_packages: estimate.pieces,
+        net_kg_per_package: estimate.initial_kg_per_piece,
+        total_net_kg: estimate.net_kg,
+        gross_weight_kg: job.quantity.gross_weight_kg,
+    }
+}
+
+/// The dry ice of a dispatch plan
+/// * estimates: The estimates of all dry ice shipments that could be estimated
+/// * vehicles: The net dry ice per vehicle, shipments without a vehicle are not counted
+/// * declarations: The dangerous goods declarations of the estimated shipments, ready for printing
+/// * problems: The dry ice shipments that could not be estimated
+#[derive(serde::Serialize, ts_rs::TS, Debug, Clone, Default, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct DryIcePlan {
+    pub estimates: Vec<DryIceEstimate>,
+    pub vehicles: Vec<VehicleDryIceTotal>,
+    pub declarations: Vec<DangerousGoodsDeclaration>,
+    #[ts(type = "Array<string>")]
+    pub problems: Vec<DryIceError>,
+}
+
+/// Estimate the dry ice of all dry ice shipments of a plan and total it per vehicle.
+/// Jobs without a dry ice temperature range are skipped, every job uses the packaging listed in its equipment.
+///
+/// # Arguments
+/// * `jobs` - The jobs of the dispatch plan
+/// * `config` - The configuration to use
+///
+/// # Returns
+/// * The dry ice of the plan
+pub fn plan_dry_ice(jobs: &[PlannedJob], config: &DryIceConfig) -> DryIcePlan {
+    let mut plan = DryIcePlan::default();
+    let mut assignments: Vec<(String, DryIceEstimate)> = Vec::new();
+
+    for planned in jobs
+        .iter()
+        .filter(|planned| planned.job.temperature_ranges.contains(&TemperatureRange::DryIce))
+    {
+        match estimate_dry_ice(&planned.job, None, config) {
+            Ok(estimate) => {
+                plan.declarations.push(create_declaration(&planned.job, &estimate));
+                if !planned.vehicle.trim().is_empty() {
+                    assignments.push((planned.vehicle.trim().to_string(), estimate.clone()));
+                }
+                plan.estimates.push(estimate);
+            }
+            Err(error) => plan.problems.push(error),
+        }
+    }
+
+    plan.vehicles = total_per_vehicle(&assignments, config);
+    plan
+}
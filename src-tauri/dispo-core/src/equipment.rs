@@ -0,0 +1,294 @@
+// Copyright 2023 Felix Kahle. All rights reserved.
+
+use crate::diagnostics::{Diagnostic, DiagnosticKind};
+use std::fmt::{self};
+
+/// The characters that separate codes in the Equipment Codes column
+pub const EQUIPMENT_CODE_SEPARATORS: [char; 4] = [',', ';', '|', '/'];
+
+/// The EquipmentKind enum represents the different kinds of equipment
+/// * PackagingSystem: An insulated or active packaging system
+/// * DataLogger: A temperature data logger
+/// * DryShipper: A dry shipper for cryogenic shipments
+/// * TailLift: A tail-lift is needed to load or unload the shipment
+/// * Other: Any other equipment
+#[derive(serde::Serialize, serde::Deserialize, ts_rs::TS, Debug, Copy, Clone, PartialEq, Eq)]
+pub enum EquipmentKind {
+    PackagingSystem,
+    DataLogger,
+    DryShipper,
+    TailLift,
+    Other,
+}
+
+impl fmt::Display for EquipmentKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            EquipmentKind::PackagingSystem => write!(f, "Packaging System"),
+            EquipmentKind::DataLogger => write!(f, "Data Logger"),
+            EquipmentKind::DryShipper => write!(f, "Dry Shipper"),
+            EquipmentKind::TailLift => write!(f, "Tail Lift"),
+            EquipmentKind::Other => write!(f, "Other"),
+        }
+    }
+}
+
+/// The VehicleCapability enum represents what a vehicle must offer to carry a piece of equipment
+/// * TailLift: The vehicle has a tail-lift
+/// * TemperatureControlled: The vehicle has a temperature controlled cargo area
+/// * DangerousGoods: The vehicle and driver may carry dangerous goods
+/// * PowerSupply: The vehicle can power active packaging systems
+#[derive(serde::Serialize, serde::Deserialize, ts_rs::TS, Debug, Copy, Clone, PartialEq, Eq)]
+pub enum VehicleCapability {
+    TailLift,
+    TemperatureControlled,
+    DangerousGoods,
+    PowerSupply,
+}
+
+impl fmt::Display for VehicleCapability {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            VehicleCapability::TailLift => write!(f, "Tail Lift"),
+            VehicleCapability::TemperatureControlled => write!(f, "Temperature Controlled"),
+            VehicleCapability::DangerousGoods => write!(f, "Dangerous Goods"),
+            VehicleCapability::PowerSupply => write!(f, "Power Supply"),
+        }
+    }
+}
+
+/// An entry of the equipment catalogue
+/// * code: The code as it appears in the Equipment Codes column, compared case insensitive
+/// * kind: The kind of equipment
+/// * description: A human readable description
+/// * capabilities: The capabilities a vehicle needs to carry the equipment
+/// * prep_minutes: The minutes needed to prepare one unit before the job
+/// * volume_liters: The volume of one unit in liters
+#[derive(serde::Serialize, serde::Deserialize, ts_rs::TS, Debug, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct EquipmentDefinition {
+    pub code: String,
+    pub kind: EquipmentKind,
+    pub description: String,
+    #[serde(default)]
+    pub capabilities: Vec<VehicleCapability>,
+    #[serde(default)]
+    #[ts(type = "number")]
+    pub prep_minutes: i64,
+    #[serde(default)]
+    pub volume_liters: f64,
+}
+
+impl EquipmentDefinition {
+    /// Create a new EquipmentDefinition
+    ///
+    /// # Arguments
+    /// * `code` - The code as it appears in the Equipment Codes column
+    /// * `kind` - The kind of equipment
+    /// * `description` - A human readable description
+    /// * `capabilities` - The capabilities a vehicle needs to carry the equipment
+    /// * `prep_minutes` - The minutes needed to prepare one unit
+    /// * `volume_liters` - The volume of one unit in liters
+    ///
+    /// # Returns
+    /// * A new EquipmentDefinition
+    pub fn new(
+        code: &str,
+        kind: EquipmentKind,
+        description: &str,
+        capabilities: Vec<VehicleCapability>,
+        prep_minutes: i64,
+        volume_liters: f64,
+    ) -> Self {
+        Self {
+            code: code.to_string(),
+            kind,
+            description: description.to_string(),
+            capabilities,
+            prep_minutes,
+            volume_liters,
+        }
+    }
+}
+
+/// The catalogue of all known equipment codes
+/// * definitions: The known equipment
+#[derive(serde::Serialize, serde::Deserialize, ts_rs::TS, Debug, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct EquipmentCatalogue {
+    pub definitions: Vec<EquipmentDefinition>,
+}
+
+impl Default for EquipmentCatalogue {
+    fn default() -> Self {
+        Self {
+            definitions: vec![
+                EquipmentDefinition::new("LOGGER", EquipmentKind::DataLogger, "Temperature data logger", vec![], 10, 0.5),
+                EquipmentDefinition::new(
+                    "DRYSHIPPER",
+                    EquipmentKind::DryShipper,
+                    "Liquid nitrogen dry shipper",
+                    vec![VehicleCapability::DangerousGoods],
+                    60,
+                    60.0,
+                ),
+                EquipmentDefinition::new(
+                    "TAILLIFT",
+                    EquipmentKind::TailLift,
+                    "Tail-lift required",
+                    vec![VehicleCapability::TailLift],
+                    0,
+                    0.0,
+                ),
+                EquipmentDefinition::new("EPS", EquipmentKind::PackagingSystem, "EPS box", vec![], 15, 40.0),
+                EquipmentDefinition::new(
+                    "ACTIVE",
+                    EquipmentKind::PackagingSystem,
+                    "Active temperature controlled container",
+                    vec![VehicleCapability::PowerSupply, VehicleCapability::TailLift],
+                    120,
+                    1000.0,
+                ),
+            ],
+        }
+    }
+}
+
+impl EquipmentCatalogue {
+    /// Find the definition of a code
+    ///
+    /// # Arguments
+    /// * `code` - The code to look up, compared case insensitive
+    ///
+    /// # Returns
+    /// * The definition or None if the code is unknown
+    pub fn find(&self, code: &str) -> Option<&EquipmentDefinition> {
+        self.definitions
+            .iter()
+            .find(|definition| definition.code.eq_ignore_ascii_case(code))
+    }
+}
+
+/// A recognised piece of equipment of a job
+/// * code: The code as defined in the catalogue
+/// * kind: The kind of equipment
+/// * description: A human readable description
+/// * quantity: The number of units
+/// * capabilities: The capabilities a vehicle needs to carry the equipment
+/// * prep_minutes: The minutes needed to prepare all units
+/// * volume_liters: The volume of all units in liters
+#[derive(serde::Serialize, serde::Deserialize, ts_rs::TS, Debug, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct Equipment {
+    pub code: String,
+    pub kind: EquipmentKind,
+    pub description: String,
+    pub quantity: u32,
+    pub capabilities: Vec<VehicleCapability>,
+    #[ts(type = "number")]
+    pub prep_minutes: i64,
+    pub volume_liters: f64,
+}
+
+impl fmt::Display for Equipment {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}x {} ({})", self.quantity, self.code, self.kind)
+    }
+}
+
+/// Split a single entry into its quantity and its code.
+/// Entries can have a quantity prefix like "2x LOGGER" or "2 x LOGGER", or a quantity suffix like "EPS x2".
+/// Only an x between a number and a code counts, so codes containing an x and dimensions like "2x4" are kept whole.
+/// Entries without a quantity have the quantity 1.
+///
+/// # Arguments
+/// * `entry` - The trimmed entry
+///
+/// # Returns
+/// * The quantity and the code
+fn split_quantity(entry: &str) -> (u32, &str) {
+    let is_x = |c: char| c == 'x' || c == 'X';
+    let parse = |quantity: &str| quantity.trim().parse::<u32>().ok();
+
+    // A prefix ends at the first x, the code must not continue with a number like in "2x4 BOX"
+    if let Some((quantity, code)) = entry.split_once(is_x) {
+        let code = code.trim();
+        if let Some(quantity) = parse(quantity) {
+            if !code.is_empty() && !code.starts_with(|c: char| c.is_ascii_digit()) {
+                return (quantity, code);
+            }
+        }
+    }
+    // A suffix starts at the last x, which must start a word of its own like in "EPS x2"
+    if let Some((code, quantity)) = entry.rsplit_once(is_x) {
+        if let Some(quantity) = parse(quantity) {
+            if code.ends_with(char::is_whitespace) && !code.trim().is_empty() {
+                return (quantity, code.trim());
+            }
+        }
+    }
+    (1, entry)
+}
+
+/// Parse the content of the Equipment Codes column.
+/// Codes are separated by EQUIPMENT_CODE_SEPARATORS and looked up in the catalogue.
+/// Unknown codes are reported as diagnostics, the same code is only reported once.
+/// Entries with a quantity of zero are reported as invalid quantities and skipped.
+/// Quantities of the same code listed more than once saturate instead of overflowing.
+///
+/// # Arguments
+/// * `input` - The content of the Equipment Codes cell
+/// * `catalogue` - The equipment catalogue
+///
+/// # Returns
+/// * The recognised equipment and the diagnostics for unknown codes
+pub fn parse_equipment_codes(input: &str, catalogue: &EquipmentCatalogue) -> (Vec<Equipment>, Vec<Diagnostic>) {
+    let mut equipment: Vec<Equipment> = Vec::new();
+    let mut diagnostics: Vec<Diagnostic> = Vec::new();
+
+    for entry in input.split(&EQUIPMENT_CODE_SEPARATORS[..]).map(|entry| entry.trim()) {
+        if entry.is_empty() {
+            continue;
+        }
+
+        let (quantity, code) = split_quantity(entry);
+        if quantity == 0 {
+            diagnostics.push(Diagnostic::warning(DiagnosticKind::InvalidQuantity {
+                field: "equipment".to_string(),
+                value: entry.to_string(),
+                reason: "zero".to_string(),
+            }));
+            continue;
+        }
+
+        match catalogue.find(code) {
+            Some(definition) => match equipment.iter_mut().find(|item| item.code == definition.code) {
+                // The same code listed twice is merged into one entry
+                Some(existing) => {
+                    existing.quantity = existing.quantity.saturating_add(quantity);
+                    existing.prep_minutes = existing
+                        .prep_minutes
+                        .saturating_add(definition.prep_minutes.saturating_mul(quantity as i64));
+                    existing.volume_liters += definition.volume_liters * quantity as f64;
+                }
+                None => equipment.push(Equipment {
+                    code: definition.code.clone(),
+                    kind: definition.kind,
+                    description: definition.description.clone(),
+                    quantity,
+                    capabilities: definition.capabilities.clone(),
+                    prep_minutes: definition.prep_minutes.saturating_mul(quantity as i64),
+                    volume_liters: definition.volume_liters * quantity as f64,
+                }),
+            },
+            None => {
+                let diagnostic = Diagnostic::warning(DiagnosticKind::UnknownEquipmentCode { code: code.to_string() });
+                if !diagnostics.contains(&diagnostic) {
+                    diagnostics.push(diagnostic);
+                }
+            }
+        }
+    }
+
+    (equipment, diagnostics)
+}
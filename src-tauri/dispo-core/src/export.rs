@@ -0,0 +1,528 @@
+// Copyright 2023 Felix Kahle. All rights reserved.
+
+use crate::{
+    job_row::{JobRow, TemperatureRange},
+    lifecycle::JobStatus,
+};
+use chrono::NaiveDateTime;
+use rust_xlsxwriter::{
+    Color, ConditionalFormatFormula, ConditionalFormatText, ConditionalFormatTextRule, Format, FormatBorder, Workbook, Worksheet,
+};
+use std::fmt::{self};
+use std::io::Write;
+
+/// The format of the NaiveDateTime columns of an export
+pub const EXPORT_DATE_FORMAT: &str = "%d.%m.%Y %H:%M";
+
+/// The header row of tabular exports, one entry per column of job_row_record
+pub const EXPORT_COLUMNS: [&str; 26] = [
+    "Mode",
+    "Job Number",
+    "HAWB",
+    "Temperature Range",
+    "Pieces",
+    "Gross Weight (kg)",
+    "Chargeable Weight (kg)",
+    "Volume (m3)",
+    "Name",
+    "Address",
+    "Postal Code",
+    "City",
+    "Country",
+    "Shipper",
+    "Consignee",
+    "Equipment",
+    "Tolerance Before",
+    "Tolerance After",
+    "Early Date",
+    "Late Date",
+    "Calculated Date",
+    "Planned Time Strategy",
+    "MAWB",
+    "Airline",
+    "Cut-Off",
+    "Diagnostics",
+];
+
+/// The format of the date columns of the .xlsx dispatch plan
+pub const PLAN_DATE_FORMAT: &str = "dd.mm.yyyy hh:mm";
+
+/// The name of the sheet containing all jobs of the dispatch plan
+pub const PLAN_ALL_JOBS_SHEET_NAME: &str = "All Jobs";
+
+/// The name of the sheet of jobs without a driver
+pub const PLAN_UNASSIGNED_SHEET_NAME: &str = "Unassigned";
+
+/// The header row of the .xlsx dispatch plan
+pub const PLAN_COLUMNS: [&str; 16] = [
+    "Driver",
+    "Vehicle",
+    "Mode",
+    "Job Number",
+    "HAWB",
+    "Planned",
+    "Early",
+    "Late",
+    "Tolerance (min)",
+    "Temperature",
+    "Name",
+    "Address",
+    "Postal Code",
+    "City",
+    "Country",
+    "Equipment",
+];
+
+// The column indices of PLAN_COLUMNS that are referenced by formatting
+const PLAN_PLANNED_COLUMN: u16 = 5;
+const PLAN_EARLY_COLUMN: u16 = 6;
+const PLAN_LATE_COLUMN: u16 = 7;
+const PLAN_TEMPERATURE_COLUMN: u16 = 9;
+
+/// A job of the dispatch plan, as it is kept by the frontend and saved in plan files.
+/// The JSON is the JSON of the JobRow with the assignments as additional fields.
+/// The assignments default to empty, so jobs without them can be read as well.
+/// * job: The job
+/// * driver: The driver the job is assigned to, empty if unassigned
+/// * vehicle: The vehicle the job is assigned to, empty if unassigned
+/// * notes: The notes of the dispatcher
+#[derive(serde::Serialize, serde::Deserialize, ts_rs::TS, Debug, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct PlannedJob {
+    #[serde(flatten)]
+    pub job: JobRow,
+    #[serde(default)]
+    pub driver: String,
+    #[serde(default)]
+    pub vehicle: String,
+    #[serde(default)]
+    pub notes: String,
+}
+
+impl From<JobRow> for PlannedJob {
+    fn from(job: JobRow) -> Self {
+        Self {
+            job,
+            driver: String::new(),
+            vehicle: String::new(),
+            notes: String::new(),
+        }
+    }
+}
+
+impl PlannedJob {
+    /// Get the id of the job
+    ///
+    /// # Returns
+    /// * The id of the job, see JobRow::id
+    pub fn id(&self) -> String {
+        self.job.id()
+    }
+
+    /// Check whether the job is assigned to a driver and a vehicle
+    ///
+    /// # Returns
+    /// * True if neither the driver nor the vehicle is empty
+    pub fn is_assigned(&self) -> bool {
+        !self.driver.trim().is_empty() && !self.vehicle.trim().is_empty()
+    }
+
+    /// Bring the status of the job in line with its assignment.
+    /// A job that gets a driver and a vehicle becomes Assigned, through Planned if it was not planned yet.
+    /// An assigned job that loses its driver or vehicle is Planned again.
+    /// Jobs that are on the way, done or cancelled keep their status.
+    ///
+    /// # Arguments
+    /// * `at` - When the assignment was changed
+    pub fn update_status(&mut self, at: NaiveDateTime) {
+        let steps: &[JobStatus] = match (self.is_assigned(), self.job.status) {
+            (true, JobStatus::Imported | JobStatus::Exception) => &[JobStatus::Planned, JobStatus::Assigned],
+            (true, JobStatus::Planned) => &[JobStatus::Assigned],
+            (false, JobStatus::Assigned) => &[JobStatus::Planned],
+            _ => &[],
+        };
+        for status in steps {
+            // Every step is allowed and needs no reason, so this only stops on a broken status
+            if self.job.change_status(*status, None, "", at).is_err() {
+                break;
+            }
+        }
+    }
+}
+
+/// This error includes all errors that can occur while exporting jobs
+///
+/// # Variants
+/// * `Io` - The export could not be written
+/// * `Csv` - The CSV export could not be created
+/// * `Xlsx` - The XLSX export could not be created
+/// * `Json` - The JSON export could not be created
+/// * `InvalidDelimiter` - The CSV delimiter is not a single byte character
+/// * `InvalidDateFormat` - The date format is not a valid chrono format
+/// * `NoDataDirectory` - The data directory of the app, where the export state is kept, is unknown
+#[derive(Debug)]
+pub enum ExportError {
+    Io(std::io::Error),
+    Csv(csv::Error),
+    Xlsx(rust_xlsxwriter::XlsxError),
+    Json(serde_json::Error),
+    InvalidDelimiter(char),
+    InvalidDateFormat(String),
+    NoDataDirectory,
+}
+
+impl From<std::io::Error> for ExportError {
+    fn from(error: std::io::Error) -> Self {
+        ExportError::Io(error)
+    }
+}
+
+impl From<csv::Error> for ExportError {
+    fn from(error: csv::Error) -> Self {
+        ExportError::Csv(error)
+    }
+}
+
+impl From<rust_xlsxwriter::XlsxError> for ExportError {
+    fn from(error: rust_xlsxwriter::XlsxError) -> Self {
+        ExportError::Xlsx(error)
+    }
+}
+
+impl From<serde_json::Error> for ExportError {
+    fn from(error: serde_json::Error) -> Self {
+        ExportError::Json(error)
+    }
+}
+
+impl fmt::Display for ExportError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ExportError::Io(error) => write!(f, "IoError: {}", error),
+            ExportError::Csv(error) => write!(f, "CsvError: {}", error),
+            ExportError::Xlsx(error) => write!(f, "XlsxError: {}", error),
+            ExportError::Json(error) => write!(f, "JsonError: {}", error),
+            ExportError::InvalidDelimiter(delimiter) => write!(f, "InvalidDelimiter: {:?} is not a single byte character", delimiter),
+            ExportError::InvalidDateFormat(format) => write!(f, "InvalidDateFormat: {:?} is not a valid date format", format),
+            ExportError::NoDataDirectory => write!(f, "NoDataDirectory: The data directory of the app is unknown"),
+        }
+    }
+}
+
+impl std::error::Error for ExportError {}
+
+impl serde::Serialize for ExportError {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        serializer.serialize_str(&self.to_string())
+    }
+}
+
+/// Format an optional number for an export
+///
+/// # Arguments
+/// * `value` - The value to format
+///
+/// # Returns
+/// * The value with two decimals or an empty string
+fn format_optional_number(value: Option<f64>) -> String {
+    value.map(|value| format!("{:.2}", value)).unwrap_or_default()
+}
+
+/// Turn a job into one row of a tabular export.
+/// The order of the cells matches EXPORT_COLUMNS.
+///
+/// # Arguments
+/// * `job` - The job to turn into a row
+///
+/// # Returns
+/// * The cells of the row
+pub fn job_row_record(job: &JobRow) -> Vec<String> {
+    let join = |items: Vec<String>| items.join(", ");
+    let flight = job.flight.as_ref();
+
+    vec![
+        job.mode.to_string(),
+        job.job_number.clone(),
+        job.hawb_number.clone(),
+        join(job.temperature_ranges.iter().map(|range| range.to_string()).collect()),
+        job.quantity.pieces.map(|pieces| pieces.to_string()).unwrap_or_default(),
+        format_optional_number(job.quantity.gross_weight_kg),
+        format_optional_number(job.quantity.chargeable_weight_kg),
+        format_optional_number(job.quantity.volume_m3),
+        job.contact_name.clone(),
+        job.address.clone(),
+        job.postal_code.clone(),
+        job.city.clone(),
+        job.country.clone(),
+        job.shipper.name.clone(),
+        job.consignee.name.clone(),
+        join(job.equipment_items.iter().map(|item| item.to_string()).collect()),
+        job.tolerance_before.to_string(),
+        job.tolerance_after.to_string(),
+        job.early_date.format(EXPORT_DATE_FORMAT).to_string(),
+        job.late_date.format(EXPORT_DATE_FORMAT).to_string(),
+        job.calculated_date.format(EXPORT_DATE_FORMAT).to_string(),
+        job.planned_time_strategy.to_string(),
+        flight.map(|flight| flight.mawb_number.clone()).unwrap_or_default(),
+        flight.map(|flight| flight.airline.clone()).unwrap_or_default(),
+        flight
+            .and_then(|flight| flight.cut_off)
+            .map(|cut_off| cut_off.format(EXPORT_DATE_FORMAT).to_string())
+            .unwrap_or_default(),
+        join(job.diagnostics.iter().map(|diagnostic| diagnostic.to_string()).collect()),
+    ]
+}
+
+/// Write jobs as a JSON array
+///
+/// # Arguments
+/// * `writer` - The writer to write to
+/// * `jobs` - The jobs to write
+///
+/// # Returns
+/// * Result containing nothing or an error
+pub fn write_json<W: Write>(mut writer: W, jobs: &[JobRow]) -> Result<(), ExportError> {
+    serde_json::to_writer_pretty(&mut writer, jobs)?;
+    writeln!(writer)?;
+    Ok(())
+}
+
+/// Write jobs as newline delimited JSON, one job per line
+///
+/// # Arguments
+/// * `writer` - The writer to write to
+/// * `jobs` - The jobs to write
+///
+/// # Returns
+/// * Result containing nothing or an error
+pub fn write_ndjson<W: Write>(mut writer: W, jobs: &[JobRow]) -> Result<(), ExportError> {
+    for job in jobs {
+        serde_json::to_writer(&mut writer, job)?;
+        writeln!(writer)?;
+    }
+    Ok(())
+}
+
+/// Write jobs as CSV with a header row
+///
+/// # Arguments
+/// * `writer` - The writer to write to
+/// * `jobs` - The jobs to write
+///
+/// # Returns
+/// * Result containing nothing or an error
+pub fn write_csv<W: Write>(writer: W, jobs: &[JobRow]) -> Result<(), ExportError> {
+    let mut csv_writer = csv::Writer::from_writer(writer);
+    csv_writer.write_record(EXPORT_COLUMNS)?;
+    for job in jobs {
+        csv_writer.write_record(job_row_record(job))?;
+    }
+    csv_writer.flush()?;
+    Ok(())
+}
+
+/// Create an .xlsx workbook with one sheet containing all jobs
+///
+/// # Arguments
+/// * `jobs` - The jobs to write
+///
+/// # Returns
+/// * Result containing the content of the .xlsx file or an error
+pub fn create_xlsx(jobs: &[JobRow]) -> Result<Vec<u8>, ExportError> {
+    let mut workbook = rust_xlsxwriter::Workbook::new();
+    let worksheet = workbook.add_worksheet();
+    worksheet.set_name("Jobs")?;
+
+    for (column, header) in EXPORT_COLUMNS.iter().enumerate() {
+        worksheet.write_string(0, column as u16, *header)?;
+    }
+    for (row, job) in jobs.iter().enumerate() {
+        for (column, cell) in job_row_record(job).iter().enumerate() {
+            worksheet.write_string(row as u32 + 1, column as u16, cell)?;
+        }
+    }
+
+    Ok(workbook.save_to_buffer()?)
+}
+
+/// Make a driver name usable as a sheet name.
+/// Excel does not allow some characters and limits sheet names to 31 characters.
+/// Names that are already taken get a number appended.
+///
+/// # Arguments
+/// * `name` - The driver name
+/// * `taken` - The sheet names that are already used
+///
+/// # Returns
+/// * A valid and unique sheet name
+fn sheet_name(name: &str, taken: &[String]) -> String {
+    // Excel rejects names starting or ending with an apostrophe, so they are trimmed after every truncation
+    let trim = |name: &str| name.trim_matches(|c: char| c == '\'' || c.is_whitespace()).to_string();
+
+    let cleaned: String = name.chars().map(|c| if "[]:*?/\\".contains(c) { '-' } else { c }).collect();
+    let cleaned = trim(&trim(&cleaned).chars().take(31).collect::<String>());
+    let base = if cleaned.is_empty() {
+        PLAN_UNASSIGNED_SHEET_NAME.to_string()
+    } else {
+        cleaned
+    };
+
+    // Excel compares sheet names case insensitive, also beyond ASCII
+    let is_taken = |candidate: &str| taken.iter().any(|name| name.to_lowercase() == candidate.to_lowercase());
+    let mut candidate = base.clone();
+    let mut number = 2;
+    while is_taken(&candidate) {
+        let suffix = format!(" ({})", number);
+        let truncated = trim(&base.chars().take(31 - suffix.len()).collect::<String>());
+        candidate = trim(&format!("{}{}", truncated, suffix));
+        number += 1;
+    }
+    candidate
+}
+
+/// Write the jobs of the dispatch plan into a worksheet.
+/// The header row is frozen and has an autofilter.
+/// Invalid temperature ranges and windows are highlighted by conditional formats,
+/// so the highlighting stays correct if the dispatcher edits the sheet.
+///
+/// # Arguments
+/// * `worksheet` - The worksheet to write to
+/// * `jobs` - The jobs to write
+///
+/// # Returns
+/// * Result containing nothing or an error
+fn write_plan_sheet(worksheet: &mut Worksheet, jobs: &[&PlannedJob]) -> Result<(), ExportError> {
+    let header_format = Format::new()
+        .set_bold()
+        .set_background_color(Color::RGB(0xD9E1F2))
+        .set_border_bottom(FormatBorder::Thin);
+    let date_format = Format::new().set_num_format(PLAN_DATE_FORMAT);
+    let highlight_format = Format::new()
+        .set_background_color(Color::RGB(0xFFC7CE))
+        .set_font_color(Color::RGB(0x9C0006));
+
+    for (column, header) in PLAN_COLUMNS.iter().enumerate() {
+        worksheet.write_string_with_format(0, column as u16, *header, &header_format)?;
+    }
+
+    for (index, planned) in jobs.iter().enumerate() {
+        let row = index as u32 + 1;
+        let job = &planned.job;
+        let temperature = job
+            .temperature_ranges
+            .iter()
+            .map(|range| range.to_string())
+            .collect::<Vec<String>>()
+            .join(", ");
+
+        worksheet.write_string(row, 0, &planned.driver)?;
+        worksheet.write_string(row, 1, &planned.vehicle)?;
+        worksheet.write_string(row, 2, job.mode.to_string())?;
+        worksheet.write_string(row, 3, &job.job_number)?;
+        worksheet.write_string(row, 4, &job.hawb_number)?;
+        worksheet.write_datetime_with_format(row, PLAN_PLANNED_COLUMN, job.calculated_date, &date_format)?;
+        worksheet.write_datetime_with_format(row, PLAN_EARLY_COLUMN, job.early_date, &date_format)?;
+        worksheet.write_datetime_with_format(row, PLAN_LATE_COLUMN, job.late_date, &date_format)?;
+        worksheet.write_number(row, 8, job.tolerance)?;
+        worksheet.write_string(row, PLAN_TEMPERATURE_COLUMN, temperature)?;
+        worksheet.write_string(row, 10, &job.contact_name)?;
+        worksheet.write_string(row, 11, &job.address)?;
+        worksheet.write_string(row, 12, &job.postal_code)?;
+        worksheet.write_string(row, 13, &job.city)?;
+        worksheet.write_string(row, 14, &job.country)?;
+        worksheet.write_string(row, 15, &job.equipment)?;
+    }
+
+    let last_column = PLAN_COLUMNS.len() as u16 - 1;
+    let last_row = jobs.len().max(1) as u32;
+    worksheet.set_freeze_panes(1, 0)?;
+    worksheet.autofilter(0, 0, last_row, last_column)?;
+    worksheet.set_column_width(PLAN_PLANNED_COLUMN, 17)?;
+    worksheet.set_column_width(PLAN_EARLY_COLUMN, 17)?;
+    worksheet.set_column_width(PLAN_LATE_COLUMN, 17)?;
+    worksheet.set_column_width(PLAN_TEMPERATURE_COLUMN, 20)?;
+    worksheet.set_column_width(11, 30)?;
+
+    let invalid_temperature = ConditionalFormatText::new()
+        .set_rule(ConditionalFormatTextRule::Contains(TemperatureRange::Invalid.to_string()))
+        .set_format(&highlight_format);
+    worksheet.add_conditional_format(1, PLAN_TEMPERATURE_COLUMN, last_row, PLAN_TEMPERATURE_COLUMN, &invalid_temperature)?;
+
+    // The window is invalid if it ends before it starts or the planned date lies outside of it
+    let planned = rust_xlsxwriter::column_number_to_name(PLAN_PLANNED_COLUMN);
+    let early = rust_xlsxwriter::column_number_to_name(PLAN_EARLY_COLUMN);
+    let late = rust_xlsxwriter::column_number_to_name(PLAN_LATE_COLUMN);
+    let rule = format!(
+        "=OR(${late}2<${early}2,${planned}2<${early}2,${planned}2>${late}2)",
+        planned = planned,
+        early = early,
+        late = late
+    );
+    let invalid_window = ConditionalFormatFormula::new()
+        .set_rule(rule.as_str())
+        .set_format(&highlight_format);
+    worksheet.add_conditional_format(1, PLAN_PLANNED_COLUMN, last_row, PLAN_LATE_COLUMN, &invalid_window)?;
+
+    Ok(())
+}
+
+/// Create the .xlsx dispatch plan.
+/// The first sheet contains all jobs, followed by one sheet per driver and one for the unassigned jobs.
+/// Jobs are ordered by their planned date.
+///
+/// # Arguments
+/// * `jobs` - The jobs of the dispatch plan
+///
+/// # Returns
+/// * Result containing the content of the .xlsx file or an error
+pub fn create_plan_xlsx(jobs: &[PlannedJob]) -> Result<Vec<u8>, ExportError> {
+    let mut sorted: Vec<&PlannedJob> = jobs.iter().collect();
+    sorted.sort_by_key(|planned| planned.job.calculated_date);
+
+    // Drivers in the order of their first job, the unassigned jobs last
+    let mut drivers: Vec<&str> = Vec::new();
+    for job in &sorted {
+        let driver = job.driver.trim();
+        if !driver.is_empty() && !drivers.contains(&driver) {
+            drivers.push(driver);
+        }
+    }
+    if sorted.iter().any(|job| job.driver.trim().is_empty()) {
+        drivers.push("");
+    }
+
+    let mut workbook = Workbook::new();
+    let mut taken: Vec<String> = vec![PLAN_ALL_JOBS_SHEET_NAME.to_string()];
+
+    let worksheet = workbook.add_worksheet();
+    worksheet.set_name(PLAN_ALL_JOBS_SHEET_NAME)?;
+    write_plan_sheet(worksheet, &sorted)?;
+
+    for driver in drivers {
+        let name = sheet_name(driver, &taken);
+        let driver_jobs: Vec<&PlannedJob> = sorted.iter().copied().filter(|job| job.driver.trim() == driver).collect();
+
+        let worksheet = workbook.add_worksheet();
+        worksheet.set_name(&name)?;
+        write_plan_sheet(worksheet, &driver_jobs)?;
+        taken.push(name);
+    }
+
+    Ok(workbook.save_to_buffer()?)
+}
+
+/// Write the .xlsx dispatch plan to a file
+///
+/// # Arguments
+/// * `path` - The path of the .xlsx file
+/// * `jobs` - The jobs of the dispatch plan
+///
+/// # Returns
+/// * Result containing nothing or an error
+pub fn write_plan_xlsx(path: &str, jobs: &[PlannedJob]) -> Result<(), ExportError> {
+    std::fs::write(path, create_plan_xlsx(jobs)?)?;
+    Ok(())
+}
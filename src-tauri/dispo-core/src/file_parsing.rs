@@ -0,0 +1,923 @@
+// Copyright 2023 Felix Kahle. All rights reserved.
+
+use std::{
+    collections::HashMap,
+    fmt::{self, Display, Formatter},
+    io::Cursor,
+};
+
+use crate::{
+    audit_log::ImportedFile,
+    calendar::BusinessCalendar,
+    equipment::EquipmentCatalogue,
+    job_row::{DispoMode, JobRow, PartyRole},
+    parse_error::ParseFilesError,
+    parse_task::{ParseStage, ParseTask},
+    planned_time::PlannedTimeSettings,
+    quantity::DEFAULT_VOLUMETRIC_KG_PER_M3,
+    tolerance::TolerancePolicySet,
+    workbook_cache::{self, CacheKey},
+};
+use calamine::{DataType, Reader};
+use polars::prelude::{DataFrameJoinOps, NamedFrom};
+
+// Column names from the .xls files downloaded from TMS
+pub const JOB_NUMBER_COLUMN_NAME: &str = "Load #";
+pub const HAWB_COLUMN_NAME: &str = "Ref: House Waybill Number";
+pub const QUANTITY_COLUMN_NAME: &str = "Actual Quantity";
+pub const SHIPPER_COLUMN_NAME: &str = "Shipper";
+pub const SHIPPER_NAME_COLUMN_NAME: &str = "Shipper Name";
+pub const SHIPPER_ADDRESS_COLUMN_NAME: &str = "Shipper Address";
+pub const SHIPPER_CITY_COLUMN_NAME: &str = "Shipper City";
+pub const SHIPPER_STATE_COLUMN_NAME: &str = "Shipper State";
+pub const SHIPPER_POSTAL_CODE_COLUMN_NAME: &str = "Shipper Postal Code";
+pub const SHIPPER_COUNTRY_COLUMN_NAME: &str = "Shipper Country";
+pub const TARGET_DELIVERY_EARLY_COLUMN_NAME: &str = "Target Delivery (Early)";
+pub const TARGET_DELIVERY_LATE_COLUMN_NAME: &str = "Target Delivery (Late)";
+pub const TARGET_SHIP_EARLY_COLUMN_NAME: &str = "Target Ship (Early)";
+pub const TARGET_SHIP_LATE_COLUMN_NAME: &str = "Target Ship (Late)";
+pub const CONSIGNEE_COLUMN_NAME: &str = "Consignee";
+pub const CONSIGNEE_NAME_COLUMN_NAME: &str = "Consignee Name";
+pub const CONSIGNEE_ADDRESS_COLUMN_NAME: &str = "Consignee Address";
+pub const CONSIGNEE_CITY_COLUMN_NAME: &str = "Consignee City";
+pub const CONSIGNEE_STATE_COLUMN_NAME: &str = "Consignee State";
+pub const CONSIGNEE_POSTAL_CODE_COLUMN_NAME: &str = "Consignee Postal Code";
+pub const CONSIGNEE_COUNTRY_COLUMN_NAME: &str = "Consignee Country";
+pub const EQUIPMENT_CODES_COLUMN_NAME: &str = "Equipment Codes";
+pub const TEMPERATURE_RANGE_COLUMN_NAME: &str = "Ref: Temperature Range";
+
+// Optional columns, they are only read if present in the export
+pub const GROSS_WEIGHT_COLUMN_NAME: &str = "Actual Weight";
+pub const WEIGHT_UNIT_COLUMN_NAME: &str = "Actual Weight UOM";
+pub const CHARGEABLE_WEIGHT_COLUMN_NAME: &str = "Chargeable Weight";
+pub const VOLUME_COLUMN_NAME: &str = "Actual Volume";
+pub const VOLUME_UNIT_COLUMN_NAME: &str = "Actual Volume UOM";
+
+// Air freight columns, only used by DispoMode::Transfer and DispoMode::Recovery
+pub const MAWB_COLUMN_NAME: &str = "Ref: Master Air Waybill Number";
+pub const AIRLINE_COLUMN_NAME: &str = "Airline";
+pub const CUT_OFF_COLUMN_NAME: &str = "Airline Cut-Off";
+
+/// The columns holding the record of one party of a job
+/// * code: The party code as used by TMS
+/// * name: The name of the party
+/// * address: The street address of the party
+/// * city: The city of the party
+/// * state: The state of the party
+/// * postal_code: The postal code of the party
+/// * country: The country of the party
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PartyColumns {
+    pub code: &'static str,
+    pub name: &'static str,
+    pub address: &'static str,
+    pub city: &'static str,
+    pub state: &'static str,
+    pub postal_code: &'static str,
+    pub country: &'static str,
+}
+
+/// The columns of the shipper record
+pub const SHIPPER_COLUMNS: PartyColumns = PartyColumns {
+    code: SHIPPER_COLUMN_NAME,
+    name: SHIPPER_NAME_COLUMN_NAME,
+    address: SHIPPER_ADDRESS_COLUMN_NAME,
+    city: SHIPPER_CITY_COLUMN_NAME,
+    state: SHIPPER_STATE_COLUMN_NAME,
+    postal_code: SHIPPER_POSTAL_CODE_COLUMN_NAME,
+    country: SHIPPER_COUNTRY_COLUMN_NAME,
+};
+
+/// The columns of the consignee record
+pub const CONSIGNEE_COLUMNS: PartyColumns = PartyColumns {
+    code: CONSIGNEE_COLUMN_NAME,
+    name: CONSIGNEE_NAME_COLUMN_NAME,
+    address: CONSIGNEE_ADDRESS_COLUMN_NAME,
+    city: CONSIGNEE_CITY_COLUMN_NAME,
+    state: CONSIGNEE_STATE_COLUMN_NAME,
+    postal_code: CONSIGNEE_POSTAL_CODE_COLUMN_NAME,
+    country: CONSIGNEE_COUNTRY_COLUMN_NAME,
+};
+
+impl PartyColumns {
+    /// Get all column names of the party record
+    ///
+    /// # Returns
+    /// * The column names
+    pub fn all(&self) -> [&'static str; 7] {
+        [
+            self.code,
+            self.name,
+            self.address,
+            self.city,
+            self.state,
+            self.postal_code,
+            self.country,
+        ]
+    }
+}
+
+impl Display for PartyColumns {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.all().join(", "))
+    }
+}
+
+/// Based on the mode of the dispo operation, the column names are different.
+/// This is a helper struct that maps to the correct column names based on the mode.
+/// So for example, if the mode is DispoMode::Delivery, the target window maps to the
+/// Target Delivery column names.
+/// Both the shipper and the consignee are always read, the mode only decides which of them is the stop.
+/// Transfers are delivered to the consignee, recoveries are collected like pickups.
+/// Returns bring empty packaging back to the shipper, so the shipper is the stop, but the delivery window applies.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ColumnMapping {
+    pub job_number: &'static str,
+    pub hawb: &'static str,
+    pub quantity: &'static str,
+    pub equipment_codes: &'static str,
+    pub temperature_range: &'static str,
+    pub shipper: PartyColumns,
+    pub consignee: PartyColumns,
+
+    // Optional columns
+    pub gross_weight: &'static str,
+    pub weight_unit: &'static str,
+    pub chargeable_weight: &'static str,
+    pub volume: &'static str,
+    pub volume_unit: &'static str,
+
+    // Air freight columns, None if the mode does not use them
+    pub mawb: Option<&'static str>,
+    pub airline: Option<&'static str>,
+    pub cut_off: Option<&'static str>,
+
+    // Mode-specific fields
+    pub stop: PartyRole,
+    pub target_early: &'static str,
+    pub target_late: &'static str,
+}
+
+impl ColumnMapping {
+    /// Create a new ColumnMapping based on the mode
+    ///
+    /// # Arguments
+    /// * `mode` - The mode to create the ColumnMapping for
+    pub fn new(mode: DispoMode) -> Self {
+        Self {
+            // Mode-independent columns
+            job_number: JOB_NUMBER_COLUMN_NAME,
+            hawb: HAWB_COLUMN_NAME,
+            quantity: QUANTITY_COLUMN_NAME,
+            equipment_codes: EQUIPMENT_CODES_COLUMN_NAME,
+            temperature_range: TEMPERATURE_RANGE_COLUMN_NAME,
+            shipper: SHIPPER_COLUMNS,
+            consignee: CONSIGNEE_COLUMNS,
+            gross_weight: GROSS_WEIGHT_COLUMN_NAME,
+            weight_unit: WEIGHT_UNIT_COLUMN_NAME,
+            chargeable_weight: CHARGEABLE_WEIGHT_COLUMN_NAME,
+            volume: VOLUME_COLUMN_NAME,
+            volume_unit: VOLUME_UNIT_COLUMN_NAME,
+
+            mawb: mode.has_flight_details().then_some(MAWB_COLUMN_NAME),
+            airline: mode.has_flight_details().then_some(AIRLINE_COLUMN_NAME),
+            cut_off: mode.has_flight_details().then_some(CUT_OFF_COLUMN_NAME),
+
+            stop: mode.stop(),
+
+            target_early: match mode.collects_freight() {
+                true => TARGET_SHIP_EARLY_COLUMN_NAME,
+                false => TARGET_DELIVERY_EARLY_COLUMN_NAME,
+            },
+
+            target_late: match mode.collects_freight() {
+                true => TARGET_SHIP_LATE_COLUMN_NAME,
+                false => TARGET_DELIVERY_LATE_COLUMN_NAME,
+            },
+        }
+    }
+
+    /// Get the columns the CL View must contain
+    ///
+    /// # Returns
+    /// * The headers of the required CL View columns
+    pub fn cl_view_columns(&self) -> Vec<&'static str> {
+        let mut columns = vec![
+            self.job_number,
+            self.quantity,
+            self.equipment_codes,
+            self.target_early,
+            self.target_late,
+        ];
+
+        // Both parties are always selected, the mode only decides which one is the stop
+        columns.extend(self.shipper.all());
+        columns.extend(self.consignee.all());
+
+        columns
+    }
+
+    /// Get the columns of the CL View that are only read if the export contains them.
+    /// The air freight columns are optional as well, jobs without them get a diagnostic.
+    ///
+    /// # Returns
+    /// * The headers of the optional CL View columns
+    pub fn optional_cl_view_columns(&self) -> Vec<&'static str> {
+        let mut columns = vec![
+            self.gross_weight,
+            self.weight_unit,
+            self.chargeable_weight,
+            self.volume,
+            self.volume_unit,
+        ];
+        columns.extend([self.mawb, self.airline, self.cut_off].into_iter().flatten());
+        columns
+    }
+
+    /// Get the columns the Shipper Site must contain
+    ///
+    /// # Returns
+    /// * The headers of the Shipper Site columns
+    pub fn shipper_site_columns(&self) -> [&'static str; 3] {
+        [self.job_number, self.hawb, self.temperature_range]
+    }
+}
+
+impl Display for ColumnMapping {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "ColumnMapping {{
+                Job Number: {},
+                HAWB: {},
+                Quantity: {},
+                Equipment Codes: {},
+                Temperature Range: {},
+                Shipper: {},
+                Consignee: {},
+                Gross Weight: {},
+                Weight Unit: {},
+                Chargeable Weight: {},
+                Volume: {},
+                Volume Unit: {},
+                MAWB: {},
+                Airline: {},
+                Cut-Off: {},
+                Stop: {},
+                Target Early: {},
+                Target Late: {}
+            }}",
+            self.job_number,
+            self.hawb,
+            self.quantity,
+            self.equipment_codes,
+            self.temperature_range,
+            self.shipper,
+            self.consignee,
+            self.gross_weight,
+            self.weight_unit,
+            self.chargeable_weight,
+            self.volume,
+            self.volume_unit,
+            self.mawb.unwrap_or_default(),
+            self.airline.unwrap_or_default(),
+            self.cut_off.unwrap_or_default(),
+            self.stop,
+            self.target_early,
+            self.target_late
+        )
+    }
+}
+
+/// Options that influence how the rows of an import are turned into jobs.
+/// Every field has a default, so the frontend only has to send what it wants to change.
+/// * tolerance_policies: The tolerance policies used to calculate the tolerance of each job
+/// * planned_time: The strategies used to calculate the planned date of each job
+/// * calendar: The business calendar the jobs are checked against
+/// * equipment_catalogue: The catalogue used to recognise the codes of the Equipment Codes column
+/// * volumetric_kg_per_m3: The factor used to calculate the volumetric weight from the volume
+/// * column_headers: The headers of exports that differ from the TMS defaults, by the default header they replace
+#[derive(serde::Serialize, serde::Deserialize, ts_rs::TS, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct ImportOptions {
+    #[serde(default)]
+    pub tolerance_policies: TolerancePolicySet,
+    #[serde(default)]
+    pub planned_time: PlannedTimeSettings,
+    #[serde(default)]
+    pub calendar: BusinessCalendar,
+    #[serde(default)]
+    pub equipment_catalogue: EquipmentCatalogue,
+    #[serde(default = "default_volumetric_kg_per_m3")]
+    pub volumetric_kg_per_m3: f64,
+    #[serde(default)]
+    pub column_headers: HashMap<String, String>,
+}
+
+/// The volumetric factor used if none is configured
+///
+/// # Returns
+/// * DEFAULT_VOLUMETRIC_KG_PER_M3
+fn default_volumetric_kg_per_m3() -> f64 {
+    DEFAULT_VOLUMETRIC_KG_PER_M3
+}
+
+impl Default for ImportOptions {
+    fn default() -> Self {
+        Self {
+            tolerance_policies: TolerancePolicySet::default(),
+            planned_time: PlannedTimeSettings::default(),
+            calendar: BusinessCalendar::default(),
+            equipment_catalogue: EquipmentCatalogue::default(),
+            volumetric_kg_per_m3: DEFAULT_VOLUMETRIC_KG_PER_M3,
+            column_headers: HashMap::new(),
+        }
+    }
+}
+
+impl ImportOptions {
+    /// Get the header a column has in the exports
+    ///
+    /// # Arguments
+    /// * `column` - The default header of the column, as exported by TMS
+    ///
+    /// # Returns
+    /// * The configured header or the default header if none is configured
+    pub fn column_header<'a>(&'a self, column: &'a str) -> &'a str {
+        self.column_headers.get(column).map_or(column, String::as_str)
+    }
+
+    /// Get the headers of columns in the exports
+    ///
+    /// # Arguments
+    /// * `columns` - The default headers of the columns
+    ///
+    /// # Returns
+    /// * The configured headers, in the order of the columns
+    pub fn column_headers_of<'a>(&'a self, columns: &[&'a str]) -> Vec<&'a str> {
+        columns.iter().map(|column| self.column_header(column)).collect()
+    }
+
+    /// Rename the configured headers of a parsed export back to the TMS defaults, so the mapping finds them
+    ///
+    /// # Arguments
+    /// * `df` - The parsed export
+    ///
+    /// # Returns
+    /// * Result containing nothing or an error
+    fn rename_to_default_headers(&self, df: &mut polars::prelude::DataFrame) -> Result<(), polars::prelude::PolarsError> {
+        for (column, header) in &self.column_headers {
+            if column == header || !df.get_column_names().contains(&header.as_str()) {
+                continue;
+            }
+            // The export may also have a column with the default header, the configured one replaces it
+            let _ = df.drop_in_place(column);
+            df.rename(header, column)?;
+        }
+        Ok(())
+    }
+}
+
+/// Remove all null bytes from a string.
+///
+/// # Arguments
+/// * `input` - The string to decode
+///
+/// # Returns
+/// * The decoded string
+#[inline]
+pub fn decode_text(input: &str) -> String {
+    input.encode_utf16().filter(|&c| c != 0).map(|c| c as u8 as char).collect()
+}
+
+/// The cell used for rows that are shorter than the header row
+static EMPTY_CELL: DataType = DataType::Empty;
+
+/// The ColumnType enum represents the polars types the columns of a sheet are converted to
+/// * Utf8: The column contains text, or text mixed with other values
+/// * Float64: The column contains floats, dates and durations, possibly mixed with integers
+/// * Int64: The column only contains integers
+/// * Boolean: The column only contains booleans
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+enum ColumnType {
+    Utf8,
+    Float64,
+    Int64,
+    Boolean,
+}
+
+impl ColumnType {
+    /// Find the type of a column from its cells.
+    /// Empty and error cells are skipped, a column with only those cells is a Utf8 column of nulls.
+    ///
+    /// # Arguments
+    /// * `cells` - The cells of the column, without the header
+    ///
+    /// # Returns
+    /// * The narrowest type all cells fit into
+    fn of<'a>(cells: impl Iterator<Item = &'a DataType>) -> Self {
+        let mut column_type: Option<ColumnType> = None;
+        for cell in cells {
+            let cell_type = match cell {
+                DataType::Empty | DataType::Error(_) => continue,
+                DataType::String(_) | DataType::DateTimeIso(_) | DataType::DurationIso(_) => return ColumnType::Utf8,
+                DataType::Float(_) | DataType::DateTime(_) | DataType::Duration(_) => ColumnType::Float64,
+                DataType::Int(_) => ColumnType::Int64,
+                DataType::Bool(_) => ColumnType::Boolean,
+            };
+            column_type = Some(match (column_type, cell_type) {
+                (None, cell_type) => cell_type,
+                (Some(column_type), cell_type) if column_type == cell_type => column_type,
+                (Some(ColumnType::Int64), ColumnType::Float64) | (Some(ColumnType::Float64), ColumnType::Int64) => ColumnType::Float64,
+                // Anything else can only be represented as text
+                _ => return ColumnType::Utf8,
+            });
+        }
+        column_type.unwrap_or(ColumnType::Utf8)
+    }
+}
+
+/// Convert a calamine::DataType to text
+/// Strings are decoded from UTF-16LE to UTF-8
+///
+/// # Arguments
+/// * `data` - The calamine::DataType to convert
+///
+/// # Returns
+/// * The text or None for empty and error cells
+fn data_type_to_text(data: &DataType) -> Option<String> {
+    match data {
+        DataType::Empty | DataType::Error(_) => None,
+        DataType::String(s) | DataType::DateTimeIso(s) | DataType::DurationIso(s) => Some(decode_text(s)),
+        _ => Some(decode_text(&data.to_string())),
+    }
+}
+
+/// Convert a calamine::DataType to a float
+/// Dates and durations are kept as their Excel serial number
+///
+/// # Arguments
+/// * `data` - The calamine::DataType to convert
+///
+/// # Returns
+/// * The float or None for cells without a number
+fn data_type_to_float(data: &DataType) -> Option<f64> {
+    match data {
+        DataType::Float(f) => Some(*f),
+        DataType::DateTime(f) => Some(*f),
+        DataType::Duration(f) => Some(*f),
+        DataType::Int(i) => Some(*i as f64),
+        _ => None,
+    }
+}
+
+/// Convert the cells of a column, checking for cancellation on every row
+///
+/// # Arguments
+/// * `cells` - The cells of the column, without the header
+/// * `convert` - Converts one cell
+/// * `task` - The task to check
+///
+/// # Returns
+/// * Result containing the converted cells or ParseFilesError::Cancelled
+fn convert_cells<'a, T>(
+    cells: impl Iterator<Item = &'a DataType>,
+    convert: impl Fn(&DataType) -> Option<T>,
+    task: &ParseTask,
+) -> Result<Vec<Option<T>>, ParseFilesError> {
+    cells
+        .map(|cell| {
+            task.check()?;
+            Ok(convert(cell))
+        })
+        .collect()
+}
+
+/// Convert one column of a sheet into a typed polars::prelude::Series
+/// The cells are converted straight into the type of the column, without going through AnyValue.
+///
+/// # Arguments
+/// * `range` - The calamine::Range containing the column
+/// * `index` - The index of the column
+/// * `name` - The header of the column
+/// * `task` - The task to check for cancellation
+///
+/// # Returns
+/// * Result containing the series of the column or ParseFilesError::Cancelled
+fn parse_column(
+    range: &calamine::Range<DataType>,
+    index: usize,
+    name: &str,
+    task: &ParseTask,
+) -> Result<polars::prelude::Series, ParseFilesError> {
+    let cells = || range.rows().skip(1).map(|row| row.get(index).unwrap_or(&EMPTY_CELL));
+
+    Ok(match ColumnType::of(cells()) {
+        ColumnType::Utf8 => polars::prelude::Series::new(name, convert_cells(cells(), data_type_to_text, task)?),
+        ColumnType::Float64 => polars::prelude::Series::new(name, convert_cells(cells(), data_type_to_float, task)?),
+        ColumnType::Int64 => polars::prelude::Series::new(
+            name,
+            convert_cells(
+                cells(),
+                |cell| match cell {
+                    DataType::Int(i) => Some(*i),
+                    _ => None,
+                },
+                task,
+            )?,
+        ),
+        ColumnType::Boolean => polars::prelude::Series::new(
+            name,
+            convert_cells(
+                cells(),
+                |cell| match cell {
+                    DataType::Bool(b) => Some(*b),
+                    _ => None,
+                },
+                task,
+            )?,
+        ),
+    })
+}
+
+/// Get the header names from a calamine::Range
+/// Strings are decoded from UTF-16LE to UTF-8
+/// All other types are converted to strings and then decoded from UTF-16LE to UTF-8
+/// This is because we need strings to be in the header row.
+///
+/// # Arguments
+/// * `range` - The calamine::Range to get the header names from
+///
+/// # Returns
+/// * Result containing a Vec<String> of header names or an error
+fn get_header_names(range: &calamine::Range<calamine::DataType>) -> Result<Vec<String>, ParseFilesError> {
+    match range.rows().next() {
+        Some(header_row) => {
+            let header_names: Vec<String> = header_row
+                .iter()
+                .map(|cell| match cell {
+                    calamine::DataType::String(s) => Ok(decode_text(s)),
+                    _ => Ok(decode_text(&cell.to_string())),
+                })
+                .collect::<Result<Vec<String>, calamine::Error>>()?;
+            Ok(header_names)
+        }
+        // No header row found, return an error then.
+        None => Err(ParseFilesError::NoHeadersFound),
+    }
+}
+
+/// Parse a sheet into a polars::prelude::DataFrame
+/// The first row is expected to be the header row.
+/// The headers are resolved first, so only the projected columns are converted.
+/// Projected columns missing in the sheet are skipped, selecting them later reports the error.
+///
+/// # Arguments
+/// * `range` - The calamine::Range to parse
+/// * `projection` - The headers of the columns to convert, or None for all columns
+///
+/// # Returns
+/// * Result containing a polars::prelude::DataFrame or an error
+pub fn parse_sheet(
+    range: &calamine::Range<calamine::DataType>,
+    projection: Option<&[&str]>,
+) -> Result<polars::prelude::DataFrame, ParseFilesError> {
+    parse_sheet_with_task(range, projection, &ParseTask::silent())
+}
+
+/// Parse a sheet into a polars::prelude::DataFrame, checking a task for cancellation.
+/// Behaves like parse_sheet, but stops with ParseFilesError::Cancelled once the task is cancelled.
+///
+/// # Arguments
+/// * `range` - The calamine::Range to parse
+/// * `projection` - The headers of the columns to convert, or None for all columns
+/// * `task` - The task to check for cancellation
+///
+/// # Returns
+/// * Result containing a polars::prelude::DataFrame or an error
+pub fn parse_sheet_with_task(
+    range: &calamine::Range<calamine::DataType>,
+    projection: Option<&[&str]>,
+    task: &ParseTask,
+) -> Result<polars::prelude::DataFrame, ParseFilesError> {
+    // Get the header names
+    let header_names = get_header_names(range)?;
+
+    let series = header_names
+        .iter()
+        .enumerate()
+        .filter(|(_, name)| projection.is_none_or(|projection| projection.contains(&name.as_str())))
+        .map(|(index, name)| {
+            task.check()?;
+            parse_column(range, index, name, task)
+        })
+        .collect::<Result<Vec<polars::prelude::Series>, ParseFilesError>>()?;
+
+    let df = polars::prelude::DataFrame::new(series)?;
+
+    Ok(df)
+}
+
+/// Parse a .xls file into a polars::prelude::DataFrame
+/// The first row is expected to be the header row.
+/// The format is detected from the contents, so .xlsx files are parsed as well.
+/// The first sheet is parsed.
+/// If there are multiple sheets, an error is returned.
+/// If the sheet is not found, an error is returned.
+/// If the sheet is empty, an error is returned.
+/// If the sheet contains only the header row, an empty DataFrame is returned.
+///
+/// # Arguments
+/// * `file_path` - The path to the .xls file
+///
+/// # Returns
+/// * Result containing a polars::prelude::DataFrame or an error
+pub fn parse_xls_file_tms(file_path: &str) -> Result<polars::prelude::DataFrame, ParseFilesError> {
+    parse_xls_file_tms_with_task(file_path, None, &ParseTask::silent())
+}
+
+/// Parse a .xls file into a polars::prelude::DataFrame, reporting the progress to a task.
+/// Behaves like parse_xls_file_tms, but stops with ParseFilesError::Cancelled once the task is cancelled.
+///
+/// # Arguments
+/// * `file_path` - The path to the .xls file
+/// * `projection` - The headers of the columns to convert, or None for all columns
+/// * `task` - The task to report to
+///
+/// # Returns
+/// * Result containing a polars::prelude::DataFrame or an error
+pub fn parse_xls_file_tms_with_task(
+    file_path: &str,
+    projection: Option<&[&str]>,
+    task: &ParseTask,
+) -> Result<polars::prelude::DataFrame, ParseFilesError> {
+    read_xls_file_tms(file_path, projection, task).map(|(df, _)| df)
+}
+
+/// Parse a .xls file like parse_xls_file_tms_with_task and hash the content that was parsed
+///
+/// # Arguments
+/// * `file_path` - The path to the .xls file
+/// * `projection` - The headers of the columns to convert, or None for all columns
+/// * `task` - The task to report to
+///
+/// # Returns
+/// * Result containing the polars::prelude::DataFrame and the parsed file or an error
+fn read_xls_file_tms(
+    file_path: &str,
+    projection: Option<&[&str]>,
+    task: &ParseTask,
+) -> Result<(polars::prelude::DataFrame, ImportedFile), ParseFilesError> {
+    task.check()?;
+    let contents = std::fs::read(file_path).map_err(calamine::Error::Io)?;
+    let file = ImportedFile::of(file_path, &contents);
+
+    // Re-running an import with another mode or mapping reads the same files again
+    let cache = workbook_cache::global();
+    let key = CacheKey::of(&contents);
+    if let Some(df) = cache.and_then(|cache| cache.load(&key, projection)) {
+        task.report(ParseStage::Opened, Some(file_path), 0);
+        task.report(ParseStage::RowsParsed, Some(file_path), df.height());
+        return Ok((df, file));
+    }
+
+    // TMS exports .xls, but a workbook saved again by Excel is .xlsx
+    let mut workbook = calamine::open_workbook_auto_from_rs(Cursor::new(contents))?;
+    let sheet_names = workbook.sheet_names();
+
+    if sheet_names.len() != 1 {
+        return Err(ParseFilesError::InvalidSheetCount((1, sheet_names.len() as i32)));
+    }
+
+    let range = match workbook.worksheet_range(&sheet_names[0]) {
+        Some(Ok(range)) => range,
+        Some(Err(e)) => return Err(e.into()),
+        None => return Err(calamine::Error::Msg("Sheet not found").into()),
+    };
+    task.report(ParseStage::Opened, Some(file_path), 0);
+    task.check()?;
+
+    let df = match cache {
+        Some(cache) => {
+            // The columns cached for other mappings are parsed again, so they stay cached
+            let cached_columns = cache.cached_columns(&key);
+            let projection: Option<Vec<&str>> = projection.map(|projection| {
+                projection
+                    .iter()
+                    .copied()
+                    .chain(cached_columns.iter().map(|column| column.as_str()))
+                    .collect()
+            });
+            let df = parse_sheet_with_task(&range, projection.as_deref(), task)?;
+            // The import does not depend on the cache, a failed write only costs the next import some time
+            let _ = cache.store(&key, &get_header_names(&range)?, &df);
+            df
+        }
+        None => parse_sheet_with_task(&range, projection, task)?,
+    };
+    task.report(ParseStage::RowsParsed, Some(file_path), df.height());
+
+    Ok((df, file))
+}
+
+/// Parse two .xls files into a Vec<JobRow>
+///
+/// # Arguments
+/// * `cl_view_path` - The path to the CL View .xls file
+/// * `shipper_site_path` - The path to the Shipper Site .xls file
+/// * `mode` - The DispoMode to use
+/// * `options` - The ImportOptions to use
+///
+/// # Returns
+/// * Result containing a Vec<JobRow> or an error
+pub fn create_job_rows(
+    cl_view_path: &str,
+    shipper_site_path: &str,
+    mode: DispoMode,
+    options: &ImportOptions,
+) -> Result<Vec<JobRow>, ParseFilesError> {
+    create_job_rows_with_task(cl_view_path, shipper_site_path, mode, options, &ParseTask::silent())
+}
+
+/// Parse two .xls files into a Vec<JobRow>, reporting the progress to a task.
+/// Both files are parsed concurrently.
+/// Behaves like create_job_rows, but stops with ParseFilesError::Cancelled once the task is cancelled.
+///
+/// # Arguments
+/// * `cl_view_path` - The path to the CL View .xls file
+/// * `shipper_site_path` - The path to the Shipper Site .xls file
+/// * `mode` - The DispoMode to use
+/// * `options` - The ImportOptions to use
+/// * `task` - The task to report to
+///
+/// # Returns
+/// * Result containing a Vec<JobRow> or an error
+pub fn create_job_rows_with_task(
+    cl_view_path: &str,
+    shipper_site_path: &str,
+    mode: DispoMode,
+    options: &ImportOptions,
+    task: &ParseTask,
+) -> Result<Vec<JobRow>, ParseFilesError> {
+    create_job_rows_with_files(cl_view_path, shipper_site_path, mode, options, task).map(|(jobs, _)| jobs)
+}
+
+/// Parse two .xls files into a Vec<JobRow> like create_job_rows_with_task and hash the content that was parsed.
+/// The hashes are of the bytes the jobs were created from, for the audit log.
+///
+/// # Arguments
+/// * `cl_view_path` - The path to the CL View .xls file
+/// * `shipper_site_path` - The path to the Shipper Site .xls file
+/// * `mode` - The DispoMode to use
+/// * `options` - The ImportOptions to use
+/// * `task` - The task to report to
+///
+/// # Returns
+/// * Result containing the Vec<JobRow> and the CL View and Shipper Site as parsed or an error
+pub fn create_job_rows_with_files(
+    cl_view_path: &str,
+    shipper_site_path: &str,
+    mode: DispoMode,
+    options: &ImportOptions,
+    task: &ParseTask,
+) -> Result<(Vec<JobRow>, Vec<ImportedFile>), ParseFilesError> {
+    options.planned_time.validate()?;
+    let column_mapping = ColumnMapping::new(mode);
+
+    // Only the mapped columns are converted, the exports have many more
+    let mut cl_view_columns = column_mapping.cl_view_columns();
+    cl_view_columns.extend(column_mapping.optional_cl_view_columns());
+    let cl_view_columns = options.column_headers_of(&cl_view_columns);
+    let shipper_site_columns = options.column_headers_of(&column_mapping.shipper_site_columns());
+
+    let (cl_view, shipper_site) = std::thread::scope(|scope| {
+        let shipper_site = scope.spawn(|| read_xls_file_tms(shipper_site_path, Some(&shipper_site_columns), task));
+        let cl_view = read_xls_file_tms(cl_view_path, Some(&cl_view_columns), task);
+        let shipper_site = shipper_site.join().unwrap_or_else(|panic| std::panic::resume_unwind(panic));
+        (cl_view, shipper_site)
+    });
+    let (mut cl_view, cl_view_file) = cl_view?;
+    let (mut shipper_site, shipper_site_file) = shipper_site?;
+    options.rename_to_default_headers(&mut cl_view)?;
+    options.rename_to_default_headers(&mut shipper_site)?;
+    task.check()?;
+
+    let jobs = join_job_rows(&cl_view, &shipper_site, mode, options, task)?;
+    Ok((jobs, vec![cl_view_file, shipper_site_file]))
+}
+
+/// Turn an already loaded CL View and Shipper Site into a Vec<JobRow>.
+/// Meant for tools that do not read the exports from .xls files, the sheets must have the same headers.
+///
+/// # Arguments
+/// * `cl_view` - The sheet of the CL View, with the header row
+/// * `shipper_site` - The sheet of the Shipper Site, with the header row
+/// * `mode` - The DispoMode to use
+/// * `options` - The ImportOptions to use
+///
+/// # Returns
+/// * Result containing a Vec<JobRow> or an error
+pub fn create_job_rows_from_sheets(
+    cl_view: &calamine::Range<DataType>,
+    shipper_site: &calamine::Range<DataType>,
+    mode: DispoMode,
+    options: &ImportOptions,
+) -> Result<Vec<JobRow>, ParseFilesError> {
+    options.planned_time.validate()?;
+    let column_mapping = ColumnMapping::new(mode);
+
+    let mut cl_view_columns = column_mapping.cl_view_columns();
+    cl_view_columns.extend(column_mapping.optional_cl_view_columns());
+    let mut cl_view = parse_sheet(cl_view, Some(&options.column_headers_of(&cl_view_columns)))?;
+    let mut shipper_site = parse_sheet(
+        shipper_site,
+        Some(&options.column_headers_of(&column_mapping.shipper_site_columns())),
+    )?;
+    options.rename_to_default_headers(&mut cl_view)?;
+    options.rename_to_default_headers(&mut shipper_site)?;
+
+    join_job_rows(&cl_view, &shipper_site, mode, options, &ParseTask::silent())
+}
+
+/// Join the parsed CL View and Shipper Site and convert the rows into a Vec<JobRow>
+///
+/// # Arguments
+/// * `cl_view` - The parsed CL View
+/// * `shipper_site` - The parsed Shipper Site
+/// * `mode` - The DispoMode to use
+/// * `options` - The ImportOptions to use
+/// * `task` - The task to report to
+///
+/// # Returns
+/// * Result containing a Vec<JobRow> or an error
+fn join_job_rows(
+    cl_view: &polars::prelude::DataFrame,
+    shipper_site: &polars::prelude::DataFrame,
+    mode: DispoMode,
+    options: &ImportOptions,
+    task: &ParseTask,
+) -> Result<Vec<JobRow>, ParseFilesError> {
+    let column_mapping = ColumnMapping::new(mode);
+
+    // Create new DataFrames containg only the wanted columns
+    let cl_view = select_columns_cl_view(cl_view, &column_mapping)?;
+    let shipper_site = select_columns_shipper_site(shipper_site, &column_mapping)?;
+
+    // Join the DataFrames to create a DataFrame containing all wanted columns.
+    // A load can have several HAWBs, so the Shipper Site may have more rows than the CL View.
+    // Those rows are merged into one job again by JobRow::from_dataframe.
+    // Loads missing in the Shipper Site are kept, they get a diagnostic instead of being dropped.
+    let joined = cl_view.left_join(&shipper_site, [JOB_NUMBER_COLUMN_NAME], [JOB_NUMBER_COLUMN_NAME])?;
+    // We don't need the selected DataFrames anymore
+    drop(cl_view);
+    drop(shipper_site);
+    task.report(ParseStage::Joined, None, joined.height());
+    task.check()?;
+
+    // Create a Vec<JobRow> from the DataFrame
+    let rows = JobRow::from_dataframe_with_task(&joined, mode, options, task)?;
+    task.report(ParseStage::Converted, None, rows.len());
+
+    Ok(rows)
+}
+
+/// Select only the wanted columns from a DataFrame containing the CL View
+///
+/// # Arguments
+/// * `df` - The DataFrame to select the columns from
+/// * `mapping` - The ColumnMapping to use
+///
+/// # Returns
+/// * Result containing a DataFrame with only the wanted columns or an error
+fn select_columns_cl_view(
+    df: &polars::prelude::DataFrame,
+    mapping: &ColumnMapping,
+) -> Result<polars::prelude::DataFrame, polars::prelude::PolarsError> {
+    let mut columns = mapping.cl_view_columns();
+
+    // Optional columns are only selected if the export contains them
+    let available = df.get_column_names();
+    columns.extend(
+        mapping
+            .optional_cl_view_columns()
+            .into_iter()
+            .filter(|column| available.contains(column)),
+    );
+
+    df.select(columns)
+}
+
+/// Select only the wanted columns from a DataFrame containing the Shipper Site
+///
+/// # Arguments
+/// * `df` - The DataFrame to select the columns from
+/// * `mapping` - The ColumnMapping to use
+///
+/// # Returns
+/// * Result containing a DataFrame with only the wanted columns or an error
+fn select_columns_shipper_site(
+    df: &polars::prelude::DataFrame,
+    mapping: &ColumnMapping,
+) -> Result<polars::prelude::DataFrame, polars::prelude::PolarsError> {
+    df.select(mapping.shipper_site_columns())
+}
@@ -0,0 +1,289 @@
+// Copyright 2023 Felix Kahle. All rights reserved.
+
+use crate::{
+    export::{ExportError, PlannedJob},
+    workbook_cache::content_hash,
+};
+use chrono::{Duration, NaiveDate, NaiveDateTime, Utc};
+use std::fmt::{self};
+use std::path::Path;
+
+/// The product identifier written into every calendar
+pub const ICALENDAR_PRODUCT_ID: &str = "-//Felix Kahle//Dispo Master//EN";
+
+/// The domain part of the event UIDs, so they do not collide with events of other applications
+pub const ICALENDAR_UID_DOMAIN: &str = "dispo-master";
+
+/// The format of local date-times in iCalendar
+const ICALENDAR_DATE_FORMAT: &str = "%Y%m%dT%H%M%S";
+
+/// Lines longer than this many octets are folded, as required by RFC 5545
+const ICALENDAR_LINE_LIMIT: usize = 75;
+
+/// The sequence of an event counts the seconds since this date, so it stays a 32-bit integer for decades
+const ICALENDAR_SEQUENCE_EPOCH: (i32, u32, u32) = (2023, 1, 1);
+
+/// The CalendarGrouping enum decides for whom the calendars are created
+/// * Driver: One calendar per driver
+/// * Vehicle: One calendar per vehicle
+#[derive(serde::Serialize, serde::Deserialize, ts_rs::TS, Debug, Copy, Clone, PartialEq, Eq)]
+pub enum CalendarGrouping {
+    Driver,
+    Vehicle,
+}
+
+impl fmt::Display for CalendarGrouping {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CalendarGrouping::Driver => write!(f, "Driver"),
+            CalendarGrouping::Vehicle => write!(f, "Vehicle"),
+        }
+    }
+}
+
+impl CalendarGrouping {
+    /// Get the name of the calendar a job belongs to
+    ///
+    /// # Arguments
+    /// * `job` - The job
+    ///
+    /// # Returns
+    /// * The driver or vehicle of the job, empty if unassigned
+    fn key<'a>(&self, job: &'a PlannedJob) -> &'a str {
+        match self {
+            CalendarGrouping::Driver => job.driver.trim(),
+            CalendarGrouping::Vehicle => job.vehicle.trim(),
+        }
+    }
+}
+
+/// The schedule of one driver or vehicle as an iCalendar file
+/// * name: The driver or vehicle
+/// * content: The content of the .ics file
+#[derive(serde::Serialize, ts_rs::TS, Debug, Clone, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct Calendar {
+    pub name: String,
+    pub content: String,
+}
+
+/// Escape a text value as required by RFC 5545
+///
+/// # Arguments
+/// * `value` - The text to escape
+///
+/// # Returns
+/// * The escaped text
+fn escape_text(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace(';', "\\;")
+        .replace(',', "\\,")
+        .replace("\r\n", "\\n")
+        .replace('\n', "\\n")
+}
+
+/// Append a content line, folded after ICALENDAR_LINE_LIMIT octets.
+/// Folding never splits a UTF-8 character.
+///
+/// # Arguments
+/// * `output` - The calendar to append to
+/// * `line` - The unfolded content line
+fn push_line(output: &mut String, line: &str) {
+    let mut length = 0;
+    for c in line.chars() {
+        if length + c.len_utf8() > ICALENDAR_LINE_LIMIT {
+            output.push_str("\r\n ");
+            // The leading space of a continuation line counts towards its length
+            length = 1;
+        }
+        output.push(c);
+        length += c.len_utf8();
+    }
+    output.push_str("\r\n");
+}
+
+/// Create the stable UID of a job.
+/// The UID only depends on the job, so exporting again updates the events instead of duplicating them.
+///
+/// # Arguments
+/// * `job` - The job
+///
+/// # Returns
+/// * The UID of the event of the job
+pub fn event_uid(job: &PlannedJob) -> String {
+    let id: String = job
+        .id()
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() || c == '-' { c } else { '-' })
+        .collect();
+    format!("{}@{}", id, ICALENDAR_UID_DOMAIN)
+}
+
+/// Create the description of the event of a job
+///
+/// # Arguments
+/// * `job` - The job
+///
+/// # Returns
+/// * The unescaped description
+fn event_description(planned: &PlannedJob) -> String {
+    let job = &planned.job;
+    let temperature = job
+        .temperature_ranges
+        .iter()
+        .map(|range| range.to_string())
+        .collect::<Vec<String>>()
+        .join(", ");
+    let pieces = job.quantity.pieces.map_or("-".to_string(), |pieces| pieces.to_string());
+
+    let mut lines = vec![
+        format!("HAWB: {}", job.hawb_number),
+        format!("Temperature: {}", temperature),
+        format!("Pieces: {}", pieces),
+        format!("Contact: {}", job.contact_name),
+    ];
+    if !job.equipment.trim().is_empty() {
+        lines.push(format!("Equipment: {}", job.equipment));
+    }
+    if !planned.notes.trim().is_empty() {
+        lines.push(format!("Notes: {}", planned.notes));
+    }
+    lines.join("\n")
+}
+
+/// Append the VEVENT of a job.
+/// The event spans the calculated date minus the tolerance before to the calculated date plus the tolerance after.
+///
+/// # Arguments
+/// * `output` - The calendar to append to
+/// * `job` - The job
+/// * `stamp` - The time of the export
+/// * `sequence` - The revision of the event, must grow with every export
+fn push_event(output: &mut String, planned: &PlannedJob, stamp: NaiveDateTime, sequence: i64) {
+    let job = &planned.job;
+    let start = job.calculated_date - Duration::minutes(job.tolerance_before.max(0) as i64);
+    let end = job.calculated_date + Duration::minutes(job.tolerance_after.max(0) as i64);
+    let location = format!("{}, {} {}, {}", job.address, job.postal_code, job.city, job.country);
+    let summary = format!("{} {} {}", job.mode, job.job_number, job.contact_name);
+
+    push_line(output, "BEGIN:VEVENT");
+    push_line(output, &format!("UID:{}", event_uid(planned)));
+    push_line(output, &format!("DTSTAMP:{}Z", stamp.format(ICALENDAR_DATE_FORMAT)));
+    push_line(output, &format!("LAST-MODIFIED:{}Z", stamp.format(ICALENDAR_DATE_FORMAT)));
+    push_line(output, &format!("SEQUENCE:{}", sequence));
+    push_line(output, &format!("DTSTART:{}", start.format(ICALENDAR_DATE_FORMAT)));
+    push_line(output, &format!("DTEND:{}", end.format(ICALENDAR_DATE_FORMAT)));
+    push_line(output, &format!("SUMMARY:{}", escape_text(summary.trim())));
+    push_line(output, &format!("LOCATION:{}", escape_text(&location)));
+    push_line(output, &format!("DESCRIPTION:{}", escape_text(&event_description(planned))));
+    push_line(output, "END:VEVENT");
+}
+
+/// Create one calendar per driver or vehicle.
+/// Jobs without a driver or vehicle are left out.
+/// The dates are written as floating local times, so they are shown as planned in every time zone.
+///
+/// # Arguments
+/// * `jobs` - The jobs of the dispatch plan
+/// * `grouping` - Whether one calendar per driver or per vehicle is created
+///
+/// # Returns
+/// * The calendars, in the order of the first job of each driver or vehicle
+pub fn create_calendars(jobs: &[PlannedJob], grouping: CalendarGrouping) -> Vec<Calendar> {
+    let now = Utc::now();
+    let stamp = now.naive_utc();
+    // Clients only replace an event with the same UID if the sequence grew, the export time always grows
+    let (year, month, day) = ICALENDAR_SEQUENCE_EPOCH;
+    let sequence = NaiveDate::from_ymd_opt(year, month, day)
+        .and_then(|date| date.and_hms_opt(0, 0, 0))
+        .map_or(0, |epoch| (stamp - epoch).num_seconds());
+
+    let mut sorted: Vec<&PlannedJob> = jobs.iter().filter(|job| !grouping.key(job).is_empty()).collect();
+    sorted.sort_by_key(|planned| planned.job.calculated_date);
+
+    let mut names: Vec<&str> = Vec::new();
+    for job in &sorted {
+        let name = grouping.key(job);
+        if !names.contains(&name) {
+            names.push(name);
+        }
+    }
+
+    names
+        .into_iter()
+        .map(|name| {
+            let mut content = String::new();
+            push_line(&mut content, "BEGIN:VCALENDAR");
+            push_line(&mut content, "VERSION:2.0");
+            push_line(&mut content, &format!("PRODID:{}", ICALENDAR_PRODUCT_ID));
+            push_line(&mut content, "CALSCALE:GREGORIAN");
+            push_line(&mut content, "METHOD:PUBLISH");
+            push_line(&mut content, &format!("X-WR-CALNAME:{}", escape_text(name)));
+            for job in sorted.iter().filter(|job| grouping.key(job) == name) {
+                push_event(&mut content, job, stamp, sequence);
+            }
+            push_line(&mut content, "END:VCALENDAR");
+
+            Calendar {
+                name: name.to_string(),
+                content,
+            }
+        })
+        .collect()
+}
+
+/// Get the file name of the calendar of a driver or vehicle.
+/// Characters that are not allowed in file names are replaced. A name that had to be changed gets the hash
+/// of the original name appended, so "A/B" and "A_B" do not overwrite each other and keep their files
+/// across exports.
+///
+/// # Arguments
+/// * `name` - The driver or vehicle
+///
+/// # Returns
+/// * The file name, without the extension
+pub fn calendar_file_name(name: &str) -> String {
+    let file_name: String = name
+        .chars()
+        .map(|c| {
+            if c.is_alphanumeric() || c == ' ' || c == '-' || c == '_' {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect();
+    let file_name = file_name.trim();
+    match file_name == name {
+        true => file_name.to_string(),
+        false => format!("{}-{:08x}", file_name, content_hash(name.as_bytes()) as u32),
+    }
+}
+
+/// Write one .ics file per driver or vehicle into a directory.
+/// The files are named after the driver or vehicle, so exporting again overwrites them.
+/// Names that only differ in case get the hash of the name appended, file systems may not tell them apart.
+///
+/// # Arguments
+/// * `directory` - The directory to write to
+/// * `jobs` - The jobs of the dispatch plan
+/// * `grouping` - Whether one calendar per driver or per vehicle is created
+///
+/// # Returns
+/// * Result containing the paths of the written files or an error
+pub fn write_calendars(directory: &str, jobs: &[PlannedJob], grouping: CalendarGrouping) -> Result<Vec<String>, ExportError> {
+    let mut paths = Vec::new();
+    let mut file_names: Vec<String> = Vec::new();
+    for calendar in create_calendars(jobs, grouping) {
+        let mut file_name = calendar_file_name(&calendar.name);
+        if file_names.iter().any(|existing| existing.eq_ignore_ascii_case(&file_name)) {
+            file_name = format!("{}-{:08x}", file_name, content_hash(calendar.name.as_bytes()) as u32);
+        }
+        let path = Path::new(directory).join(format!("{}.ics", file_name));
+        file_names.push(file_name);
+        std::fs::write(&path, calendar.content)?;
+        paths.push(path.to_string_lossy().to_string());
+    }
+    Ok(paths)
+}
@@ -0,0 +1,1038 @@
+// Copyright 2023 Felix Kahle. All rights reserved.
+
+use crate::{
+    calendar::CalendarCheck,
+    diagnostics::{Diagnostic, DiagnosticKind},
+    equipment::{parse_equipment_codes, Equipment},
+    file_parsing::{ColumnMapping, ImportOptions, PartyColumns},
+    lifecycle::{JobStatus, StatusChange},
+    parse_error::ParseFilesError,
+    parse_task::ParseTask,
+    planned_time::{apply_cut_off, PlannedTimeStrategy},
+    quantity::{Quantity, QuantityCells},
+};
+use chrono::NaiveDateTime;
+use polars::frame::DataFrame;
+use std::collections::HashMap;
+use std::fmt::{self};
+use std::str::FromStr;
+
+/// The format of the dates in the exports of TMS
+pub const TMS_DATE_FORMAT: &str = "%m/%d/%Y %H:%M";
+
+/// The DispoMode enum represents the different modes of a dispo operation
+/// * Delivery: The dispo operation is a delivery
+/// * Pickup: The dispo operation is a pickup
+/// * Transfer: The dispo operation brings export freight from the station to the airport
+/// * Recovery: The dispo operation recovers import freight from the airline at the airport
+/// * Return: The dispo operation returns empty packaging to the shipper
+#[derive(serde::Serialize, serde::Deserialize, ts_rs::TS, Debug, Copy, Clone, PartialEq, Eq)]
+pub enum DispoMode {
+    Delivery,
+    Pickup,
+    Transfer,
+    Recovery,
+    Return,
+}
+
+#[derive(Debug)]
+pub struct StringToDispoModeError(String);
+
+impl fmt::Display for StringToDispoModeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "Error parsing '{}' to a DispoMode. Expected 'Delivery', 'Pickup', 'Transfer', 'Recovery' or 'Return'",
+            self.0
+        )
+    }
+}
+
+impl std::error::Error for StringToDispoModeError {}
+
+/// Create a DispoMode from a string.
+/// The string must be one of the following:
+/// * Delivery
+/// * Pickup
+/// * Transfer
+/// * Recovery
+/// * Return
+impl FromStr for DispoMode {
+    type Err = StringToDispoModeError;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value {
+            "Delivery" => Ok(DispoMode::Delivery),
+            "Pickup" => Ok(DispoMode::Pickup),
+            "Transfer" => Ok(DispoMode::Transfer),
+            "Recovery" => Ok(DispoMode::Recovery),
+            "Return" => Ok(DispoMode::Return),
+            _ => Err(StringToDispoModeError(value.to_string())),
+        }
+    }
+}
+
+impl DispoMode {
+    /// Check whether jobs of this mode are handed over to or taken over from an airline
+    ///
+    /// # Returns
+    /// * True for Transfer and Recovery
+    pub fn has_flight_details(&self) -> bool {
+        matches!(self, DispoMode::Transfer | DispoMode::Recovery)
+    }
+
+    /// Get the party that is visited by jobs of this mode
+    ///
+    /// # Returns
+    /// * PartyRole::Shipper for Pickup, Recovery and Return, PartyRole::Consignee for Delivery and Transfer
+    pub fn stop(&self) -> PartyRole {
+        match self {
+            DispoMode::Pickup | DispoMode::Recovery | DispoMode::Return => PartyRole::Shipper,
+            DispoMode::Delivery | DispoMode::Transfer => PartyRole::Consignee,
+        }
+    }
+
+    /// Check whether jobs of this mode collect freight at their stop.
+    /// A return visits the shipper as well, but it brings something instead of collecting it.
+    ///
+    /// # Returns
+    /// * True for Pickup and Recovery
+    pub fn collects_freight(&self) -> bool {
+        matches!(self, DispoMode::Pickup | DispoMode::Recovery)
+    }
+}
+
+impl fmt::Display for DispoMode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DispoMode::Delivery => write!(f, "Delivery"),
+            DispoMode::Pickup => write!(f, "Pickup"),
+            DispoMode::Transfer => write!(f, "Transfer"),
+            DispoMode::Recovery => write!(f, "Recovery"),
+            DispoMode::Return => write!(f, "Return"),
+        }
+    }
+}
+
+/// The TemperatureRange enum represents the different temperature ranges of a dispo operation
+#[derive(serde::Serialize, serde::Deserialize, ts_rs::TS, Debug, Copy, Clone, PartialEq, Eq)]
+pub enum TemperatureRange {
+    DryIce,
+    DryShipper,
+    Refrigerated,
+    ControlledAmbient,
+    Frozen,
+    Ambient,
+    NonSOP,
+    Invalid,
+}
+
+#[derive(Debug, Clone)]
+pub struct StringToTemperatureRangeError(String);
+
+impl fmt::Display for StringToTemperatureRangeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "String can not be parsed to TemperatureRange: {}", self.0)
+    }
+}
+
+impl TemperatureRange {
+    /// Create a TemperatureRange from a string.
+    /// The string must be one of the following:
+    /// * Frozen Dry Ice -80C to -20C
+    /// * Deep Frozen Dry Ice -70C [+/-10C]
+    /// * Cryogenics -190C to -150C
+    /// * Refrigerated +2C to +8C
+    /// * Controlled Ambient +15C to +25C
+    /// * Frozen -25C to -15C
+    /// * Ambient
+    /// * Frozen -50C  [+/-10C]
+    ///
+    /// # Arguments
+    /// * `value` - The string to create the TemperatureRange from
+    ///
+    /// # Returns
+    /// * Result containing the TemperatureRange or an error
+    fn from_str(value: &str) -> Result<Self, StringToTemperatureRangeError> {
+        match value {
+            "Frozen Dry Ice -80C to -20C" => Ok(TemperatureRange::DryIce),
+            "Deep Frozen Dry Ice -70C [+/-10C]" => Ok(TemperatureRange::DryIce),
+            "Cryogenics -190C to -150C" => Ok(TemperatureRange::DryShipper),
+            "Refrigerated +2C to +8C" => Ok(TemperatureRange::Refrigerated),
+            "Controlled Ambient +15C to +25C" => Ok(TemperatureRange::ControlledAmbient),
+            "Frozen -25C to -15C" => Ok(TemperatureRange::Frozen),
+            "Ambient" => Ok(TemperatureRange::Ambient),
+            "Frozen -50C  [+/-10C]" => Ok(TemperatureRange::NonSOP),
+            _ => Err(StringToTemperatureRangeError(value.to_owned())),
+        }
+    }
+}
+
+impl fmt::Display for TemperatureRange {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TemperatureRange::DryIce => write!(f, "Dry Ice"),
+            TemperatureRange::DryShipper => write!(f, "Dry Shipper"),
+            TemperatureRange::Refrigerated => write!(f, "Refrigerated"),
+            TemperatureRange::ControlledAmbient => write!(f, "Controlled Ambient"),
+            TemperatureRange::Frozen => write!(f, "Frozen"),
+            TemperatureRange::Ambient => write!(f, "Ambient"),
+            TemperatureRange::NonSOP => write!(f, "Non SOP"),
+            TemperatureRange::Invalid => write!(f, "Invalid"),
+        }
+    }
+}
+
+/// The PartyRole enum represents the role of a party of a job
+/// * Shipper: The party the freight comes from
+/// * Consignee: The party the freight goes to
+#[derive(serde::Serialize, serde::Deserialize, ts_rs::TS, Debug, Copy, Clone, PartialEq, Eq)]
+pub enum PartyRole {
+    Shipper,
+    Consignee,
+}
+
+impl fmt::Display for PartyRole {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PartyRole::Shipper => write!(f, "Shipper"),
+            PartyRole::Consignee => write!(f, "Consignee"),
+        }
+    }
+}
+
+/// The record of a shipper or consignee
+/// * code: The party code as used by TMS
+/// * name: The name of the party
+/// * address: The street address of the party
+/// * city: The city of the party
+/// * state: The state of the party
+/// * postal_code: The postal code of the party
+/// * country: The country of the party
+#[derive(serde::Serialize, serde::Deserialize, ts_rs::TS, Debug, Clone, Default, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct Party {
+    pub code: String,
+    pub name: String,
+    pub address: String,
+    pub city: String,
+    pub state: String,
+    pub postal_code: String,
+    pub country: String,
+}
+
+impl Party {
+    /// Get the keys used to look up customer specific settings, in the order they are tried.
+    /// The party code is stable, so it is tried first. Settings made before codes were imported are keyed by name,
+    /// so the name is tried as well.
+    ///
+    /// # Returns
+    /// * The party code and the name, without empty and duplicate keys
+    pub fn customer_keys(&self) -> Vec<&str> {
+        let mut keys: Vec<&str> = Vec::with_capacity(2);
+        for key in [self.code.trim(), self.name.trim()] {
+            if !key.is_empty() && !keys.iter().any(|existing| existing.eq_ignore_ascii_case(key)) {
+                keys.push(key);
+            }
+        }
+        keys
+    }
+}
+
+impl fmt::Display for Party {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} ({}), {}, {} {}, {}",
+            self.name, self.code, self.address, self.postal_code, self.city, self.country
+        )
+    }
+}
+
+/// The air freight details of a Transfer or Recovery
+/// * mawb_number: The master air waybill number
+/// * airline: The airline
+/// * cut_off: The latest time the freight can be handed over to the airline
+#[derive(serde::Serialize, serde::Deserialize, ts_rs::TS, Debug, Clone, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct FlightDetails {
+    pub mawb_number: String,
+    pub airline: String,
+    pub cut_off: Option<NaiveDateTime>,
+}
+
+impl fmt::Display for FlightDetails {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.cut_off {
+            Some(cut_off) => write!(f, "{} {} (cut-off {})", self.airline, self.mawb_number, cut_off),
+            None => write!(f, "{} {}", self.airline, self.mawb_number),
+        }
+    }
+}
+
+/// A house air waybill of a job and the temperature ranges listed for it
+/// * number: The hawb number
+/// * temperature_ranges: The temperature ranges of the hawb
+#[derive(serde::Serialize, serde::Deserialize, ts_rs::TS, Debug, Clone, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct Hawb {
+    pub number: String,
+    pub temperature_ranges: Vec<TemperatureRange>,
+}
+
+impl fmt::Display for Hawb {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let temperature_ranges = self
+            .temperature_ranges
+            .iter()
+            .map(|range| range.to_string())
+            .collect::<Vec<String>>()
+            .join(", ");
+        write!(f, "{} ({})", self.number, temperature_ranges)
+    }
+}
+
+/// The JobOrigin enum represents where a job comes from
+/// * Imported: The job was imported from the exports of TMS
+/// * Manual: The job was created by a dispatcher, e.g. for an order taken by phone
+#[derive(serde::Serialize, serde::Deserialize, ts_rs::TS, Debug, Copy, Clone, PartialEq, Eq)]
+pub enum JobOrigin {
+    Imported,
+    Manual,
+}
+
+impl fmt::Display for JobOrigin {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            JobOrigin::Imported => write!(f, "Imported"),
+            JobOrigin::Manual => write!(f, "Manual"),
+        }
+    }
+}
+
+/// The JobRow struct represents a single row of a dispo operation
+/// * mode: The mode of the dispo operation
+/// * job_number: The job number of the dispo operation
+/// * hawb_number: The hawb numbers of the dispo operation, separated by a comma
+/// * hawbs: The hawbs of the dispo operation with their temperature ranges
+/// * temperature_range: The temperature ranges of all hawbs of the dispo operation
+/// * quantity: The pieces, weight and volume of the dispo operation
+/// * shipper: The party the freight comes from
+/// * consignee: The party the freight goes to
+/// * stop: The party that is visited, decided by the mode
+/// * address: The address of the stop
+/// * postal_code: The postal code of the stop
+/// * city: The city of the stop
+/// * country: The country of the stop
+/// * equipment: The equipment of the dispo operation, as found in the Equipment Codes column
+/// * equipment_items: The recognised equipment of the dispo operation
+/// * tolerance: The tolerance of the dispo operation
+/// * tolerance_before: The tolerance before the calculated date
+/// * tolerance_after: The tolerance after the calculated date
+/// * tolerance_policy: The name of the tolerance policy that produced the tolerance
+/// * early_date: The early date of the dispo operation
+/// * late_date: The late date of the dispo operation
+/// * calculated_date: The calculated date of the dispo operation.
+/// * planned_time_strategy: The strategy that produced the calculated date
+/// * contact_name: The name of the stop
+/// * calendar: The result of checking the window and calculated date against the business calendar
+/// * diagnostics: The problems found while importing the dispo operation
+/// * flight: The air freight details, only set for Transfer and Recovery
+/// * origin: Where the dispo operation comes from
+/// * status: Where the dispo operation is in its lifecycle, only changed through JobRow::change_status
+/// * status_history: The status changes of the dispo operation, oldest first
+#[derive(serde::Serialize, serde::Deserialize, ts_rs::TS, Debug, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct JobRow {
+    pub mode: DispoMode,
+    pub job_number: String,
+    pub hawb_number: String,
+    pub hawbs: Vec<Hawb>,
+    pub temperature_ranges: Vec<TemperatureRange>,
+    pub quantity: Quantity,
+    pub shipper: Party,
+    pub consignee: Party,
+    pub stop: PartyRole,
+    pub address: String,
+    pub postal_code: String,
+    pub city: String,
+    pub country: String,
+    pub equipment: String,
+    pub equipment_items: Vec<Equipment>,
+    /// The tolerance of the dispo operation
+    /// This is the larger of tolerance_before and tolerance_after.
+    pub tolerance: i32,
+    pub tolerance_before: i32,
+    pub tolerance_after: i32,
+    pub tolerance_policy: String,
+    pub early_date: NaiveDateTime,
+    pub late_date: NaiveDateTime,
+    /// The calculated date of the dispo operation.
+    /// This is being calculated from the early and late date using the planned_time_strategy.
+    pub calculated_date: NaiveDateTime,
+    pub planned_time_strategy: PlannedTimeStrategy,
+    pub contact_name: String,
+    pub calendar: CalendarCheck,
+    pub diagnostics: Vec<Diagnostic>,
+    pub flight: Option<FlightDetails>,
+    pub origin: JobOrigin,
+    pub status: JobStatus,
+    pub status_history: Vec<StatusChange>,
+}
+
+impl fmt::Display for JobRow {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let temperature_range = self
+            .temperature_ranges
+            .iter()
+            .map(|range| range.to_string())
+            .collect::<Vec<String>>()
+            .join(", ");
+        let hawbs = self.hawbs.iter().map(|hawb| hawb.to_string()).collect::<Vec<String>>().join(", ");
+        let equipment_items = self
+            .equipment_items
+            .iter()
+            .map(|item| item.to_string())
+            .collect::<Vec<String>>()
+            .join(", ");
+        let calendar_issues = self
+            .calendar
+            .issues
+            .iter()
+            .map(|issue| issue.to_string())
+            .collect::<Vec<String>>()
+            .join(", ");
+
+        write!(
+            f,
+            "JobRow {{
+                mode: {},
+                job_number: {},
+                hawb_number: {},
+                hawbs: {},
+                temperature_range: {},
+                quantity: {},
+                shipper: {},
+                consignee: {},
+                stop: {},
+                address: {},
+                postal_code: {},
+                city: {},
+                country: {},
+                equipment: {},
+                equipment_items: {},
+                tolerance: {},
+                tolerance_before: {},
+                tolerance_after: {},
+                tolerance_policy: {},
+                early_date: {},
+                late_date: {},
+                calculated_date: {},
+                planned_time_strategy: {},
+                contact_name: {},
+                calendar_issues: {},
+                flight: {},
+                origin: {},
+                status: {}
+            }}",
+            self.mode,
+            self.job_number,
+            self.hawb_number,
+            hawbs,
+            temperature_range,
+            self.quantity,
+            self.shipper,
+            self.consignee,
+            self.stop,
+            self.address,
+            self.postal_code,
+            self.city,
+            self.country,
+            self.equipment,
+            equipment_items,
+            self.tolerance,
+            self.tolerance_before,
+            self.tolerance_after,
+            self.tolerance_policy,
+            self.early_date,
+            self.late_date,
+            self.calculated_date,
+            self.planned_time_strategy,
+            self.contact_name,
+            calendar_issues,
+            self.flight.as_ref().map(|flight| flight.to_string()).unwrap_or_default(),
+            self.origin,
+            self.status
+        )
+    }
+}
+
+impl JobRow {
+    /// Create a JobRow from the values of a job, applying the planning options like an import does.
+    /// The planned time, cut-off, tolerance, equipment, quantity and calendar check are all derived here,
+    /// so imported and manually created jobs are treated the same.
+    ///
+    /// # Arguments
+    /// * `input` - The values of the job
+    /// * `options` - The options of the import
+    ///
+    /// # Returns
+    /// * A new JobRow with the Imported status
+    pub fn new(input: JobInput, options: &ImportOptions) -> Self {
+        let JobInput {
+            mode,
+            job_number,
+            hawbs,
+            mut diagnostics,
+            shipper,
+            consignee,
+            equipment_codes,
+            quantity_cells,
+            early_date,
+            late_date,
+            flight,
+            origin,
+        } = input;
+
+        let stop = mode.stop();
+        let stop_party = match stop {
+            PartyRole::Shipper => &shipper,
+            PartyRole::Consignee => &consignee,
+        };
+        let customers = stop_party.customer_keys();
+
+        let planned_time_strategy = options.planned_time.resolve(&customers, mode).clone();
+        let mut calculated_date = planned_time_strategy.calculate(early_date, late_date);
+
+        // Freight for the airline has to be there before the cut-off, no matter what the strategy says
+        if let (DispoMode::Transfer, Some(cut_off)) = (mode, flight.as_ref().and_then(|flight| flight.cut_off)) {
+            let (capped, diagnostic) = apply_cut_off(calculated_date, early_date, cut_off, options.planned_time.cut_off_buffer_minutes);
+            calculated_date = capped;
+            diagnostics.extend(diagnostic);
+        }
+
+        let tolerance_policy = options.tolerance_policies.resolve(&customers, &stop_party.country, mode);
+        let tolerance = tolerance_policy.calculate(early_date, late_date, calculated_date);
+
+        let (equipment_items, equipment_diagnostics) = parse_equipment_codes(&equipment_codes, &options.equipment_catalogue);
+        diagnostics.extend(equipment_diagnostics);
+
+        let (quantity, quantity_diagnostics) = Quantity::from_cells(&quantity_cells, options.volumetric_kg_per_m3);
+        diagnostics.extend(quantity_diagnostics);
+
+        let calendar = options.calendar.check(
+            early_date,
+            late_date,
+            calculated_date,
+            &customers,
+            &stop_party.country,
+            &stop_party.state,
+        );
+
+        let address = stop_party.address.clone();
+        let postal_code = stop_party.postal_code.clone();
+        let city = stop_party.city.clone();
+        let country = stop_party.country.clone();
+        let contact_name = stop_party.name.clone();
+
+        let hawb_number = hawbs.iter().map(|hawb| hawb.number.clone()).collect::<Vec<String>>().join(", ");
+        let mut temperature_ranges: Vec<TemperatureRange> = Vec::new();
+        for range in hawbs.iter().flat_map(|hawb| hawb.temperature_ranges.iter()) {
+            if !temperature_ranges.contains(range) {
+                temperature_ranges.push(*range);
+            }
+        }
+        if temperature_ranges.is_empty() {
+            temperature_ranges.push(TemperatureRange::Invalid);
+        }
+
+        JobRow {
+            mode,
+            job_number,
+            hawb_number,
+            hawbs,
+            temperature_ranges,
+            quantity,
+            shipper,
+            consignee,
+            stop,
+            address,
+            postal_code,
+            city,
+            country,
+            equipment: equipment_codes,
+            equipment_items,
+            tolerance: tolerance.symmetric(),
+            tolerance_before: tolerance.before,
+            tolerance_after: tolerance.after,
+            tolerance_policy: tolerance.policy,
+            early_date,
+            late_date,
+            calculated_date,
+            planned_time_strategy,
+            contact_name,
+            calendar,
+            diagnostics,
+            flight,
+            origin,
+            status: JobStatus::Imported,
+            status_history: Vec::new(),
+        }
+    }
+
+    /// Get the id of the job.
+    /// Job numbers are only unique per mode, so the mode is part of the id.
+    ///
+    /// # Returns
+    /// * The id of the job
+    pub fn id(&self) -> String {
+        format!("{}:{}", self.mode, self.job_number)
+    }
+
+    /// Create a vector of JobRow from a polars DataFrame
+    ///
+    /// # Arguments
+    /// * `df` - The DataFrame to create the JobRow from
+    /// * `mode` - The mode of the dispo operation
+    /// * `options` - The options of the import
+    ///
+    /// # Returns
+    /// * Result containing a vector of JobRow or an error
+    pub fn from_dataframe(
+        df: &polars::prelude::DataFrame,
+        mode: DispoMode,
+        options: &ImportOptions,
+    ) -> Result<Vec<JobRow>, ParseFilesError> {
+        Self::from_dataframe_with_task(df, mode, options, &ParseTask::silent())
+    }
+
+    /// Create a vector of JobRow from a polars DataFrame, checking a task for cancellation.
+    /// Behaves like from_dataframe, but stops with ParseFilesError::Cancelled once the task is cancelled.
+    ///
+    /// # Arguments
+    /// * `df` - The DataFrame to create the JobRow from
+    /// * `mode` - The mode of the dispo operation
+    /// * `options` - The options of the import
+    /// * `task` - The task to check for cancellation
+    ///
+    /// # Returns
+    /// * Result containing a vector of JobRow or an error
+    pub fn from_dataframe_with_task(
+        df: &polars::prelude::DataFrame,
+        mode: DispoMode,
+        options: &ImportOptions,
+        task: &ParseTask,
+    ) -> Result<Vec<JobRow>, ParseFilesError> {
+        let column_mapping = ColumnMapping::new(mode);
+
+        let job_numbers = extract_column_as_string(df, column_mapping.job_number)?;
+        let hawb_numbers = extract_optional_column_as_text(df, column_mapping.hawb);
+        let temperature_ranges = extract_column_as_temperature_ranges(df, column_mapping.temperature_range)?;
+        let shippers = extract_parties(df, &column_mapping.shipper)?;
+        let consignees = extract_parties(df, &column_mapping.consignee)?;
+        let pieces = extract_optional_column_as_text(df, column_mapping.quantity);
+        let gross_weights = extract_optional_column_as_text(df, column_mapping.gross_weight);
+        let weight_units = extract_optional_column_as_text(df, column_mapping.weight_unit);
+        let chargeable_weights = extract_optional_column_as_text(df, column_mapping.chargeable_weight);
+        let volumes = extract_optional_column_as_text(df, column_mapping.volume);
+        let volume_units = extract_optional_column_as_text(df, column_mapping.volume_unit);
+        let equipment = extract_column_as_string(df, column_mapping.equipment_codes)?;
+        let early_dates: Vec<Option<NaiveDateTime>> = df
+            .column(column_mapping.target_early)?
+            .iter()
+            .map(|cell| any_value_to_naive_date_time(&cell, TMS_DATE_FORMAT).ok())
+            .collect();
+        let late_dates: Vec<Option<NaiveDateTime>> = df
+            .column(column_mapping.target_late)?
+            .iter()
+            .map(|cell| any_value_to_naive_date_time(&cell, TMS_DATE_FORMAT).ok())
+            .collect();
+
+        // Air freight columns are optional, jobs without them get a diagnostic instead of failing the import
+        let mawb_numbers = match column_mapping.mawb {
+            Some(column) => extract_optional_column_as_text(df, column),
+            None => Vec::new(),
+        };
+        let airlines = match column_mapping.airline {
+            Some(column) => extract_optional_column_as_text(df, column),
+            None => Vec::new(),
+        };
+        let cut_offs: Vec<Option<NaiveDateTime>> = match column_mapping.cut_off.map(|column| df.column(column)) {
+            Some(Ok(column)) => column
+                .iter()
+                .map(|cell| any_value_to_naive_date_time(&cell, TMS_DATE_FORMAT).ok())
+                .collect(),
+            _ => Vec::new(),
+        };
+
+        // A load with several hawbs has one row per hawb after the join, those rows are merged into one job
+        let mut loads: Vec<Vec<usize>> = Vec::new();
+        let mut load_indices: HashMap<&str, usize> = HashMap::new();
+        for (index, job_number) in job_numbers.iter().enumerate() {
+            match load_indices.get(job_number.as_str()) {
+                Some(load) => loads[*load].push(index),
+                None => {
+                    load_indices.insert(job_number, loads.len());
+                    loads.push(vec![index]);
+                }
+            }
+        }
+
+        let mut result = Vec::<JobRow>::with_capacity(loads.len());
+
+        for rows in loads {
+            task.check()?;
+            // All rows of a load share the CL View columns, so they are read from the first row
+            let index = rows[0];
+            let (hawbs, mut diagnostics) = aggregate_hawbs(&rows, &hawb_numbers, &temperature_ranges);
+
+            let flight = mode.has_flight_details().then(|| FlightDetails {
+                mawb_number: mawb_numbers.get(index).cloned().flatten().unwrap_or_default(),
+                airline: airlines.get(index).cloned().flatten().unwrap_or_default(),
+                cut_off: cut_offs.get(index).cloned().flatten(),
+            });
+            if let Some(flight) = &flight {
+                diagnostics.extend(missing_flight_details(mode, flight));
+            }
+
+            // The job is kept so the dispatcher sees it, but its window can not be trusted
+            let early_date = early_dates.get(index).cloned().flatten();
+            let late_date = late_dates.get(index).cloned().flatten();
+            for (date, column) in [(early_date, column_mapping.target_early), (late_date, column_mapping.target_late)] {
+                if date.is_none() {
+                    diagnostics.push(Diagnostic::error(DiagnosticKind::MissingTargetDate { field: column.to_string() }));
+                }
+            }
+            if let (Some(early_date), Some(late_date)) = (early_date, late_date) {
+                if late_date < early_date {
+                    diagnostics.push(Diagnostic::error(DiagnosticKind::InvertedWindow { early_date, late_date }));
+                }
+            }
+
+            let input = JobInput {
+                mode,
+                job_number: job_numbers.get(index).unwrap_or(&String::new()).to_string(),
+                hawbs,
+                diagnostics,
+                shipper: shippers.get(index).cloned().unwrap_or_default(),
+                consignee: consignees.get(index).cloned().unwrap_or_default(),
+                equipment_codes: equipment.get(index).unwrap_or(&String::new()).to_string(),
+                quantity_cells: QuantityCells {
+                    pieces: pieces.get(index).cloned().flatten(),
+                    gross_weight: gross_weights.get(index).cloned().flatten(),
+                    weight_unit: weight_units.get(index).cloned().flatten(),
+                    chargeable_weight: chargeable_weights.get(index).cloned().flatten(),
+                    volume: volumes.get(index).cloned().flatten(),
+                    volume_unit: volume_units.get(index).cloned().flatten(),
+                },
+                early_date: early_date.unwrap_or_default(),
+                late_date: late_date.unwrap_or_default(),
+                flight,
+                origin: JobOrigin::Imported,
+            };
+
+            result.push(JobRow::new(input, options));
+        }
+
+        Ok(result)
+    }
+}
+
+/// The values of a job before the planning options are applied, see JobRow::new
+/// * mode: The mode of the job
+/// * job_number: The job number of the job
+/// * hawbs: The hawbs of the job with their temperature ranges
+/// * diagnostics: The problems already found, e.g. while merging the hawbs of a load
+/// * shipper: The party the freight comes from
+/// * consignee: The party the freight goes to
+/// * equipment_codes: The equipment codes, as found in the Equipment Codes column
+/// * quantity_cells: The raw pieces, weight and volume
+/// * early_date: The early date of the job
+/// * late_date: The late date of the job
+/// * flight: The air freight details, only set for Transfer and Recovery
+/// * origin: Where the job comes from
+#[derive(Debug, Clone)]
+pub struct JobInput {
+    pub mode: DispoMode,
+    pub job_number: String,
+    pub hawbs: Vec<Hawb>,
+    pub diagnostics: Vec<Diagnostic>,
+    pub shipper: Party,
+    pub consignee: Party,
+    pub equipment_codes: String,
+    pub quantity_cells: QuantityCells,
+    pub early_date: NaiveDateTime,
+    pub late_date: NaiveDateTime,
+    pub flight: Option<FlightDetails>,
+    pub origin: JobOrigin,
+}
+
+/// Extract a column from a DataFrame as a vector of strings
+///
+/// # Arguments
+/// * `df` - The DataFrame to extract the column from
+/// * `column_name` - The name of the column to extract
+///
+/// # Returns
+/// * Result containing a vector of strings or an error
+pub fn extract_column_as_string(df: &DataFrame, column_name: &str) -> Result<Vec<String>, polars::prelude::PolarsError> {
+    Ok(df
+        .column(column_name)?
+        .iter()
+        .map(|cell| match cell {
+            polars::prelude::AnyValue::Utf8(s) => s.to_owned(),
+            polars::prelude::AnyValue::Utf8Owned(s) => s.to_string(),
+            _ => cell.to_string(),
+        })
+        .collect())
+}
+
+/// Collect the hawbs of all rows of one load.
+/// A hawb listed in more than one row is only kept once.
+/// Several hawbs are expected and only noted, unless they need different temperature ranges.
+///
+/// # Arguments
+/// * `rows` - The indices of the rows of the load
+/// * `hawb_numbers` - The hawb numbers of all rows, None if the row has no Shipper Site data
+/// * `temperature_ranges` - The temperature ranges of all rows
+///
+/// # Returns
+/// * The hawbs of the load and the diagnostics for an unexpected relationship
+fn aggregate_hawbs(
+    rows: &[usize],
+    hawb_numbers: &[Option<String>],
+    temperature_ranges: &[Vec<TemperatureRange>],
+) -> (Vec<Hawb>, Vec<Diagnostic>) {
+    let mut hawbs: Vec<Hawb> = Vec::new();
+    let mut diagnostics: Vec<Diagnostic> = Vec::new();
+
+    for index in rows {
+        let number = match hawb_numbers.get(*index).cloned().flatten() {
+            Some(number) => number.trim().to_string(),
+            None => continue,
+        };
+        let ranges = temperature_ranges.get(*index).cloned().unwrap_or(vec![TemperatureRange::Invalid]);
+
+        match hawbs.iter_mut().find(|hawb| hawb.number == number) {
+            Some(existing) => {
+                for range in ranges {
+                    if !existing.temperature_ranges.contains(&range) {
+                        existing.temperature_ranges.push(range);
+                    }
+                }
+                let diagnostic = Diagnostic::info(DiagnosticKind::DuplicateHawb { hawb: number });
+                if !diagnostics.contains(&diagnostic) {
+                    diagnostics.push(diagnostic);
+                }
+            }
+            None => hawbs.push(Hawb {
+                number,
+                temperature_ranges: ranges,
+            }),
+        }
+    }
+
+    if hawbs.is_empty() {
+        diagnostics.push(Diagnostic::warning(DiagnosticKind::MissingShipperSiteRow));
+    } else if hawbs.len() > 1 {
+        let numbers = hawbs.iter().map(|hawb| hawb.number.clone()).collect();
+        diagnostics.push(Diagnostic::info(DiagnosticKind::MultipleHawbs { hawbs: numbers }));
+
+        let mut ranges: Vec<TemperatureRange> = Vec::new();
+        for range in hawbs.iter().flat_map(|hawb| hawb.temperature_ranges.iter()) {
+            if !ranges.contains(range) {
+                ranges.push(*range);
+            }
+        }
+        if ranges.len() > 1 {
+            diagnostics.push(Diagnostic::warning(DiagnosticKind::ConflictingTemperatureRanges { ranges }));
+        }
+    }
+
+    (hawbs, diagnostics)
+}
+
+/// Extract the party records from a DataFrame
+///
+/// # Arguments
+/// * `df` - The DataFrame to extract the parties from
+/// * `columns` - The columns of the party record
+///
+/// # Returns
+/// * Result containing one Party per row or an error
+fn extract_parties(df: &DataFrame, columns: &PartyColumns) -> Result<Vec<Party>, polars::prelude::PolarsError> {
+    let codes = extract_column_as_string(df, columns.code)?;
+    let names = extract_column_as_string(df, columns.name)?;
+    let addresses = extract_column_as_string(df, columns.address)?;
+    let cities = extract_column_as_string(df, columns.city)?;
+    let states = extract_column_as_string(df, columns.state)?;
+    let postal_codes = extract_column_as_string(df, columns.postal_code)?;
+    let countries = extract_column_as_string(df, columns.country)?;
+
+    Ok(codes
+        .into_iter()
+        .zip(names)
+        .zip(addresses)
+        .zip(cities)
+        .zip(states)
+        .zip(postal_codes)
+        .zip(countries)
+        .map(|((((((code, name), address), city), state), postal_code), country)| Party {
+            code,
+            name,
+            address,
+            city,
+            state,
+            postal_code,
+            country,
+        })
+        .collect())
+}
+
+/// Extract the temperature ranges from a string
+/// The string must be a comma separated list of temperature ranges
+/// If the string is empty, the Ambient temperature range is returned
+///
+/// # Arguments
+/// * `input` - The string to extract the temperature ranges from
+///
+/// # Returns
+/// * A vector of TemperatureRange
+fn extract_column_as_temperature_ranges(
+    df: &DataFrame,
+    column_name: &str,
+) -> Result<Vec<Vec<TemperatureRange>>, polars::prelude::PolarsError> {
+    Ok(df
+        .column(column_name)?
+        .iter()
+        .map(|cell| match cell {
+            polars::prelude::AnyValue::Utf8(s) => extract_temperature_ranges(s),
+            polars::prelude::AnyValue::Utf8Owned(s) => extract_temperature_ranges(&s),
+            _ => vec![TemperatureRange::Ambient],
+        })
+        .collect())
+}
+
+/// Extract the temperature ranges from a string
+/// The string must be a comma separated list of temperature ranges or a single temperature range
+/// If the string is empty, the Ambient temperature range is returned
+/// If the string is invalid, the Invalid temperature range is returned
+///
+/// # Arguments
+/// * `input` - The string to extract the temperature ranges from
+///
+/// # Returns
+/// * A vector of TemperatureRange
+fn extract_temperature_ranges(input: &str) -> Vec<TemperatureRange> {
+    if input.is_empty() {
+        return vec![TemperatureRange::Ambient];
+    }
+
+    let splitted: Vec<&str> = input.split(",").collect();
+    splitted
+        .iter()
+        .map(|s| TemperatureRange::from_str(s.trim()).unwrap_or(TemperatureRange::Invalid))
+        .collect()
+}
+
+/// Check the air freight details of a Transfer or Recovery for missing values.
+/// The cut-off is only needed when the freight is handed over to the airline.
+///
+/// # Arguments
+/// * `mode` - The mode of the job
+/// * `flight` - The air freight details of the job
+///
+/// # Returns
+/// * A warning naming the missing details, or None if all details are there
+fn missing_flight_details(mode: DispoMode, flight: &FlightDetails) -> Option<Diagnostic> {
+    let mut fields = Vec::new();
+    if flight.mawb_number.trim().is_empty() {
+        fields.push("mawbNumber".to_string());
+    }
+    if flight.airline.trim().is_empty() {
+        fields.push("airline".to_string());
+    }
+    if mode == DispoMode::Transfer && flight.cut_off.is_none() {
+        fields.push("cutOff".to_string());
+    }
+    (!fields.is_empty()).then(|| Diagnostic::warning(DiagnosticKind::MissingFlightDetails { fields }))
+}
+
+/// Extract an optional column from a DataFrame as a vector of optional strings
+/// Empty cells are None. If the column does not exist, all cells are None.
+///
+/// # Arguments
+/// * `df` - The DataFrame to extract the column from
+/// * `column_name` - The name of the column to extract
+///
+/// # Returns
+/// * A vector of optional strings with one entry per row
+fn extract_optional_column_as_text(df: &DataFrame, column_name: &str) -> Vec<Option<String>> {
+    match df.column(column_name) {
+        Ok(column) => column
+            .iter()
+            .map(|cell| match cell {
+                polars::prelude::AnyValue::Null => None,
+                polars::prelude::AnyValue::Utf8(s) => Some(s.to_owned()),
+                polars::prelude::AnyValue::Utf8Owned(s) => Some(s.to_string()),
+                _ => Some(cell.to_string()),
+            })
+            .map(|cell| cell.filter(|s| !s.trim().is_empty()))
+            .collect(),
+        Err(_) => vec![None; df.height()],
+    }
+}
+
+/// Error type for the any_value_to_naive_date_time function
+///
+/// # Variants
+/// * InvalidType: The AnyValue is not a string
+/// * ParseError: The AnyValue is a string but could not be parsed
+#[derive(Debug)]
+pub enum AnyValueToNaiveDateTimeParseError {
+    InvalidType(String),
+    ParseError(String),
+}
+
+impl fmt::Display for AnyValueToNaiveDateTimeParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AnyValueToNaiveDateTimeParseError::InvalidType(value) => write!(f, "Value can not parsed to NaiveDateTime: {}", value),
+            AnyValueToNaiveDateTimeParseError::ParseError(value) => write!(f, "Error parsing string to NaiveDateTime: {}", value),
+        }
+    }
+}
+
+impl std::error::Error for AnyValueToNaiveDateTimeParseError {}
+
+/// Convert a polars AnyValue to a NaiveDateTime
+/// The format string must be a valid format string for the NaiveDateTime::parse_from_str function
+///
+/// # Arguments
+/// * `value` - The AnyValue to convert
+/// * `format` - The format string to use for parsing
+///
+/// # Returns
+/// * Result containing the NaiveDateTime or an error
+fn any_value_to_naive_date_time(
+    value: &polars::prelude::AnyValue,
+    format: &str,
+) -> Result<NaiveDateTime, AnyValueToNaiveDateTimeParseError> {
+    match value {
+        polars::prelude::AnyValue::Utf8(date_str) => {
+            if let Ok(d) = NaiveDateTime::parse_from_str(date_str, format) {
+                Ok(d)
+            } else {
+                Err(AnyValueToNaiveDateTimeParseError::ParseError(value.to_string()))
+            }
+        }
+        polars::prelude::AnyValue::Utf8Owned(date_str) => {
+            if let Ok(d) = NaiveDateTime::parse_from_str(date_str, format) {
+                Ok(d)
+            } else {
+                Err(AnyValueToNaiveDateTimeParseError::ParseError(value.to_string()))
+            }
+        }
+        _ => Err(AnyValueToNaiveDateTimeParseError::InvalidType(value.to_string())),
+    }
+}
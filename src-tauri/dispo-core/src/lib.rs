@@ -0,0 +1,48 @@
+// Copyright 2023 Felix Kahle. All rights reserved.
+
+//! Parsing of the CL View and Shipper Site exports of TMS and the domain model of Dispo Master.
+//!
+//! The crate does not depend on Tauri, so it can be used by the app, the CLI and other tools alike.
+//! The usual entry point is [`create_job_rows`], which turns both exports into [`JobRow`]s:
+//!
+//! ```no_run
+//! use dispo_core::{create_job_rows, DispoMode, ImportOptions};
+//!
+//! let jobs = create_job_rows("cl_view.xls", "shipper_site.xls", DispoMode::Delivery, &ImportOptions::default())?;
+//! for job in &jobs {
+//!     println!("{} {}", job.job_number, job.calculated_date);
+//! }
+//! # Ok::<(), dispo_core::ParseFilesError>(())
+//! ```
+
+pub mod audit_log;
+pub mod calendar;
+pub mod diagnostics;
+pub mod dry_ice;
+pub mod equipment;
+pub mod export;
+pub mod file_parsing;
+pub mod icalendar;
+pub mod job_row;
+pub mod lifecycle;
+pub mod manual_job;
+pub mod parse_error;
+pub mod parse_task;
+pub mod plan_file;
+pub mod planned_time;
+pub mod planning_workbook;
+pub mod quantity;
+pub mod shipment;
+pub mod tms_export;
+pub mod tolerance;
+pub mod workbook_cache;
+
+pub use audit_log::{AuditAction, AuditEntries, AuditEntry, AuditLog, AuditLogError, AuditQuery};
+pub use diagnostics::{Diagnostic, DiagnosticKind, Severity};
+pub use file_parsing::{create_job_rows, create_job_rows_from_sheets, ColumnMapping, ImportOptions};
+pub use job_row::{DispoMode, JobOrigin, JobRow, Party, PartyRole, TemperatureRange};
+pub use lifecycle::{JobStatus, StatusChange, StatusChangeError, StatusReason};
+pub use manual_job::{FieldError, JobEditError, ManualJob};
+pub use parse_error::ParseFilesError;
+pub use parse_task::{ParseProgress, ParseStage, ParseTask};
+pub use plan_file::{PlanFile, PlanFileError};
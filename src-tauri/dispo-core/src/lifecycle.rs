@@ -0,0 +1,292 @@
+// Copyright 2023 Felix Kahle. All rights reserved.
+
+use crate::job_row::JobRow;
+use chrono::{NaiveDateTime, Utc};
+use std::fmt::{self};
+
+/// The JobStatus enum represents where a job is in its lifecycle
+/// * Imported: The job is known, but not planned yet. Manually created jobs start here as well.
+/// * Planned: The job has a planned date
+/// * Assigned: The job is assigned to a driver and vehicle
+/// * Dispatched: The driver is on the way
+/// * PickedUp: The freight was collected
+/// * Delivered: The freight was handed over, the job is done
+/// * Exception: Something went wrong and the job needs a dispatcher
+/// * Cancelled: The job will not be done
+#[derive(serde::Serialize, serde::Deserialize, ts_rs::TS, Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum JobStatus {
+    Imported,
+    Planned,
+    Assigned,
+    Dispatched,
+    PickedUp,
+    Delivered,
+    Exception,
+    Cancelled,
+}
+
+impl fmt::Display for JobStatus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            JobStatus::Imported => write!(f, "Imported"),
+            JobStatus::Planned => write!(f, "Planned"),
+            JobStatus::Assigned => write!(f, "Assigned"),
+            JobStatus::Dispatched => write!(f, "Dispatched"),
+            JobStatus::PickedUp => write!(f, "Picked Up"),
+            JobStatus::Delivered => write!(f, "Delivered"),
+            JobStatus::Exception => write!(f, "Exception"),
+            JobStatus::Cancelled => write!(f, "Cancelled"),
+        }
+    }
+}
+
+impl JobStatus {
+    /// Get the statuses a job can change to from this status.
+    /// Planning can be undone until the driver is on the way, a job with an exception can be planned again.
+    ///
+    /// # Returns
+    /// * The allowed next statuses, empty for Delivered and Cancelled
+    pub fn next(&self) -> &'static [JobStatus] {
+        match self {
+            JobStatus::Imported => &[JobStatus::Planned, JobStatus::Cancelled],
+            JobStatus::Planned => &[JobStatus::Assigned, JobStatus::Imported, JobStatus::Cancelled],
+            JobStatus::Assigned => &[JobStatus::Dispatched, JobStatus::Planned, JobStatus::Cancelled],
+            JobStatus::Dispatched => &[JobStatus::PickedUp, JobStatus::Exception, JobStatus::Cancelled],
+            JobStatus::PickedUp => &[JobStatus::Delivered, JobStatus::Exception],
+            JobStatus::Exception => &[JobStatus::Planned, JobStatus::Cancelled],
+            JobStatus::Delivered | JobStatus::Cancelled => &[],
+        }
+    }
+
+    /// Check whether a job can change from this status to another one
+    ///
+    /// # Arguments
+    /// * `to` - The new status
+    ///
+    /// # Returns
+    /// * True if the transition is allowed
+    pub fn can_change_to(&self, to: JobStatus) -> bool {
+        self.next().contains(&to)
+    }
+
+    /// Check whether a transition to this status needs a reason
+    ///
+    /// # Returns
+    /// * True for Exception and Cancelled
+    pub fn needs_reason(&self) -> bool {
+        matches!(self, JobStatus::Exception | JobStatus::Cancelled)
+    }
+}
+
+/// The StatusReason enum represents why the status of a job was changed
+/// * CustomerRequest: The shipper or consignee asked for the change
+/// * CustomerUnavailable: Nobody was there to hand over or take over the freight
+/// * AddressNotFound: The driver could not find the address
+/// * FreightNotReady: The freight was not ready for pickup
+/// * Refused: The consignee refused the freight
+/// * Damaged: The freight or its packaging is damaged
+/// * TemperatureExcursion: The freight left its temperature range
+/// * VehicleBreakdown: The vehicle broke down
+/// * MissedCutOff: The freight did not reach the airline before the cut-off
+/// * Other: Any other reason, explained in the note
+#[derive(serde::Serialize, serde::Deserialize, ts_rs::TS, Debug, Copy, Clone, PartialEq, Eq)]
+pub enum StatusReason {
+    CustomerRequest,
+    CustomerUnavailable,
+    AddressNotFound,
+    FreightNotReady,
+    Refused,
+    Damaged,
+    TemperatureExcursion,
+    VehicleBreakdown,
+    MissedCutOff,
+    Other,
+}
+
+impl fmt::Display for StatusReason {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            StatusReason::CustomerRequest => write!(f, "Customer request"),
+            StatusReason::CustomerUnavailable => write!(f, "Customer unavailable"),
+            StatusReason::AddressNotFound => write!(f, "Address not found"),
+            StatusReason::FreightNotReady => write!(f, "Freight not ready"),
+            StatusReason::Refused => write!(f, "Refused"),
+            StatusReason::Damaged => write!(f, "Damaged"),
+            StatusReason::TemperatureExcursion => write!(f, "Temperature excursion"),
+            StatusReason::VehicleBreakdown => write!(f, "Vehicle breakdown"),
+            StatusReason::MissedCutOff => write!(f, "Missed cut-off"),
+            StatusReason::Other => write!(f, "Other"),
+        }
+    }
+}
+
+/// A change of the status of a job
+/// * from: The status before the change
+/// * to: The status after the change
+/// * at: When the status was changed, in UTC
+/// * reason: Why the status was changed, always set for Exception and Cancelled
+/// * note: A free text explanation, empty if there is none
+#[derive(serde::Serialize, serde::Deserialize, ts_rs::TS, Debug, Clone, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct StatusChange {
+    pub from: JobStatus,
+    pub to: JobStatus,
+    pub at: NaiveDateTime,
+    pub reason: Option<StatusReason>,
+    #[serde(default)]
+    #[ts(optional, as = "Option<String>")]
+    pub note: String,
+}
+
+impl fmt::Display for StatusChange {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} {} -> {}", self.at, self.from, self.to)?;
+        if let Some(reason) = self.reason {
+            write!(f, " ({})", reason)?;
+        }
+        if !self.note.is_empty() {
+            write!(f, ": {}", self.note)?;
+        }
+        Ok(())
+    }
+}
+
+/// This error includes all errors that can occur while changing the status of a job
+///
+/// # Variants
+/// * `NotAllowed` - The job can not change from its status to the new one
+/// * `ReasonRequired` - The new status needs a reason
+/// * `UnknownJob` - There is no job with the id
+/// * `InconsistentHistory` - The status of the job with the id does not follow from its status history
+#[derive(Debug)]
+pub enum StatusChangeError {
+    NotAllowed((JobStatus, JobStatus)),
+    ReasonRequired(JobStatus),
+    UnknownJob(String),
+    InconsistentHistory(String),
+}
+
+impl fmt::Display for StatusChangeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            StatusChangeError::NotAllowed((from, to)) => {
+                write!(f, "NotAllowed: A job can not change from {} to {}", from, to)
+            }
+            StatusChangeError::ReasonRequired(status) => write!(f, "ReasonRequired: A reason is required for {}", status),
+            StatusChangeError::UnknownJob(id) => write!(f, "UnknownJob: There is no job {}", id),
+            StatusChangeError::InconsistentHistory(id) => write!(
+                f,
+                "InconsistentHistory: The status of the job {} does not follow from its status history",
+                id
+            ),
+        }
+    }
+}
+
+impl std::error::Error for StatusChangeError {}
+
+impl serde::Serialize for StatusChangeError {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        serializer.serialize_str(&self.to_string())
+    }
+}
+
+impl JobRow {
+    /// Change the status of the job and record the change in its history
+    ///
+    /// # Arguments
+    /// * `to` - The new status
+    /// * `reason` - Why the status is changed, required for Exception and Cancelled
+    /// * `note` - A free text explanation, may be empty
+    /// * `at` - When the status was changed
+    ///
+    /// # Returns
+    /// * Result containing the recorded change or an error if the transition is not allowed
+    pub fn change_status(
+        &mut self,
+        to: JobStatus,
+        reason: Option<StatusReason>,
+        note: &str,
+        at: NaiveDateTime,
+    ) -> Result<&StatusChange, StatusChangeError> {
+        if !self.status.can_change_to(to) {
+            return Err(StatusChangeError::NotAllowed((self.status, to)));
+        }
+        if to.needs_reason() && reason.is_none() {
+            return Err(StatusChangeError::ReasonRequired(to));
+        }
+
+        self.status_history.push(StatusChange {
+            from: self.status,
+            to,
+            at,
+            reason,
+            note: note.trim().to_string(),
+        });
+        self.status = to;
+        Ok(&self.status_history[self.status_history.len() - 1])
+    }
+
+    /// Check that the status of the job follows from its history.
+    /// The history has to start at Imported, every change has to start where the one before ended and has to be allowed,
+    /// and the last change has to end at the status of the job.
+    /// Jobs come back from the frontend and from files, so a status that was changed without change_status is found here.
+    ///
+    /// # Returns
+    /// * Result containing nothing or an error if the status does not follow from the history
+    pub fn check_status_history(&self) -> Result<(), StatusChangeError> {
+        let mut status = JobStatus::Imported;
+        for change in &self.status_history {
+            if change.from != status || !status.can_change_to(change.to) || (change.to.needs_reason() && change.reason.is_none()) {
+                return Err(StatusChangeError::InconsistentHistory(self.id()));
+            }
+            status = change.to;
+        }
+        match status == self.status {
+            true => Ok(()),
+            false => Err(StatusChangeError::InconsistentHistory(self.id())),
+        }
+    }
+}
+
+/// Check that the status of every job follows from its history, see JobRow::check_status_history
+///
+/// # Arguments
+/// * `jobs` - The jobs to check
+///
+/// # Returns
+/// * Result containing nothing or the error of the first job whose status does not follow from its history
+pub fn check_status_histories(jobs: &[JobRow]) -> Result<(), StatusChangeError> {
+    jobs.iter().try_for_each(JobRow::check_status_history)
+}
+
+/// Change the status of one of the jobs now.
+/// The jobs are rejected if the status of one of them does not follow from its history.
+///
+/// # Arguments
+/// * `jobs` - The current jobs
+/// * `id` - The id of the job, see JobRow::id
+/// * `to` - The new status
+/// * `reason` - Why the status is changed, required for Exception and Cancelled
+/// * `note` - A free text explanation, may be empty
+///
+/// # Returns
+/// * Result containing the jobs with the changed job or an error
+pub fn change_job_status(
+    mut jobs: Vec<JobRow>,
+    id: &str,
+    to: JobStatus,
+    reason: Option<StatusReason>,
+    note: &str,
+) -> Result<Vec<JobRow>, StatusChangeError> {
+    check_status_histories(&jobs)?;
+    let job = jobs
+        .iter_mut()
+        .find(|job| job.id() == id)
+        .ok_or_else(|| StatusChangeError::UnknownJob(id.to_string()))?;
+    job.change_status(to, reason, note, Utc::now().naive_utc())?;
+    Ok(jobs)
+}
@@ -0,0 +1,421 @@
+// Copyright 2023 Felix Kahle. All rights reserved.
+
+use crate::{
+    diagnostics::DiagnosticKind,
+    file_parsing::ImportOptions,
+    job_row::{DispoMode, FlightDetails, Hawb, JobInput, JobOrigin, JobRow, Party, PartyRole, TemperatureRange, TMS_DATE_FORMAT},
+    lifecycle::{self, StatusChangeError},
+    quantity::{Quantity, QuantityCells},
+};
+use chrono::NaiveDateTime;
+use std::fmt::{self};
+
+/// The formats accepted for the dates of a manual job.
+/// The first ones are sent by date inputs of the frontend, the last one is the format of TMS.
+const MANUAL_DATE_FORMATS: [&str; 3] = ["%Y-%m-%dT%H:%M", "%Y-%m-%dT%H:%M:%S", TMS_DATE_FORMAT];
+
+/// A field of a manual job that did not pass the validation
+/// * field: The path of the field in camelCase, like "consignee.postalCode" or "hawbs.0.number"
+/// * message: Why the value is not valid
+#[derive(serde::Serialize, serde::Deserialize, ts_rs::TS, Debug, Clone, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct FieldError {
+    pub field: String,
+    pub message: String,
+}
+
+impl FieldError {
+    /// Create a new FieldError
+    ///
+    /// # Arguments
+    /// * `field` - The path of the field
+    /// * `message` - Why the value is not valid
+    ///
+    /// # Returns
+    /// * A new FieldError
+    pub fn new(field: impl Into<String>, message: impl Into<String>) -> Self {
+        Self {
+            field: field.into(),
+            message: message.into(),
+        }
+    }
+}
+
+impl fmt::Display for FieldError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {}", self.field, self.message)
+    }
+}
+
+/// This error includes all errors that can occur while creating, editing or deleting jobs manually
+///
+/// # Variants
+/// * `Invalid` - The job did not pass the validation
+/// * `UnknownJob` - There is no job with the id
+/// * `Status` - The status of one of the current jobs does not follow from its history
+#[derive(Debug)]
+pub enum JobEditError {
+    Invalid(Vec<FieldError>),
+    UnknownJob(String),
+    Status(StatusChangeError),
+}
+
+impl From<StatusChangeError> for JobEditError {
+    fn from(error: StatusChangeError) -> Self {
+        JobEditError::Status(error)
+    }
+}
+
+impl fmt::Display for JobEditError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            JobEditError::Invalid(errors) => write!(
+                f,
+                "InvalidJob: {}",
+                errors.iter().map(|error| error.to_string()).collect::<Vec<String>>().join(", ")
+            ),
+            JobEditError::UnknownJob(id) => write!(f, "UnknownJob: There is no job {}", id),
+            JobEditError::Status(error) => write!(f, "{}", error),
+        }
+    }
+}
+
+impl std::error::Error for JobEditError {}
+
+/// What a failed job edit sends to the frontend.
+/// Unlike other errors it is not only a message, the field errors let the form mark the invalid fields.
+/// * message: The message of the error
+/// * field_errors: The fields that did not pass the validation, empty if the error is not about a field
+#[derive(serde::Serialize, ts_rs::TS, Debug, Clone, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct JobEditRejection {
+    pub message: String,
+    pub field_errors: Vec<FieldError>,
+}
+
+impl serde::Serialize for JobEditError {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        let field_errors = match self {
+            JobEditError::Invalid(errors) => errors.clone(),
+            JobEditError::UnknownJob(_) | JobEditError::Status(_) => Vec::new(),
+        };
+        JobEditRejection {
+            message: self.to_string(),
+            field_errors,
+        }
+        .serialize(serializer)
+    }
+}
+
+/// The air freight details of a manual Transfer or Recovery
+/// * mawb_number: The master air waybill number
+/// * airline: The airline
+/// * cut_off: The latest time the freight can be handed over to the airline, empty if unknown
+#[derive(serde::Serialize, serde::Deserialize, ts_rs::TS, Debug, Clone, Default, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct ManualFlight {
+    pub mawb_number: String,
+    pub airline: String,
+    #[serde(default)]
+    pub cut_off: String,
+}
+
+/// A job as entered by a dispatcher, e.g. for an order taken by phone that never goes through TMS.
+/// The values are kept as entered, so invalid ones can be reported field by field instead of failing the whole request.
+/// * mode: The mode of the job
+/// * job_number: The job number of the job, unique per mode
+/// * hawbs: The hawbs of the job with their temperature ranges
+/// * shipper: The party the freight comes from
+/// * consignee: The party the freight goes to
+/// * early_date: The early date, like "2024-03-04T08:00"
+/// * late_date: The late date, like "2024-03-04T12:00"
+/// * equipment: The equipment codes, like in the Equipment Codes column of TMS
+/// * quantity: The pieces, weight and volume as entered, units are parsed like in the imports
+/// * flight: The air freight details, needed for Transfer and Recovery
+#[derive(serde::Serialize, serde::Deserialize, ts_rs::TS, Debug, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct ManualJob {
+    pub mode: DispoMode,
+    pub job_number: String,
+    pub hawbs: Vec<Hawb>,
+    pub shipper: Party,
+    pub consignee: Party,
+    pub early_date: String,
+    pub late_date: String,
+    #[serde(default)]
+    #[ts(optional, as = "Option<String>")]
+    pub equipment: String,
+    #[serde(default)]
+    #[ts(optional, as = "Option<QuantityCells>")]
+    pub quantity: QuantityCells,
+    #[serde(default)]
+    pub flight: Option<ManualFlight>,
+}
+
+/// The form of an existing job, to edit it.
+/// Weights and volumes are given without a unit, which the imports read as kilograms and cubic meters.
+impl From<&JobRow> for ManualJob {
+    fn from(job: &JobRow) -> Self {
+        let number = |value: Option<f64>| value.map(|value| value.to_string());
+        Self {
+            mode: job.mode,
+            job_number: job.job_number.clone(),
+            hawbs: job.hawbs.clone(),
+            shipper: job.shipper.clone(),
+            consignee: job.consignee.clone(),
+            early_date: job.early_date.format(MANUAL_DATE_FORMATS[0]).to_string(),
+            late_date: job.late_date.format(MANUAL_DATE_FORMATS[0]).to_string(),
+            equipment: job.equipment.clone(),
+            quantity: QuantityCells {
+                pieces: job.quantity.pieces.map(|pieces| pieces.to_string()),
+                gross_weight: number(job.quantity.gross_weight_kg),
+                weight_unit: None,
+                chargeable_weight: number(job.quantity.chargeable_weight_kg),
+                volume: number(job.quantity.volume_m3),
+                volume_unit: None,
+            },
+            flight: job.flight.as_ref().map(|flight| ManualFlight {
+                mawb_number: flight.mawb_number.clone(),
+                airline: flight.airline.clone(),
+                cut_off: flight
+                    .cut_off
+                    .map(|cut_off| cut_off.format(MANUAL_DATE_FORMATS[0]).to_string())
+                    .unwrap_or_default(),
+            }),
+        }
+    }
+}
+
+impl ManualJob {
+    /// Validate the job and create its JobRow.
+    /// Quantities are checked by the same rules as the imports, but a value the imports would only warn about is an error here,
+    /// because the dispatcher can still correct it.
+    ///
+    /// # Arguments
+    /// * `options` - The options of the import, used for the planned time, tolerance and calendar check
+    ///
+    /// # Returns
+    /// * Result containing the JobRow, marked as manual, or all fields that did not pass the validation
+    pub fn to_job_row(&self, options: &ImportOptions) -> Result<JobRow, Vec<FieldError>> {
+        let mut errors = Vec::new();
+
+        if self.job_number.trim().is_empty() {
+            errors.push(FieldError::new("jobNumber", "must not be empty"));
+        }
+
+        if self.hawbs.is_empty() {
+            errors.push(FieldError::new("hawbs", "at least one HAWB is needed for the temperature range"));
+        }
+        for (index, hawb) in self.hawbs.iter().enumerate() {
+            if hawb.number.trim().is_empty() {
+                errors.push(FieldError::new(format!("hawbs.{}.number", index), "must not be empty"));
+            } else if self.hawbs[..index].iter().any(|other| other.number.trim() == hawb.number.trim()) {
+                errors.push(FieldError::new(format!("hawbs.{}.number", index), "is listed more than once"));
+            }
+            if hawb.temperature_ranges.is_empty() {
+                errors.push(FieldError::new(format!("hawbs.{}.temperatureRanges", index), "must not be empty"));
+            } else if hawb.temperature_ranges.contains(&TemperatureRange::Invalid) {
+                errors.push(FieldError::new(
+                    format!("hawbs.{}.temperatureRanges", index),
+                    "must not contain Invalid",
+                ));
+            }
+        }
+
+        let early_date = parse_date("earlyDate", &self.early_date, &mut errors);
+        let late_date = parse_date("lateDate", &self.late_date, &mut errors);
+        if let (Some(early_date), Some(late_date)) = (early_date, late_date) {
+            if late_date < early_date {
+                errors.push(FieldError::new("lateDate", "must not be before the early date"));
+            }
+        }
+
+        let (field, stop) = match self.mode.stop() {
+            PartyRole::Shipper => ("shipper", &self.shipper),
+            PartyRole::Consignee => ("consignee", &self.consignee),
+        };
+        for (name, value) in [
+            ("name", &stop.name),
+            ("address", &stop.address),
+            ("postalCode", &stop.postal_code),
+            ("city", &stop.city),
+            ("country", &stop.country),
+        ] {
+            if value.trim().is_empty() {
+                errors.push(FieldError::new(format!("{}.{}", field, name), "must not be empty"));
+            }
+        }
+
+        let (_, quantity_diagnostics) = Quantity::from_cells(&self.quantity, options.volumetric_kg_per_m3);
+        for diagnostic in quantity_diagnostics {
+            if let DiagnosticKind::InvalidQuantity { field, value, reason } = diagnostic.kind {
+                errors.push(FieldError::new(format!("quantity.{}", field), format!("'{}' is {}", value, reason)));
+            }
+        }
+
+        let flight = match (self.mode.has_flight_details(), &self.flight) {
+            (false, _) => None,
+            (true, None) => {
+                errors.push(FieldError::new("flight", format!("is needed for a {}", self.mode)));
+                None
+            }
+            (true, Some(flight)) => {
+                if flight.mawb_number.trim().is_empty() {
+                    errors.push(FieldError::new("flight.mawbNumber", "must not be empty"));
+                }
+                let cut_off = match flight.cut_off.trim().is_empty() {
+                    true => None,
+                    false => parse_date("flight.cutOff", &flight.cut_off, &mut errors),
+                };
+                Some(FlightDetails {
+                    mawb_number: flight.mawb_number.trim().to_string(),
+                    airline: flight.airline.trim().to_string(),
+                    cut_off,
+                })
+            }
+        };
+
+        let (early_date, late_date) = match (early_date, late_date) {
+            (Some(early_date), Some(late_date)) if errors.is_empty() => (early_date, late_date),
+            _ => return Err(errors),
+        };
+
+        let hawbs = self
+            .hawbs
+            .iter()
+            .map(|hawb| Hawb {
+                number: hawb.number.trim().to_string(),
+                temperature_ranges: hawb.temperature_ranges.clone(),
+            })
+            .collect();
+
+        let input = JobInput {
+            mode: self.mode,
+            job_number: self.job_number.trim().to_string(),
+            hawbs,
+            diagnostics: Vec::new(),
+            shipper: self.shipper.clone(),
+            consignee: self.consignee.clone(),
+            equipment_codes: self.equipment.trim().to_string(),
+            quantity_cells: self.quantity.clone(),
+            early_date,
+            late_date,
+            flight,
+            origin: JobOrigin::Manual,
+        };
+        Ok(JobRow::new(input, options))
+    }
+}
+
+/// Parse a date of a manual job
+///
+/// # Arguments
+/// * `field` - The path of the field, used for the error
+/// * `value` - The date as entered
+/// * `errors` - The field errors, the error is added here if the date can not be parsed
+///
+/// # Returns
+/// * The date, or None if it can not be parsed
+fn parse_date(field: &str, value: &str, errors: &mut Vec<FieldError>) -> Option<NaiveDateTime> {
+    let date = MANUAL_DATE_FORMATS
+        .iter()
+        .find_map(|format| NaiveDateTime::parse_from_str(value.trim(), format).ok());
+    if date.is_none() {
+        let message = match value.trim().is_empty() {
+            true => "must not be empty".to_string(),
+            false => format!("'{}' is not a date like 2024-03-04T08:00", value),
+        };
+        errors.push(FieldError::new(field, message));
+    }
+    date
+}
+
+/// Check that no other job has the same id
+///
+/// # Arguments
+/// * `jobs` - The current jobs
+/// * `job` - The new or edited job
+/// * `replaced` - The index of the job that is edited, it is not compared with itself
+///
+/// # Returns
+/// * Result containing nothing or the field error of the job number
+fn check_unique(jobs: &[JobRow], job: &JobRow, replaced: Option<usize>) -> Result<(), JobEditError> {
+    let id = job.id();
+    match jobs
+        .iter()
+        .enumerate()
+        .any(|(index, other)| Some(index) != replaced && other.id() == id)
+    {
+        true => Err(JobEditError::Invalid(vec![FieldError::new(
+            "jobNumber",
+            format!("there already is a {} with this job number", job.mode),
+        )])),
+        false => Ok(()),
+    }
+}
+
+/// Create a job manually and add it to the jobs.
+/// The jobs are rejected if the status of one of them does not follow from its history.
+///
+/// # Arguments
+/// * `jobs` - The current jobs
+/// * `job` - The job as entered by the dispatcher
+/// * `options` - The options of the import
+///
+/// # Returns
+/// * Result containing the jobs with the new job at the end or an error
+pub fn create_job(mut jobs: Vec<JobRow>, job: &ManualJob, options: &ImportOptions) -> Result<Vec<JobRow>, JobEditError> {
+    lifecycle::check_status_histories(&jobs)?;
+    let row = job.to_job_row(options).map_err(JobEditError::Invalid)?;
+    check_unique(&jobs, &row, None)?;
+    jobs.push(row);
+    Ok(jobs)
+}
+
+/// Replace a job with an edited version.
+/// The edited job keeps its origin, so an edited import is still shown as imported, and its status with the history.
+/// The jobs are rejected if the status of one of them does not follow from its history.
+///
+/// # Arguments
+/// * `jobs` - The current jobs
+/// * `id` - The id of the job to edit, see JobRow::id
+/// * `job` - The edited job
+/// * `options` - The options of the import
+///
+/// # Returns
+/// * Result containing the jobs with the edited job in the place of the old one or an error
+pub fn update_job(mut jobs: Vec<JobRow>, id: &str, job: &ManualJob, options: &ImportOptions) -> Result<Vec<JobRow>, JobEditError> {
+    lifecycle::check_status_histories(&jobs)?;
+    let index = jobs
+        .iter()
+        .position(|other| other.id() == id)
+        .ok_or_else(|| JobEditError::UnknownJob(id.to_string()))?;
+    let mut row = job.to_job_row(options).map_err(JobEditError::Invalid)?;
+    row.origin = jobs[index].origin;
+    row.status = jobs[index].status;
+    row.status_history = jobs[index].status_history.clone();
+    check_unique(&jobs, &row, Some(index))?;
+
+    jobs[index] = row;
+    Ok(jobs)
+}
+
+/// Remove a job
+///
+/// # Arguments
+/// * `jobs` - The current jobs
+/// * `id` - The id of the job to remove, see JobRow::id
+///
+/// # Returns
+/// * Result containing the remaining jobs or an error
+pub fn delete_job(mut jobs: Vec<JobRow>, id: &str) -> Result<Vec<JobRow>, JobEditError> {
+    let index = jobs
+        .iter()
+        .position(|job| job.id() == id)
+        .ok_or_else(|| JobEditError::UnknownJob(id.to_string()))?;
+    jobs.remove(index);
+    Ok(jobs)
+}
@@ -1,9 +1,8 @@
 // Copyright 2023 Felix Kahle. All rights reserved.
 
-use tauri::InvokeError;
-
-use crate::job_row::{
-    AnyValueToNaiveDateTimeParseError, AnyValueToNumericParseError, StringToDispoModeError, StringToTemperatureRangeError,
+use crate::{
+    job_row::{AnyValueToNaiveDateTimeParseError, StringToDispoModeError, StringToTemperatureRangeError},
+    planned_time::PlannedTimeError,
 };
 
 /// This error includes all errors that can occur while parsing files
@@ -13,21 +12,26 @@ use crate::job_row::{
 /// * `PolarsError` - An error that occurred while converting the Excel files to DataFrames
 /// * `NoHeadersFound` - No headers were found in the Excel files
 /// * `InvalidSheetCount` - The number of sheets in the Excel file is not equal to the number of sheets expected
-/// * `AnyValueToNumericParse` - An error that occurred while parsing a value to a numeric type
 /// * `AnyValueToNaiveDateTimeParse` - An error that occurred while parsing a value to a NaiveDateTime type
 /// * `StringToDispoMode` - An error that occurred while parsing a string to a DispoMode
 /// * `StringToTemperatureRange` - An error that occurred while parsing a string to a TemperatureRange
+/// * `PlannedTime` - The planned time settings of the ImportOptions can not be used
+/// * `Cancelled` - The import was cancelled
+/// * `DuplicateTask` - A task with the same id is already running
+/// * `TaskFailed` - The background task of the import failed
 #[derive(Debug)]
 pub enum ParseFilesError {
     CalamineError(calamine::Error),
     PolarsError(polars::error::PolarsError),
     NoHeadersFound,
-    MismatchedRowCount((i32, i32)),
     InvalidSheetCount((i32, i32)),
-    AnyValueToNumericParse(AnyValueToNumericParseError),
     AnyValueToNaiveDateTimeParse(AnyValueToNaiveDateTimeParseError),
     StringToDispoMode(StringToDispoModeError),
     StringToTemperatureRange(StringToTemperatureRangeError),
+    PlannedTime(PlannedTimeError),
+    Cancelled,
+    DuplicateTask(String),
+    TaskFailed(String),
 }
 
 impl From<calamine::Error> for ParseFilesError {
@@ -48,12 +52,6 @@ impl From<polars::error::PolarsError> for ParseFilesError {
     }
 }
 
-impl From<AnyValueToNumericParseError> for ParseFilesError {
-    fn from(error: AnyValueToNumericParseError) -> Self {
-        ParseFilesError::AnyValueToNumericParse(error)
-    }
-}
-
 impl From<AnyValueToNaiveDateTimeParseError> for ParseFilesError {
     fn from(error: AnyValueToNaiveDateTimeParseError) -> Self {
         ParseFilesError::AnyValueToNaiveDateTimeParse(error)
@@ -72,17 +70,19 @@ impl From<StringToTemperatureRangeError> for ParseFilesError {
     }
 }
 
+impl From<PlannedTimeError> for ParseFilesError {
+    fn from(error: PlannedTimeError) -> Self {
+        ParseFilesError::PlannedTime(error)
+    }
+}
+
 impl std::fmt::Display for ParseFilesError {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
             ParseFilesError::CalamineError(error) => write!(f, "CalamineError: {}", error),
             ParseFilesError::PolarsError(error) => write!(f, "PolarsError: {}", error),
             ParseFilesError::NoHeadersFound => write!(f, "NoHeadersFound"),
-            ParseFilesError::MismatchedRowCount((first, second)) => {
-                write!(f, "Mismatched row count. Found {} and {}", first, second)
-            }
             ParseFilesError::InvalidSheetCount((expected, actual)) => write!(f, "Expected {} sheets, found {}", expected, actual),
-            ParseFilesError::AnyValueToNumericParse(error) => write!(f, "AnyValueToNumericParseError: {}", error),
             ParseFilesError::AnyValueToNaiveDateTimeParse(error) => {
                 write!(f, "AnyValueToNaiveDateTimeParseError: {}", error)
             }
@@ -90,13 +90,21 @@ impl std::fmt::Display for ParseFilesError {
             ParseFilesError::StringToTemperatureRange(error) => {
                 write!(f, "StringToTemperatureRangeError: {}", error)
             }
+            ParseFilesError::PlannedTime(error) => write!(f, "PlannedTimeError: {}", error),
+            ParseFilesError::Cancelled => write!(f, "Cancelled"),
+            ParseFilesError::DuplicateTask(id) => write!(f, "DuplicateTask: The task {} is already running", id),
+            ParseFilesError::TaskFailed(error) => write!(f, "TaskFailed: {}", error),
         }
     }
 }
 
-impl Into<InvokeError> for ParseFilesError {
-    fn into(self) -> InvokeError {
-        InvokeError::from(self.to_string())
+/// Errors are serialized as their message, this is how they reach the frontend of the app.
+impl serde::Serialize for ParseFilesError {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        serializer.serialize_str(&self.to_string())
     }
 }
 
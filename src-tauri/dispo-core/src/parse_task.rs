@@ -0,0 +1,169 @@
+// Copyright 2023 Felix Kahle. All rights reserved.
+
+use crate::parse_error::ParseFilesError;
+use std::{
+    collections::HashMap,
+    fmt::{self},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Mutex,
+    },
+};
+
+/// The event emitted for every step of a parse task
+pub const PARSE_PROGRESS_EVENT: &str = "parse-progress";
+
+/// The ParseStage enum represents the steps of an import
+/// * Opened: A workbook was opened
+/// * RowsParsed: The rows of a workbook were parsed
+/// * Joined: The CL View and the Shipper Site were joined
+/// * Converted: The joined rows were converted into jobs
+#[derive(serde::Serialize, serde::Deserialize, ts_rs::TS, Debug, Copy, Clone, PartialEq, Eq)]
+pub enum ParseStage {
+    Opened,
+    RowsParsed,
+    Joined,
+    Converted,
+}
+
+impl fmt::Display for ParseStage {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ParseStage::Opened => write!(f, "Opened"),
+            ParseStage::RowsParsed => write!(f, "Rows Parsed"),
+            ParseStage::Joined => write!(f, "Joined"),
+            ParseStage::Converted => write!(f, "Converted"),
+        }
+    }
+}
+
+/// The progress of a parse task
+/// * task_id: The id of the task
+/// * stage: The step that was finished
+/// * file: The workbook the step belongs to, None for the steps that use both workbooks
+/// * rows: The number of rows after the step, 0 for Opened
+#[derive(serde::Serialize, ts_rs::TS, Debug, Clone, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct ParseProgress {
+    pub task_id: String,
+    pub stage: ParseStage,
+    pub file: Option<String>,
+    pub rows: usize,
+}
+
+/// A running import that reports its progress and can be cancelled.
+/// The import checks for cancellation between its steps and for every row and column it converts.
+/// * id: The id of the task
+/// * cancelled: Set once the task is cancelled
+/// * progress: Called after every step
+pub struct ParseTask {
+    id: String,
+    cancelled: Arc<AtomicBool>,
+    progress: Box<dyn Fn(ParseProgress) + Send + Sync>,
+}
+
+impl ParseTask {
+    /// Create a new ParseTask
+    ///
+    /// # Arguments
+    /// * `id` - The id of the task
+    /// * `cancelled` - The flag that cancels the task
+    /// * `progress` - Called after every step
+    ///
+    /// # Returns
+    /// * A new ParseTask
+    pub fn new<F>(id: &str, cancelled: Arc<AtomicBool>, progress: F) -> Self
+    where
+        F: Fn(ParseProgress) + Send + Sync + 'static,
+    {
+        Self {
+            id: id.to_string(),
+            cancelled,
+            progress: Box::new(progress),
+        }
+    }
+
+    /// Create a task that reports nothing and is never cancelled
+    ///
+    /// # Returns
+    /// * A new ParseTask
+    pub fn silent() -> Self {
+        Self::new("", Arc::new(AtomicBool::new(false)), |_| {})
+    }
+
+    /// Report a finished step
+    ///
+    /// # Arguments
+    /// * `stage` - The step that was finished
+    /// * `file` - The workbook the step belongs to, None for the steps that use both workbooks
+    /// * `rows` - The number of rows after the step
+    pub fn report(&self, stage: ParseStage, file: Option<&str>, rows: usize) {
+        (self.progress)(ParseProgress {
+            task_id: self.id.clone(),
+            stage,
+            file: file.map(|file| file.to_string()),
+            rows,
+        });
+    }
+
+    /// Check whether the task was cancelled
+    ///
+    /// # Returns
+    /// * Result containing nothing or ParseFilesError::Cancelled
+    pub fn check(&self) -> Result<(), ParseFilesError> {
+        match self.cancelled.load(Ordering::Relaxed) {
+            true => Err(ParseFilesError::Cancelled),
+            false => Ok(()),
+        }
+    }
+}
+
+/// The running parse tasks of the app, managed by Tauri
+/// * 0: The cancellation flag of every running task, by task id
+#[derive(Default)]
+pub struct ParseTasks(pub Mutex<HashMap<String, Arc<AtomicBool>>>);
+
+impl ParseTasks {
+    /// Register a new task.
+    /// The id of a running task can not be used again, cancelling it could otherwise stop the wrong import.
+    ///
+    /// # Arguments
+    /// * `id` - The id of the task
+    ///
+    /// # Returns
+    /// * Result containing the flag that cancels the task or ParseFilesError::DuplicateTask
+    pub fn register(&self, id: &str) -> Result<Arc<AtomicBool>, ParseFilesError> {
+        let mut tasks = self.0.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+        if tasks.contains_key(id) {
+            return Err(ParseFilesError::DuplicateTask(id.to_string()));
+        }
+        let cancelled = Arc::new(AtomicBool::new(false));
+        tasks.insert(id.to_string(), cancelled.clone());
+        Ok(cancelled)
+    }
+
+    /// Cancel a running task
+    ///
+    /// # Arguments
+    /// * `id` - The id of the task
+    ///
+    /// # Returns
+    /// * True if the task was running
+    pub fn cancel(&self, id: &str) -> bool {
+        match self.0.lock().unwrap_or_else(|poisoned| poisoned.into_inner()).get(id) {
+            Some(cancelled) => {
+                cancelled.store(true, Ordering::Relaxed);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Remove a finished task
+    ///
+    /// # Arguments
+    /// * `id` - The id of the task
+    pub fn finish(&self, id: &str) {
+        self.0.lock().unwrap_or_else(|poisoned| poisoned.into_inner()).remove(id);
+    }
+}
@@ -0,0 +1,301 @@
+// Copyright 2023 Felix Kahle. All rights reserved.
+
+use crate::{
+    export::PlannedJob,
+    job_row::JobOrigin,
+    lifecycle::{JobStatus, StatusChangeError},
+};
+use chrono::{NaiveDateTime, Utc};
+use serde_json::Value;
+use std::{
+    fmt::{self},
+    fs::File,
+    io::{BufReader, BufWriter, Read, Write},
+};
+
+/// The value of the format field, identifies a JSON file as a plan file
+pub const PLAN_FILE_FORMAT: &str = "dispo-master-plan";
+
+/// The version of the plan files written by this build.
+/// Must be increased whenever the JSON of a job changes, together with a migration in MIGRATIONS.
+///
+/// Versions:
+/// * 0: A bare JSON array of jobs, as written by `dispo-cli --format json`
+/// * 1: The jobs wrapped into an object with format, version and savedAt
+/// * 2: Every job has an origin
+/// * 3: Every job has a status and a status history
+/// * 4: Every job has the driver, vehicle and notes the dispatcher assigned
+pub const PLAN_FILE_VERSION: u64 = 4;
+
+/// The migrations between the versions, the migration at index n turns version n into version n + 1
+const MIGRATIONS: [fn(Value) -> Result<Value, PlanFileError>; PLAN_FILE_VERSION as usize] =
+    [migrate_v0_to_v1, migrate_v1_to_v2, migrate_v2_to_v3, migrate_v3_to_v4];
+
+/// This error includes all errors that can occur while reading or writing plan files
+///
+/// # Variants
+/// * `Io` - The file could not be read or written
+/// * `Json` - The file is not valid JSON or its jobs are invalid
+/// * `NotAPlanFile` - The JSON is neither a plan file nor an array of jobs
+/// * `UnsupportedVersion` - The file was written by a newer version of the app
+/// * `Status` - The status of a job does not follow from its history
+#[derive(Debug)]
+pub enum PlanFileError {
+    Io(std::io::Error),
+    Json(serde_json::Error),
+    NotAPlanFile,
+    UnsupportedVersion(u64),
+    Status(StatusChangeError),
+}
+
+impl From<std::io::Error> for PlanFileError {
+    fn from(error: std::io::Error) -> Self {
+        PlanFileError::Io(error)
+    }
+}
+
+impl From<serde_json::Error> for PlanFileError {
+    fn from(error: serde_json::Error) -> Self {
+        PlanFileError::Json(error)
+    }
+}
+
+impl From<StatusChangeError> for PlanFileError {
+    fn from(error: StatusChangeError) -> Self {
+        PlanFileError::Status(error)
+    }
+}
+
+impl fmt::Display for PlanFileError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PlanFileError::Io(error) => write!(f, "IoError: {}", error),
+            PlanFileError::Json(error) => write!(f, "JsonError: {}", error),
+            PlanFileError::NotAPlanFile => write!(f, "NotAPlanFile: The file does not contain a plan"),
+            PlanFileError::UnsupportedVersion(version) => write!(
+                f,
+                "UnsupportedVersion: The plan file has version {}, only versions up to {} are supported",
+                version, PLAN_FILE_VERSION
+            ),
+            PlanFileError::Status(error) => write!(f, "{}", error),
+        }
+    }
+}
+
+impl std::error::Error for PlanFileError {}
+
+impl serde::Serialize for PlanFileError {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        serializer.serialize_str(&self.to_string())
+    }
+}
+
+/// A saved set of jobs.
+/// The JSON is an object like `{"format": "dispo-master-plan", "version": 1, "savedAt": "2024-03-04T08:00:00", "jobs": [...]}`,
+/// every job is a PlannedJob, the JobRow as the app sends it to the frontend with the assignments of the dispatcher.
+/// Files of older versions are migrated when they are read, files of newer versions are rejected.
+/// * format: Always PLAN_FILE_FORMAT
+/// * version: The version of the file, PLAN_FILE_VERSION for new files
+/// * saved_at: When the file was written, in UTC. None for files migrated from version 0.
+/// * jobs: The saved jobs
+#[derive(serde::Serialize, serde::Deserialize, ts_rs::TS, Debug, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct PlanFile {
+    pub format: String,
+    #[ts(type = "number")]
+    pub version: u64,
+    pub saved_at: Option<NaiveDateTime>,
+    pub jobs: Vec<PlannedJob>,
+}
+
+impl PlanFile {
+    /// Create a new PlanFile of the current version, saved now
+    ///
+    /// # Arguments
+    /// * `jobs` - The jobs to save
+    ///
+    /// # Returns
+    /// * A new PlanFile
+    pub fn new(jobs: Vec<PlannedJob>) -> Self {
+        Self {
+            format: PLAN_FILE_FORMAT.to_string(),
+            version: PLAN_FILE_VERSION,
+            saved_at: Some(Utc::now().naive_utc()),
+            jobs,
+        }
+    }
+
+    /// Write the plan file as JSON.
+    /// IO errors are returned as serde_json errors as well, so the CLI can write plan files like its other formats.
+    ///
+    /// # Arguments
+    /// * `writer` - The writer to write to
+    ///
+    /// # Returns
+    /// * Result containing nothing or an error
+    pub fn to_writer<W: Write>(&self, mut writer: W) -> Result<(), serde_json::Error> {
+        serde_json::to_writer_pretty(&mut writer, self)?;
+        writeln!(writer).map_err(serde_json::Error::io)
+    }
+
+    /// Read a plan file of any supported version.
+    /// The file is rejected if the status of one of its jobs does not follow from its history.
+    ///
+    /// # Arguments
+    /// * `reader` - The reader to read the JSON from
+    ///
+    /// # Returns
+    /// * Result containing the plan file, migrated to the current version, or an error
+    pub fn from_reader<R: Read>(reader: R) -> Result<Self, PlanFileError> {
+        let value: Value = serde_json::from_reader(reader)?;
+        let plan: PlanFile = serde_json::from_value(migrate(value)?)?;
+        plan.jobs.iter().try_for_each(|saved| saved.job.check_status_history())?;
+        Ok(plan)
+    }
+}
+
+/// Find the version of a plan file
+///
+/// # Arguments
+/// * `value` - The JSON of the plan file
+///
+/// # Returns
+/// * Result containing the version or an error if the JSON is not a plan file
+fn version_of(value: &Value) -> Result<u64, PlanFileError> {
+    match value {
+        Value::Array(_) => Ok(0),
+        Value::Object(object) if object.get("format").and_then(Value::as_str) == Some(PLAN_FILE_FORMAT) => {
+            object.get("version").and_then(Value::as_u64).ok_or(PlanFileError::NotAPlanFile)
+        }
+        _ => Err(PlanFileError::NotAPlanFile),
+    }
+}
+
+/// Migrate the JSON of a plan file to the current version
+///
+/// # Arguments
+/// * `value` - The JSON of the plan file
+///
+/// # Returns
+/// * Result containing the JSON of the current version or an error
+fn migrate(mut value: Value) -> Result<Value, PlanFileError> {
+    let version = version_of(&value)?;
+    if version > PLAN_FILE_VERSION {
+        return Err(PlanFileError::UnsupportedVersion(version));
+    }
+    for migration in &MIGRATIONS[version as usize..] {
+        value = migration(value)?;
+    }
+    Ok(value)
+}
+
+/// Wrap the bare array of jobs of version 0 into the object of version 1.
+/// The time the file was saved is unknown.
+///
+/// # Arguments
+/// * `value` - The JSON of version 0
+///
+/// # Returns
+/// * Result containing the JSON of version 1 or an error
+fn migrate_v0_to_v1(value: Value) -> Result<Value, PlanFileError> {
+    Ok(serde_json::json!({
+        "format": PLAN_FILE_FORMAT,
+        "version": 1,
+        "savedAt": null,
+        "jobs": value,
+    }))
+}
+
+/// Mark all jobs of version 1 as imported, manual jobs did not exist before version 2.
+/// A bare JSON export already has the origin of its jobs, it is kept.
+///
+/// # Arguments
+/// * `value` - The JSON of version 1
+///
+/// # Returns
+/// * Result containing the JSON of version 2 or an error
+fn migrate_v1_to_v2(mut value: Value) -> Result<Value, PlanFileError> {
+    let jobs = value
+        .get_mut("jobs")
+        .and_then(Value::as_array_mut)
+        .ok_or(PlanFileError::NotAPlanFile)?;
+    for job in jobs {
+        let job = job.as_object_mut().ok_or(PlanFileError::NotAPlanFile)?;
+        job.entry("origin").or_insert(Value::String(JobOrigin::Imported.to_string()));
+    }
+    value["version"] = Value::from(2);
+    Ok(value)
+}
+
+/// Give all jobs of version 2 the Imported status, their status was not tracked before version 3.
+/// A bare JSON export already has the status and history of its jobs, they are kept.
+///
+/// # Arguments
+/// * `value` - The JSON of version 2
+///
+/// # Returns
+/// * Result containing the JSON of version 3 or an error
+fn migrate_v2_to_v3(mut value: Value) -> Result<Value, PlanFileError> {
+    let jobs = value
+        .get_mut("jobs")
+        .and_then(Value::as_array_mut)
+        .ok_or(PlanFileError::NotAPlanFile)?;
+    for job in jobs {
+        let job = job.as_object_mut().ok_or(PlanFileError::NotAPlanFile)?;
+        job.entry("status").or_insert(serde_json::to_value(JobStatus::Imported)?);
+        job.entry("statusHistory").or_insert(Value::Array(Vec::new()));
+    }
+    value["version"] = Value::from(3);
+    Ok(value)
+}
+
+/// Give all jobs of version 3 empty assignments, they were not saved before version 4
+///
+/// # Arguments
+/// * `value` - The JSON of version 3
+///
+/// # Returns
+/// * Result containing the JSON of version 4 or an error
+fn migrate_v3_to_v4(mut value: Value) -> Result<Value, PlanFileError> {
+    let jobs = value
+        .get_mut("jobs")
+        .and_then(Value::as_array_mut)
+        .ok_or(PlanFileError::NotAPlanFile)?;
+    for job in jobs {
+        let job = job.as_object_mut().ok_or(PlanFileError::NotAPlanFile)?;
+        for field in ["driver", "vehicle", "notes"] {
+            job.entry(field).or_insert(Value::String(String::new()));
+        }
+    }
+    value["version"] = Value::from(4);
+    Ok(value)
+}
+
+/// Save jobs to a plan file
+///
+/// # Arguments
+/// * `path` - The path of the .json file
+/// * `jobs` - The jobs to save, with their assignments
+///
+/// # Returns
+/// * Result containing nothing or an error
+pub fn write_plan_file(path: &str, jobs: Vec<PlannedJob>) -> Result<(), PlanFileError> {
+    let mut writer = BufWriter::new(File::create(path)?);
+    PlanFile::new(jobs).to_writer(&mut writer)?;
+    writer.flush()?;
+    Ok(())
+}
+
+/// Load the jobs of a plan file of any supported version
+///
+/// # Arguments
+/// * `path` - The path of the .json file
+///
+/// # Returns
+/// * Result containing the plan file, migrated to the current version, or an error
+pub fn read_plan_file(path: &str) -> Result<PlanFile, PlanFileError> {
+    PlanFile::from_reader(BufReader::new(File::open(path)?))
+}
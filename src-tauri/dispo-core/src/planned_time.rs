@@ -0,0 +1,379 @@
+// Copyright 2023 Felix Kahle. All rights reserved.
+
+use crate::{
+    diagnostics::{Diagnostic, DiagnosticKind},
+    job_row::DispoMode,
+};
+use chrono::{Duration, NaiveDateTime, NaiveTime, Timelike};
+use std::fmt::{self};
+
+/// The default number of minutes a transfer has to arrive before the airline cut-off
+pub const DEFAULT_CUT_OFF_BUFFER_MINUTES: i64 = 60;
+
+/// The largest number of minutes a strategy or the cut-off buffer may be configured with, 31 days
+pub const MAX_CONFIGURED_MINUTES: i64 = 31 * 24 * 60;
+
+/// The edge of a target window
+/// * Early: The early date of the window
+/// * Late: The late date of the window
+#[derive(serde::Serialize, serde::Deserialize, ts_rs::TS, Debug, Copy, Clone, PartialEq, Eq)]
+pub enum WindowEdge {
+    Early,
+    Late,
+}
+
+/// The PlannedTimeStrategy enum decides how the calculated date of a job is derived from its target window.
+/// The result is always kept inside the window.
+/// * Earliest: The early date
+/// * Latest: The late date minus a safety margin
+/// * Midpoint: The middle between the early and the late date
+/// * FixedOffset: A fixed number of minutes after the early date or before the late date
+/// * CustomerPreference: The preferred time of day of the customer on the first day of the window where it fits
+/// * QuarterHour: The middle between the early and the late date, rounded to the nearest quarter hour
+#[derive(serde::Serialize, serde::Deserialize, ts_rs::TS, Debug, Clone, Default, PartialEq, Eq)]
+#[serde(tag = "type", rename_all = "camelCase", rename_all_fields = "camelCase")]
+pub enum PlannedTimeStrategy {
+    Earliest,
+    Latest {
+        #[serde(default)]
+        #[ts(type = "number")]
+        safety_minutes: i64,
+    },
+    #[default]
+    Midpoint,
+    FixedOffset {
+        #[ts(type = "number")]
+        minutes: i64,
+        from: WindowEdge,
+    },
+    CustomerPreference {
+        time: NaiveTime,
+    },
+    QuarterHour,
+}
+
+impl fmt::Display for PlannedTimeStrategy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PlannedTimeStrategy::Earliest => write!(f, "Earliest"),
+            PlannedTimeStrategy::Latest { safety_minutes } => write!(f, "Latest (-{} min)", safety_minutes),
+            PlannedTimeStrategy::Midpoint => write!(f, "Midpoint"),
+            PlannedTimeStrategy::FixedOffset { minutes, from } => match from {
+                WindowEdge::Early => write!(f, "Early +{} min", minutes),
+                WindowEdge::Late => write!(f, "Late -{} min", minutes),
+            },
+            PlannedTimeStrategy::CustomerPreference { time } => write!(f, "Customer preference {}", time.format("%H:%M")),
+            PlannedTimeStrategy::QuarterHour => write!(f, "Quarter hour"),
+        }
+    }
+}
+
+impl PlannedTimeStrategy {
+    /// Calculate the planned date for a target window.
+    /// It does not matter which date is the earlier and which is the later date.
+    ///
+    /// # Arguments
+    /// * `early_date` - The early date of the window
+    /// * `late_date` - The late date of the window
+    ///
+    /// # Returns
+    /// * The planned date, always inside the window
+    pub fn calculate(&self, early_date: NaiveDateTime, late_date: NaiveDateTime) -> NaiveDateTime {
+        let (early, late) = if early_date <= late_date {
+            (early_date, late_date)
+        } else {
+            (late_date, early_date)
+        };
+
+        // An offset never leaves the window, so it is capped at the length of the window
+        let window = late - early;
+        let offset = |minutes: i64| Duration::try_minutes(minutes.max(0)).map_or(window, |offset| offset.min(window));
+
+        let planned = match self {
+            PlannedTimeStrategy::Earliest => early,
+            PlannedTimeStrategy::Latest { safety_minutes } => late - offset(*safety_minutes),
+            PlannedTimeStrategy::Midpoint => middle_between_dates(early, late),
+            PlannedTimeStrategy::FixedOffset { minutes, from } => match from {
+                WindowEdge::Early => early + offset(*minutes),
+                WindowEdge::Late => late - offset(*minutes),
+            },
+            PlannedTimeStrategy::CustomerPreference { time } => preferred_time_in_window(early, late, *time),
+            PlannedTimeStrategy::QuarterHour => round_to_quarter_hour(middle_between_dates(early, late)),
+        };
+
+        planned.clamp(early, late)
+    }
+
+    /// Check that the minutes of the strategy can be used
+    ///
+    /// # Returns
+    /// * Ok or an error if the minutes are negative or larger than MAX_CONFIGURED_MINUTES
+    pub fn validate(&self) -> Result<(), PlannedTimeError> {
+        let minutes = match self {
+            PlannedTimeStrategy::Latest { safety_minutes } => *safety_minutes,
+            PlannedTimeStrategy::FixedOffset { minutes, .. } => *minutes,
+            _ => return Ok(()),
+        };
+        check_minutes(&self.to_string(), minutes)
+    }
+}
+
+/// This error includes all errors of the planned time settings
+///
+/// # Variants
+/// * `MinutesOutOfRange` - The minutes of a strategy or the cut-off buffer are negative or larger than MAX_CONFIGURED_MINUTES
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PlannedTimeError {
+    MinutesOutOfRange((String, i64)),
+}
+
+impl fmt::Display for PlannedTimeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PlannedTimeError::MinutesOutOfRange((setting, minutes)) => write!(
+                f,
+                "{} minutes are configured for {}, expected 0 to {}",
+                minutes, setting, MAX_CONFIGURED_MINUTES
+            ),
+        }
+    }
+}
+
+impl std::error::Error for PlannedTimeError {}
+
+/// Check that configured minutes are between 0 and MAX_CONFIGURED_MINUTES
+///
+/// # Arguments
+/// * `setting` - The setting the minutes belong to, for the error
+/// * `minutes` - The configured minutes
+///
+/// # Returns
+/// * Ok or an error if the minutes are out of range
+fn check_minutes(setting: &str, minutes: i64) -> Result<(), PlannedTimeError> {
+    match (0..=MAX_CONFIGURED_MINUTES).contains(&minutes) {
+        true => Ok(()),
+        false => Err(PlannedTimeError::MinutesOutOfRange((setting.to_string(), minutes))),
+    }
+}
+
+/// The strategy to use for the jobs of one customer
+/// * customer: The customer, compared case insensitive
+/// * strategy: The strategy to use for this customer
+#[derive(serde::Serialize, serde::Deserialize, ts_rs::TS, Debug, Clone, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct CustomerPlannedTime {
+    pub customer: String,
+    pub strategy: PlannedTimeStrategy,
+}
+
+/// The strategy to use for the jobs of one mode
+/// * mode: The mode
+/// * strategy: The strategy to use for this mode
+#[derive(serde::Serialize, serde::Deserialize, ts_rs::TS, Debug, Clone, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct ModePlannedTime {
+    pub mode: DispoMode,
+    pub strategy: PlannedTimeStrategy,
+}
+
+/// The planned time strategies of an import
+/// A customer specific strategy wins over a mode specific strategy, which wins over the default strategy.
+/// * default: The strategy used for all jobs without a customer or mode specific strategy
+/// * modes: The mode specific strategies
+/// * customers: The customer specific strategies
+/// * cut_off_buffer_minutes: The minutes a transfer has to arrive before the airline cut-off
+#[derive(serde::Serialize, serde::Deserialize, ts_rs::TS, Debug, Clone, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct PlannedTimeSettings {
+    #[serde(default)]
+    pub default: PlannedTimeStrategy,
+    #[serde(default = "default_mode_strategies")]
+    pub modes: Vec<ModePlannedTime>,
+    #[serde(default)]
+    pub customers: Vec<CustomerPlannedTime>,
+    #[serde(default = "default_cut_off_buffer_minutes")]
+    #[ts(type = "number")]
+    pub cut_off_buffer_minutes: i64,
+}
+
+/// The mode specific strategies used if none are configured.
+/// Recoveries are planned as early as possible, because the freight is waiting at the airline.
+///
+/// # Returns
+/// * The default mode specific strategies
+fn default_mode_strategies() -> Vec<ModePlannedTime> {
+    vec![ModePlannedTime {
+        mode: DispoMode::Recovery,
+        strategy: PlannedTimeStrategy::Earliest,
+    }]
+}
+
+/// The cut-off buffer used if none is configured
+///
+/// # Returns
+/// * DEFAULT_CUT_OFF_BUFFER_MINUTES
+fn default_cut_off_buffer_minutes() -> i64 {
+    DEFAULT_CUT_OFF_BUFFER_MINUTES
+}
+
+impl Default for PlannedTimeSettings {
+    fn default() -> Self {
+        Self {
+            default: PlannedTimeStrategy::default(),
+            modes: default_mode_strategies(),
+            customers: Vec::new(),
+            cut_off_buffer_minutes: DEFAULT_CUT_OFF_BUFFER_MINUTES,
+        }
+    }
+}
+
+impl PlannedTimeSettings {
+    /// Get the strategy to use for a job
+    ///
+    /// # Arguments
+    /// * `customers` - The keys of the customer of the job, see Party::customer_keys. The first key with a strategy wins.
+    /// * `mode` - The mode of the job
+    ///
+    /// # Returns
+    /// * The customer specific strategy, the mode specific strategy or the default strategy
+    pub fn resolve(&self, customers: &[&str], mode: DispoMode) -> &PlannedTimeStrategy {
+        customers
+            .iter()
+            .find_map(|customer| {
+                self.customers
+                    .iter()
+                    .find(|entry| entry.customer.trim().eq_ignore_ascii_case(customer.trim()))
+            })
+            .map(|entry| &entry.strategy)
+            .or_else(|| self.modes.iter().find(|entry| entry.mode == mode).map(|entry| &entry.strategy))
+            .unwrap_or(&self.default)
+    }
+
+    /// Check that every strategy and the cut-off buffer can be used.
+    /// The settings come from the configuration of the frontend, so they are checked before an import uses them.
+    ///
+    /// # Returns
+    /// * Ok or the first setting that can not be used
+    pub fn validate(&self) -> Result<(), PlannedTimeError> {
+        self.default.validate()?;
+        for entry in &self.modes {
+            entry.strategy.validate()?;
+        }
+        for entry in &self.customers {
+            entry.strategy.validate()?;
+        }
+        check_minutes("the cut-off buffer", self.cut_off_buffer_minutes)
+    }
+}
+
+/// Make sure a planned date is early enough to meet an airline cut-off.
+/// The planned date is moved to the cut-off minus the buffer if it is later, but never before the early date.
+/// If the cut-off can not be met inside the window, a diagnostic is returned.
+///
+/// # Arguments
+/// * `planned` - The planned date
+/// * `early_date` - The early date of the window
+/// * `cut_off` - The airline cut-off
+/// * `buffer_minutes` - The minutes the freight has to arrive before the cut-off
+///
+/// # Returns
+/// * The planned date and a diagnostic if the cut-off can not be met
+pub fn apply_cut_off(
+    planned: NaiveDateTime,
+    early_date: NaiveDateTime,
+    cut_off: NaiveDateTime,
+    buffer_minutes: i64,
+) -> (NaiveDateTime, Option<Diagnostic>) {
+    let buffer = Duration::try_minutes(buffer_minutes.max(0)).unwrap_or(Duration::MAX);
+    let latest = cut_off.checked_sub_signed(buffer).unwrap_or(NaiveDateTime::MIN);
+    if planned <= latest {
+        return (planned, None);
+    }
+    if latest < early_date {
+        let diagnostic = Diagnostic::error(DiagnosticKind::CutOffBeforeWindow { cut_off });
+        return (early_date, Some(diagnostic));
+    }
+    (latest, None)
+}
+
+/// Calculate the middle between two dates
+/// It does not matter which date is the earlier and which is the later date
+/// They can both be the same date, in that case the same date is returned.
+///
+/// # Arguments
+/// * `date1` - The first date
+/// * `date2` - The second date
+///
+/// # Returns
+/// * The middle between the two dates
+pub fn middle_between_dates(date1: NaiveDateTime, date2: NaiveDateTime) -> NaiveDateTime {
+    // Determine the earlier and later dates
+    let (earlier_date, later_date) = if date1 < date2 { (date1, date2) } else { (date2, date1) };
+    // Calculate the duration between the two dates
+    let duration = later_date - earlier_date;
+    // Calculate half of the duration
+    let half_duration = duration / 2;
+    // Add half of the duration to the earlier date to get the middle date
+    earlier_date + half_duration
+}
+
+/// Round a date to the nearest quarter hour.
+/// Seconds are dropped, 7 minutes and less are rounded down, 8 minutes and more are rounded up.
+///
+/// # Arguments
+/// * `date` - The date to round
+///
+/// # Returns
+/// * The rounded date
+pub fn round_to_quarter_hour(date: NaiveDateTime) -> NaiveDateTime {
+    let minute = date.minute() as i64;
+    let truncated = date - Duration::minutes(minute % 15) - Duration::seconds(date.second() as i64);
+    if minute % 15 >= 8 {
+        truncated + Duration::minutes(15)
+    } else {
+        truncated
+    }
+}
+
+/// Find the first occurrence of a time of day inside a window.
+/// If the time of day does not occur inside the window, the window edge closest to it is used.
+///
+/// # Arguments
+/// * `early` - The early date of the window
+/// * `late` - The late date of the window
+/// * `time` - The preferred time of day
+///
+/// # Returns
+/// * The preferred date
+fn preferred_time_in_window(early: NaiveDateTime, late: NaiveDateTime, time: NaiveTime) -> NaiveDateTime {
+    let mut day = early.date();
+    while day <= late.date() {
+        let candidate = day.and_time(time);
+        if candidate >= early && candidate <= late {
+            return candidate;
+        }
+        day = match day.succ_opt() {
+            Some(next) => next,
+            None => break,
+        };
+    }
+
+    // The time of day is not inside the window, so take the edge that is closer to it.
+    // The closest occurrence before the early date may be on the day before, the one after the late date on the day after.
+    let on_early_day = early.date().and_time(time);
+    let before_early = match on_early_day > early {
+        true => on_early_day.checked_sub_signed(Duration::days(1)),
+        false => Some(on_early_day),
+    };
+    let on_late_day = late.date().and_time(time);
+    let after_late = match on_late_day < late {
+        true => on_late_day.checked_add_signed(Duration::days(1)),
+        false => Some(on_late_day),
+    };
+
+    match (before_early, after_late) {
+        (Some(before_early), Some(after_late)) if early - before_early <= after_late - late => early,
+        (Some(_), None) => early,
+        _ => late,
+    }
+}
@@ -0,0 +1,455 @@
+// Copyright 2023 Felix Kahle. All rights reserved.
+
+use crate::{
+    export::{ExportError, PlannedJob, PLAN_DATE_FORMAT},
+    manual_job::JobEditError,
+    workbook_cache::content_hash,
+};
+use calamine::{DataType, Reader, Xlsx};
+use chrono::{Duration, NaiveDate, NaiveDateTime, Utc};
+use rust_xlsxwriter::{Color, Format, FormatBorder, Workbook};
+use std::fmt::{self};
+
+/// The name of the sheet of the planning workbook
+pub const PLANNING_SHEET_NAME: &str = "Planning";
+
+/// The header row of the planning workbook.
+/// The first two columns are hidden, they identify the job and its state at the time of the export.
+pub const PLANNING_COLUMNS: [&str; 14] = [
+    "Job ID",
+    "Fingerprint",
+    "Driver",
+    "Vehicle",
+    "Planned",
+    "Notes",
+    "Mode",
+    "Job Number",
+    "HAWB",
+    "Early",
+    "Late",
+    "Name",
+    "Postal Code",
+    "City",
+];
+
+// The columns that are read back from the edited workbook
+const ID_COLUMN: usize = 0;
+const FINGERPRINT_COLUMN: usize = 1;
+const DRIVER_COLUMN: usize = 2;
+const VEHICLE_COLUMN: usize = 3;
+const PLANNED_COLUMN: usize = 4;
+const NOTES_COLUMN: usize = 5;
+
+/// The serial number of the day after 9999-12-31, the last date Excel can show
+const MAX_EXCEL_SERIAL: f64 = 2958466.0;
+
+/// The formats accepted for planned dates typed into the workbook as text
+const PLANNED_TEXT_FORMATS: [&str; 3] = ["%d.%m.%Y %H:%M", "%Y-%m-%d %H:%M", "%d.%m.%Y %H:%M:%S"];
+
+/// This error includes all errors that can occur while exporting or reading a planning workbook
+///
+/// # Variants
+/// * `Export` - The workbook could not be written
+/// * `Calamine` - The workbook could not be read
+/// * `SheetNotFound` - The workbook has no planning sheet
+/// * `InvalidHeader` - The header row does not match, the workbook was not created by the planning export
+#[derive(Debug)]
+pub enum PlanningWorkbookError {
+    Export(ExportError),
+    Calamine(calamine::Error),
+    SheetNotFound,
+    InvalidHeader(String),
+}
+
+impl From<ExportError> for PlanningWorkbookError {
+    fn from(error: ExportError) -> Self {
+        PlanningWorkbookError::Export(error)
+    }
+}
+
+impl From<rust_xlsxwriter::XlsxError> for PlanningWorkbookError {
+    fn from(error: rust_xlsxwriter::XlsxError) -> Self {
+        PlanningWorkbookError::Export(ExportError::Xlsx(error))
+    }
+}
+
+impl From<std::io::Error> for PlanningWorkbookError {
+    fn from(error: std::io::Error) -> Self {
+        PlanningWorkbookError::Export(ExportError::Io(error))
+    }
+}
+
+impl From<calamine::Error> for PlanningWorkbookError {
+    fn from(error: calamine::Error) -> Self {
+        PlanningWorkbookError::Calamine(error)
+    }
+}
+
+impl From<calamine::XlsxError> for PlanningWorkbookError {
+    fn from(error: calamine::XlsxError) -> Self {
+        PlanningWorkbookError::Calamine(calamine::Error::Xlsx(error))
+    }
+}
+
+impl fmt::Display for PlanningWorkbookError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PlanningWorkbookError::Export(error) => write!(f, "{}", error),
+            PlanningWorkbookError::Calamine(error) => write!(f, "CalamineError: {}", error),
+            PlanningWorkbookError::SheetNotFound => write!(f, "The workbook has no sheet named {}", PLANNING_SHEET_NAME),
+            PlanningWorkbookError::InvalidHeader(column) => {
+                write!(f, "The workbook was not created by the planning export, expected column {}", column)
+            }
+        }
+    }
+}
+
+impl std::error::Error for PlanningWorkbookError {}
+
+impl serde::Serialize for PlanningWorkbookError {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        serializer.serialize_str(&self.to_string())
+    }
+}
+
+/// Calculate the fingerprint of a job.
+/// The fingerprint changes whenever any field of the job changes, it is used to detect conflicts.
+///
+/// # Arguments
+/// * `job` - The job to calculate the fingerprint of
+///
+/// # Returns
+/// * The fingerprint as a hex string
+pub fn fingerprint(job: &PlannedJob) -> String {
+    let content = serde_json::to_string(job).unwrap_or_default();
+    format!("{:016x}", content_hash(content.as_bytes()))
+}
+
+/// Create the planning workbook.
+/// Driver, vehicle, planned date and notes are meant to be edited, all other columns are for information only.
+///
+/// # Arguments
+/// * `jobs` - The jobs to plan
+///
+/// # Returns
+/// * Result containing the content of the .xlsx file or an error
+pub fn create_planning_workbook(jobs: &[PlannedJob]) -> Result<Vec<u8>, PlanningWorkbookError> {
+    let mut sorted: Vec<&PlannedJob> = jobs.iter().collect();
+    sorted.sort_by_key(|planned| planned.job.calculated_date);
+
+    let header_format = Format::new()
+        .set_bold()
+        .set_background_color(Color::RGB(0xD9E1F2))
+        .set_border_bottom(FormatBorder::Thin);
+    let editable_header_format = header_format.clone().set_background_color(Color::RGB(0xFFF2CC));
+    let date_format = Format::new().set_num_format(PLAN_DATE_FORMAT);
+
+    let mut workbook = Workbook::new();
+    let worksheet = workbook.add_worksheet();
+    worksheet.set_name(PLANNING_SHEET_NAME)?;
+
+    for (column, header) in PLANNING_COLUMNS.iter().enumerate() {
+        let format = match column {
+            DRIVER_COLUMN..=NOTES_COLUMN => &editable_header_format,
+            _ => &header_format,
+        };
+        worksheet.write_string_with_format(0, column as u16, *header, format)?;
+    }
+
+    for (index, planned) in sorted.iter().enumerate() {
+        let row = index as u32 + 1;
+        let job = &planned.job;
+        worksheet.write_string(row, ID_COLUMN as u16, planned.id())?;
+        worksheet.write_string(row, FINGERPRINT_COLUMN as u16, fingerprint(planned))?;
+        worksheet.write_string(row, DRIVER_COLUMN as u16, &planned.driver)?;
+        worksheet.write_string(row, VEHICLE_COLUMN as u16, &planned.vehicle)?;
+        worksheet.write_datetime_with_format(row, PLANNED_COLUMN as u16, job.calculated_date, &date_format)?;
+        worksheet.write_string(row, NOTES_COLUMN as u16, &planned.notes)?;
+        worksheet.write_string(row, 6, job.mode.to_string())?;
+        worksheet.write_string(row, 7, &job.job_number)?;
+        worksheet.write_string(row, 8, &job.hawb_number)?;
+        worksheet.write_datetime_with_format(row, 9, job.early_date, &date_format)?;
+        worksheet.write_datetime_with_format(row, 10, job.late_date, &date_format)?;
+        worksheet.write_string(row, 11, &job.contact_name)?;
+        worksheet.write_string(row, 12, &job.postal_code)?;
+        worksheet.write_string(row, 13, &job.city)?;
+    }
+
+    worksheet.set_column_hidden(ID_COLUMN as u16)?;
+    worksheet.set_column_hidden(FINGERPRINT_COLUMN as u16)?;
+    worksheet.set_column_width(PLANNED_COLUMN as u16, 17)?;
+    worksheet.set_column_width(NOTES_COLUMN as u16, 30)?;
+    worksheet.set_column_width(9, 17)?;
+    worksheet.set_column_width(10, 17)?;
+    worksheet.set_freeze_panes(1, 0)?;
+    worksheet.autofilter(0, 0, sorted.len().max(1) as u32, PLANNING_COLUMNS.len() as u16 - 1)?;
+
+    Ok(workbook.save_to_buffer()?)
+}
+
+/// Write the planning workbook to a file
+///
+/// # Arguments
+/// * `path` - The path of the .xlsx file
+/// * `jobs` - The jobs to plan
+///
+/// # Returns
+/// * Result containing nothing or an error
+pub fn write_planning_workbook(path: &str, jobs: &[PlannedJob]) -> Result<(), PlanningWorkbookError> {
+    std::fs::write(path, create_planning_workbook(jobs)?)?;
+    Ok(())
+}
+
+/// A change of one job, read from the edited planning workbook.
+/// Fields that were not changed are None.
+/// * job_id: The id of the job
+/// * job_number: The job number of the job
+/// * driver: The new driver
+/// * vehicle: The new vehicle
+/// * calculated_date: The new planned date
+/// * notes: The new notes
+#[derive(serde::Serialize, serde::Deserialize, ts_rs::TS, Debug, Clone, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct JobChange {
+    pub job_id: String,
+    pub job_number: String,
+    pub driver: Option<String>,
+    pub vehicle: Option<String>,
+    pub calculated_date: Option<NaiveDateTime>,
+    pub notes: Option<String>,
+}
+
+impl JobChange {
+    /// Check whether the change changes anything
+    ///
+    /// # Returns
+    /// * True if no field is changed
+    fn is_empty(&self) -> bool {
+        self.driver.is_none() && self.vehicle.is_none() && self.calculated_date.is_none() && self.notes.is_none()
+    }
+
+    /// Apply the change to a job.
+    /// A changed driver or vehicle also changes the status of the job, see PlannedJob::update_status.
+    ///
+    /// # Arguments
+    /// * `job` - The job to change
+    fn apply(&self, job: &mut PlannedJob) {
+        if let Some(driver) = &self.driver {
+            job.driver = driver.clone();
+        }
+        if let Some(vehicle) = &self.vehicle {
+            job.vehicle = vehicle.clone();
+        }
+        if let Some(calculated_date) = self.calculated_date {
+            job.job.calculated_date = calculated_date;
+        }
+        if let Some(notes) = &self.notes {
+            job.notes = notes.clone();
+        }
+        if self.driver.is_some() || self.vehicle.is_some() {
+            job.update_status(Utc::now().naive_utc());
+        }
+    }
+}
+
+/// A row of the edited workbook that could not be read
+/// * row: The row number as shown by Excel
+/// * reason: Why the row could not be read
+#[derive(serde::Serialize, serde::Deserialize, ts_rs::TS, Debug, Clone, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct InvalidRow {
+    pub row: u32,
+    pub reason: String,
+}
+
+/// The changes found in an edited planning workbook, to be reviewed before they are applied
+/// * changes: The changes of jobs that were not changed since the export
+/// * conflicts: The changes of jobs that were changed since the export, they are not applied by default
+/// * unknown_jobs: The ids of jobs that are no longer known
+/// * invalid_rows: The rows that could not be read
+#[derive(serde::Serialize, serde::Deserialize, ts_rs::TS, Debug, Clone, Default, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct ChangeSet {
+    pub changes: Vec<JobChange>,
+    pub conflicts: Vec<JobChange>,
+    pub unknown_jobs: Vec<String>,
+    pub invalid_rows: Vec<InvalidRow>,
+}
+
+/// Get the text of a cell
+///
+/// # Arguments
+/// * `row` - The row
+/// * `column` - The column
+///
+/// # Returns
+/// * The trimmed text, empty if the cell is empty
+fn cell_text(row: &[DataType], column: usize) -> String {
+    match row.get(column) {
+        Some(DataType::String(s)) => s.trim().to_string(),
+        Some(DataType::Empty) | None => String::new(),
+        Some(cell) => cell.to_string().trim().to_string(),
+    }
+}
+
+/// Convert the serial number Excel uses for dates into a NaiveDateTime.
+/// The result is rounded to the second, because Excel stores dates as floating point numbers.
+/// Seconds are kept, a planned date in the middle of a window can be on the half minute.
+///
+/// # Arguments
+/// * `serial` - The days since 1899-12-30
+///
+/// # Returns
+/// * The date or None if the number is not a date Excel can show
+fn excel_serial_to_naive_date_time(serial: f64) -> Option<NaiveDateTime> {
+    if !serial.is_finite() || !(0.0..MAX_EXCEL_SERIAL).contains(&serial) {
+        return None;
+    }
+    let epoch = NaiveDate::from_ymd_opt(1899, 12, 30)?.and_hms_opt(0, 0, 0)?;
+    let seconds = (serial * 24.0 * 60.0 * 60.0).round() as i64;
+    epoch.checked_add_signed(Duration::try_seconds(seconds)?)
+}
+
+/// Read the planned date of a row
+///
+/// # Arguments
+/// * `row` - The row
+///
+/// # Returns
+/// * Result containing the planned date or the reason it could not be read
+fn cell_planned_date(row: &[DataType]) -> Result<NaiveDateTime, String> {
+    match row.get(PLANNED_COLUMN) {
+        Some(DataType::DateTime(serial)) | Some(DataType::Float(serial)) => {
+            excel_serial_to_naive_date_time(*serial).ok_or(format!("Invalid planned date {}", serial))
+        }
+        Some(DataType::String(text)) => PLANNED_TEXT_FORMATS
+            .iter()
+            .find_map(|format| NaiveDateTime::parse_from_str(text.trim(), format).ok())
+            .ok_or(format!("Invalid planned date {}", text)),
+        _ => Err("The planned date is missing".to_string()),
+    }
+}
+
+/// Read an edited planning workbook and compare it with the current jobs.
+/// Nothing is applied, the result is meant to be reviewed first.
+///
+/// # Arguments
+/// * `path` - The path of the edited .xlsx file
+/// * `jobs` - The current jobs
+///
+/// # Returns
+/// * Result containing the change set or an error
+pub fn read_planning_workbook(path: &str, jobs: &[PlannedJob]) -> Result<ChangeSet, PlanningWorkbookError> {
+    let mut workbook: Xlsx<_> = calamine::open_workbook(path)?;
+    let range = match workbook.worksheet_range(PLANNING_SHEET_NAME) {
+        Some(Ok(range)) => range,
+        Some(Err(e)) => return Err(e.into()),
+        None => return Err(PlanningWorkbookError::SheetNotFound),
+    };
+
+    let mut rows = range.rows();
+    let header = rows
+        .next()
+        .ok_or(PlanningWorkbookError::InvalidHeader(PLANNING_COLUMNS[ID_COLUMN].to_string()))?;
+    for column in [
+        ID_COLUMN,
+        FINGERPRINT_COLUMN,
+        DRIVER_COLUMN,
+        VEHICLE_COLUMN,
+        PLANNED_COLUMN,
+        NOTES_COLUMN,
+    ] {
+        if cell_text(header, column) != PLANNING_COLUMNS[column] {
+            return Err(PlanningWorkbookError::InvalidHeader(PLANNING_COLUMNS[column].to_string()));
+        }
+    }
+
+    let mut change_set = ChangeSet::default();
+
+    for (index, row) in rows.enumerate() {
+        // Excel counts from 1 and the header is the first row
+        let row_number = index as u32 + 2;
+        let job_id = cell_text(row, ID_COLUMN);
+        if job_id.is_empty() {
+            continue;
+        }
+
+        let job = match jobs.iter().find(|job| job.id() == job_id) {
+            Some(job) => job,
+            None => {
+                change_set.unknown_jobs.push(job_id);
+                continue;
+            }
+        };
+
+        let calculated_date = match cell_planned_date(row) {
+            Ok(calculated_date) => calculated_date,
+            Err(reason) => {
+                change_set.invalid_rows.push(InvalidRow { row: row_number, reason });
+                continue;
+            }
+        };
+
+        let changed = |old: &str, new: String| (old.trim() != new).then_some(new);
+        let change = JobChange {
+            job_id,
+            job_number: job.job.job_number.clone(),
+            driver: changed(&job.driver, cell_text(row, DRIVER_COLUMN)),
+            vehicle: changed(&job.vehicle, cell_text(row, VEHICLE_COLUMN)),
+            calculated_date: (job.job.calculated_date != calculated_date).then_some(calculated_date),
+            notes: changed(&job.notes, cell_text(row, NOTES_COLUMN)),
+        };
+        if change.is_empty() {
+            continue;
+        }
+
+        // The job was changed after the export, so the workbook may overwrite something it never saw
+        if cell_text(row, FINGERPRINT_COLUMN) != fingerprint(job) {
+            change_set.conflicts.push(change);
+        } else {
+            change_set.changes.push(change);
+        }
+    }
+
+    Ok(change_set)
+}
+
+/// Apply a reviewed change set to the current jobs
+///
+/// # Arguments
+/// * `jobs` - The current jobs
+/// * `change_set` - The reviewed change set
+/// * `include_conflicts` - Whether the conflicting changes are applied as well
+///
+/// # Returns
+/// * The changed jobs
+pub fn apply_change_set(mut jobs: Vec<PlannedJob>, change_set: &ChangeSet, include_conflicts: bool) -> Vec<PlannedJob> {
+    let conflicts: &[JobChange] = if include_conflicts { &change_set.conflicts } else { &[] };
+    for change in change_set.changes.iter().chain(conflicts) {
+        if let Some(job) = jobs.iter_mut().find(|job| job.id() == change.job_id) {
+            change.apply(job);
+        }
+    }
+    jobs
+}
+
+/// Apply a change the dispatcher made in the app, like assigning a driver or moving the planned date.
+/// Unlike the changes of a workbook the job was just shown by the app, so an unknown job is an error.
+///
+/// # Arguments
+/// * `jobs` - The current jobs
+/// * `change` - The change of one job
+///
+/// # Returns
+/// * Result containing the changed jobs or an error if there is no job with the id of the change
+pub fn apply_job_change(mut jobs: Vec<PlannedJob>, change: &JobChange) -> Result<Vec<PlannedJob>, JobEditError> {
+    let job = jobs
+        .iter_mut()
+        .find(|job| job.id() == change.job_id)
+        .ok_or_else(|| JobEditError::UnknownJob(change.job_id.clone()))?;
+    change.apply(job);
+    Ok(jobs)
+}
@@ -0,0 +1,412 @@
+// Copyright 2023 Felix Kahle. All rights reserved.
+
+use crate::diagnostics::{Diagnostic, DiagnosticKind};
+use std::fmt::{self};
+use std::str::FromStr;
+
+/// The default volumetric factor in kilograms per cubic meter (IATA 1:6000)
+pub const DEFAULT_VOLUMETRIC_KG_PER_M3: f64 = 166.67;
+
+/// Pounds to kilograms
+const KG_PER_POUND: f64 = 0.453_592_37;
+/// Cubic feet to cubic meters
+const M3_PER_CUBIC_FOOT: f64 = 0.028_316_846_6;
+
+/// The error of parsing a unit that is not known, containing the unit
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UnknownUnitError(String);
+
+impl fmt::Display for UnknownUnitError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Unknown unit '{}'", self.0)
+    }
+}
+
+impl std::error::Error for UnknownUnitError {}
+
+/// The WeightUnit enum represents the weight units found in TMS exports
+/// * Kilogram: kg
+/// * Pound: lb
+#[derive(serde::Serialize, serde::Deserialize, ts_rs::TS, Debug, Copy, Clone, PartialEq, Eq)]
+pub enum WeightUnit {
+    Kilogram,
+    Pound,
+}
+
+/// Create a WeightUnit from a string.
+/// The comparison is case insensitive, an empty string is treated as kilograms.
+impl FromStr for WeightUnit {
+    type Err = UnknownUnitError;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value.trim().to_ascii_lowercase().as_str() {
+            "" | "kg" | "kgs" | "kilogram" | "kilograms" => Ok(WeightUnit::Kilogram),
+            "lb" | "lbs" | "pound" | "pounds" => Ok(WeightUnit::Pound),
+            _ => Err(UnknownUnitError(value.to_string())),
+        }
+    }
+}
+
+impl WeightUnit {
+    /// Convert a value in this unit to kilograms
+    ///
+    /// # Arguments
+    /// * `value` - The value in this unit
+    ///
+    /// # Returns
+    /// * The value in kilograms
+    pub fn to_kg(&self, value: f64) -> f64 {
+        match self {
+            WeightUnit::Kilogram => value,
+            WeightUnit::Pound => value * KG_PER_POUND,
+        }
+    }
+}
+
+/// The VolumeUnit enum represents the volume units found in TMS exports
+/// * CubicMeter: m3
+/// * Liter: l
+/// * CubicFoot: ft3
+#[derive(serde::Serialize, serde::Deserialize, ts_rs::TS, Debug, Copy, Clone, PartialEq, Eq)]
+pub enum VolumeUnit {
+    CubicMeter,
+    Liter,
+    CubicFoot,
+}
+
+/// Create a VolumeUnit from a string.
+/// The comparison is case insensitive, an empty string is treated as cubic meters.
+impl FromStr for VolumeUnit {
+    type Err = UnknownUnitError;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value.trim().to_ascii_lowercase().as_str() {
+            "" | "m3" | "cbm" | "m³" | "cubic meter" | "cubic meters" => Ok(VolumeUnit::CubicMeter),
+            "l" | "ltr" | "liter" | "liters" | "litre" | "litres" => Ok(VolumeUnit::Liter),
+            "ft3" | "cft" | "cuft" | "ft³" | "cubic foot" | "cubic feet" => Ok(VolumeUnit::CubicFoot),
+            _ => Err(UnknownUnitError(value.to_string())),
+        }
+    }
+}
+
+impl VolumeUnit {
+    /// Convert a value in this unit to cubic meters
+    ///
+    /// # Arguments
+    /// * `value` - The value in this unit
+    ///
+    /// # Returns
+    /// * The value in cubic meters
+    pub fn to_m3(&self, value: f64) -> f64 {
+        match self {
+            VolumeUnit::CubicMeter => value,
+            VolumeUnit::Liter => value / 1000.0,
+            VolumeUnit::CubicFoot => value * M3_PER_CUBIC_FOOT,
+        }
+    }
+}
+
+/// The quantity of a dispo operation.
+/// All weights are in kilograms and all volumes in cubic meters, regardless of the units in TMS.
+/// A field is None if it was not present in TMS or could not be parsed.
+/// * pieces: The number of pieces
+/// * gross_weight_kg: The gross weight
+/// * chargeable_weight_kg: The chargeable weight, from TMS or calculated from gross weight and volume
+/// * volume_m3: The volume
+#[derive(serde::Serialize, serde::Deserialize, ts_rs::TS, Debug, Clone, PartialEq, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct Quantity {
+    pub pieces: Option<u32>,
+    pub gross_weight_kg: Option<f64>,
+    pub chargeable_weight_kg: Option<f64>,
+    pub volume_m3: Option<f64>,
+}
+
+impl fmt::Display for Quantity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let format = |value: Option<f64>, unit: &str| match value {
+            Some(value) => format!("{:.2} {}", value, unit),
+            None => "-".to_string(),
+        };
+
+        write!(
+            f,
+            "{} pcs, {} gross, {} chargeable, {}",
+            self.pieces.map(|pieces| pieces.to_string()).unwrap_or("-".to_string()),
+            format(self.gross_weight_kg, "kg"),
+            format(self.chargeable_weight_kg, "kg"),
+            format(self.volume_m3, "m3")
+        )
+    }
+}
+
+/// The raw cells a Quantity is built from.
+/// Every cell is None if the column is missing or the cell is empty.
+/// * pieces: The Actual Quantity cell
+/// * gross_weight: The weight cell, optionally with a unit suffix like "12.5 kg"
+/// * weight_unit: The weight unit cell
+/// * chargeable_weight: The chargeable weight cell, optionally with a unit suffix
+/// * volume: The volume cell, optionally with a unit suffix like "0.2 m3"
+/// * volume_unit: The volume unit cell
+#[derive(serde::Serialize, serde::Deserialize, ts_rs::TS, Debug, Clone, Default, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct QuantityCells {
+    pub pieces: Option<String>,
+    pub gross_weight: Option<String>,
+    pub weight_unit: Option<String>,
+    pub chargeable_weight: Option<String>,
+    pub volume: Option<String>,
+    pub volume_unit: Option<String>,
+}
+
+/// Split a measurement like "12.5 kg" into its value and unit.
+/// Commas are accepted as decimal separators.
+/// A comma followed by exactly three digits can also be a thousands separator, e.g. "1,250 kg", so it is rejected instead of guessed.
+///
+/// # Arguments
+/// * `input` - The measurement
+///
+/// # Returns
+/// * Result containing the value and the unit, the unit is empty if the measurement has none, or the reason it can not be read
+pub fn split_measurement(input: &str) -> Result<(f64, String), &'static str> {
+    let input = input.trim();
+    let split = input
+        .find(|c: char| !(c.is_ascii_digit() || c == '.' || c == ',' || c == '-' || c == '+'))
+        .unwrap_or(input.len());
+    let (value, unit) = input.split_at(split);
+
+    if let Some((_, decimals)) = value.split_once(',') {
+        if decimals.len() == 3 && decimals.chars().all(|c| c.is_ascii_digit()) {
+            return Err("ambiguous comma, it can be a decimal or a thousands separator");
+        }
+    }
+
+    value
+        .replace(',', ".")
+        .parse::<f64>()
+        .map(|value| (value, unit.trim().to_string()))
+        .map_err(|_| "not a number")
+}
+
+impl Quantity {
+    /// Create a Quantity from the raw cells of a row.
+    /// Values are validated: negative numbers, fractional, oversized or suffixed pieces, ambiguous commas and unknown units
+    /// are reported as diagnostics and the affected field is left empty instead of guessing a value.
+    ///
+    /// # Arguments
+    /// * `cells` - The raw cells
+    /// * `volumetric_kg_per_m3` - The factor used to calculate the volumetric weight
+    ///
+    /// # Returns
+    /// * The Quantity and the diagnostics found while parsing
+    pub fn from_cells(cells: &QuantityCells, volumetric_kg_per_m3: f64) -> (Self, Vec<Diagnostic>) {
+        let mut diagnostics = Vec::new();
+        let mut invalid = |field: &str, value: &str, reason: &str| {
+            diagnostics.push(Diagnostic::warning(DiagnosticKind::InvalidQuantity {
+                field: field.to_string(),
+                value: value.to_string(),
+                reason: reason.to_string(),
+            }))
+        };
+
+        let pieces = match cells.pieces.as_deref().map(|value| value.trim()).filter(|value| !value.is_empty()) {
+            Some(value) => match split_measurement(value) {
+                // Pieces have no unit, "5 kg" or "1e5" are something else
+                Ok((_, suffix)) if !suffix.is_empty() => {
+                    invalid("pieces", value, &format!("unexpected suffix '{}'", suffix));
+                    None
+                }
+                Ok((number, _)) if number < 0.0 => {
+                    invalid("pieces", value, "negative");
+                    None
+                }
+                Ok((number, _)) if number.fract() != 0.0 => {
+                    invalid("pieces", value, "not a whole number");
+                    None
+                }
+                Ok((number, _)) if number > u32::MAX as f64 => {
+                    invalid("pieces", value, "too large");
+                    None
+                }
+                Ok((number, _)) => Some(number as u32),
+                Err(reason) => {
+                    invalid("pieces", value, reason);
+                    None
+                }
+            },
+            None => None,
+        };
+
+        let mut weight = |field: &str, cell: &Option<String>| -> Option<f64> {
+            let value = cell.as_deref().map(|value| value.trim()).filter(|value| !value.is_empty())?;
+            let (number, suffix) = match split_measurement(value) {
+                Ok(measurement) => measurement,
+                Err(reason) => {
+                    invalid(field, value, reason);
+                    return None;
+                }
+            };
+            let unit_text = if suffix.is_empty() {
+                cells.weight_unit.clone().unwrap_or_default()
+            } else {
+                suffix
+            };
+            match unit_text.parse::<WeightUnit>() {
+                Ok(_) if number < 0.0 => {
+                    invalid(field, value, "negative");
+                    None
+                }
+                Ok(unit) => Some(unit.to_kg(number)),
+                Err(_) => {
+                    invalid(field, value, &format!("unknown weight unit '{}'", unit_text));
+                    None
+                }
+            }
+        };
+
+        let gross_weight_kg = weight("grossWeight", &cells.gross_weight);
+        let chargeable_from_tms = weight("chargeableWeight", &cells.chargeable_weight);
+
+        let volume_m3 = match cells.volume.as_deref().map(|value| value.trim()).filter(|value| !value.is_empty()) {
+            Some(value) => match split_measurement(value) {
+                Ok((number, suffix)) => {
+                    let unit_text = if suffix.is_empty() {
+                        cells.volume_unit.clone().unwrap_or_default()
+                    } else {
+                        suffix
+                    };
+                    match unit_text.parse::<VolumeUnit>() {
+                        Ok(_) if number < 0.0 => {
+                            invalid("volume", value, "negative");
+                            None
+                        }
+                        Ok(unit) => Some(unit.to_m3(number)),
+                        Err(_) => {
+                            invalid("volume", value, &format!("unknown volume unit '{}'", unit_text));
+                            None
+                        }
+                    }
+                }
+                Err(reason) => {
+                    invalid("volume", value, reason);
+                    None
+                }
+            },
+            None => None,
+        };
+
+        // Prefer the chargeable weight from TMS, otherwise use the larger of gross and volumetric weight
+        let chargeable_weight_kg = chargeable_from_tms.or(match (gross_weight_kg, volume_m3) {
+            (Some(gross), Some(volume)) => Some(gross.max(volume * volumetric_kg_per_m3)),
+            (Some(gross), None) => Some(gross),
+            (None, Some(volume)) => Some(volume * volumetric_kg_per_m3),
+            (None, None) => None,
+        });
+
+        (
+            Self {
+                pieces,
+                gross_weight_kg,
+                chargeable_weight_kg,
+                volume_m3,
+            },
+            diagnostics,
+        )
+    }
+}
+
+/// The summed quantities of several dispo operations, e.g. everything loaded on one vehicle
+/// * jobs: The number of dispo operations
+/// * pieces: The total number of pieces
+/// * gross_weight_kg: The total gross weight
+/// * chargeable_weight_kg: The total chargeable weight
+/// * volume_m3: The total volume
+/// * incomplete: True if at least one dispo operation is missing a value
+#[derive(serde::Serialize, ts_rs::TS, Debug, Clone, PartialEq, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct QuantityTotals {
+    pub jobs: u32,
+    #[ts(type = "number")]
+    pub pieces: u64,
+    pub gross_weight_kg: f64,
+    pub chargeable_weight_kg: f64,
+    pub volume_m3: f64,
+    pub incomplete: bool,
+}
+
+impl QuantityTotals {
+    /// Add a Quantity to the totals.
+    /// The counts saturate instead of overflowing.
+    ///
+    /// # Arguments
+    /// * `quantity` - The Quantity to add
+    pub fn add(&mut self, quantity: &Quantity) {
+        self.jobs = self.jobs.saturating_add(1);
+        self.pieces = self.pieces.saturating_add(quantity.pieces.unwrap_or(0) as u64);
+        self.gross_weight_kg += quantity.gross_weight_kg.unwrap_or(0.0);
+        self.chargeable_weight_kg += quantity.chargeable_weight_kg.unwrap_or(0.0);
+        self.volume_m3 += quantity.volume_m3.unwrap_or(0.0);
+        self.incomplete |= quantity.pieces.is_none() || quantity.gross_weight_kg.is_none() || quantity.volume_m3.is_none();
+    }
+}
+
+impl<'a> FromIterator<&'a Quantity> for QuantityTotals {
+    fn from_iter<I: IntoIterator<Item = &'a Quantity>>(iter: I) -> Self {
+        let mut totals = QuantityTotals::default();
+        for quantity in iter {
+            totals.add(quantity);
+        }
+        totals
+    }
+}
+
+/// The capacity of a vehicle. A limit that is None is not checked.
+/// * max_pieces: The maximum number of pieces
+/// * max_weight_kg: The maximum payload
+/// * max_volume_m3: The maximum cargo volume
+#[derive(serde::Serialize, serde::Deserialize, ts_rs::TS, Debug, Clone, PartialEq, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct VehicleCapacity {
+    #[serde(default)]
+    pub max_pieces: Option<u32>,
+    #[serde(default)]
+    pub max_weight_kg: Option<f64>,
+    #[serde(default)]
+    pub max_volume_m3: Option<f64>,
+}
+
+/// A limit of a vehicle that is exceeded
+/// * Pieces: Too many pieces
+/// * Weight: Too much weight
+/// * Volume: Too much volume
+#[derive(serde::Serialize, ts_rs::TS, Debug, Copy, Clone, PartialEq, Eq)]
+pub enum CapacityLimit {
+    Pieces,
+    Weight,
+    Volume,
+}
+
+impl VehicleCapacity {
+    /// Check the totals of a vehicle against its capacity
+    ///
+    /// # Arguments
+    /// * `totals` - The totals of everything loaded on the vehicle
+    ///
+    /// # Returns
+    /// * The exceeded limits, empty if everything fits
+    pub fn check(&self, totals: &QuantityTotals) -> Vec<CapacityLimit> {
+        let mut exceeded = Vec::new();
+
+        if self.max_pieces.is_some_and(|max| totals.pieces > max as u64) {
+            exceeded.push(CapacityLimit::Pieces);
+        }
+        if self.max_weight_kg.is_some_and(|max| totals.gross_weight_kg > max) {
+            exceeded.push(CapacityLimit::Weight);
+        }
+        if self.max_volume_m3.is_some_and(|max| totals.volume_m3 > max) {
+            exceeded.push(CapacityLimit::Volume);
+        }
+
+        exceeded
+    }
+}
@@ -0,0 +1,389 @@
+// Copyright 2023 Felix Kahle. All rights reserved.
+
+use crate::{
+    job_row::{DispoMode, JobRow},
+    planned_time::MAX_CONFIGURED_MINUTES,
+};
+use chrono::{Duration, NaiveDateTime};
+use std::fmt::{self};
+
+/// The default number of minutes between collecting and delivering a shipment
+pub const DEFAULT_TRANSIT_MINUTES: i64 = 120;
+
+/// One job of a shipment, as sent by the frontend for linking.
+/// Only the fields needed for linking are used, so jobs of older imports can be linked as well.
+/// * job_number: The job number of the job
+/// * hawb_numbers: The hawb numbers of the job, a load can have several
+/// * mode: The mode of the job
+/// * country: The country of the stop
+/// * early_date: The early date of the job
+/// * late_date: The late date of the job
+#[derive(serde::Serialize, serde::Deserialize, ts_rs::TS, Debug, Clone, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct ShipmentLeg {
+    pub job_number: String,
+    pub hawb_numbers: Vec<String>,
+    pub mode: DispoMode,
+    #[serde(default)]
+    pub country: String,
+    pub early_date: NaiveDateTime,
+    pub late_date: NaiveDateTime,
+}
+
+impl From<&JobRow> for ShipmentLeg {
+    fn from(job: &JobRow) -> Self {
+        Self {
+            job_number: job.job_number.clone(),
+            hawb_numbers: job.hawbs.iter().map(|hawb| hawb.number.clone()).collect(),
+            mode: job.mode,
+            country: job.country.clone(),
+            early_date: job.early_date,
+            late_date: job.late_date,
+        }
+    }
+}
+
+impl fmt::Display for ShipmentLeg {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} {} ({})", self.mode, self.job_number, self.hawb_numbers.join(", "))
+    }
+}
+
+impl ShipmentLeg {
+    /// Check whether two legs belong to the same shipment.
+    /// Legs match if they share any hawb number or the job number, empty numbers never match.
+    ///
+    /// # Arguments
+    /// * `other` - The other leg
+    ///
+    /// # Returns
+    /// * True if both legs belong to the same shipment
+    fn matches(&self, other: &ShipmentLeg) -> bool {
+        let same = |a: &str, b: &str| !a.trim().is_empty() && a.trim().eq_ignore_ascii_case(b.trim());
+        let shared_hawb = self
+            .hawb_numbers
+            .iter()
+            .any(|hawb| other.hawb_numbers.iter().any(|other_hawb| same(hawb, other_hawb)));
+        shared_hawb || same(&self.job_number, &other.job_number)
+    }
+
+    /// Check whether the leg collects the freight
+    ///
+    /// # Returns
+    /// * True if the mode of the leg collects freight
+    fn is_collection(&self) -> bool {
+        self.mode.collects_freight()
+    }
+}
+
+/// The transit time between two countries
+/// * from_country: The country the freight is collected in
+/// * to_country: The country the freight is delivered to
+/// * minutes: The minutes between collecting and delivering
+#[derive(serde::Serialize, serde::Deserialize, ts_rs::TS, Debug, Clone, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct TransitTime {
+    pub from_country: String,
+    pub to_country: String,
+    #[ts(type = "number")]
+    pub minutes: i64,
+}
+
+/// Options for linking the legs of shipments
+/// * transit_minutes: The transit time used if no route specific transit time is configured
+/// * routes: The route specific transit times
+#[derive(serde::Serialize, serde::Deserialize, ts_rs::TS, Debug, Clone, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct LinkOptions {
+    #[serde(default = "default_transit_minutes")]
+    #[ts(type = "number")]
+    pub transit_minutes: i64,
+    #[serde(default)]
+    pub routes: Vec<TransitTime>,
+}
+
+/// The transit time used if none is configured
+///
+/// # Returns
+/// * DEFAULT_TRANSIT_MINUTES
+fn default_transit_minutes() -> i64 {
+    DEFAULT_TRANSIT_MINUTES
+}
+
+impl Default for LinkOptions {
+    fn default() -> Self {
+        Self {
+            transit_minutes: DEFAULT_TRANSIT_MINUTES,
+            routes: Vec::new(),
+        }
+    }
+}
+
+/// This error includes all errors of the link options
+///
+/// # Variants
+/// * `TransitOutOfRange` - A transit time is negative or larger than MAX_CONFIGURED_MINUTES
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum LinkOptionsError {
+    TransitOutOfRange((String, i64)),
+}
+
+impl fmt::Display for LinkOptionsError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LinkOptionsError::TransitOutOfRange((route, minutes)) => write!(
+                f,
+                "{} transit minutes are configured for {}, expected 0 to {}",
+                minutes, route, MAX_CONFIGURED_MINUTES
+            ),
+        }
+    }
+}
+
+impl std::error::Error for LinkOptionsError {}
+
+impl serde::Serialize for LinkOptionsError {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        serializer.serialize_str(&self.to_string())
+    }
+}
+
+/// Check that configured transit minutes are between 0 and MAX_CONFIGURED_MINUTES
+///
+/// # Arguments
+/// * `route` - The route the minutes belong to, for the error
+/// * `minutes` - The configured minutes
+///
+/// # Returns
+/// * Ok or an error if the minutes are out of range
+fn check_transit_minutes(route: &str, minutes: i64) -> Result<(), LinkOptionsError> {
+    match (0..=MAX_CONFIGURED_MINUTES).contains(&minutes) {
+        true => Ok(()),
+        false => Err(LinkOptionsError::TransitOutOfRange((route.to_string(), minutes))),
+    }
+}
+
+impl LinkOptions {
+    /// Check that the default and every route specific transit time can be used.
+    /// The options come from the frontend, so they are checked before the legs are linked.
+    ///
+    /// # Returns
+    /// * Ok or the first transit time that can not be used
+    pub fn validate(&self) -> Result<(), LinkOptionsError> {
+        check_transit_minutes("the default route", self.transit_minutes)?;
+        for route in &self.routes {
+            check_transit_minutes(&format!("the route {} to {}", route.from_country, route.to_country), route.minutes)?;
+        }
+        Ok(())
+    }
+
+    /// Get the transit time between two countries
+    ///
+    /// # Arguments
+    /// * `from_country` - The country the freight is collected in
+    /// * `to_country` - The country the freight is delivered to
+    ///
+    /// # Returns
+    /// * The route specific transit time or the default transit time, None if it is too large for a Duration
+    pub fn transit(&self, from_country: &str, to_country: &str) -> Option<Duration> {
+        let minutes = self
+            .routes
+            .iter()
+            .find(|route| {
+                route.from_country.eq_ignore_ascii_case(from_country.trim()) && route.to_country.eq_ignore_ascii_case(to_country.trim())
+            })
+            .map(|route| route.minutes)
+            .unwrap_or(self.transit_minutes);
+        Duration::try_minutes(minutes)
+    }
+}
+
+/// A problem with the chain of legs of a shipment
+/// * DuplicateLeg: The shipment has more than one leg of the same mode
+/// * MissingCollection: The shipment is delivered more than once, but never collected
+/// * DeliveryBeforeCollection: The delivery window ends before the collection window starts
+/// * Unreachable: The delivery window can not be reached after the collection window plus transit time
+#[derive(serde::Serialize, ts_rs::TS, Debug, Clone, PartialEq, Eq)]
+#[serde(tag = "type", rename_all = "camelCase", rename_all_fields = "camelCase")]
+pub enum ChainIssue {
+    DuplicateLeg {
+        mode: DispoMode,
+        job_numbers: Vec<String>,
+    },
+    MissingCollection,
+    DeliveryBeforeCollection {
+        collection: String,
+        delivery: String,
+    },
+    Unreachable {
+        collection: String,
+        delivery: String,
+        earliest_arrival: NaiveDateTime,
+    },
+}
+
+impl fmt::Display for ChainIssue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ChainIssue::DuplicateLeg { mode, job_numbers } => {
+                write!(f, "More than one {} leg: {}", mode, job_numbers.join(", "))
+            }
+            ChainIssue::MissingCollection => write!(f, "The shipment is delivered more than once, but never collected"),
+            ChainIssue::DeliveryBeforeCollection { collection, delivery } => {
+                write!(f, "Delivery {} ends before collection {} starts", delivery, collection)
+            }
+            ChainIssue::Unreachable {
+                collection,
+                delivery,
+                earliest_arrival,
+            } => write!(
+                f,
+                "Delivery {} can not be reached after collection {}, earliest arrival {}",
+                delivery, collection, earliest_arrival
+            ),
+        }
+    }
+}
+
+/// A shipment made of the linked legs of several jobs
+/// * hawb_number: The hawb number of the shipment, the job number of the first leg if no leg has one
+/// * legs: The legs, ordered by their early date
+/// * issues: The problems with the chain of legs, empty if the chain is valid
+#[derive(serde::Serialize, ts_rs::TS, Debug, Clone, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct Shipment {
+    pub hawb_number: String,
+    pub legs: Vec<ShipmentLeg>,
+    pub issues: Vec<ChainIssue>,
+}
+
+impl Shipment {
+    /// Check whether the chain of legs has problems
+    ///
+    /// # Returns
+    /// * True if at least one issue was found
+    pub fn is_broken(&self) -> bool {
+        !self.issues.is_empty()
+    }
+
+    /// Validate the chain of legs and collect the issues
+    ///
+    /// # Arguments
+    /// * `options` - The options used for the transit times
+    fn validate(&mut self, options: &LinkOptions) {
+        self.issues.clear();
+
+        for mode in [
+            DispoMode::Pickup,
+            DispoMode::Recovery,
+            DispoMode::Transfer,
+            DispoMode::Delivery,
+            DispoMode::Return,
+        ] {
+            let job_numbers: Vec<String> = self
+                .legs
+                .iter()
+                .filter(|leg| leg.mode == mode)
+                .map(|leg| leg.job_number.clone())
+                .collect();
+            if job_numbers.len() > 1 {
+                self.issues.push(ChainIssue::DuplicateLeg { mode, job_numbers });
+            }
+        }
+
+        let collections: Vec<&ShipmentLeg> = self.legs.iter().filter(|leg| leg.is_collection()).collect();
+        let deliveries: Vec<&ShipmentLeg> = self.legs.iter().filter(|leg| !leg.is_collection()).collect();
+
+        if collections.is_empty() && deliveries.len() > 1 {
+            self.issues.push(ChainIssue::MissingCollection);
+        }
+
+        for collection in &collections {
+            for delivery in &deliveries {
+                if delivery.late_date < collection.early_date {
+                    self.issues.push(ChainIssue::DeliveryBeforeCollection {
+                        collection: collection.job_number.clone(),
+                        delivery: delivery.job_number.clone(),
+                    });
+                    continue;
+                }
+
+                // An arrival after the last representable date can not be in any window
+                let earliest_arrival = options
+                    .transit(&collection.country, &delivery.country)
+                    .and_then(|transit| collection.early_date.checked_add_signed(transit))
+                    .unwrap_or(NaiveDateTime::MAX);
+                if earliest_arrival > delivery.late_date {
+                    self.issues.push(ChainIssue::Unreachable {
+                        collection: collection.job_number.clone(),
+                        delivery: delivery.job_number.clone(),
+                        earliest_arrival,
+                    });
+                }
+            }
+        }
+    }
+}
+
+impl fmt::Display for Shipment {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let legs = self.legs.iter().map(|leg| leg.to_string()).collect::<Vec<String>>().join(" -> ");
+        write!(f, "{}: {}", self.hawb_number, legs)
+    }
+}
+
+/// Link the legs of shipments across modes and import batches.
+/// Legs that share a hawb number or a job number, directly or through other legs, form one shipment.
+/// Legs without a partner are not returned.
+///
+/// # Arguments
+/// * `legs` - The legs of all known jobs
+/// * `options` - The options used for the transit times
+///
+/// # Returns
+/// * Result containing the shipments with at least two legs, the broken ones first, or an error if the options can not be used
+pub fn link_shipments(legs: Vec<ShipmentLeg>, options: &LinkOptions) -> Result<Vec<Shipment>, LinkOptionsError> {
+    options.validate()?;
+    let mut groups: Vec<Vec<ShipmentLeg>> = Vec::new();
+
+    for leg in legs {
+        // A leg can connect groups that did not match each other before, so all matching groups are merged
+        let (matching, mut rest): (Vec<Vec<ShipmentLeg>>, Vec<Vec<ShipmentLeg>>) =
+            groups.into_iter().partition(|group| group.iter().any(|other| other.matches(&leg)));
+        let mut merged: Vec<ShipmentLeg> = matching.into_iter().flatten().collect();
+        // The same job sent twice is only kept once
+        if !merged.contains(&leg) {
+            merged.push(leg);
+        }
+        rest.push(merged);
+        groups = rest;
+    }
+
+    let mut shipments: Vec<Shipment> = groups
+        .into_iter()
+        .filter(|group| group.len() > 1)
+        .map(|mut group| {
+            group.sort_by_key(|leg| (leg.early_date, !leg.is_collection()));
+            let hawb_number = group
+                .iter()
+                .flat_map(|leg| leg.hawb_numbers.iter())
+                .map(|hawb| hawb.trim())
+                .find(|hawb| !hawb.is_empty())
+                .unwrap_or(group[0].job_number.trim())
+                .to_string();
+            let mut shipment = Shipment {
+                hawb_number,
+                legs: group,
+                issues: Vec::new(),
+            };
+            shipment.validate(options);
+            shipment
+        })
+        .collect();
+
+    shipments.sort_by(|a, b| b.is_broken().cmp(&a.is_broken()).then_with(|| a.hawb_number.cmp(&b.hawb_number)));
+    Ok(shipments)
+}
@@ -0,0 +1,358 @@
+// Copyright 2023 Felix Kahle. All rights reserved.
+
+use crate::{
+    export::{ExportError, PlannedJob},
+    file_parsing::{HAWB_COLUMN_NAME, JOB_NUMBER_COLUMN_NAME},
+};
+use chrono::{NaiveDateTime, Utc};
+use std::{
+    collections::HashMap,
+    fmt::{self, Write},
+    fs::File,
+    io::BufWriter,
+    path::Path,
+};
+
+/// The name of the file the export state is kept in, inside the data directory of the app
+pub const TMS_EXPORT_STATE_FILE_NAME: &str = "tms_export_state.json";
+
+/// The TmsField enum represents the values that can be written into a column of the status export
+/// * LoadNumber: The Load # of the job
+/// * Hawb: The hawb number of the job
+/// * Mode: The mode of the job
+/// * Driver: The assigned driver
+/// * Vehicle: The assigned vehicle
+/// * PlannedPickup: The planned date, only set for jobs that stop at the shipper
+/// * PlannedDelivery: The planned date, only set for jobs that stop at the consignee
+/// * Status: The planning status of the job
+#[derive(serde::Serialize, serde::Deserialize, ts_rs::TS, Debug, Copy, Clone, PartialEq, Eq)]
+pub enum TmsField {
+    LoadNumber,
+    Hawb,
+    Mode,
+    Driver,
+    Vehicle,
+    PlannedPickup,
+    PlannedDelivery,
+    Status,
+}
+
+impl fmt::Display for TmsField {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TmsField::LoadNumber => write!(f, "Load Number"),
+            TmsField::Hawb => write!(f, "HAWB"),
+            TmsField::Mode => write!(f, "Mode"),
+            TmsField::Driver => write!(f, "Driver"),
+            TmsField::Vehicle => write!(f, "Vehicle"),
+            TmsField::PlannedPickup => write!(f, "Planned Pickup"),
+            TmsField::PlannedDelivery => write!(f, "Planned Delivery"),
+            TmsField::Status => write!(f, "Status"),
+        }
+    }
+}
+
+/// A column of the status export
+/// * header: The header of the column, as expected by TMS
+/// * field: The value written into the column
+#[derive(serde::Serialize, serde::Deserialize, ts_rs::TS, Debug, Clone, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct TmsColumn {
+    pub header: String,
+    pub field: TmsField,
+}
+
+impl TmsColumn {
+    /// Create a new TmsColumn
+    ///
+    /// # Arguments
+    /// * `header` - The header of the column
+    /// * `field` - The value written into the column
+    ///
+    /// # Returns
+    /// * A new TmsColumn
+    pub fn new(header: &str, field: TmsField) -> Self {
+        Self {
+            header: header.to_string(),
+            field,
+        }
+    }
+}
+
+/// The file format of the status export
+/// * Csv: A CSV file with the configured delimiter
+/// * Xlsx: An .xlsx file with one sheet
+#[derive(serde::Serialize, serde::Deserialize, ts_rs::TS, Debug, Copy, Clone, PartialEq, Eq)]
+pub enum TmsFileFormat {
+    Csv,
+    Xlsx,
+}
+
+/// The layout of the status export, it has to match the import definition in TMS
+/// * format: The file format
+/// * delimiter: The delimiter of CSV files
+/// * date_format: The chrono format of the planned dates
+/// * planned_status: The status of jobs with a driver
+/// * unplanned_status: The status of jobs without a driver
+/// * columns: The columns, in the order they are written
+#[derive(serde::Serialize, serde::Deserialize, ts_rs::TS, Debug, Clone, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct TmsExportLayout {
+    pub format: TmsFileFormat,
+    pub delimiter: char,
+    pub date_format: String,
+    pub planned_status: String,
+    pub unplanned_status: String,
+    pub columns: Vec<TmsColumn>,
+}
+
+impl Default for TmsExportLayout {
+    fn default() -> Self {
+        Self {
+            format: TmsFileFormat::Csv,
+            delimiter: ';',
+            date_format: "%m/%d/%Y %H:%M".to_string(),
+            planned_status: "PLANNED".to_string(),
+            unplanned_status: "OPEN".to_string(),
+            columns: vec![
+                TmsColumn::new(JOB_NUMBER_COLUMN_NAME, TmsField::LoadNumber),
+                TmsColumn::new(HAWB_COLUMN_NAME, TmsField::Hawb),
+                TmsColumn::new("Carrier", TmsField::Driver),
+                TmsColumn::new("Vehicle", TmsField::Vehicle),
+                TmsColumn::new("Planned Pickup", TmsField::PlannedPickup),
+                TmsColumn::new("Planned Delivery", TmsField::PlannedDelivery),
+                TmsColumn::new("Status", TmsField::Status),
+            ],
+        }
+    }
+}
+
+impl TmsExportLayout {
+    /// Format a planned date.
+    /// The date format is entered by the user, an invalid format is an error instead of a panic.
+    ///
+    /// # Arguments
+    /// * `date` - The date
+    ///
+    /// # Returns
+    /// * Result containing the formatted date or an error
+    fn format_date(&self, date: NaiveDateTime) -> Result<String, ExportError> {
+        let mut formatted = String::new();
+        write!(formatted, "{}", date.format(&self.date_format)).map_err(|_| ExportError::InvalidDateFormat(self.date_format.clone()))?;
+        Ok(formatted)
+    }
+
+    /// Get the value of one field of a job
+    ///
+    /// # Arguments
+    /// * `job` - The job
+    /// * `field` - The field
+    ///
+    /// # Returns
+    /// * Result containing the value as written into the export or an error
+    fn value(&self, job: &PlannedJob, field: TmsField) -> Result<String, ExportError> {
+        let planned = || self.format_date(job.job.calculated_date);
+        Ok(match field {
+            TmsField::LoadNumber => job.job.job_number.clone(),
+            TmsField::Hawb => job.job.hawb_number.clone(),
+            TmsField::Mode => job.job.mode.to_string(),
+            TmsField::Driver => job.driver.trim().to_string(),
+            TmsField::Vehicle => job.vehicle.trim().to_string(),
+            TmsField::PlannedPickup => match job.job.mode.collects_freight() {
+                true => planned()?,
+                false => String::new(),
+            },
+            TmsField::PlannedDelivery => match job.job.mode.collects_freight() {
+                true => String::new(),
+                false => planned()?,
+            },
+            TmsField::Status => match job.driver.trim().is_empty() {
+                true => self.unplanned_status.clone(),
+                false => self.planned_status.clone(),
+            },
+        })
+    }
+
+    /// Turn a job into one row of the export
+    ///
+    /// # Arguments
+    /// * `job` - The job
+    ///
+    /// # Returns
+    /// * Result containing the cells of the row, one per column, or an error
+    pub fn record(&self, job: &PlannedJob) -> Result<Vec<String>, ExportError> {
+        self.columns.iter().map(|column| self.value(job, column.field)).collect()
+    }
+
+    /// Get the header row of the export
+    ///
+    /// # Returns
+    /// * The headers of all columns
+    pub fn headers(&self) -> Vec<String> {
+        self.columns.iter().map(|column| column.header.clone()).collect()
+    }
+}
+
+/// What was exported to TMS before, so the next export only contains the changes
+/// * exported: The last exported row of every job, by job id
+/// * last_export: The time of the last export
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, Default, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct TmsExportState {
+    #[serde(default)]
+    pub exported: HashMap<String, Vec<String>>,
+    #[serde(default)]
+    pub last_export: Option<NaiveDateTime>,
+}
+
+impl TmsExportState {
+    /// Load the export state.
+    /// If nothing was exported yet, an empty state is returned.
+    ///
+    /// # Arguments
+    /// * `path` - The path of the state file
+    ///
+    /// # Returns
+    /// * Result containing the state or an error
+    pub fn load(path: &Path) -> Result<Self, ExportError> {
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+        let file = File::open(path)?;
+        Ok(serde_json::from_reader(std::io::BufReader::new(file))?)
+    }
+
+    /// Save the export state
+    ///
+    /// # Arguments
+    /// * `path` - The path of the state file
+    ///
+    /// # Returns
+    /// * Result containing nothing or an error
+    pub fn save(&self, path: &Path) -> Result<(), ExportError> {
+        if let Some(directory) = path.parent() {
+            std::fs::create_dir_all(directory)?;
+        }
+        serde_json::to_writer(BufWriter::new(File::create(path)?), self)?;
+        Ok(())
+    }
+}
+
+/// The result of a status export
+/// * exported: The number of jobs written to the file
+/// * unchanged: The number of jobs left out, because they were exported before without changes
+/// * written: Whether a file was written, no file is written if nothing changed
+#[derive(serde::Serialize, ts_rs::TS, Debug, Clone, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct TmsExportSummary {
+    pub exported: usize,
+    pub unchanged: usize,
+    pub written: bool,
+}
+
+/// Write the rows of the status export as CSV
+///
+/// # Arguments
+/// * `path` - The path of the file
+/// * `layout` - The layout of the export
+/// * `records` - The rows to write
+///
+/// # Returns
+/// * Result containing nothing or an error
+fn write_csv(path: &str, layout: &TmsExportLayout, records: &[Vec<String>]) -> Result<(), ExportError> {
+    if !layout.delimiter.is_ascii() {
+        return Err(ExportError::InvalidDelimiter(layout.delimiter));
+    }
+    let mut writer = csv::WriterBuilder::new().delimiter(layout.delimiter as u8).from_path(path)?;
+    writer.write_record(layout.headers())?;
+    for record in records {
+        writer.write_record(record)?;
+    }
+    writer.flush()?;
+    Ok(())
+}
+
+/// Write the rows of the status export as .xlsx
+///
+/// # Arguments
+/// * `path` - The path of the file
+/// * `layout` - The layout of the export
+/// * `records` - The rows to write
+///
+/// # Returns
+/// * Result containing nothing or an error
+fn write_xlsx(path: &str, layout: &TmsExportLayout, records: &[Vec<String>]) -> Result<(), ExportError> {
+    let mut workbook = rust_xlsxwriter::Workbook::new();
+    let worksheet = workbook.add_worksheet();
+    for (column, header) in layout.headers().iter().enumerate() {
+        worksheet.write_string(0, column as u16, header)?;
+    }
+    for (row, record) in records.iter().enumerate() {
+        for (column, cell) in record.iter().enumerate() {
+            worksheet.write_string(row as u32 + 1, column as u16, cell)?;
+        }
+    }
+    workbook.save(path)?;
+    Ok(())
+}
+
+/// Export the planning status of jobs to a file TMS can import.
+/// Only jobs that changed since the last export are written, unless a full export is requested.
+/// The export state is only updated after the file was written.
+///
+/// # Arguments
+/// * `path` - The path of the file to write
+/// * `state_path` - The path of the export state file
+/// * `jobs` - The jobs of the dispatch plan
+/// * `layout` - The layout of the export
+/// * `full` - Whether all jobs are exported, regardless of what was exported before
+///
+/// # Returns
+/// * Result containing the summary of the export or an error
+pub fn export_status(
+    path: &str,
+    state_path: &Path,
+    jobs: &[PlannedJob],
+    layout: &TmsExportLayout,
+    full: bool,
+) -> Result<TmsExportSummary, ExportError> {
+    let mut state = TmsExportState::load(state_path)?;
+
+    let mut sorted: Vec<&PlannedJob> = jobs.iter().collect();
+    sorted.sort_by_key(|planned| planned.job.calculated_date);
+
+    let mut changed: Vec<(String, Vec<String>)> = Vec::new();
+    for job in sorted {
+        let id = job.id();
+        let record = layout.record(job)?;
+        if full || state.exported.get(&id) != Some(&record) {
+            changed.push((id, record));
+        }
+    }
+
+    let unchanged = jobs.len() - changed.len();
+    if changed.is_empty() {
+        return Ok(TmsExportSummary {
+            exported: 0,
+            unchanged,
+            written: false,
+        });
+    }
+
+    let records: Vec<Vec<String>> = changed.iter().map(|(_, record)| record.clone()).collect();
+    match layout.format {
+        TmsFileFormat::Csv => write_csv(path, layout, &records)?,
+        TmsFileFormat::Xlsx => write_xlsx(path, layout, &records)?,
+    }
+
+    let exported = changed.len();
+    state.exported.extend(changed);
+    state.last_export = Some(Utc::now().naive_utc());
+    state.save(state_path)?;
+
+    Ok(TmsExportSummary {
+        exported,
+        unchanged,
+        written: true,
+    })
+}
@@ -0,0 +1,276 @@
+// Copyright 2023 Felix Kahle. All rights reserved.
+
+use crate::job_row::DispoMode;
+use chrono::NaiveDateTime;
+use std::fmt::{self};
+
+/// The name of the policy that reproduces the original fixed tolerance buckets
+pub const LEGACY_POLICY_NAME: &str = "Legacy";
+
+/// The buckets used by the legacy policy, in minutes
+pub const LEGACY_BUCKETS: [i64; 5] = [0, 15, 30, 60, 120];
+
+/// Decides what happens with a difference that is larger than the largest bucket
+/// * LargestBucket: The difference is rounded down to the largest bucket
+/// * Exact: The exact difference is kept
+#[derive(serde::Serialize, serde::Deserialize, ts_rs::TS, Debug, Copy, Clone, PartialEq, Eq)]
+pub enum BucketOverflow {
+    LargestBucket,
+    Exact,
+}
+
+/// Decides how the difference between the planned date and a window edge becomes a tolerance
+/// * Exact: The exact difference in minutes is used
+/// * Buckets: The difference is rounded up to the next bucket
+#[derive(serde::Serialize, serde::Deserialize, ts_rs::TS, Debug, Clone, PartialEq, Eq)]
+#[serde(tag = "type", rename_all = "camelCase")]
+pub enum ToleranceRounding {
+    Exact,
+    Buckets {
+        #[ts(type = "number[]")]
+        buckets: Vec<i64>,
+        overflow: BucketOverflow,
+    },
+}
+
+impl ToleranceRounding {
+    /// Round a difference in minutes using this rounding.
+    /// Negative differences are treated as 0.
+    ///
+    /// # Arguments
+    /// * `minutes` - The difference in minutes
+    ///
+    /// # Returns
+    /// * The tolerance in minutes
+    pub fn apply(&self, minutes: i64) -> i64 {
+        let minutes = minutes.max(0);
+
+        match self {
+            ToleranceRounding::Exact => minutes,
+            ToleranceRounding::Buckets { buckets, overflow } => {
+                let mut sorted = buckets.clone();
+                sorted.sort_unstable();
+
+                match sorted.iter().find(|&&bucket| minutes <= bucket) {
+                    Some(bucket) => *bucket,
+                    None => match (overflow, sorted.last()) {
+                        (BucketOverflow::LargestBucket, Some(largest)) => *largest,
+                        _ => minutes,
+                    },
+                }
+            }
+        }
+    }
+}
+
+impl fmt::Display for ToleranceRounding {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ToleranceRounding::Exact => write!(f, "Exact"),
+            ToleranceRounding::Buckets { buckets, .. } => write!(
+                f,
+                "Buckets [{}]",
+                buckets.iter().map(|bucket| bucket.to_string()).collect::<Vec<String>>().join(", ")
+            ),
+        }
+    }
+}
+
+/// A tolerance policy as agreed with a customer or for a country
+/// The optional customer, country and mode fields decide which jobs the policy applies to.
+/// A field that is None matches every job.
+/// * name: The name of the policy, reported on every job it produced the tolerance for
+/// * customer: The customer the policy applies to
+/// * country: The country the policy applies to
+/// * mode: The mode the policy applies to
+/// * before: The rounding of the time between the early date and the planned date
+/// * after: The rounding of the time between the planned date and the late date
+#[derive(serde::Serialize, serde::Deserialize, ts_rs::TS, Debug, Clone, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct TolerancePolicy {
+    pub name: String,
+    #[serde(default)]
+    pub customer: Option<String>,
+    #[serde(default)]
+    pub country: Option<String>,
+    #[serde(default)]
+    pub mode: Option<DispoMode>,
+    pub before: ToleranceRounding,
+    pub after: ToleranceRounding,
+}
+
+impl TolerancePolicy {
+    /// Create the policy that reproduces the original 0/15/30/60/120 buckets
+    ///
+    /// # Returns
+    /// * The legacy policy
+    pub fn legacy() -> Self {
+        let rounding = ToleranceRounding::Buckets {
+            buckets: LEGACY_BUCKETS.to_vec(),
+            overflow: BucketOverflow::LargestBucket,
+        };
+
+        Self {
+            name: LEGACY_POLICY_NAME.to_string(),
+            customer: None,
+            country: None,
+            mode: None,
+            before: rounding.clone(),
+            after: rounding,
+        }
+    }
+
+    /// Get how specific the policy is for a job, or None if it does not apply
+    /// A matching customer weighs more than a matching country and mode together,
+    /// and a matching country more than a matching mode.
+    /// Customers and countries are compared case insensitive.
+    ///
+    /// # Arguments
+    /// * `customer` - The customer of the job
+    /// * `country` - The country of the job
+    /// * `mode` - The mode of the job
+    ///
+    /// # Returns
+    /// * The specificity or None if the policy does not apply
+    fn specificity(&self, customer: &str, country: &str, mode: DispoMode) -> Option<u8> {
+        let mut specificity = 0;
+
+        if let Some(policy_customer) = &self.customer {
+            if !policy_customer.trim().eq_ignore_ascii_case(customer.trim()) {
+                return None;
+            }
+            specificity += 4;
+        }
+        if let Some(policy_country) = &self.country {
+            if !policy_country.trim().eq_ignore_ascii_case(country.trim()) {
+                return None;
+            }
+            specificity += 2;
+        }
+        if let Some(policy_mode) = self.mode {
+            if policy_mode != mode {
+                return None;
+            }
+            specificity += 1;
+        }
+
+        Some(specificity)
+    }
+
+    /// Calculate the tolerance of a job using this policy.
+    /// Tolerances that do not fit into an i32, like the exact tolerance of a window of thousands of years, are clamped.
+    ///
+    /// # Arguments
+    /// * `early_date` - The early date of the dispo operation
+    /// * `late_date` - The late date of the dispo operation
+    /// * `calculated_date` - The planned date of the dispo operation
+    ///
+    /// # Returns
+    /// * The tolerance of the dispo operation
+    pub fn calculate(&self, early_date: NaiveDateTime, late_date: NaiveDateTime, calculated_date: NaiveDateTime) -> Tolerance {
+        let before = calculated_date.signed_duration_since(early_date).num_minutes();
+        let after = late_date.signed_duration_since(calculated_date).num_minutes();
+
+        Tolerance {
+            before: i32::try_from(self.before.apply(before)).unwrap_or(i32::MAX),
+            after: i32::try_from(self.after.apply(after)).unwrap_or(i32::MAX),
+            policy: self.name.clone(),
+        }
+    }
+}
+
+/// The set of all configured tolerance policies
+/// * policies: The configured policies
+/// * default: The policy used if no configured policy applies
+#[derive(serde::Serialize, serde::Deserialize, ts_rs::TS, Debug, Clone, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct TolerancePolicySet {
+    #[serde(default)]
+    pub policies: Vec<TolerancePolicy>,
+    #[serde(default = "TolerancePolicy::legacy")]
+    pub default: TolerancePolicy,
+}
+
+impl Default for TolerancePolicySet {
+    fn default() -> Self {
+        Self {
+            policies: Vec::new(),
+            default: TolerancePolicy::legacy(),
+        }
+    }
+}
+
+impl TolerancePolicySet {
+    /// Find the policy that applies to a job.
+    /// A policy for the customer wins over one for the country, which wins over one for the mode.
+    /// The most specific policy wins, if two policies are equally specific the first one wins.
+    /// The keys of the customer are tried in order, so a policy for the party code wins over one for the name.
+    ///
+    /// # Arguments
+    /// * `customers` - The keys of the customer of the job, see Party::customer_keys
+    /// * `country` - The country of the job
+    /// * `mode` - The mode of the job
+    ///
+    /// # Returns
+    /// * The policy that applies
+    pub fn resolve(&self, customers: &[&str], country: &str, mode: DispoMode) -> &TolerancePolicy {
+        for customer in customers {
+            let policy = self.resolve_customer(customer, country, mode);
+            if policy.customer.is_some() {
+                return policy;
+            }
+        }
+        self.resolve_customer("", country, mode)
+    }
+
+    /// Find the policy that applies to a job for one key of its customer
+    ///
+    /// # Arguments
+    /// * `customer` - The key of the customer of the job
+    /// * `country` - The country of the job
+    /// * `mode` - The mode of the job
+    ///
+    /// # Returns
+    /// * The policy that applies
+    fn resolve_customer(&self, customer: &str, country: &str, mode: DispoMode) -> &TolerancePolicy {
+        let mut best: Option<(u8, &TolerancePolicy)> = None;
+
+        for policy in &self.policies {
+            if let Some(specificity) = policy.specificity(customer, country, mode) {
+                if best.is_none_or(|(best_specificity, _)| specificity > best_specificity) {
+                    best = Some((specificity, policy));
+                }
+            }
+        }
+
+        best.map(|(_, policy)| policy).unwrap_or(&self.default)
+    }
+}
+
+/// The tolerance of a dispo operation
+/// * before: The tolerance in minutes before the planned date
+/// * after: The tolerance in minutes after the planned date
+/// * policy: The name of the policy that produced the tolerance
+#[derive(serde::Serialize, ts_rs::TS, Debug, Clone, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct Tolerance {
+    pub before: i32,
+    pub after: i32,
+    pub policy: String,
+}
+
+impl Tolerance {
+    /// The larger of the two sides, used where a single symmetric tolerance is expected
+    ///
+    /// # Returns
+    /// * The symmetric tolerance in minutes
+    pub fn symmetric(&self) -> i32 {
+        self.before.max(self.after)
+    }
+}
+
+impl fmt::Display for Tolerance {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "-{} / +{} ({})", self.before, self.after, self.policy)
+    }
+}
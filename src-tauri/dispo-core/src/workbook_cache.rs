@@ -0,0 +1,391 @@
+// Copyright 2023 Felix Kahle. All rights reserved.
+
+use chrono::{NaiveDateTime, Utc};
+use polars::prelude::{DataFrame, IpcReader, IpcWriter, SerReader, SerWriter};
+use std::{
+    fmt::{self},
+    fs::File,
+    io::{BufReader, BufWriter},
+    path::{Path, PathBuf},
+    sync::{Mutex, OnceLock},
+};
+
+/// The version of the sheet parser.
+/// Must be increased whenever parse_sheet produces different DataFrames, so old cache entries are not used anymore.
+pub const PARSER_VERSION: u32 = 1;
+
+/// The size the cache may grow to if no other limit is configured
+pub const DEFAULT_CACHE_LIMIT_BYTES: u64 = 256 * 1024 * 1024;
+
+/// The cache of the app, only set once configure was called
+static WORKBOOK_CACHE: OnceLock<WorkbookCache> = OnceLock::new();
+
+/// This error includes all errors that can occur while writing the workbook cache.
+/// Reading never fails, an entry that can not be read is a cache miss.
+///
+/// # Variants
+/// * `Io` - A file of the cache could not be written or removed
+/// * `Json` - The CacheEntry could not be written
+/// * `Polars` - The DataFrame could not be written
+#[derive(Debug)]
+pub enum WorkbookCacheError {
+    Io(std::io::Error),
+    Json(serde_json::Error),
+    Polars(polars::error::PolarsError),
+}
+
+impl From<std::io::Error> for WorkbookCacheError {
+    fn from(error: std::io::Error) -> Self {
+        WorkbookCacheError::Io(error)
+    }
+}
+
+impl From<serde_json::Error> for WorkbookCacheError {
+    fn from(error: serde_json::Error) -> Self {
+        WorkbookCacheError::Json(error)
+    }
+}
+
+impl From<polars::error::PolarsError> for WorkbookCacheError {
+    fn from(error: polars::error::PolarsError) -> Self {
+        WorkbookCacheError::Polars(error)
+    }
+}
+
+impl fmt::Display for WorkbookCacheError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            WorkbookCacheError::Io(error) => write!(f, "IoError: {}", error),
+            WorkbookCacheError::Json(error) => write!(f, "JsonError: {}", error),
+            WorkbookCacheError::Polars(error) => write!(f, "PolarsError: {}", error),
+        }
+    }
+}
+
+impl std::error::Error for WorkbookCacheError {}
+
+impl serde::Serialize for WorkbookCacheError {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        serializer.serialize_str(&self.to_string())
+    }
+}
+
+/// The key of a cached workbook.
+/// Only depends on the content of the file and the parser, so renamed or copied files are found as well.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct CacheKey(String);
+
+impl CacheKey {
+    /// Create the key of a workbook
+    ///
+    /// # Arguments
+    /// * `contents` - The content of the workbook file
+    ///
+    /// # Returns
+    /// * The key of the workbook
+    pub fn of(contents: &[u8]) -> Self {
+        Self::versioned(contents, PARSER_VERSION)
+    }
+
+    /// Create the key of a workbook for a version of the sheet parser
+    ///
+    /// # Arguments
+    /// * `contents` - The content of the workbook file
+    /// * `parser_version` - The version of the sheet parser
+    ///
+    /// # Returns
+    /// * The key of the workbook
+    pub fn versioned(contents: &[u8], parser_version: u32) -> Self {
+        Self(format!("{:016x}-{}-v{}", content_hash(contents), contents.len(), parser_version))
+    }
+}
+
+/// Hash the content of a file.
+/// FNV-1a is used because it is stable across builds, unlike the hasher of the standard library.
+///
+/// # Arguments
+/// * `contents` - The content of the file
+///
+/// # Returns
+/// * The hash of the content
+pub fn content_hash(contents: &[u8]) -> u64 {
+    contents.iter().fold(0xcbf29ce484222325u64, |hash, byte| {
+        (hash ^ *byte as u64).wrapping_mul(0x100000001b3)
+    })
+}
+
+/// What is known about a cached workbook, stored next to the DataFrame
+/// * headers: All headers of the sheet, in the order of the sheet
+/// * columns: The headers of the columns that were parsed and cached
+/// * bytes: The size of the cached DataFrame
+/// * last_used: The last time the entry was read or written, used for the eviction
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+struct CacheEntry {
+    headers: Vec<String>,
+    columns: Vec<String>,
+    bytes: u64,
+    last_used: NaiveDateTime,
+}
+
+impl CacheEntry {
+    /// Get the cached columns a caller asked for
+    ///
+    /// # Arguments
+    /// * `projection` - The headers of the wanted columns, or None for all columns
+    ///
+    /// # Returns
+    /// * The wanted columns in the order of the sheet, None if not all of them are cached
+    fn wanted_columns(&self, projection: Option<&[&str]>) -> Option<Vec<String>> {
+        let wanted: Vec<String> = self
+            .headers
+            .iter()
+            .filter(|header| projection.is_none_or(|projection| projection.contains(&header.as_str())))
+            .cloned()
+            .collect();
+        match wanted.iter().all(|header| self.columns.contains(header)) {
+            true => Some(wanted),
+            false => None,
+        }
+    }
+}
+
+/// A cache of parsed workbooks in a directory.
+/// Every entry is an Arrow IPC file with the DataFrame and a JSON file with its CacheEntry.
+/// Once the entries are larger than the limit, the least recently used ones are removed.
+/// * directory: The directory of the entries
+/// * limit_bytes: The size the entries may grow to
+/// * lock: Serialises the writes, the workbooks of an import are parsed concurrently
+pub struct WorkbookCache {
+    directory: PathBuf,
+    limit_bytes: u64,
+    lock: Mutex<()>,
+}
+
+impl WorkbookCache {
+    /// Create a new WorkbookCache
+    ///
+    /// # Arguments
+    /// * `directory` - The directory of the entries, created on the first write
+    /// * `limit_bytes` - The size the entries may grow to
+    ///
+    /// # Returns
+    /// * A new WorkbookCache
+    pub fn new(directory: PathBuf, limit_bytes: u64) -> Self {
+        Self {
+            directory,
+            limit_bytes,
+            lock: Mutex::new(()),
+        }
+    }
+
+    /// Get the path of the DataFrame of an entry
+    ///
+    /// # Arguments
+    /// * `key` - The key of the workbook
+    ///
+    /// # Returns
+    /// * The path of the Arrow IPC file
+    fn data_path(&self, key: &CacheKey) -> PathBuf {
+        self.directory.join(format!("{}.arrow", key.0))
+    }
+
+    /// Get the path of the CacheEntry of an entry
+    ///
+    /// # Arguments
+    /// * `key` - The key of the workbook
+    ///
+    /// # Returns
+    /// * The path of the JSON file
+    fn entry_path(&self, key: &CacheKey) -> PathBuf {
+        self.directory.join(format!("{}.json", key.0))
+    }
+
+    /// Read the CacheEntry of an entry
+    ///
+    /// # Arguments
+    /// * `path` - The path of the JSON file
+    ///
+    /// # Returns
+    /// * The entry or None if it does not exist or can not be read
+    fn read_entry(path: &Path) -> Option<CacheEntry> {
+        let file = File::open(path).ok()?;
+        serde_json::from_reader(BufReader::new(file)).ok()
+    }
+
+    /// Write the CacheEntry of an entry.
+    /// The entry is written to a temporary file first, so readers and the eviction never see a partial entry.
+    /// Must only be called while holding the lock.
+    ///
+    /// # Arguments
+    /// * `path` - The path of the JSON file
+    /// * `entry` - The entry to write
+    ///
+    /// # Returns
+    /// * Result containing nothing or an error
+    fn write_entry(path: &Path, entry: &CacheEntry) -> Result<(), WorkbookCacheError> {
+        let temporary_path = path.with_extension("json.tmp");
+        let mut writer = BufWriter::new(File::create(&temporary_path)?);
+        serde_json::to_writer(&mut writer, entry)?;
+        writer.into_inner().map_err(|error| error.into_error())?;
+        std::fs::rename(&temporary_path, path)?;
+        Ok(())
+    }
+
+    /// Load a cached workbook
+    ///
+    /// # Arguments
+    /// * `key` - The key of the workbook
+    /// * `projection` - The headers of the wanted columns, or None for all columns
+    ///
+    /// # Returns
+    /// * The DataFrame with the wanted columns, None if they are not all cached
+    pub fn load(&self, key: &CacheKey, projection: Option<&[&str]>) -> Option<DataFrame> {
+        let entry = Self::read_entry(&self.entry_path(key))?;
+        let columns = entry.wanted_columns(projection)?;
+        let file = File::open(self.data_path(key)).ok()?;
+        let df = IpcReader::new(file).with_columns(Some(columns)).finish().ok()?;
+
+        // A stale timestamp only makes the entry an earlier candidate for the eviction
+        let _ = self.touch(key);
+
+        Some(df)
+    }
+
+    /// Mark an entry as used now.
+    /// The entry is read again under the lock, so columns stored since it was loaded are kept.
+    ///
+    /// # Arguments
+    /// * `key` - The key of the workbook
+    ///
+    /// # Returns
+    /// * Result containing nothing or an error
+    fn touch(&self, key: &CacheKey) -> Result<(), WorkbookCacheError> {
+        let _guard = self.lock.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+        let path = self.entry_path(key);
+        // The entry was evicted in the meantime
+        let Some(mut entry) = Self::read_entry(&path) else {
+            return Ok(());
+        };
+        entry.last_used = Utc::now().naive_utc();
+        Self::write_entry(&path, &entry)
+    }
+
+    /// Get the columns cached for a workbook.
+    /// Parsing them again together with the wanted columns keeps alternating imports from evicting each other's columns.
+    ///
+    /// # Arguments
+    /// * `key` - The key of the workbook
+    ///
+    /// # Returns
+    /// * The headers of the cached columns, empty if the workbook is not cached
+    pub fn cached_columns(&self, key: &CacheKey) -> Vec<String> {
+        Self::read_entry(&self.entry_path(key))
+            .map(|entry| entry.columns)
+            .unwrap_or_default()
+    }
+
+    /// Store a parsed workbook, replacing the entry of the same key.
+    /// The DataFrame is written to a temporary file first, so readers never see a partial entry.
+    ///
+    /// # Arguments
+    /// * `key` - The key of the workbook
+    /// * `headers` - All headers of the sheet
+    /// * `df` - The parsed columns
+    ///
+    /// # Returns
+    /// * Result containing nothing or an error
+    pub fn store(&self, key: &CacheKey, headers: &[String], df: &DataFrame) -> Result<(), WorkbookCacheError> {
+        let _guard = self.lock.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+        std::fs::create_dir_all(&self.directory)?;
+
+        let data_path = self.data_path(key);
+        let temporary_path = data_path.with_extension("arrow.tmp");
+        IpcWriter::new(&mut BufWriter::new(File::create(&temporary_path)?)).finish(&mut df.clone())?;
+        std::fs::rename(&temporary_path, &data_path)?;
+
+        let entry = CacheEntry {
+            headers: headers.to_vec(),
+            columns: df.get_column_names().iter().map(|name| name.to_string()).collect(),
+            bytes: std::fs::metadata(&data_path)?.len(),
+            last_used: Utc::now().naive_utc(),
+        };
+        Self::write_entry(&self.entry_path(key), &entry)?;
+
+        self.evict()
+    }
+
+    /// Remove the least recently used entries until the entries fit into the limit.
+    /// Must only be called while holding the lock.
+    ///
+    /// # Returns
+    /// * Result containing nothing or an error
+    fn evict(&self) -> Result<(), WorkbookCacheError> {
+        let mut entries: Vec<(PathBuf, CacheEntry)> = Vec::new();
+        for file in std::fs::read_dir(&self.directory)? {
+            let path = file?.path();
+            if path.extension().is_some_and(|extension| extension == "json") {
+                match Self::read_entry(&path) {
+                    Some(entry) => entries.push((path, entry)),
+                    // Entries are replaced by a rename, so an entry that can not be read is broken and not still being written
+                    None => remove_entry(&path),
+                }
+            }
+        }
+
+        entries.sort_by_key(|(_, entry)| entry.last_used);
+        let mut total: u64 = entries.iter().map(|(_, entry)| entry.bytes).sum();
+        for (path, entry) in entries {
+            if total <= self.limit_bytes {
+                break;
+            }
+            remove_entry(&path);
+            total = total.saturating_sub(entry.bytes);
+        }
+        Ok(())
+    }
+
+    /// Remove all entries
+    ///
+    /// # Returns
+    /// * Result containing nothing or an error
+    pub fn clear(&self) -> Result<(), WorkbookCacheError> {
+        let _guard = self.lock.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+        match std::fs::remove_dir_all(&self.directory) {
+            Err(error) if error.kind() != std::io::ErrorKind::NotFound => Err(error.into()),
+            _ => Ok(()),
+        }
+    }
+}
+
+/// Remove the files of an entry
+///
+/// # Arguments
+/// * `entry_path` - The path of the JSON file of the entry
+fn remove_entry(entry_path: &Path) {
+    // Files that are already gone do not need to be removed
+    let _ = std::fs::remove_file(entry_path.with_extension("arrow"));
+    let _ = std::fs::remove_file(entry_path);
+}
+
+/// Set the cache used by parse_xls_file_tms.
+/// Without calling this, for example in the CLI, every workbook is parsed from scratch.
+/// Only the first call has an effect.
+///
+/// # Arguments
+/// * `directory` - The directory of the entries
+/// * `limit_bytes` - The size the entries may grow to
+pub fn configure(directory: PathBuf, limit_bytes: u64) {
+    let _ = WORKBOOK_CACHE.set(WorkbookCache::new(directory, limit_bytes));
+}
+
+/// Get the cache used by parse_xls_file_tms
+///
+/// # Returns
+/// * The cache or None if it was not configured
+pub fn global() -> Option<&'static WorkbookCache> {
+    WORKBOOK_CACHE.get()
+}
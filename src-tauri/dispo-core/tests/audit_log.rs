@@ -0,0 +1,178 @@
+// Copyright 2023 Felix Kahle. All rights reserved.
+
+//! Recording, querying and exporting the audit log
+
+mod common;
+
+use dispo_core::{
+    audit_log::{self, ImportedFile, AUDIT_LOG_COLUMNS},
+    AuditAction, AuditLog, AuditQuery, DispoMode, JobRow, JobStatus,
+};
+
+#[test]
+fn entries_are_appended_and_numbered() {
+    let path = common::temp_path("audit-appended.jsonl");
+    let log = AuditLog::open(path.clone(), "dispatcher").expect("the log can be opened");
+    let fixture = common::fixture_path("cl_view.csv");
+    let contents = std::fs::read(&fixture).expect("the fixture can be read");
+    let files = vec![ImportedFile::of(&fixture.to_string_lossy(), &contents)];
+    log.append(
+        AuditAction::Import {
+            mode: DispoMode::Delivery,
+            files,
+            watched: false,
+        },
+        vec!["Delivery:L1001".to_string()],
+        Vec::new(),
+    )
+    .expect("the entry can be appended");
+
+    // A reopened log continues the numbering instead of starting over
+    let log = AuditLog::open(path.clone(), "supervisor").expect("the log can be opened again");
+    let entry = log
+        .append(AuditAction::JobDeleted, Vec::new(), Vec::new())
+        .expect("the entry can be appended");
+    assert_eq!(entry.sequence, 2);
+
+    let entries = log.entries(&AuditQuery::default()).expect("the log can be read").entries;
+    assert_eq!(entries.len(), 2);
+    assert_eq!(entries[0].user, "dispatcher");
+    assert_eq!(entries[1].user, "supervisor");
+    match &entries[0].action {
+        AuditAction::Import { files, .. } => assert_eq!(files[0].hash.len(), 16),
+        action => panic!("expected an import, got {}", action),
+    }
+    let _ = std::fs::remove_file(path);
+}
+
+#[test]
+fn only_changed_fields_are_recorded() {
+    let before = common::import(DispoMode::Delivery);
+    let mut after = before.clone();
+    let at = after[1].early_date;
+    after[1]
+        .change_status(JobStatus::Planned, None, "", at)
+        .expect("the transition is allowed");
+    after.remove(2);
+
+    let (job_ids, changes) = audit_log::diff_jobs(&before, &after, JobRow::id);
+    assert_eq!(job_ids, ["Delivery:L1002", "Delivery:L1003"]);
+
+    let l1002: Vec<(&str, &str, &str)> = changes
+        .iter()
+        .filter(|change| change.job_id == "Delivery:L1002")
+        .map(|change| (change.field.as_str(), change.before.as_str(), change.after.as_str()))
+        .collect();
+    assert_eq!(l1002.len(), 2);
+    assert_eq!(l1002[0], ("status", "Imported", "Planned"));
+    assert_eq!(l1002[1].0, "statusHistory");
+
+    // A deleted job has all its fields in before and nothing in after
+    assert!(changes
+        .iter()
+        .filter(|change| change.job_id == "Delivery:L1003")
+        .all(|change| change.after.is_empty()));
+}
+
+#[test]
+fn entries_are_queried_per_job_and_exported() {
+    let path = common::temp_path("audit-queried.jsonl");
+    let log = AuditLog::open(path.clone(), "dispatcher").expect("the log can be opened");
+    let before = common::import(DispoMode::Delivery);
+    let mut after = before.clone();
+    let at = after[0].early_date;
+    after[0]
+        .change_status(JobStatus::Planned, None, "", at)
+        .expect("the transition is allowed");
+    let (job_ids, changes) = audit_log::diff_jobs(&before, &after, JobRow::id);
+    log.append(AuditAction::Assignment, job_ids, changes)
+        .expect("the entry can be appended");
+    let print = AuditAction::Print {
+        printer: "Office".to_string(),
+    };
+    log.append(print, vec!["Delivery:L1002".to_string()], Vec::new())
+        .expect("the entry can be appended");
+
+    let query = AuditQuery {
+        job_id: Some("Delivery:L1001".to_string()),
+        ..AuditQuery::default()
+    };
+    let entries = log.entries(&query).expect("the log can be read").entries;
+    assert_eq!(entries.len(), 1);
+    assert_eq!(entries[0].action, AuditAction::Assignment);
+
+    let mut csv = Vec::new();
+    audit_log::write_csv(&mut csv, &log.entries(&AuditQuery::default()).expect("the log can be read").entries)
+        .expect("the log can be exported");
+    let csv = String::from_utf8(csv).expect("the export is UTF-8");
+    let lines: Vec<&str> = csv.lines().collect();
+    assert_eq!(lines[0], AUDIT_LOG_COLUMNS.join(","));
+    // One row per changed field of the assignment and one row for the print
+    assert_eq!(lines.len(), 1 + 2 + 1);
+    assert!(lines[3].contains("Print on Office"));
+    let _ = std::fs::remove_file(path);
+}
+
+#[test]
+fn broken_line_is_reported_and_skipped() {
+    let path = common::temp_path("audit-broken.jsonl");
+    std::fs::write(&path, "{\"sequence\": 1, \"at\": \n").expect("the log can be written");
+    let log = AuditLog::open(path.clone(), "dispatcher").expect("the log can be opened");
+    log.append(AuditAction::JobDeleted, Vec::new(), Vec::new())
+        .expect("the entry can be appended");
+
+    // The entries after the broken line can still be read
+    let result = log.entries(&AuditQuery::default()).expect("the log can be read");
+    let _ = std::fs::remove_file(path);
+
+    assert_eq!(result.entries.len(), 1);
+    assert_eq!(result.entries[0].action, AuditAction::JobDeleted);
+    assert_eq!(result.invalid_lines.len(), 1);
+    assert_eq!(result.invalid_lines[0].line, 1);
+}
+
+#[test]
+fn numbering_continues_after_the_last_valid_entry() {
+    let path = common::temp_path("audit-continued.jsonl");
+    let log = AuditLog::open(path.clone(), "dispatcher").expect("the log can be opened");
+    for _ in 0..3 {
+        log.append(AuditAction::JobDeleted, Vec::new(), Vec::new())
+            .expect("the entry can be appended");
+    }
+    // A crash left a broken line and an empty line behind
+    let mut content = std::fs::read_to_string(&path).expect("the log can be read");
+    content.push_str("{\"sequence\": 4, \"at\": \n\n");
+    std::fs::write(&path, content).expect("the log can be written");
+
+    let log = AuditLog::open(path.clone(), "dispatcher").expect("the log can be opened again");
+    let entry = log
+        .append(AuditAction::JobDeleted, Vec::new(), Vec::new())
+        .expect("the entry can be appended");
+    assert_eq!(entry.sequence, 4);
+    let _ = std::fs::remove_file(path);
+}
+
+#[test]
+fn entry_after_a_broken_line_starts_a_new_line() {
+    let path = common::temp_path("audit-unterminated.jsonl");
+    let log = AuditLog::open(path.clone(), "dispatcher").expect("the log can be opened");
+    log.append(AuditAction::JobDeleted, Vec::new(), Vec::new())
+        .expect("the entry can be appended");
+    // A crash during an append left half an entry without a newline
+    let mut content = std::fs::read_to_string(&path).expect("the log can be read");
+    content.push_str("{\"sequence\": 2, \"at\":");
+    std::fs::write(&path, content).expect("the log can be written");
+
+    let log = AuditLog::open(path.clone(), "dispatcher").expect("the log can be opened again");
+    let entry = log
+        .append(AuditAction::JobCreated, Vec::new(), Vec::new())
+        .expect("the entry can be appended");
+    let content = std::fs::read_to_string(&path).expect("the log can be read");
+    let _ = std::fs::remove_file(path);
+
+    assert_eq!(entry.sequence, 2);
+    let lines: Vec<&str> = content.lines().collect();
+    assert_eq!(lines.len(), 3);
+    assert_eq!(lines[1], "{\"sequence\": 2, \"at\":");
+    assert_eq!(serde_json::from_str::<dispo_core::AuditEntry>(lines[2]).ok(), Some(entry));
+}
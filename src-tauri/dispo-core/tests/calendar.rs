@@ -0,0 +1,257 @@
+// Copyright 2023 Felix Kahle. All rights reserved.
+
+//! Checking dates against the business calendar
+
+mod common;
+
+use chrono::{NaiveDate, NaiveDateTime, NaiveTime, Weekday};
+use common::date;
+use dispo_core::calendar::{
+    BusinessCalendar, CalendarCheck, CalendarIssue, Holiday, HolidaySet, NonWorkingReason, OpeningDay, OpeningHours,
+};
+
+#[test]
+fn bundled_holidays_apply_to_every_year() {
+    let calendar = BusinessCalendar::default();
+    assert_eq!(calendar, BusinessCalendar::default());
+
+    for christmas in ["1999-12-25 10:00", "2024-12-25 10:00", "2087-12-25 10:00"] {
+        assert_eq!(
+            calendar.non_working_reason(date(christmas), &[], "Germany", ""),
+            Some(NonWorkingReason::Holiday("1. Weihnachtstag".to_string()))
+        );
+    }
+    // Easter Monday moves every year
+    assert_eq!(
+        calendar.non_working_reason(date("2031-04-14 10:00"), &[], "DE", ""),
+        Some(NonWorkingReason::Holiday("Ostermontag".to_string()))
+    );
+    assert_eq!(calendar.non_working_reason(date("2031-04-15 10:00"), &[], "DE", ""), None);
+}
+
+#[test]
+fn bundled_holidays_can_be_turned_off() {
+    let calendar = BusinessCalendar {
+        bundled_holidays: false,
+        ..BusinessCalendar::default()
+    };
+
+    assert_eq!(calendar.non_working_reason(date("2024-12-25 10:00"), &[], "DE", ""), None);
+    assert_eq!(
+        calendar.non_working_reason(date("2024-12-28 10:00"), &[], "DE", ""),
+        Some(NonWorkingReason::Weekend)
+    );
+}
+
+/// A calendar without holidays where ACME receives on weekdays from 08:00 to 12:00
+///
+/// # Returns
+/// * The calendar
+fn acme_calendar() -> BusinessCalendar {
+    let time = |value: &str| NaiveTime::parse_from_str(value, "%H:%M").expect("the time is valid");
+    let days = [Weekday::Mon, Weekday::Tue, Weekday::Wed, Weekday::Thu, Weekday::Fri]
+        .into_iter()
+        .map(|weekday| OpeningDay {
+            weekday,
+            open: time("08:00"),
+            close: time("12:00"),
+        })
+        .collect();
+
+    BusinessCalendar {
+        bundled_holidays: false,
+        opening_hours: vec![OpeningHours {
+            customer: "ACME".to_string(),
+            days,
+        }],
+        ..BusinessCalendar::default()
+    }
+}
+
+#[test]
+fn customers_are_only_served_during_their_opening_hours() {
+    let calendar = acme_calendar();
+
+    // 2024-03-04 is a Monday
+    assert_eq!(calendar.non_working_reason(date("2024-03-04 09:00"), &["acme"], "DE", ""), None);
+    assert_eq!(calendar.non_working_reason(date("2024-03-04 12:00"), &["acme"], "DE", ""), None);
+    assert_eq!(
+        calendar.non_working_reason(date("2024-03-04 14:00"), &["acme"], "DE", ""),
+        Some(NonWorkingReason::Closed)
+    );
+    // Customers without opening hours receive all day, the first key with opening hours wins
+    assert_eq!(calendar.non_working_reason(date("2024-03-04 14:00"), &["Other"], "DE", ""), None);
+    assert_eq!(
+        calendar.non_working_reason(date("2024-03-04 14:00"), &["Other", "ACME"], "DE", ""),
+        Some(NonWorkingReason::Closed)
+    );
+}
+
+#[test]
+fn customers_without_opening_hours_are_served_until_midnight() {
+    let calendar = acme_calendar();
+    let late_evening = NaiveDate::from_ymd_opt(2024, 3, 4)
+        .and_then(|day| day.and_hms_opt(23, 59, 30))
+        .expect("the date is valid");
+
+    assert_eq!(calendar.non_working_reason(late_evening, &["Other"], "DE", ""), None);
+    // 2024-03-09 is a Saturday, its midnight is not part of Friday
+    assert_eq!(
+        calendar.non_working_reason(date("2024-03-09 00:00"), &["Other"], "DE", ""),
+        Some(NonWorkingReason::Weekend)
+    );
+    let window = (date("2024-03-09 00:00"), date("2024-03-09 06:00"));
+    assert_eq!(
+        calendar.nearest_valid_slot(date("2024-03-09 00:00"), window, &["Other"], "DE", ""),
+        Some(date("2024-03-08 23:59"))
+    );
+}
+
+#[test]
+fn weekends_are_not_worked() {
+    let calendar = acme_calendar();
+
+    assert_eq!(
+        calendar.non_working_reason(date("2024-03-09 10:00"), &[], "DE", ""),
+        Some(NonWorkingReason::Weekend)
+    );
+    assert_eq!(
+        calendar.non_working_reason(date("2024-03-10 10:00"), &["ACME"], "DE", ""),
+        Some(NonWorkingReason::Weekend)
+    );
+
+    let calendar = BusinessCalendar {
+        weekend: vec![Weekday::Fri],
+        ..acme_calendar()
+    };
+    assert_eq!(calendar.non_working_reason(date("2024-03-09 10:00"), &[], "DE", ""), None);
+    assert_eq!(
+        calendar.non_working_reason(date("2024-03-08 10:00"), &[], "DE", ""),
+        Some(NonWorkingReason::Weekend)
+    );
+}
+
+#[test]
+fn nearest_valid_slot_prefers_the_window() {
+    let calendar = acme_calendar();
+    let window = (date("2024-03-04 06:00"), date("2024-03-04 16:00"));
+
+    // 14:00 is closer to the end of the opening hours than to anything else
+    assert_eq!(
+        calendar.nearest_valid_slot(date("2024-03-04 14:00"), window, &["ACME"], "DE", ""),
+        Some(date("2024-03-04 12:00"))
+    );
+    assert_eq!(
+        calendar.nearest_valid_slot(date("2024-03-04 07:00"), window, &["ACME"], "DE", ""),
+        Some(date("2024-03-04 08:00"))
+    );
+
+    // Friday evening, the window ends before Monday, so the closest working time outside it is used
+    let window = (date("2024-03-08 18:00"), date("2024-03-08 23:00"));
+    assert_eq!(
+        calendar.nearest_valid_slot(date("2024-03-08 20:00"), window, &["ACME"], "DE", ""),
+        Some(date("2024-03-08 12:00"))
+    );
+
+    // A window inside working time is taken even if working time outside of it is closer
+    let window = (date("2024-03-05 08:00"), date("2024-03-05 12:00"));
+    assert_eq!(
+        calendar.nearest_valid_slot(date("2024-03-04 13:00"), window, &["ACME"], "DE", ""),
+        Some(date("2024-03-05 08:00"))
+    );
+}
+
+#[test]
+fn nearest_valid_slot_gives_up_after_the_search_horizon() {
+    let calendar = BusinessCalendar {
+        weekend: vec![
+            Weekday::Mon,
+            Weekday::Tue,
+            Weekday::Wed,
+            Weekday::Thu,
+            Weekday::Fri,
+            Weekday::Sat,
+            Weekday::Sun,
+        ],
+        ..acme_calendar()
+    };
+    let window = (date("2024-03-04 08:00"), date("2024-03-04 12:00"));
+
+    assert_eq!(calendar.nearest_valid_slot(date("2024-03-04 10:00"), window, &[], "DE", ""), None);
+}
+
+#[test]
+fn check_reports_windows_and_dates_outside_working_time() {
+    let calendar = acme_calendar();
+
+    let check = calendar.check(
+        date("2024-03-09 08:00"),
+        date("2024-03-09 12:00"),
+        date("2024-03-09 10:00"),
+        &["ACME"],
+        "DE",
+        "",
+    );
+    assert_eq!(
+        check.issues,
+        [
+            CalendarIssue::WindowOutsideWorkingTime(NonWorkingReason::Weekend),
+            CalendarIssue::CalculatedDateOutsideWorkingTime(NonWorkingReason::Weekend)
+        ]
+    );
+    assert_eq!(check.suggested_date, Some(date("2024-03-08 12:00")));
+
+    let check = calendar.check(
+        date("2024-03-04 06:00"),
+        date("2024-03-04 16:00"),
+        date("2024-03-04 11:00"),
+        &["ACME"],
+        "DE",
+        "",
+    );
+    assert_eq!(check, CalendarCheck::default());
+}
+
+#[test]
+fn check_only_searches_the_start_of_long_windows() {
+    // Every day of January is a holiday, the working time of February is beyond the search horizon
+    let holidays = (1..=31)
+        .map(|day| Holiday {
+            date: NaiveDate::from_ymd_opt(2024, 1, day).expect("the day is valid"),
+            name: "Inventur".to_string(),
+        })
+        .collect();
+    let calendar = BusinessCalendar {
+        holiday_sets: vec![HolidaySet {
+            country: "DE".to_string(),
+            aliases: vec![],
+            region: None,
+            holidays,
+        }],
+        ..BusinessCalendar::default()
+    };
+
+    let check = calendar.check(
+        date("2024-01-01 08:00"),
+        date("2024-02-29 16:00"),
+        date("2024-01-30 12:00"),
+        &[],
+        "DE",
+        "",
+    );
+    assert_eq!(
+        check.issues[0],
+        CalendarIssue::WindowOutsideWorkingTime(NonWorkingReason::Holiday("Inventur".to_string()))
+    );
+
+    // A missing early date becomes 1970, only the first days of the window are checked
+    let check = calendar.check(
+        NaiveDateTime::default(),
+        date("2024-03-04 12:00"),
+        date("2024-03-04 10:00"),
+        &[],
+        "DE",
+        "",
+    );
+    assert!(check.issues.is_empty());
+}
@@ -0,0 +1,172 @@
+// Copyright 2023 Felix Kahle. All rights reserved.
+
+use calamine::{DataType, Range};
+use chrono::NaiveDateTime;
+use dispo_core::{
+    create_job_rows_from_sheets,
+    export::PlannedJob,
+    job_row::{Hawb, JobInput},
+    quantity::QuantityCells,
+    DispoMode, ImportOptions, JobOrigin, JobRow, Party, TemperatureRange,
+};
+use std::path::PathBuf;
+
+/// Get the path of a fixture
+///
+/// # Arguments
+/// * `name` - The file name of the fixture
+///
+/// # Returns
+/// * The path of the fixture
+pub fn fixture_path(name: &str) -> PathBuf {
+    PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests").join("fixtures").join(name)
+}
+
+/// Get a path in the temporary directory, unique per test run.
+/// Whatever a previous run left at the path is removed.
+///
+/// # Arguments
+/// * `name` - The name of the file or directory
+///
+/// # Returns
+/// * The path, nothing exists at it
+#[allow(dead_code)]
+pub fn temp_path(name: &str) -> PathBuf {
+    let path = std::env::temp_dir().join(format!("dispo-core-{}-{}", std::process::id(), name));
+    let _ = std::fs::remove_file(&path);
+    let _ = std::fs::remove_dir_all(&path);
+    path
+}
+
+/// Parse a date of a test
+///
+/// # Arguments
+/// * `value` - The date, e.g. "2024-03-04 08:00"
+///
+/// # Returns
+/// * The date
+#[allow(dead_code)]
+pub fn date(value: &str) -> NaiveDateTime {
+    NaiveDateTime::parse_from_str(value, "%Y-%m-%d %H:%M").expect("the date is valid")
+}
+
+/// Load a CSV fixture into a sheet, the way calamine reads the exports.
+/// Empty fields become empty cells, everything else strings, like the text columns of TMS.
+///
+/// # Arguments
+/// * `name` - The file name of the fixture
+///
+/// # Returns
+/// * The sheet with the header row
+pub fn load_sheet(name: &str) -> Range<DataType> {
+    let mut reader = csv::ReaderBuilder::new()
+        .has_headers(false)
+        .from_path(fixture_path(name))
+        .expect("the fixture can be opened");
+    let records: Vec<csv::StringRecord> = reader.records().map(|record| record.expect("the fixture is valid CSV")).collect();
+
+    let width = records.iter().map(|record| record.len()).max().unwrap_or(0);
+    let mut range = Range::new((0, 0), (records.len().saturating_sub(1) as u32, width.saturating_sub(1) as u32));
+    for (row, record) in records.iter().enumerate() {
+        for (column, field) in record.iter().enumerate() {
+            let cell = match field.is_empty() {
+                true => DataType::Empty,
+                false => DataType::String(field.to_string()),
+            };
+            range.set_value((row as u32, column as u32), cell);
+        }
+    }
+    range
+}
+
+/// Import the default fixtures
+///
+/// # Arguments
+/// * `mode` - The DispoMode to use
+///
+/// # Returns
+/// * The imported jobs
+// Every test crate compiles the module on its own, not all of them import jobs
+#[allow(dead_code)]
+pub fn import(mode: DispoMode) -> Vec<JobRow> {
+    let cl_view = load_sheet("cl_view.csv");
+    let shipper_site = load_sheet("shipper_site.csv");
+    create_job_rows_from_sheets(&cl_view, &shipper_site, mode, &ImportOptions::default()).expect("the fixtures can be imported")
+}
+
+/// A delivery of two refrigerated pieces in an EPS box to City Hospital, planned like an import
+///
+/// # Arguments
+/// * `job_number` - The job number of the job
+/// * `early_date` - The early date of the job, e.g. "2024-03-04 08:00"
+/// * `late_date` - The late date of the job
+///
+/// # Returns
+/// * The job
+#[allow(dead_code)]
+pub fn job(job_number: &str, early_date: &str, late_date: &str) -> JobRow {
+    let input = JobInput {
+        mode: DispoMode::Delivery,
+        job_number: job_number.to_string(),
+        hawbs: vec![Hawb {
+            number: format!("H-{}", job_number),
+            temperature_ranges: vec![TemperatureRange::Refrigerated],
+        }],
+        diagnostics: Vec::new(),
+        shipper: Party {
+            name: "Pharma Labs".to_string(),
+            ..Party::default()
+        },
+        consignee: Party {
+            code: "CON01".to_string(),
+            name: "City Hospital".to_string(),
+            address: "Klinikweg 5".to_string(),
+            city: "Frankfurt".to_string(),
+            state: "HE".to_string(),
+            postal_code: "60596".to_string(),
+            country: "DE".to_string(),
+        },
+        equipment_codes: "EPS".to_string(),
+        quantity_cells: QuantityCells {
+            pieces: Some("2".to_string()),
+            ..QuantityCells::default()
+        },
+        early_date: date(early_date),
+        late_date: date(late_date),
+        flight: None,
+        origin: JobOrigin::Imported,
+    };
+    JobRow::new(input, &ImportOptions::default())
+}
+
+/// Assign a job to a driver and a vehicle
+///
+/// # Arguments
+/// * `job` - The job
+/// * `driver` - The driver, empty if unassigned
+/// * `vehicle` - The vehicle, empty if unassigned
+///
+/// # Returns
+/// * The planned job
+#[allow(dead_code)]
+pub fn assign(job: JobRow, driver: &str, vehicle: &str) -> PlannedJob {
+    PlannedJob {
+        driver: driver.to_string(),
+        vehicle: vehicle.to_string(),
+        ..PlannedJob::from(job)
+    }
+}
+
+/// A job of 2024-03-04 from 08:00 to 12:00, planned at 10:00
+///
+/// # Arguments
+/// * `job_number` - The job number of the job
+/// * `driver` - The driver, empty if unassigned
+/// * `vehicle` - The vehicle, empty if unassigned
+///
+/// # Returns
+/// * The planned job
+#[allow(dead_code)]
+pub fn planned_job(job_number: &str, driver: &str, vehicle: &str) -> PlannedJob {
+    assign(job(job_number, "2024-03-04 08:00", "2024-03-04 12:00"), driver, vehicle)
+}
@@ -0,0 +1,187 @@
+// Copyright 2023 Felix Kahle. All rights reserved.
+
+//! Estimating the dry ice of a dispatch plan
+
+mod common;
+
+use common::date;
+use dispo_core::{
+    dry_ice::{self, DryIceConfig, DryIceError, PackagingType, DRY_ICE_UN_NUMBER},
+    equipment::{Equipment, EquipmentKind},
+    export::PlannedJob,
+    TemperatureRange,
+};
+
+/// A planned dry ice delivery
+///
+/// # Arguments
+/// * `job_number` - The job number of the job
+/// * `pieces` - The number of packages
+/// * `early_date` - The early date of the job
+/// * `late_date` - The late date of the job
+/// * `vehicle` - The vehicle the job is assigned to, empty if unassigned
+///
+/// # Returns
+/// * The planned job
+fn dry_ice_job(job_number: &str, pieces: u32, early_date: &str, late_date: &str, vehicle: &str) -> PlannedJob {
+    let mut job = common::job(job_number, early_date, late_date);
+    job.hawbs[0].temperature_ranges = vec![TemperatureRange::DryIce];
+    job.temperature_ranges = vec![TemperatureRange::DryIce];
+    job.quantity.pieces = Some(pieces);
+    job.equipment = String::new();
+    job.equipment_items = Vec::new();
+    job.calculated_date = date(early_date);
+    PlannedJob {
+        vehicle: vehicle.to_string(),
+        ..PlannedJob::from(job)
+    }
+}
+
+/// A piece of equipment of a job
+///
+/// # Arguments
+/// * `code` - The code of the equipment
+/// * `kind` - The kind of equipment
+/// * `quantity` - The number of units
+///
+/// # Returns
+/// * The equipment
+fn equipment(code: &str, kind: EquipmentKind, quantity: u32) -> Equipment {
+    Equipment {
+        code: code.to_string(),
+        kind,
+        description: String::new(),
+        quantity,
+        capabilities: Vec::new(),
+        prep_minutes: 0,
+        volume_liters: 0.0,
+    }
+}
+
+#[test]
+fn short_transit_needs_one_charge() {
+    let jobs = vec![dry_ice_job("D1", 3, "2024-03-04 08:00", "2024-03-04 12:00", "Truck 1")];
+
+    let plan = dry_ice::plan_dry_ice(&jobs, &DryIceConfig::default());
+    let estimate = &plan.estimates[0];
+    // 4 hours window plus 4 hours handling, 5 kg per day with 20% margin
+    assert_eq!(estimate.transit_hours, 8);
+    assert_eq!(estimate.replenishments, 0);
+    assert_eq!(estimate.initial_kg_per_piece, 2.0);
+    assert_eq!(estimate.net_kg, 6.0);
+    assert_eq!(estimate.total_kg, 6.0);
+
+    let declaration = &plan.declarations[0];
+    assert_eq!(declaration.un_number, DRY_ICE_UN_NUMBER);
+    assert_eq!(declaration.number_of_packages, 3);
+    assert_eq!(declaration.total_net_kg, 6.0);
+}
+
+#[test]
+fn long_transit_is_replenished() {
+    let jobs = vec![dry_ice_job("D2", 2, "2024-03-01 08:00", "2024-03-05 08:00", "")];
+
+    let plan = dry_ice::plan_dry_ice(&jobs, &DryIceConfig::default());
+    let estimate = &plan.estimates[0];
+    // 100 hours are covered by a charge for 72 hours and one refill
+    assert_eq!(estimate.transit_hours, 100);
+    assert_eq!(estimate.replenishments, 1);
+    assert_eq!(estimate.initial_kg_per_piece, 18.0);
+    assert_eq!(estimate.net_kg, 36.0);
+    assert_eq!(estimate.total_kg, 72.0);
+    // Unassigned jobs are not counted for a vehicle
+    assert!(plan.vehicles.is_empty());
+}
+
+#[test]
+fn vehicle_limit_is_checked() {
+    let mut ambient = dry_ice_job("A1", 50, "2024-03-04 08:00", "2024-03-04 12:00", "Truck 1");
+    ambient.job.temperature_ranges = vec![TemperatureRange::Ambient];
+    let jobs = vec![
+        dry_ice_job("D1", 3, "2024-03-04 08:00", "2024-03-04 12:00", "Truck 1"),
+        dry_ice_job("D2", 11, "2024-03-01 08:00", "2024-03-05 08:00", "Truck 1"),
+        dry_ice_job("D3", 1, "2024-03-04 08:00", "2024-03-04 12:00", "Truck 2"),
+        ambient,
+    ];
+
+    let plan = dry_ice::plan_dry_ice(&jobs, &DryIceConfig::default());
+    assert_eq!(plan.estimates.len(), 3);
+    assert_eq!(plan.vehicles.len(), 2);
+    assert_eq!(plan.vehicles[0].vehicle, "Truck 1");
+    assert_eq!(plan.vehicles[0].job_numbers, ["D1", "D2"]);
+    assert_eq!(plan.vehicles[0].net_kg, 204.0);
+    assert!(plan.vehicles[0].exceeds_limit);
+    assert!(!plan.vehicles[1].exceeds_limit);
+}
+
+#[test]
+fn jobs_without_pieces_are_problems() {
+    let mut unknown = dry_ice_job("D1", 1, "2024-03-04 08:00", "2024-03-04 12:00", "Truck 1");
+    unknown.job.quantity.pieces = None;
+    let jobs = vec![
+        unknown,
+        dry_ice_job("D2", 1, "2024-03-04 08:00", "2024-03-04 12:00", "Truck 1"),
+        dry_ice_job("D3", 0, "2024-03-04 08:00", "2024-03-04 12:00", "Truck 1"),
+    ];
+
+    let plan = dry_ice::plan_dry_ice(&jobs, &DryIceConfig::default());
+    assert_eq!(
+        plan.problems,
+        [
+            DryIceError::InvalidPieceCount("D1".to_string()),
+            DryIceError::InvalidPieceCount("D3".to_string())
+        ]
+    );
+    assert_eq!(plan.estimates.len(), 1);
+    assert_eq!(plan.vehicles[0].net_kg, 2.0);
+}
+
+#[test]
+fn packaging_comes_from_the_equipment() {
+    let mut pur = dry_ice_job("D1", 10, "2024-03-04 08:00", "2024-03-04 12:00", "Truck 1");
+    pur.job.equipment_items = vec![
+        equipment("LOGGER", EquipmentKind::DataLogger, 1),
+        equipment("PUR", EquipmentKind::PackagingSystem, 4),
+    ];
+    let mut dry_shipper = dry_ice_job("D2", 10, "2024-03-04 08:00", "2024-03-04 12:00", "Truck 1");
+    dry_shipper.job.equipment_items = vec![equipment("DRYSHIPPER", EquipmentKind::DryShipper, 1)];
+    let mut active = dry_ice_job("D3", 3, "2024-03-04 08:00", "2024-03-04 12:00", "Truck 1");
+    active.job.equipment_items = vec![equipment("ACTIVE", EquipmentKind::PackagingSystem, 1)];
+
+    let plan = dry_ice::plan_dry_ice(&[pur, dry_shipper, active], &DryIceConfig::default());
+    let packaging: Vec<(PackagingType, u32, f64)> = plan
+        .estimates
+        .iter()
+        .map(|estimate| (estimate.packaging, estimate.pieces, estimate.initial_kg_per_piece))
+        .collect();
+    // The listed packages are counted instead of the pieces, a packaging that is not known for dry ice uses the default
+    assert_eq!(
+        packaging,
+        [
+            (PackagingType::PurBox, 4, 1.5),
+            (PackagingType::VacuumInsulated, 1, 1.0),
+            (PackagingType::EpsBox, 3, 2.0)
+        ]
+    );
+}
+
+#[test]
+fn given_packaging_wins_over_the_equipment() {
+    let mut job = dry_ice_job("D1", 2, "2024-03-04 08:00", "2024-03-04 12:00", "").job;
+    job.equipment_items = vec![equipment("EPS", EquipmentKind::PackagingSystem, 2)];
+
+    let estimate =
+        dry_ice::estimate_dry_ice(&job, Some(PackagingType::PurBox), &DryIceConfig::default()).expect("the job can be estimated");
+    assert_eq!(estimate.packaging, PackagingType::PurBox);
+    assert_eq!(estimate.pieces, 2);
+}
+
+#[test]
+fn declaration_has_the_gross_weight() {
+    let mut job = dry_ice_job("D1", 2, "2024-03-04 08:00", "2024-03-04 12:00", "Truck 1");
+    job.job.quantity.gross_weight_kg = Some(42.5);
+
+    let plan = dry_ice::plan_dry_ice(&[job], &DryIceConfig::default());
+    assert_eq!(plan.declarations[0].gross_weight_kg, Some(42.5));
+    assert_eq!(plan.declarations[0].number_of_packages, 2);
+}
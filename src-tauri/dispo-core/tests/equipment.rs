@@ -0,0 +1,86 @@
+// Copyright 2023 Felix Kahle. All rights reserved.
+
+//! Parsing the Equipment Codes column
+
+use dispo_core::{
+    equipment::{parse_equipment_codes, EquipmentCatalogue, EquipmentDefinition, EquipmentKind},
+    DiagnosticKind,
+};
+
+/// Parse a cell and get the quantity of every recognised code
+///
+/// # Arguments
+/// * `input` - The content of the Equipment Codes cell
+/// * `catalogue` - The equipment catalogue
+///
+/// # Returns
+/// * The codes with their quantities
+fn quantities(input: &str, catalogue: &EquipmentCatalogue) -> Vec<(String, u32)> {
+    let (equipment, _) = parse_equipment_codes(input, catalogue);
+    equipment.into_iter().map(|item| (item.code, item.quantity)).collect()
+}
+
+#[test]
+fn quantity_can_be_a_prefix_or_a_suffix() {
+    let catalogue = EquipmentCatalogue::default();
+
+    assert_eq!(quantities("2x LOGGER", &catalogue), [("LOGGER".to_string(), 2)]);
+    assert_eq!(quantities("3 X logger", &catalogue), [("LOGGER".to_string(), 3)]);
+    assert_eq!(quantities("EPS x2", &catalogue), [("EPS".to_string(), 2)]);
+    assert_eq!(
+        quantities("eps X 4; TAILLIFT", &catalogue),
+        [("EPS".to_string(), 4), ("TAILLIFT".to_string(), 1)]
+    );
+}
+
+#[test]
+fn codes_containing_an_x_are_kept_whole() {
+    let mut catalogue = EquipmentCatalogue::default();
+    for code in ["BOX", "XL-BOX", "BOX 2X4"] {
+        catalogue
+            .definitions
+            .push(EquipmentDefinition::new(code, EquipmentKind::Other, code, vec![], 0, 0.0));
+    }
+
+    assert_eq!(quantities("box x2", &catalogue), [("BOX".to_string(), 2)]);
+    assert_eq!(quantities("XL-BOX", &catalogue), [("XL-BOX".to_string(), 1)]);
+    assert_eq!(quantities("2x XL-BOX", &catalogue), [("XL-BOX".to_string(), 2)]);
+    assert_eq!(quantities("BOX 2X4", &catalogue), [("BOX 2X4".to_string(), 1)]);
+
+    // A dimension is not a quantity, the whole entry is reported
+    let (equipment, diagnostics) = parse_equipment_codes("2x4 PALLET", &catalogue);
+    assert!(equipment.is_empty());
+    assert_eq!(
+        diagnostics[0].kind,
+        DiagnosticKind::UnknownEquipmentCode {
+            code: "2x4 PALLET".to_string()
+        }
+    );
+}
+
+#[test]
+fn repeated_codes_saturate_instead_of_overflowing() {
+    let catalogue = EquipmentCatalogue::default();
+
+    let (equipment, diagnostics) = parse_equipment_codes("4294967295x EPS, EPS", &catalogue);
+    assert!(diagnostics.is_empty());
+    assert_eq!(equipment[0].quantity, u32::MAX);
+    assert!(equipment[0].prep_minutes >= 0);
+}
+
+#[test]
+fn zero_quantities_are_reported() {
+    let catalogue = EquipmentCatalogue::default();
+
+    let (equipment, diagnostics) = parse_equipment_codes("0x EPS; LOGGER", &catalogue);
+    assert_eq!(equipment.len(), 1);
+    assert_eq!(equipment[0].code, "LOGGER");
+    assert_eq!(
+        diagnostics[0].kind,
+        DiagnosticKind::InvalidQuantity {
+            field: "equipment".to_string(),
+            value: "0x EPS".to_string(),
+            reason: "zero".to_string(),
+        }
+    );
+}
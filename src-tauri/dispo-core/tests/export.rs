@@ -0,0 +1,106 @@
+// Copyright 2023 Felix Kahle. All rights reserved.
+
+//! Exporting the formatted .xlsx dispatch plan
+
+mod common;
+
+use calamine::{Reader, Xlsx};
+use dispo_core::export::{create_plan_xlsx, write_plan_xlsx, PlannedJob, PLAN_ALL_JOBS_SHEET_NAME, PLAN_UNASSIGNED_SHEET_NAME};
+use std::io::Cursor;
+
+/// Open the content of a .xlsx file
+///
+/// # Arguments
+/// * `content` - The content of the .xlsx file
+///
+/// # Returns
+/// * The workbook
+fn open(content: Vec<u8>) -> Xlsx<Cursor<Vec<u8>>> {
+    Xlsx::new(Cursor::new(content)).expect("the plan is a valid .xlsx file")
+}
+
+/// Get the job numbers listed on a sheet of the plan
+///
+/// # Arguments
+/// * `workbook` - The plan
+/// * `sheet` - The name of the sheet
+///
+/// # Returns
+/// * The job numbers below the header row
+fn job_numbers(workbook: &mut Xlsx<Cursor<Vec<u8>>>, sheet: &str) -> Vec<String> {
+    let range = workbook
+        .worksheet_range(sheet)
+        .expect("the sheet exists")
+        .expect("the sheet can be read");
+    range.rows().skip(1).map(|row| row[3].to_string()).collect()
+}
+
+#[test]
+fn plan_has_a_sheet_per_driver_and_for_the_unassigned_jobs() {
+    let jobs = vec![
+        common::assign(common::job("L1001", "2024-03-04 12:00", "2024-03-04 14:00"), "Ben", "Truck 2"),
+        common::planned_job("L1002", "Anna", "Truck 1"),
+        common::planned_job("L1003", "", ""),
+        common::assign(common::job("L1004", "2024-03-04 14:00", "2024-03-04 16:00"), "Anna", "Truck 1"),
+    ];
+
+    let mut workbook = open(create_plan_xlsx(&jobs).expect("the plan can be created"));
+    // Drivers are ordered by their first job, the unassigned jobs come last
+    assert_eq!(
+        workbook.sheet_names(),
+        [PLAN_ALL_JOBS_SHEET_NAME, "Anna", "Ben", PLAN_UNASSIGNED_SHEET_NAME]
+    );
+    assert_eq!(job_numbers(&mut workbook, PLAN_ALL_JOBS_SHEET_NAME).len(), 4);
+    assert_eq!(job_numbers(&mut workbook, "Anna"), ["L1002", "L1004"]);
+    assert_eq!(job_numbers(&mut workbook, "Ben"), ["L1001"]);
+    assert_eq!(job_numbers(&mut workbook, PLAN_UNASSIGNED_SHEET_NAME), ["L1003"]);
+}
+
+#[test]
+fn invalid_and_duplicate_driver_names_become_valid_sheet_names() {
+    let drivers = [
+        "Transportunternehmen Mueller O'Brien",
+        "'Quoted'",
+        "A/B: [Night]",
+        "anna",
+        "Anna",
+        "Unassigned",
+        "'''",
+        "Transportunternehmen Mueller O'Brien Junior",
+    ];
+    let jobs: Vec<PlannedJob> = drivers
+        .iter()
+        .enumerate()
+        .map(|(index, driver)| common::planned_job(&format!("L{}", 1001 + index), driver, "Truck 1"))
+        .collect();
+
+    let workbook = open(create_plan_xlsx(&jobs).expect("the plan can be created"));
+    assert_eq!(
+        workbook.sheet_names(),
+        [
+            PLAN_ALL_JOBS_SHEET_NAME,
+            "Transportunternehmen Mueller O",
+            "Quoted",
+            "A-B- -Night-",
+            "anna",
+            "Anna (2)",
+            "Unassigned",
+            "Unassigned (2)",
+            "Transportunternehmen Muelle (2)",
+        ]
+    );
+}
+
+#[test]
+fn plan_is_written_to_a_file() {
+    let jobs = vec![common::planned_job("L1001", "Anna", "Truck 1")];
+
+    let path = common::temp_path("plan.xlsx");
+    write_plan_xlsx(path.to_str().expect("the path is valid UTF-8"), &jobs).expect("the plan can be written");
+    let content = std::fs::read(&path);
+    let _ = std::fs::remove_file(&path);
+
+    let mut workbook = open(content.expect("the plan exists"));
+    assert_eq!(workbook.sheet_names(), [PLAN_ALL_JOBS_SHEET_NAME, "Anna"]);
+    assert_eq!(job_numbers(&mut workbook, "Anna"), ["L1001"]);
+}
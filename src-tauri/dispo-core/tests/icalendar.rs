@@ -0,0 +1,126 @@
+// Copyright 2023 Felix Kahle. All rights reserved.
+
+//! Exporting the dispatch plan as iCalendar files
+
+mod common;
+
+use dispo_core::{
+    export::PlannedJob,
+    icalendar::{calendar_file_name, create_calendars, event_uid, write_calendars, CalendarGrouping},
+};
+
+/// Unfold the content lines of a calendar and get the values of a property
+///
+/// # Arguments
+/// * `content` - The content of the .ics file
+/// * `name` - The name of the property, e.g. "UID"
+///
+/// # Returns
+/// * The values of every line of the property
+fn property_values(content: &str, name: &str) -> Vec<String> {
+    let prefix = format!("{}:", name);
+    content
+        .replace("\r\n ", "")
+        .split("\r\n")
+        .filter_map(|line| line.strip_prefix(&prefix).map(str::to_string))
+        .collect()
+}
+
+/// A job planned at 10:00, 30 minutes may be spent before and 90 minutes after
+///
+/// # Arguments
+/// * `job_number` - The job number of the job
+/// * `driver` - The driver the job is assigned to
+///
+/// # Returns
+/// * The planned job
+fn tolerant_job(job_number: &str, driver: &str) -> PlannedJob {
+    let mut planned = common::planned_job(job_number, driver, "");
+    planned.job.tolerance = 90;
+    planned.job.tolerance_before = 30;
+    planned.job.tolerance_after = 90;
+    planned
+}
+
+#[test]
+fn event_spans_the_asymmetric_tolerance() {
+    let calendars = create_calendars(&[tolerant_job("L1001", "Anna")], CalendarGrouping::Driver);
+
+    assert_eq!(calendars.len(), 1);
+    assert!(calendars[0].content.contains("DTSTART:20240304T093000\r\n"));
+    assert!(calendars[0].content.contains("DTEND:20240304T113000\r\n"));
+}
+
+#[test]
+fn changed_names_do_not_share_a_file() {
+    assert_eq!(calendar_file_name("Anna Schmidt"), "Anna Schmidt");
+    assert_ne!(calendar_file_name("A/B"), calendar_file_name("A_B"));
+    assert_eq!(calendar_file_name("A/B"), calendar_file_name("A/B"));
+
+    let directory = common::temp_path("calendars");
+    std::fs::create_dir_all(&directory).expect("the directory can be created");
+    let jobs = [
+        tolerant_job("L1001", "A/B"),
+        tolerant_job("L1002", "A_B"),
+        tolerant_job("L1003", "a_b"),
+    ];
+    let paths = write_calendars(
+        directory.to_str().expect("the path is valid UTF-8"),
+        &jobs,
+        CalendarGrouping::Driver,
+    );
+    let _ = std::fs::remove_dir_all(&directory);
+
+    let mut paths = paths.expect("the calendars can be written");
+    assert_eq!(paths.len(), 3);
+    paths.sort();
+    paths.dedup_by(|a, b| a.eq_ignore_ascii_case(b));
+    assert_eq!(paths.len(), 3);
+}
+
+#[test]
+fn export_again_keeps_the_uids_and_does_not_lower_the_sequence() {
+    let jobs = [tolerant_job("L1001", "Anna"), tolerant_job("L1002", "Anna")];
+
+    let first = create_calendars(&jobs, CalendarGrouping::Driver);
+    let second = create_calendars(&jobs, CalendarGrouping::Driver);
+
+    let uids: Vec<String> = jobs.iter().map(event_uid).collect();
+    // The UID does not change when the job is assigned to someone else
+    assert_eq!(event_uid(&tolerant_job("L1001", "Ben")), uids[0]);
+    assert_eq!(property_values(&first[0].content, "UID"), uids);
+    assert_eq!(property_values(&second[0].content, "UID"), uids);
+
+    let sequence = |content: &str| -> Vec<i64> {
+        property_values(content, "SEQUENCE")
+            .iter()
+            .map(|value| value.parse().expect("the sequence is a number"))
+            .collect()
+    };
+    for (first, second) in sequence(&first[0].content).into_iter().zip(sequence(&second[0].content)) {
+        assert!(second >= first, "the sequence went down from {} to {}", first, second);
+    }
+}
+
+#[test]
+fn text_is_escaped_and_long_lines_are_folded() {
+    let mut job = tolerant_job("L1001", "Anna");
+    job.job.contact_name = "Müller; Lab, Floor 2\nRöntgenabteilung für Übergrößen und Kühlware äöü äöü äöü".to_string();
+
+    let content = &create_calendars(&[job], CalendarGrouping::Driver)[0].content;
+
+    assert_eq!(
+        property_values(content, "SUMMARY"),
+        ["Delivery L1001 Müller\\; Lab\\, Floor 2\\nRöntgenabteilung für Übergrößen und Kühlware äöü äöü äöü"]
+    );
+    assert!(content.ends_with("\r\n"));
+    for line in content.split("\r\n") {
+        assert!(line.len() <= 75, "the line is not folded: {}", line);
+    }
+    // The summary does not fit into one line, the continuation starts with a space
+    let summary = content
+        .split("\r\n")
+        .position(|line| line.starts_with("SUMMARY:"))
+        .expect("the event has a summary");
+    assert!(content.split("\r\n").nth(summary + 1).is_some_and(|line| line.starts_with(' ')));
+}
@@ -0,0 +1,340 @@
+// Copyright 2023 Felix Kahle. All rights reserved.
+
+//! Imports of the fixtures through the public API of the crate
+
+mod common;
+
+use dispo_core::{
+    create_job_rows, create_job_rows_from_sheets,
+    file_parsing::create_job_rows_with_files,
+    parse_task::ParseTask,
+    planned_time::{CustomerPlannedTime, PlannedTimeStrategy},
+    DiagnosticKind, DispoMode, ImportOptions, JobRow, ParseFilesError, PartyRole, Severity, TemperatureRange,
+};
+
+/// Find a job by its load number
+///
+/// # Arguments
+/// * `jobs` - The imported jobs
+/// * `job_number` - The load number of the job
+///
+/// # Returns
+/// * The job
+fn job<'a>(jobs: &'a [JobRow], job_number: &str) -> &'a JobRow {
+    jobs.iter()
+        .find(|job| job.job_number == job_number)
+        .unwrap_or_else(|| panic!("{} was imported", job_number))
+}
+
+/// Load a fixture and rename some of its headers, like an export with another layout
+///
+/// # Arguments
+/// * `name` - The file name of the fixture
+/// * `headers` - The default headers and the headers that replace them
+///
+/// # Returns
+/// * The sheet with the renamed headers
+fn load_renamed_sheet(name: &str, headers: &[(&str, &str)]) -> calamine::Range<calamine::DataType> {
+    let mut sheet = common::load_sheet(name);
+    for column in 0..sheet.width() as u32 {
+        let header = sheet.get_value((0, column)).map(|header| header.to_string()).unwrap_or_default();
+        if let Some((_, renamed)) = headers.iter().find(|(default, _)| *default == header) {
+            sheet.set_value((0, column), calamine::DataType::String(renamed.to_string()));
+        }
+    }
+    sheet
+}
+
+#[test]
+fn every_load_becomes_one_job() {
+    let jobs = common::import(DispoMode::Delivery);
+
+    let job_numbers: Vec<&str> = jobs.iter().map(|job| job.job_number.as_str()).collect();
+    assert_eq!(job_numbers, ["L1001", "L1002", "L1003"]);
+}
+
+#[test]
+fn delivery_stops_at_the_consignee() {
+    let jobs = common::import(DispoMode::Delivery);
+    let job = job(&jobs, "L1001");
+
+    assert_eq!(job.stop, PartyRole::Consignee);
+    assert_eq!(job.city, "Frankfurt");
+    assert_eq!(job.postal_code, "60596");
+    assert_eq!(job.shipper.name, "Pharma Labs");
+    assert_eq!(job.early_date.to_string(), "2024-03-04 08:00:00");
+    assert_eq!(job.late_date.to_string(), "2024-03-04 12:00:00");
+}
+
+#[test]
+fn pickup_stops_at_the_shipper() {
+    let jobs = common::import(DispoMode::Pickup);
+    let job = job(&jobs, "L1001");
+
+    assert_eq!(job.stop, PartyRole::Shipper);
+    assert_eq!(job.city, "Mainz");
+    assert_eq!(job.early_date.to_string(), "2024-03-01 09:00:00");
+}
+
+#[test]
+fn return_brings_packaging_back_to_the_shipper() {
+    let jobs = common::import(DispoMode::Return);
+    let job = job(&jobs, "L1001");
+
+    assert_eq!(job.stop, PartyRole::Shipper);
+    assert_eq!(job.city, "Mainz");
+    // The packaging is delivered, so the delivery window applies
+    assert_eq!(job.early_date.to_string(), "2024-03-04 08:00:00");
+    assert!(job.flight.is_none());
+}
+
+#[test]
+fn air_freight_columns_are_optional() {
+    let jobs = common::import(DispoMode::Transfer);
+    let transfer = job(&jobs, "L1001");
+    assert_eq!(transfer.stop, PartyRole::Consignee);
+    let missing = DiagnosticKind::MissingFlightDetails {
+        fields: vec!["mawbNumber".to_string(), "airline".to_string(), "cutOff".to_string()],
+    };
+    assert!(transfer.diagnostics.iter().any(|diagnostic| diagnostic.kind == missing));
+
+    // A recovery does not need a cut-off
+    let jobs = common::import(DispoMode::Recovery);
+    let recovery = job(&jobs, "L1001");
+    assert_eq!(recovery.stop, PartyRole::Shipper);
+    let missing = DiagnosticKind::MissingFlightDetails {
+        fields: vec!["mawbNumber".to_string(), "airline".to_string()],
+    };
+    assert!(recovery.diagnostics.iter().any(|diagnostic| diagnostic.kind == missing));
+}
+
+#[test]
+fn shipper_site_is_joined_by_load_number() {
+    let jobs = common::import(DispoMode::Delivery);
+    let job = job(&jobs, "L1001");
+
+    assert_eq!(job.hawb_number, "H-100");
+    assert_eq!(job.temperature_ranges, [TemperatureRange::Refrigerated]);
+    assert!(job.diagnostics.is_empty(), "unexpected diagnostics: {:?}", job.diagnostics);
+}
+
+#[test]
+fn hawbs_of_a_load_are_merged() {
+    let jobs = common::import(DispoMode::Delivery);
+    let job = job(&jobs, "L1002");
+
+    let hawbs: Vec<&str> = job.hawbs.iter().map(|hawb| hawb.number.as_str()).collect();
+    assert_eq!(hawbs, ["H-200", "H-201"]);
+    assert!(job.temperature_ranges.contains(&TemperatureRange::Ambient));
+    assert!(job.temperature_ranges.contains(&TemperatureRange::Frozen));
+    assert!(job
+        .diagnostics
+        .iter()
+        .any(|diagnostic| matches!(diagnostic.kind, DiagnosticKind::MultipleHawbs { .. }) && diagnostic.severity == Severity::Info));
+    let conflict = DiagnosticKind::ConflictingTemperatureRanges {
+        ranges: vec![TemperatureRange::Ambient, TemperatureRange::Frozen],
+    };
+    assert!(job
+        .diagnostics
+        .iter()
+        .any(|diagnostic| diagnostic.kind == conflict && diagnostic.severity == Severity::Warning));
+}
+
+#[test]
+fn hawbs_with_the_same_temperature_range_are_not_a_warning() {
+    let cl_view = common::load_sheet("cl_view.csv");
+    let shipper_site = common::load_sheet("shipper_site_same_temperature.csv");
+
+    let jobs = create_job_rows_from_sheets(&cl_view, &shipper_site, DispoMode::Delivery, &ImportOptions::default())
+        .expect("the fixtures can be imported");
+    let job = job(&jobs, "L1002");
+
+    assert_eq!(job.hawbs.len(), 2);
+    assert_eq!(job.temperature_ranges, [TemperatureRange::Ambient]);
+    assert!(job.diagnostics.iter().all(|diagnostic| diagnostic.severity == Severity::Info));
+}
+
+#[test]
+fn load_missing_in_shipper_site_is_kept() {
+    // Unlike an inner join, a load without Shipper Site rows still becomes a job, so it is not lost silently
+    let jobs = common::import(DispoMode::Delivery);
+    let job = job(&jobs, "L1003");
+
+    assert!(job.hawbs.is_empty());
+    assert_eq!(job.hawb_number, "");
+    // Without a HAWB there is no temperature range, the job has to be checked by hand
+    assert_eq!(job.temperature_ranges, [TemperatureRange::Invalid]);
+    assert!(job
+        .diagnostics
+        .iter()
+        .any(|diagnostic| diagnostic.kind == DiagnosticKind::MissingShipperSiteRow && diagnostic.severity == Severity::Warning));
+}
+
+#[test]
+fn customer_settings_match_the_code_or_the_name() {
+    let cl_view = common::load_sheet("cl_view.csv");
+    let shipper_site = common::load_sheet("shipper_site.csv");
+    let mut options = ImportOptions::default();
+    options.planned_time.customers.push(CustomerPlannedTime {
+        customer: "city hospital".to_string(),
+        strategy: PlannedTimeStrategy::Earliest,
+    });
+
+    // The consignee of L1001 has the code CON01, the setting is keyed by its name
+    let jobs = create_job_rows_from_sheets(&cl_view, &shipper_site, DispoMode::Delivery, &options).expect("the fixtures can be imported");
+    let hospital = job(&jobs, "L1001");
+    assert_eq!(hospital.planned_time_strategy, PlannedTimeStrategy::Earliest);
+    assert_eq!(hospital.calculated_date, hospital.early_date);
+
+    // A setting for the code wins over one for the name
+    options.planned_time.customers.push(CustomerPlannedTime {
+        customer: "CON01".to_string(),
+        strategy: PlannedTimeStrategy::Latest { safety_minutes: 0 },
+    });
+    let jobs = create_job_rows_from_sheets(&cl_view, &shipper_site, DispoMode::Delivery, &options).expect("the fixtures can be imported");
+    assert_eq!(job(&jobs, "L1001").calculated_date, job(&jobs, "L1001").late_date);
+}
+
+#[test]
+fn planned_time_settings_are_checked_before_the_import() {
+    let cl_view = common::load_sheet("cl_view.csv");
+    let shipper_site = common::load_sheet("shipper_site.csv");
+    let mut options = ImportOptions::default();
+    options.planned_time.default = PlannedTimeStrategy::Latest { safety_minutes: i64::MAX };
+
+    let result = create_job_rows_from_sheets(&cl_view, &shipper_site, DispoMode::Delivery, &options);
+    assert!(matches!(result, Err(ParseFilesError::PlannedTime(_))));
+}
+
+#[test]
+fn broken_target_windows_are_errors() {
+    let cl_view = common::load_sheet("cl_view_invalid_window.csv");
+    let shipper_site = common::load_sheet("shipper_site.csv");
+
+    let jobs = create_job_rows_from_sheets(&cl_view, &shipper_site, DispoMode::Delivery, &ImportOptions::default())
+        .expect("the fixtures can be imported");
+    let errors = |job_number: &str| -> Vec<DiagnosticKind> {
+        job(&jobs, job_number)
+            .diagnostics
+            .iter()
+            .filter(|diagnostic| diagnostic.severity == Severity::Error)
+            .map(|diagnostic| diagnostic.kind.clone())
+            .collect()
+    };
+
+    assert!(matches!(errors("L1001")[..], [DiagnosticKind::InvertedWindow { .. }]));
+    assert_eq!(
+        errors("L1002"),
+        [DiagnosticKind::MissingTargetDate {
+            field: "Target Delivery (Late)".to_string()
+        }]
+    );
+    assert!(errors("L1003").is_empty());
+}
+
+#[test]
+fn missing_column_is_an_error() {
+    let cl_view = common::load_sheet("cl_view.csv");
+    let shipper_site = common::load_sheet("shipper_site_missing_column.csv");
+
+    let result = create_job_rows_from_sheets(&cl_view, &shipper_site, DispoMode::Delivery, &ImportOptions::default());
+    assert!(matches!(result, Err(ParseFilesError::PolarsError(_))));
+}
+
+#[test]
+fn workbooks_are_read_from_files() {
+    let cl_view = common::fixture_path("cl_view.xlsx");
+    let shipper_site = common::fixture_path("shipper_site.xlsx");
+
+    let jobs = create_job_rows(
+        cl_view.to_str().expect("the path is valid UTF-8"),
+        shipper_site.to_str().expect("the path is valid UTF-8"),
+        DispoMode::Delivery,
+        &ImportOptions::default(),
+    )
+    .expect("the workbooks can be imported");
+
+    // The quantity and weight are number cells in the workbook, text in the CSV fixtures
+    assert_eq!(jobs, common::import(DispoMode::Delivery));
+}
+
+#[test]
+fn renamed_columns_are_mapped_by_the_options() {
+    let headers = [("Load #", "Load Number"), ("Target Delivery (Early)", "Delivery From")];
+    let cl_view = load_renamed_sheet("cl_view.csv", &headers);
+    let shipper_site = load_renamed_sheet("shipper_site.csv", &headers);
+
+    // Without the headers in the options the columns are missing
+    let result = create_job_rows_from_sheets(&cl_view, &shipper_site, DispoMode::Delivery, &ImportOptions::default());
+    assert!(matches!(result, Err(ParseFilesError::PolarsError(_))));
+
+    let options = ImportOptions {
+        column_headers: headers
+            .iter()
+            .map(|(default, renamed)| (default.to_string(), renamed.to_string()))
+            .collect(),
+        ..ImportOptions::default()
+    };
+    let jobs = create_job_rows_from_sheets(&cl_view, &shipper_site, DispoMode::Delivery, &options).expect("the sheets can be imported");
+    assert_eq!(jobs, common::import(DispoMode::Delivery));
+
+    // The same applies to files, which are parsed through another path
+    let paths = [("cl_view", &cl_view), ("shipper_site", &shipper_site)].map(|(name, sheet)| {
+        let mut workbook = rust_xlsxwriter::Workbook::new();
+        let worksheet = workbook.add_worksheet();
+        for (row, cells) in sheet.rows().enumerate() {
+            for (column, cell) in cells.iter().enumerate() {
+                if !cell.is_empty() {
+                    worksheet
+                        .write_string(row as u32, column as u16, cell.to_string())
+                        .expect("the cell can be written");
+                }
+            }
+        }
+        let path = common::temp_path(&format!("renamed_{}.xlsx", name));
+        workbook.save(&path).expect("the workbook can be written");
+        path
+    });
+    let jobs = create_job_rows(
+        paths[0].to_str().expect("the path is valid UTF-8"),
+        paths[1].to_str().expect("the path is valid UTF-8"),
+        DispoMode::Delivery,
+        &options,
+    );
+    for path in paths {
+        let _ = std::fs::remove_file(path);
+    }
+    assert_eq!(jobs.expect("the workbooks can be imported"), common::import(DispoMode::Delivery));
+}
+
+#[test]
+fn parsed_files_are_hashed() {
+    let cl_view = common::fixture_path("cl_view.xlsx");
+    let shipper_site = common::fixture_path("shipper_site.xlsx");
+    let cl_view = cl_view.to_str().expect("the path is valid UTF-8");
+    let shipper_site = shipper_site.to_str().expect("the path is valid UTF-8");
+
+    let (jobs, files) = create_job_rows_with_files(
+        cl_view,
+        shipper_site,
+        DispoMode::Delivery,
+        &ImportOptions::default(),
+        &ParseTask::silent(),
+    )
+    .expect("the workbooks can be imported");
+
+    assert_eq!(jobs, common::import(DispoMode::Delivery));
+    let paths: Vec<&str> = files.iter().map(|file| file.path.as_str()).collect();
+    assert_eq!(paths, [cl_view, shipper_site]);
+    assert!(files.iter().all(|file| file.hash.len() == 16));
+    assert_ne!(files[0].hash, files[1].hash);
+}
+
+#[test]
+fn missing_file_is_an_error() {
+    let missing = common::fixture_path("missing.xls");
+    let path = missing.to_str().expect("the path is valid UTF-8");
+
+    let result = create_job_rows(path, path, DispoMode::Delivery, &ImportOptions::default());
+    assert!(matches!(result, Err(ParseFilesError::CalamineError(_))));
+}
@@ -0,0 +1,108 @@
+// Copyright 2023 Felix Kahle. All rights reserved.
+
+//! The lifecycle of imported jobs
+
+mod common;
+
+use chrono::NaiveDateTime;
+use dispo_core::{lifecycle, DispoMode, JobStatus, StatusChangeError, StatusReason};
+
+/// A fixed time for the status changes
+///
+/// # Returns
+/// * 2024-03-04 07:00
+fn now() -> NaiveDateTime {
+    NaiveDateTime::parse_from_str("2024-03-04 07:00", "%Y-%m-%d %H:%M").expect("the time is valid")
+}
+
+#[test]
+fn imported_jobs_have_no_history() {
+    let jobs = common::import(DispoMode::Delivery);
+
+    assert!(jobs
+        .iter()
+        .all(|job| job.status == JobStatus::Imported && job.status_history.is_empty()));
+}
+
+#[test]
+fn job_goes_through_its_lifecycle() {
+    let mut job = common::import(DispoMode::Delivery).remove(0);
+
+    for status in [
+        JobStatus::Planned,
+        JobStatus::Assigned,
+        JobStatus::Dispatched,
+        JobStatus::PickedUp,
+        JobStatus::Delivered,
+    ] {
+        job.change_status(status, None, "", now()).expect("the transition is allowed");
+    }
+
+    assert_eq!(job.status, JobStatus::Delivered);
+    assert_eq!(job.status_history.len(), 5);
+    assert_eq!(job.status_history[0].from, JobStatus::Imported);
+    assert_eq!(job.status_history[4].from, JobStatus::PickedUp);
+    assert!(JobStatus::Delivered.next().is_empty());
+}
+
+#[test]
+fn steps_can_not_be_skipped() {
+    let mut job = common::import(DispoMode::Delivery).remove(0);
+
+    let result = job.change_status(JobStatus::Dispatched, None, "", now());
+    assert!(matches!(
+        result,
+        Err(StatusChangeError::NotAllowed((JobStatus::Imported, JobStatus::Dispatched)))
+    ));
+    assert_eq!(job.status, JobStatus::Imported);
+    assert!(job.status_history.is_empty());
+}
+
+#[test]
+fn cancellation_needs_a_reason() {
+    let mut job = common::import(DispoMode::Delivery).remove(0);
+
+    let result = job.change_status(JobStatus::Cancelled, None, "", now());
+    assert!(matches!(result, Err(StatusChangeError::ReasonRequired(JobStatus::Cancelled))));
+
+    let change = job
+        .change_status(JobStatus::Cancelled, Some(StatusReason::CustomerRequest), " called at 6:45 ", now())
+        .expect("the transition is allowed");
+    assert_eq!(change.reason, Some(StatusReason::CustomerRequest));
+    assert_eq!(change.note, "called at 6:45");
+}
+
+#[test]
+fn status_is_changed_by_id() {
+    let jobs = lifecycle::change_job_status(common::import(DispoMode::Delivery), "Delivery:L1002", JobStatus::Planned, None, "")
+        .expect("the job exists");
+
+    let statuses: Vec<JobStatus> = jobs.iter().map(|job| job.status).collect();
+    assert_eq!(statuses, [JobStatus::Imported, JobStatus::Planned, JobStatus::Imported]);
+
+    let result = lifecycle::change_job_status(jobs, "Pickup:L1002", JobStatus::Planned, None, "");
+    assert!(matches!(result, Err(StatusChangeError::UnknownJob(_))));
+}
+
+#[test]
+fn status_must_follow_from_the_history() {
+    let mut jobs = common::import(DispoMode::Delivery);
+    jobs[1]
+        .change_status(JobStatus::Planned, None, "", now())
+        .expect("the transition is allowed");
+    assert!(lifecycle::check_status_histories(&jobs).is_ok());
+
+    // The status was changed without recording it
+    jobs[0].status = JobStatus::Dispatched;
+    let result = lifecycle::change_job_status(jobs.clone(), "Delivery:L1002", JobStatus::Assigned, None, "");
+    assert!(matches!(result, Err(StatusChangeError::InconsistentHistory(id)) if id == "Delivery:L1001"));
+
+    // The history skips a step
+    jobs[0].status = JobStatus::Assigned;
+    jobs[0].status_history = jobs[1].status_history.clone();
+    jobs[0].status_history[0].to = JobStatus::Assigned;
+    assert!(matches!(
+        jobs[0].check_status_history(),
+        Err(StatusChangeError::InconsistentHistory(_))
+    ));
+}
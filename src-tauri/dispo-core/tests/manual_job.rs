@@ -0,0 +1,160 @@
+// Copyright 2023 Felix Kahle. All rights reserved.
+
+//! Creating, editing and deleting jobs manually
+
+mod common;
+
+use dispo_core::{
+    job_row::Hawb,
+    manual_job::{self, JobEditError},
+    quantity::QuantityCells,
+    DispoMode, FieldError, ImportOptions, JobOrigin, JobRow, JobStatus, ManualJob, Party, StatusChangeError, TemperatureRange,
+};
+
+/// A valid delivery as a dispatcher would enter it
+///
+/// # Returns
+/// * The manual job
+fn phone_order() -> ManualJob {
+    ManualJob {
+        mode: DispoMode::Delivery,
+        job_number: "P2001".to_string(),
+        hawbs: vec![Hawb {
+            number: "H-900".to_string(),
+            temperature_ranges: vec![TemperatureRange::Refrigerated],
+        }],
+        shipper: Party {
+            name: "Pharma Labs".to_string(),
+            ..Party::default()
+        },
+        consignee: Party {
+            code: "UKF".to_string(),
+            name: "Uniklinik Frankfurt".to_string(),
+            address: "Theodor-Stern-Kai 7".to_string(),
+            city: "Frankfurt".to_string(),
+            state: "HE".to_string(),
+            postal_code: "60596".to_string(),
+            country: "DE".to_string(),
+        },
+        early_date: "2024-03-05T08:00".to_string(),
+        late_date: "2024-03-05T12:00".to_string(),
+        equipment: "EPS".to_string(),
+        quantity: QuantityCells {
+            pieces: Some("2".to_string()),
+            gross_weight: Some("12.5 kg".to_string()),
+            ..QuantityCells::default()
+        },
+        flight: None,
+    }
+}
+
+/// Get the fields of a rejected job edit
+///
+/// # Arguments
+/// * `result` - The result of the job edit
+///
+/// # Returns
+/// * The paths of the invalid fields
+fn invalid_fields(result: Result<Vec<JobRow>, JobEditError>) -> Vec<String> {
+    match result {
+        Err(JobEditError::Invalid(errors)) => errors.into_iter().map(|error: FieldError| error.field).collect(),
+        other => panic!("expected field errors, got {:?}", other.map(|jobs| jobs.len())),
+    }
+}
+
+#[test]
+fn created_job_is_planned_like_an_import() {
+    let jobs =
+        manual_job::create_job(common::import(DispoMode::Delivery), &phone_order(), &ImportOptions::default()).expect("the job is valid");
+
+    let job = jobs.last().expect("the job was added");
+    assert_eq!(jobs.len(), 4);
+    assert_eq!(job.id(), "Delivery:P2001");
+    assert_eq!(job.origin, JobOrigin::Manual);
+    assert_eq!(job.city, "Frankfurt");
+    assert_eq!(job.early_date.to_string(), "2024-03-05 08:00:00");
+    assert_eq!(job.quantity.gross_weight_kg, Some(12.5));
+    assert_eq!(job.quantity.pieces, Some(2));
+    assert!(jobs[..3].iter().all(|job| job.origin == JobOrigin::Imported));
+}
+
+#[test]
+fn invalid_fields_are_reported_together() {
+    let mut job = phone_order();
+    job.early_date = "05.03.2024".to_string();
+    job.consignee.postal_code = String::new();
+    job.hawbs[0].temperature_ranges = vec![TemperatureRange::Invalid];
+    job.quantity.pieces = Some("1.5".to_string());
+
+    let fields = invalid_fields(manual_job::create_job(Vec::new(), &job, &ImportOptions::default()));
+    assert_eq!(
+        fields,
+        ["hawbs.0.temperatureRanges", "earlyDate", "consignee.postalCode", "quantity.pieces"]
+    );
+}
+
+#[test]
+fn window_must_not_end_before_it_starts() {
+    let mut job = phone_order();
+    job.late_date = "2024-03-05T07:00".to_string();
+
+    let fields = invalid_fields(manual_job::create_job(Vec::new(), &job, &ImportOptions::default()));
+    assert_eq!(fields, ["lateDate"]);
+}
+
+#[test]
+fn job_number_must_be_unique_per_mode() {
+    let mut job = phone_order();
+    job.job_number = "L1001".to_string();
+
+    let fields = invalid_fields(manual_job::create_job(
+        common::import(DispoMode::Delivery),
+        &job,
+        &ImportOptions::default(),
+    ));
+    assert_eq!(fields, ["jobNumber"]);
+
+    job.mode = DispoMode::Pickup;
+    job.shipper = job.consignee.clone();
+    assert!(manual_job::create_job(common::import(DispoMode::Delivery), &job, &ImportOptions::default()).is_ok());
+}
+
+#[test]
+fn edited_import_keeps_its_origin() {
+    let jobs = common::import(DispoMode::Delivery);
+    let mut job = ManualJob::from(&jobs[0]);
+    job.late_date = "2024-03-04T16:00".to_string();
+
+    let jobs = manual_job::update_job(jobs, "Delivery:L1001", &job, &ImportOptions::default()).expect("the job is valid");
+    assert_eq!(jobs[0].job_number, "L1001");
+    assert_eq!(jobs[0].late_date.to_string(), "2024-03-04 16:00:00");
+    assert_eq!(jobs[0].origin, JobOrigin::Imported);
+}
+
+#[test]
+fn unknown_job_can_not_be_deleted() {
+    let jobs = manual_job::delete_job(common::import(DispoMode::Delivery), "Delivery:L1002").expect("the job exists");
+    assert_eq!(jobs.len(), 2);
+
+    let result = manual_job::delete_job(jobs, "Delivery:L1002");
+    assert!(matches!(result, Err(JobEditError::UnknownJob(id)) if id == "Delivery:L1002"));
+}
+
+#[test]
+fn jobs_with_an_inconsistent_status_are_rejected() {
+    let mut jobs = common::import(DispoMode::Delivery);
+    jobs[2].status = JobStatus::Delivered;
+
+    let result = manual_job::create_job(jobs.clone(), &phone_order(), &ImportOptions::default());
+    assert!(matches!(
+        result,
+        Err(JobEditError::Status(StatusChangeError::InconsistentHistory(_)))
+    ));
+
+    let job = ManualJob::from(&jobs[0]);
+    let result = manual_job::update_job(jobs, "Delivery:L1001", &job, &ImportOptions::default());
+    assert!(matches!(
+        result,
+        Err(JobEditError::Status(StatusChangeError::InconsistentHistory(_)))
+    ));
+}
@@ -0,0 +1,42 @@
+// Copyright 2023 Felix Kahle. All rights reserved.
+
+//! Cancelling parse tasks
+
+mod common;
+
+use dispo_core::{
+    file_parsing::parse_sheet_with_task,
+    parse_task::{ParseTask, ParseTasks},
+    ParseFilesError,
+};
+use std::sync::{atomic::Ordering, Arc};
+
+#[test]
+fn cancelled_task_stops_parsing_the_sheet() {
+    let sheet = common::load_sheet("cl_view.csv");
+    let tasks = ParseTasks::default();
+    let cancelled = tasks.register("import").expect("the task is new");
+    let task = ParseTask::new("import", cancelled, |_| {});
+
+    assert!(parse_sheet_with_task(&sheet, None, &task).is_ok());
+    assert!(tasks.cancel("import"));
+    assert!(matches!(
+        parse_sheet_with_task(&sheet, None, &task),
+        Err(ParseFilesError::Cancelled)
+    ));
+}
+
+#[test]
+fn running_task_id_can_not_be_registered_again() {
+    let tasks = ParseTasks::default();
+    let first = tasks.register("import").expect("the task is new");
+
+    assert!(matches!(tasks.register("import"), Err(ParseFilesError::DuplicateTask(id)) if id == "import"));
+    // The running task can still be cancelled
+    assert!(tasks.cancel("import"));
+    assert!(first.load(Ordering::Relaxed));
+
+    tasks.finish("import");
+    let second = tasks.register("import").expect("the task finished");
+    assert!(!Arc::ptr_eq(&first, &second));
+}
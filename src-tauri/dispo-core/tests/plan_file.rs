@@ -0,0 +1,176 @@
+// Copyright 2023 Felix Kahle. All rights reserved.
+
+//! Saving and loading imported jobs as plan files
+
+mod common;
+
+use chrono::NaiveDateTime;
+use dispo_core::{
+    export::{self, PlannedJob},
+    plan_file::{PLAN_FILE_FORMAT, PLAN_FILE_VERSION},
+    DispoMode, JobOrigin, JobRow, JobStatus, PlanFile, PlanFileError, StatusChangeError,
+};
+
+/// Turn jobs into planned jobs without assignments
+///
+/// # Arguments
+/// * `jobs` - The jobs
+///
+/// # Returns
+/// * The planned jobs
+fn saved(jobs: Vec<JobRow>) -> Vec<PlannedJob> {
+    jobs.into_iter().map(PlannedJob::from).collect()
+}
+
+#[test]
+fn jobs_survive_a_round_trip() {
+    let jobs = saved(common::import(DispoMode::Delivery));
+
+    let mut json = Vec::new();
+    PlanFile::new(jobs.clone())
+        .to_writer(&mut json)
+        .expect("the plan file can be written");
+    let plan = PlanFile::from_reader(json.as_slice()).expect("the plan file can be read");
+
+    assert_eq!(plan.format, PLAN_FILE_FORMAT);
+    assert_eq!(plan.version, PLAN_FILE_VERSION);
+    assert!(plan.saved_at.is_some());
+    assert_eq!(plan.jobs, jobs);
+}
+
+#[test]
+fn bare_json_export_is_migrated() {
+    let jobs = common::import(DispoMode::Delivery);
+
+    let mut json = Vec::new();
+    export::write_json(&mut json, &jobs).expect("the jobs can be exported");
+    let plan = PlanFile::from_reader(json.as_slice()).expect("the export can be read");
+
+    assert_eq!(plan.version, PLAN_FILE_VERSION);
+    assert_eq!(plan.saved_at, None);
+    assert_eq!(plan.jobs, saved(jobs));
+}
+
+#[test]
+fn bare_json_export_keeps_the_origin() {
+    let mut jobs = common::import(DispoMode::Delivery);
+    jobs[0].origin = JobOrigin::Manual;
+
+    let mut json = Vec::new();
+    export::write_json(&mut json, &jobs).expect("the jobs can be exported");
+    let plan = PlanFile::from_reader(json.as_slice()).expect("the export can be read");
+
+    assert_eq!(plan.jobs[0].job.origin, JobOrigin::Manual);
+    assert!(plan.jobs[1..].iter().all(|saved| saved.job.origin == JobOrigin::Imported));
+}
+
+#[test]
+fn bare_json_export_keeps_the_status_and_history() {
+    let mut jobs = common::import(DispoMode::Delivery);
+    jobs[0].origin = JobOrigin::Manual;
+    let at = NaiveDateTime::parse_from_str("2024-03-04 07:00", "%Y-%m-%d %H:%M").expect("the time is valid");
+    for status in [JobStatus::Planned, JobStatus::Assigned, JobStatus::Dispatched] {
+        jobs[0].change_status(status, None, "", at).expect("the transition is allowed");
+    }
+
+    let mut json = Vec::new();
+    export::write_json(&mut json, &jobs).expect("the jobs can be exported");
+    let plan = PlanFile::from_reader(json.as_slice()).expect("the export can be read");
+
+    assert_eq!(plan.jobs[0].job, jobs[0]);
+    assert_eq!(plan.jobs[0].job.origin, JobOrigin::Manual);
+    assert_eq!(plan.jobs[0].job.status, JobStatus::Dispatched);
+    assert_eq!(plan.jobs[0].job.status_history.len(), 3);
+}
+
+#[test]
+fn jobs_of_version_1_are_imported() {
+    let mut json = Vec::new();
+    PlanFile::new(saved(common::import(DispoMode::Delivery)))
+        .to_writer(&mut json)
+        .expect("the plan file can be written");
+    let mut value: serde_json::Value = serde_json::from_slice(&json).expect("the plan file is JSON");
+    value["version"] = serde_json::Value::from(1);
+    for job in value["jobs"].as_array_mut().expect("the plan file has jobs") {
+        let job = job.as_object_mut().expect("a job is an object");
+        for field in ["origin", "driver", "vehicle", "notes"] {
+            job.remove(field);
+        }
+    }
+
+    let plan = PlanFile::from_reader(value.to_string().as_bytes()).expect("the plan file can be read");
+    assert_eq!(plan.version, PLAN_FILE_VERSION);
+    assert!(plan.jobs.iter().all(|saved| saved.job.origin == JobOrigin::Imported));
+    assert!(plan.jobs.iter().all(|saved| saved.driver.is_empty() && saved.notes.is_empty()));
+}
+
+#[test]
+fn assignments_survive_a_round_trip() {
+    let mut jobs = saved(common::import(DispoMode::Delivery));
+    jobs[0].driver = "Max Mustermann".to_string();
+    jobs[0].vehicle = "B-DM 1234".to_string();
+    jobs[0].notes = "Call before arrival".to_string();
+
+    let mut json = Vec::new();
+    PlanFile::new(jobs.clone())
+        .to_writer(&mut json)
+        .expect("the plan file can be written");
+    let plan = PlanFile::from_reader(json.as_slice()).expect("the plan file can be read");
+
+    assert_eq!(plan.jobs, jobs);
+    assert_eq!(plan.jobs[0].driver, "Max Mustermann");
+    assert_eq!(plan.jobs[0].vehicle, "B-DM 1234");
+    assert_eq!(plan.jobs[0].notes, "Call before arrival");
+}
+
+#[test]
+fn jobs_without_assignments_are_read_as_unassigned() {
+    let mut json = Vec::new();
+    PlanFile::new(saved(common::import(DispoMode::Delivery)))
+        .to_writer(&mut json)
+        .expect("the plan file can be written");
+    let mut value: serde_json::Value = serde_json::from_slice(&json).expect("the plan file is JSON");
+    for job in value["jobs"].as_array_mut().expect("the plan file has jobs") {
+        let job = job.as_object_mut().expect("a job is an object");
+        for field in ["driver", "vehicle", "notes"] {
+            job.remove(field);
+        }
+    }
+
+    let plan = PlanFile::from_reader(value.to_string().as_bytes()).expect("the plan file can be read");
+    assert!(plan.jobs.iter().all(|job| !job.is_assigned() && job.notes.is_empty()));
+}
+
+#[test]
+fn newer_version_is_rejected() {
+    let json = format!(
+        r#"{{"format": "{}", "version": {}, "savedAt": null, "jobs": []}}"#,
+        PLAN_FILE_FORMAT,
+        PLAN_FILE_VERSION + 1
+    );
+
+    let result = PlanFile::from_reader(json.as_bytes());
+    assert!(matches!(result, Err(PlanFileError::UnsupportedVersion(version)) if version == PLAN_FILE_VERSION + 1));
+}
+
+#[test]
+fn other_json_is_rejected() {
+    let result = PlanFile::from_reader(r#"{"tolerancePolicies": []}"#.as_bytes());
+    assert!(matches!(result, Err(PlanFileError::NotAPlanFile)));
+}
+
+#[test]
+fn status_without_history_is_rejected() {
+    let mut json = Vec::new();
+    PlanFile::new(saved(common::import(DispoMode::Delivery)))
+        .to_writer(&mut json)
+        .expect("the plan file can be written");
+    let mut value: serde_json::Value = serde_json::from_slice(&json).expect("the plan file is JSON");
+    value["jobs"][0]["status"] = serde_json::Value::from("Delivered");
+
+    let result = PlanFile::from_reader(value.to_string().as_bytes());
+    assert!(matches!(
+        result,
+        Err(PlanFileError::Status(StatusChangeError::InconsistentHistory(id))) if id == "Delivery:L1001"
+    ));
+}
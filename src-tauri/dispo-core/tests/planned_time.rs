@@ -0,0 +1,86 @@
+// Copyright 2023 Felix Kahle. All rights reserved.
+
+//! Calculating the planned date of a target window
+
+mod common;
+
+use chrono::NaiveTime;
+use common::date;
+use dispo_core::planned_time::{PlannedTimeError, PlannedTimeSettings, PlannedTimeStrategy, WindowEdge, MAX_CONFIGURED_MINUTES};
+
+#[test]
+fn offsets_stay_inside_the_window() {
+    let (early, late) = (date("2024-03-04 08:00"), date("2024-03-04 10:00"));
+
+    let fixed = |minutes: i64, from: WindowEdge| PlannedTimeStrategy::FixedOffset { minutes, from }.calculate(early, late);
+    assert_eq!(fixed(30, WindowEdge::Early), date("2024-03-04 08:30"));
+    assert_eq!(fixed(30, WindowEdge::Late), date("2024-03-04 09:30"));
+    assert_eq!(fixed(500, WindowEdge::Early), late);
+    assert_eq!(fixed(-30, WindowEdge::Late), late);
+    assert_eq!(
+        PlannedTimeStrategy::Latest { safety_minutes: 15 }.calculate(late, early),
+        date("2024-03-04 09:45")
+    );
+}
+
+#[test]
+fn preferred_time_outside_the_window_takes_the_closer_edge() {
+    let preferred = |early: &str, late: &str, time: &str| {
+        let time = NaiveTime::parse_from_str(time, "%H:%M").expect("the time is valid");
+        PlannedTimeStrategy::CustomerPreference { time }.calculate(date(early), date(late))
+    };
+
+    assert_eq!(preferred("2024-03-04 08:00", "2024-03-04 10:00", "09:15"), date("2024-03-04 09:15"));
+    assert_eq!(preferred("2024-03-04 08:00", "2024-03-04 10:00", "07:00"), date("2024-03-04 08:00"));
+    assert_eq!(preferred("2024-03-04 08:00", "2024-03-04 10:00", "11:00"), date("2024-03-04 10:00"));
+    // 23:00 of the day before is closer to the early date than 23:00 of the same day to the late date
+    assert_eq!(preferred("2024-03-04 08:00", "2024-03-04 10:00", "23:00"), date("2024-03-04 08:00"));
+
+    // A window across midnight
+    assert_eq!(preferred("2024-03-04 22:00", "2024-03-05 01:00", "00:30"), date("2024-03-05 00:30"));
+    assert_eq!(preferred("2024-03-04 22:00", "2024-03-05 01:00", "02:00"), date("2024-03-05 01:00"));
+    assert_eq!(preferred("2024-03-04 22:00", "2024-03-05 01:00", "21:00"), date("2024-03-04 22:00"));
+}
+
+#[test]
+fn huge_offsets_do_not_overflow() {
+    let (early, late) = (date("2024-03-04 08:00"), date("2024-03-04 10:00"));
+
+    assert_eq!(
+        PlannedTimeStrategy::Latest { safety_minutes: i64::MAX }.calculate(early, late),
+        early
+    );
+    assert_eq!(
+        PlannedTimeStrategy::Latest { safety_minutes: i64::MIN }.calculate(early, late),
+        late
+    );
+    for from in [WindowEdge::Early, WindowEdge::Late] {
+        for minutes in [i64::MAX, i64::MIN] {
+            let planned = PlannedTimeStrategy::FixedOffset { minutes, from }.calculate(early, late);
+            assert!(planned >= early && planned <= late);
+        }
+    }
+}
+
+#[test]
+fn settings_out_of_range_are_an_error() {
+    assert_eq!(PlannedTimeSettings::default().validate(), Ok(()));
+
+    let mut settings = PlannedTimeSettings {
+        default: PlannedTimeStrategy::Latest {
+            safety_minutes: MAX_CONFIGURED_MINUTES + 1,
+        },
+        ..PlannedTimeSettings::default()
+    };
+    assert!(matches!(
+        settings.validate(),
+        Err(PlannedTimeError::MinutesOutOfRange((_, minutes))) if minutes == MAX_CONFIGURED_MINUTES + 1
+    ));
+
+    settings.default = PlannedTimeStrategy::Midpoint;
+    settings.cut_off_buffer_minutes = -1;
+    assert_eq!(
+        settings.validate(),
+        Err(PlannedTimeError::MinutesOutOfRange(("the cut-off buffer".to_string(), -1)))
+    );
+}
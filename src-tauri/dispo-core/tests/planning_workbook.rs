@@ -0,0 +1,262 @@
+// Copyright 2023 Felix Kahle. All rights reserved.
+
+//! Exporting and re-importing the planning workbook
+
+mod common;
+
+use calamine::{DataType, Reader, Xlsx};
+use common::date;
+use dispo_core::{
+    export::PlannedJob,
+    manual_job::JobEditError,
+    planning_workbook::{
+        apply_change_set, apply_job_change, read_planning_workbook, write_planning_workbook, ChangeSet, JobChange, PLANNING_COLUMNS,
+        PLANNING_SHEET_NAME,
+    },
+    JobStatus,
+};
+
+#[test]
+fn unedited_workbook_has_no_changes() {
+    // A window of an odd number of minutes has its middle on the half minute
+    let jobs = vec![
+        common::assign(common::job("L1001", "2024-03-04 10:00", "2024-03-04 10:15"), "Anna", "Truck 1"),
+        common::planned_job("L1002", "Anna", "Truck 1"),
+    ];
+    assert_eq!(jobs[0].job.calculated_date.to_string(), "2024-03-04 10:07:30");
+
+    let path = common::temp_path("planning.xlsx");
+    let path = path.to_str().expect("the path is valid UTF-8");
+    write_planning_workbook(path, &jobs).expect("the workbook can be written");
+    let change_set = read_planning_workbook(path, &jobs);
+    let _ = std::fs::remove_file(path);
+
+    let change_set = change_set.expect("the workbook can be read");
+    assert!(change_set.changes.is_empty(), "unexpected changes: {:?}", change_set.changes);
+    assert!(change_set.conflicts.is_empty());
+    assert!(change_set.invalid_rows.is_empty());
+}
+
+#[test]
+fn change_of_the_dispatcher_is_applied() {
+    let jobs = vec![
+        common::assign(common::job("L1001", "2024-03-04 10:00", "2024-03-04 10:15"), "Anna", "Truck 1"),
+        common::assign(common::job("L1002", "2024-03-04 12:00", "2024-03-04 14:00"), "Anna", "Truck 1"),
+    ];
+    let mut change = JobChange {
+        job_id: "Delivery:L1002".to_string(),
+        job_number: "L1002".to_string(),
+        driver: Some("Ben".to_string()),
+        vehicle: None,
+        calculated_date: Some(date("2024-03-04 12:30")),
+        notes: None,
+    };
+
+    let changed = apply_job_change(jobs.clone(), &change).expect("the job exists");
+    assert_eq!(changed[0], jobs[0]);
+    assert_eq!(changed[1].driver, "Ben");
+    assert_eq!(changed[1].vehicle, "Truck 1");
+    assert_eq!(changed[1].job.calculated_date, date("2024-03-04 12:30"));
+
+    change.job_id = "Pickup:L1002".to_string();
+    let result = apply_job_change(jobs, &change);
+    assert!(matches!(result, Err(JobEditError::UnknownJob(id)) if id == "Pickup:L1002"));
+}
+
+#[test]
+fn assigning_a_driver_and_vehicle_assigns_the_job() {
+    let jobs = vec![PlannedJob::from(common::job("L1001", "2024-03-04 08:00", "2024-03-04 12:00"))];
+    let mut change = JobChange {
+        job_id: "Delivery:L1001".to_string(),
+        job_number: "L1001".to_string(),
+        driver: Some("Ben".to_string()),
+        vehicle: None,
+        calculated_date: None,
+        notes: None,
+    };
+
+    // A driver alone does not assign the job yet
+    let jobs = apply_job_change(jobs, &change).expect("the job exists");
+    assert_eq!(jobs[0].job.status, JobStatus::Imported);
+
+    change.driver = None;
+    change.vehicle = Some("Truck 2".to_string());
+    let jobs = apply_job_change(jobs, &change).expect("the job exists");
+    assert_eq!(jobs[0].job.status, JobStatus::Assigned);
+    let steps: Vec<(JobStatus, JobStatus)> = jobs[0].job.status_history.iter().map(|step| (step.from, step.to)).collect();
+    assert_eq!(
+        steps,
+        [(JobStatus::Imported, JobStatus::Planned), (JobStatus::Planned, JobStatus::Assigned)]
+    );
+    assert!(jobs[0].job.check_status_history().is_ok());
+
+    // Removing the driver undoes the assignment, but not the planning
+    change.driver = Some(String::new());
+    change.vehicle = None;
+    let jobs = apply_job_change(jobs, &change).expect("the job exists");
+    assert_eq!(jobs[0].job.status, JobStatus::Planned);
+}
+
+#[test]
+fn change_set_assigns_the_jobs() {
+    let jobs = vec![
+        PlannedJob::from(common::job("L1001", "2024-03-04 08:00", "2024-03-04 12:00")),
+        PlannedJob::from(common::job("L1002", "2024-03-04 08:00", "2024-03-04 12:00")),
+    ];
+    let change_set = ChangeSet {
+        changes: vec![JobChange {
+            job_id: "Delivery:L1001".to_string(),
+            job_number: "L1001".to_string(),
+            driver: Some("Anna".to_string()),
+            vehicle: Some("Truck 1".to_string()),
+            calculated_date: None,
+            notes: None,
+        }],
+        conflicts: vec![JobChange {
+            job_id: "Delivery:L1002".to_string(),
+            job_number: "L1002".to_string(),
+            driver: Some("Ben".to_string()),
+            vehicle: Some("Truck 2".to_string()),
+            calculated_date: None,
+            notes: None,
+        }],
+        ..ChangeSet::default()
+    };
+
+    let jobs = apply_change_set(jobs, &change_set, false);
+    assert_eq!(jobs[0].job.status, JobStatus::Assigned);
+    // The conflict was not applied, so the job is not assigned
+    assert_eq!(jobs[1].job.status, JobStatus::Imported);
+}
+
+#[test]
+fn planned_job_is_the_job_row_with_assignments() {
+    let job = common::job("L1001", "2024-03-04 08:00", "2024-03-04 12:00");
+    let mut json = serde_json::to_value(&job).expect("the job can be serialized");
+    json["driver"] = serde_json::Value::from("Anna");
+
+    // The frontend may leave out the assignments it does not know yet
+    let planned: PlannedJob = serde_json::from_value(json).expect("the job can be deserialized");
+    assert_eq!(planned.job, job);
+    assert_eq!(planned.driver, "Anna");
+    assert_eq!(planned.vehicle, "");
+}
+
+#[test]
+fn planned_number_that_is_no_date_is_an_invalid_row() {
+    let jobs = vec![common::planned_job("L1001", "Anna", "Truck 1")];
+
+    // The dispatcher typed numbers into the Planned column that Excel can not show as dates
+    let mut workbook = rust_xlsxwriter::Workbook::new();
+    let worksheet = workbook.add_worksheet();
+    worksheet.set_name(PLANNING_SHEET_NAME).expect("the name is valid");
+    for (column, header) in PLANNING_COLUMNS.iter().enumerate() {
+        worksheet
+            .write_string(0, column as u16, *header)
+            .expect("the header can be written");
+    }
+    for (row, planned) in [4.9e12, -1.0].into_iter().enumerate() {
+        let row = row as u32 + 1;
+        worksheet.write_string(row, 0, "Delivery:L1001").expect("the id can be written");
+        worksheet.write_number(row, 4, planned).expect("the date can be written");
+    }
+    let path = common::temp_path("planning-serial.xlsx");
+    workbook.save(&path).expect("the workbook can be written");
+
+    let change_set = read_planning_workbook(path.to_str().expect("the path is valid UTF-8"), &jobs);
+    let _ = std::fs::remove_file(path);
+
+    let change_set = change_set.expect("the workbook can be read");
+    let rows: Vec<u32> = change_set.invalid_rows.iter().map(|row| row.row).collect();
+    assert_eq!(rows, [2, 3]);
+    assert!(change_set.changes.is_empty());
+}
+
+#[test]
+fn edited_workbook_is_compared_with_the_current_jobs() {
+    let mut jobs = vec![
+        common::planned_job("L1001", "Anna", "Truck 1"),
+        common::assign(common::job("L1002", "2024-03-04 12:00", "2024-03-04 14:00"), "Anna", "Truck 1"),
+    ];
+    let exported = common::temp_path("planning-exported.xlsx");
+    let exported = exported.to_str().expect("the path is valid UTF-8");
+    write_planning_workbook(exported, &jobs).expect("the workbook can be written");
+
+    // The dispatcher edits the workbook in Excel, which saves it again
+    let mut original: Xlsx<_> = calamine::open_workbook(exported).expect("the workbook can be opened");
+    let range = original
+        .worksheet_range(PLANNING_SHEET_NAME)
+        .expect("the sheet exists")
+        .expect("the sheet can be read");
+    let _ = std::fs::remove_file(exported);
+
+    let mut workbook = rust_xlsxwriter::Workbook::new();
+    let worksheet = workbook.add_worksheet();
+    worksheet.set_name(PLANNING_SHEET_NAME).expect("the name is valid");
+    let date_format = rust_xlsxwriter::Format::new().set_num_format("dd.mm.yyyy hh:mm");
+    for (row, cells) in range.rows().enumerate() {
+        for (column, cell) in cells.iter().enumerate() {
+            let (row, column) = (row as u32, column as u16);
+            let result = match cell {
+                DataType::DateTime(serial) | DataType::Float(serial) => {
+                    worksheet.write_number_with_format(row, column, *serial, &date_format)
+                }
+                DataType::Empty => continue,
+                cell => worksheet.write_string(row, column, cell.to_string()),
+            };
+            result.expect("the cell can be written");
+        }
+    }
+    let row_of = |job_number: &str| {
+        range
+            .rows()
+            .position(|cells| cells[7] == job_number)
+            .expect("the job is in the workbook") as u32
+    };
+    let (first, second) = (row_of("L1001"), row_of("L1002"));
+    worksheet.write_string(first, 2, "Ben").expect("the driver can be written");
+    worksheet
+        .write_datetime_with_format(first, 4, date("2024-03-04 10:30"), &date_format)
+        .expect("the date can be written");
+    worksheet.write_string(second, 2, "Clara").expect("the driver can be written");
+    // A job that was deleted from the plan after the export
+    worksheet.write_string(3, 0, "Delivery:L9999").expect("the id can be written");
+    worksheet
+        .write_datetime_with_format(3, 4, date("2024-03-04 10:00"), &date_format)
+        .expect("the date can be written");
+
+    let edited = common::temp_path("planning-edited.xlsx");
+    workbook.save(&edited).expect("the workbook can be written");
+
+    // The address of the second job was corrected in the app while the workbook was edited
+    jobs[1].job.consignee.address = "Klinikweg 7".to_string();
+
+    let change_set = read_planning_workbook(edited.to_str().expect("the path is valid UTF-8"), &jobs);
+    let _ = std::fs::remove_file(&edited);
+
+    let change_set = change_set.expect("the workbook can be read");
+    assert_eq!(
+        change_set.changes,
+        [JobChange {
+            job_id: "Delivery:L1001".to_string(),
+            job_number: "L1001".to_string(),
+            driver: Some("Ben".to_string()),
+            vehicle: None,
+            calculated_date: Some(date("2024-03-04 10:30")),
+            notes: None,
+        }]
+    );
+    assert_eq!(
+        change_set.conflicts,
+        [JobChange {
+            job_id: "Delivery:L1002".to_string(),
+            job_number: "L1002".to_string(),
+            driver: Some("Clara".to_string()),
+            vehicle: None,
+            calculated_date: None,
+            notes: None,
+        }]
+    );
+    assert_eq!(change_set.unknown_jobs, ["Delivery:L9999"]);
+    assert!(change_set.invalid_rows.is_empty());
+}
@@ -0,0 +1,140 @@
+// Copyright 2023 Felix Kahle. All rights reserved.
+
+//! Parsing the pieces, weights and volumes of a job
+
+use dispo_core::{
+    quantity::{
+        CapacityLimit, Quantity, QuantityCells, QuantityTotals, VehicleCapacity, VolumeUnit, WeightUnit, DEFAULT_VOLUMETRIC_KG_PER_M3,
+    },
+    DiagnosticKind,
+};
+
+#[test]
+fn units_are_parsed_case_insensitive() {
+    assert_eq!("".parse::<WeightUnit>(), Ok(WeightUnit::Kilogram));
+    assert_eq!(" LBS ".parse::<WeightUnit>(), Ok(WeightUnit::Pound));
+    assert_eq!("Liter".parse::<VolumeUnit>(), Ok(VolumeUnit::Liter));
+    assert_eq!("cuft".parse::<VolumeUnit>(), Ok(VolumeUnit::CubicFoot));
+    assert!("stone".parse::<WeightUnit>().is_err());
+    assert!("gallon".parse::<VolumeUnit>().is_err());
+}
+
+#[test]
+fn cells_are_converted_to_kilograms_and_cubic_meters() {
+    let cells = QuantityCells {
+        pieces: Some("3".to_string()),
+        gross_weight: Some("22,05 lb".to_string()),
+        volume: Some("500".to_string()),
+        volume_unit: Some("l".to_string()),
+        ..QuantityCells::default()
+    };
+
+    let (quantity, diagnostics) = Quantity::from_cells(&cells, DEFAULT_VOLUMETRIC_KG_PER_M3);
+    assert!(diagnostics.is_empty());
+    assert_eq!(quantity.pieces, Some(3));
+    assert!((quantity.gross_weight_kg.expect("the weight is parsed") - 10.0).abs() < 0.01);
+    assert_eq!(quantity.volume_m3, Some(0.5));
+    // Without a chargeable weight from TMS the volumetric weight is larger than the gross weight
+    assert!((quantity.chargeable_weight_kg.expect("the weight is calculated") - 83.335).abs() < 0.01);
+}
+
+#[test]
+fn invalid_cells_are_reported_instead_of_truncated() {
+    let cells = QuantityCells {
+        pieces: Some("2.5".to_string()),
+        gross_weight: Some("-4 kg".to_string()),
+        volume: Some("1 barrel".to_string()),
+        ..QuantityCells::default()
+    };
+
+    let (quantity, diagnostics) = Quantity::from_cells(&cells, DEFAULT_VOLUMETRIC_KG_PER_M3);
+    assert_eq!(quantity, Quantity::default());
+    let fields: Vec<String> = diagnostics
+        .into_iter()
+        .map(|diagnostic| match diagnostic.kind {
+            DiagnosticKind::InvalidQuantity { field, .. } => field,
+            kind => panic!("unexpected diagnostic {:?}", kind),
+        })
+        .collect();
+    assert_eq!(fields, ["pieces", "grossWeight", "volume"]);
+}
+
+/// Get the fields and reasons of the quantity diagnostics
+///
+/// # Arguments
+/// * `cells` - The raw cells
+///
+/// # Returns
+/// * The Quantity and the field and reason of every diagnostic
+fn parse(cells: &QuantityCells) -> (Quantity, Vec<(String, String)>) {
+    let (quantity, diagnostics) = Quantity::from_cells(cells, DEFAULT_VOLUMETRIC_KG_PER_M3);
+    let diagnostics = diagnostics
+        .into_iter()
+        .map(|diagnostic| match diagnostic.kind {
+            DiagnosticKind::InvalidQuantity { field, reason, .. } => (field, reason),
+            kind => panic!("unexpected diagnostic {:?}", kind),
+        })
+        .collect();
+    (quantity, diagnostics)
+}
+
+#[test]
+fn pieces_with_a_suffix_or_too_many_pieces_are_reported() {
+    for value in ["1e5", "5 kg", "4294967296"] {
+        let cells = QuantityCells {
+            pieces: Some(value.to_string()),
+            ..QuantityCells::default()
+        };
+        let (quantity, diagnostics) = parse(&cells);
+        assert_eq!(quantity.pieces, None, "{} was parsed", value);
+        assert_eq!(diagnostics.len(), 1, "{} was not reported", value);
+        assert_eq!(diagnostics[0].0, "pieces");
+    }
+
+    let cells = QuantityCells {
+        pieces: Some("4294967295".to_string()),
+        ..QuantityCells::default()
+    };
+    assert_eq!(parse(&cells).0.pieces, Some(u32::MAX));
+}
+
+#[test]
+fn comma_before_three_digits_is_ambiguous() {
+    let cells = QuantityCells {
+        pieces: Some("1,000".to_string()),
+        gross_weight: Some("1,250 kg".to_string()),
+        volume: Some("1,5 m3".to_string()),
+        ..QuantityCells::default()
+    };
+
+    let (quantity, diagnostics) = parse(&cells);
+    assert_eq!(quantity.pieces, None);
+    assert_eq!(quantity.gross_weight_kg, None);
+    // Any other number of decimals is still read as a decimal comma
+    assert_eq!(quantity.volume_m3, Some(1.5));
+    let fields: Vec<&str> = diagnostics.iter().map(|(field, _)| field.as_str()).collect();
+    assert_eq!(fields, ["pieces", "grossWeight"]);
+    assert!(diagnostics[0].1.contains("ambiguous"));
+}
+
+#[test]
+fn totals_of_large_piece_counts_do_not_overflow() {
+    let (quantity, diagnostics) = Quantity::from_cells(
+        &QuantityCells {
+            pieces: Some("3000000000".to_string()),
+            ..QuantityCells::default()
+        },
+        DEFAULT_VOLUMETRIC_KG_PER_M3,
+    );
+    assert!(diagnostics.is_empty());
+
+    let totals = [&quantity, &quantity].into_iter().collect::<QuantityTotals>();
+    assert_eq!(totals.jobs, 2);
+    assert_eq!(totals.pieces, 6_000_000_000);
+
+    let capacity = VehicleCapacity {
+        max_pieces: Some(u32::MAX),
+        ..VehicleCapacity::default()
+    };
+    assert_eq!(capacity.check(&totals), [CapacityLimit::Pieces]);
+}
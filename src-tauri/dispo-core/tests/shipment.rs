@@ -0,0 +1,289 @@
+// Copyright 2023 Felix Kahle. All rights reserved.
+
+//! Linking the pickup and delivery legs of shipments
+
+mod common;
+
+use chrono::NaiveDateTime;
+use common::date;
+use dispo_core::{
+    shipment::{link_shipments, ChainIssue, LinkOptions, LinkOptionsError, ShipmentLeg, TransitTime},
+    DispoMode,
+};
+
+/// Create a leg in Germany
+///
+/// # Arguments
+/// * `job_number` - The job number of the leg
+/// * `hawb_number` - The hawb numbers of the leg, separated by a comma
+/// * `mode` - The mode of the leg
+/// * `early_date` - The early date of the leg
+/// * `late_date` - The late date of the leg
+///
+/// # Returns
+/// * The leg
+fn leg(job_number: &str, hawb_number: &str, mode: DispoMode, early_date: &str, late_date: &str) -> ShipmentLeg {
+    ShipmentLeg {
+        job_number: job_number.to_string(),
+        hawb_numbers: hawb_number.split(',').map(|hawb| hawb.trim().to_string()).collect(),
+        mode,
+        country: "DE".to_string(),
+        early_date: date(early_date),
+        late_date: date(late_date),
+    }
+}
+
+/// Get the job numbers of the legs of every shipment
+///
+/// # Arguments
+/// * `legs` - The legs to link
+///
+/// # Returns
+/// * The job numbers per shipment
+fn linked_job_numbers(legs: Vec<ShipmentLeg>) -> Vec<Vec<String>> {
+    link_shipments(legs, &LinkOptions::default())
+        .expect("the options are valid")
+        .into_iter()
+        .map(|shipment| shipment.legs.into_iter().map(|leg| leg.job_number).collect())
+        .collect()
+}
+
+#[test]
+fn legs_are_linked_by_hawb_or_job_number() {
+    let shipments = link_shipments(
+        vec![
+            leg("P1", "H1", DispoMode::Pickup, "2024-03-04 08:00", "2024-03-04 10:00"),
+            leg("D1", "h1", DispoMode::Delivery, "2024-03-04 14:00", "2024-03-04 16:00"),
+            leg("P2", "", DispoMode::Pickup, "2024-03-05 08:00", "2024-03-05 10:00"),
+            leg("P2", "", DispoMode::Delivery, "2024-03-05 14:00", "2024-03-05 16:00"),
+            leg("P3", "H3", DispoMode::Pickup, "2024-03-06 08:00", "2024-03-06 10:00"),
+        ],
+        &LinkOptions::default(),
+    )
+    .expect("the options are valid");
+
+    assert_eq!(shipments.len(), 2);
+    assert_eq!(shipments[0].hawb_number, "H1");
+    assert_eq!(shipments[0].legs[0].job_number, "P1");
+    assert_eq!(shipments[0].legs[1].job_number, "D1");
+    // Without any hawb number the shipment is named after its first leg
+    assert_eq!(shipments[1].hawb_number, "P2");
+    assert!(shipments.iter().all(|shipment| !shipment.is_broken()));
+}
+
+#[test]
+fn legs_with_several_hawbs_share_any_of_them() {
+    let linked = linked_job_numbers(vec![
+        leg("P1", "H1, H2", DispoMode::Pickup, "2024-03-04 08:00", "2024-03-04 10:00"),
+        leg("D1", "h2", DispoMode::Delivery, "2024-03-04 14:00", "2024-03-04 16:00"),
+        leg("P3", "H3, H4", DispoMode::Pickup, "2024-03-05 08:00", "2024-03-05 10:00"),
+        leg("D3", "H4, H3", DispoMode::Delivery, "2024-03-05 14:00", "2024-03-05 16:00"),
+    ]);
+
+    assert_eq!(linked, [["P1", "D1"], ["P3", "D3"]]);
+}
+
+#[test]
+fn legs_are_linked_transitively() {
+    // D1 and T1 do not match each other until R1 arrives, which shares the hawb of one and the job number of the other
+    let linked = linked_job_numbers(vec![
+        leg("D1", "H1", DispoMode::Delivery, "2024-03-04 14:00", "2024-03-04 16:00"),
+        leg("T1", "H2", DispoMode::Transfer, "2024-03-04 12:00", "2024-03-04 13:00"),
+        leg("T1", "H1", DispoMode::Recovery, "2024-03-04 08:00", "2024-03-04 10:00"),
+    ]);
+
+    assert_eq!(linked, [["T1", "T1", "D1"]]);
+}
+
+#[test]
+fn the_same_leg_sent_twice_is_kept_once() {
+    let pickup = leg("P1", "H1", DispoMode::Pickup, "2024-03-04 08:00", "2024-03-04 10:00");
+
+    assert!(linked_job_numbers(vec![pickup.clone(), pickup.clone()]).is_empty());
+    assert_eq!(
+        linked_job_numbers(vec![
+            pickup.clone(),
+            pickup,
+            leg("D1", "H1", DispoMode::Delivery, "2024-03-04 14:00", "2024-03-04 16:00"),
+        ]),
+        [["P1", "D1"]]
+    );
+}
+
+#[test]
+fn empty_numbers_never_match() {
+    let linked = linked_job_numbers(vec![
+        leg("P1", "", DispoMode::Pickup, "2024-03-04 08:00", "2024-03-04 10:00"),
+        leg("D1", " ", DispoMode::Delivery, "2024-03-04 14:00", "2024-03-04 16:00"),
+        leg("", "H2", DispoMode::Pickup, "2024-03-05 08:00", "2024-03-05 10:00"),
+        leg(" ", "H3", DispoMode::Delivery, "2024-03-05 14:00", "2024-03-05 16:00"),
+    ]);
+
+    assert!(linked.is_empty());
+}
+
+#[test]
+fn duplicate_legs_are_reported() {
+    let shipments = link_shipments(
+        vec![
+            leg("P1", "H1", DispoMode::Pickup, "2024-03-04 08:00", "2024-03-04 10:00"),
+            leg("P2", "H1", DispoMode::Pickup, "2024-03-04 08:30", "2024-03-04 10:00"),
+            leg("D1", "H1", DispoMode::Delivery, "2024-03-04 14:00", "2024-03-04 16:00"),
+        ],
+        &LinkOptions::default(),
+    )
+    .expect("the options are valid");
+
+    assert_eq!(
+        shipments[0].issues,
+        [ChainIssue::DuplicateLeg {
+            mode: DispoMode::Pickup,
+            job_numbers: vec!["P1".to_string(), "P2".to_string()],
+        }]
+    );
+}
+
+#[test]
+fn deliveries_without_collection_are_reported() {
+    let shipments = link_shipments(
+        vec![
+            leg("T1", "H1", DispoMode::Transfer, "2024-03-04 08:00", "2024-03-04 10:00"),
+            leg("D1", "H1", DispoMode::Delivery, "2024-03-04 14:00", "2024-03-04 16:00"),
+        ],
+        &LinkOptions::default(),
+    )
+    .expect("the options are valid");
+
+    assert_eq!(shipments[0].issues, [ChainIssue::MissingCollection]);
+}
+
+#[test]
+fn delivery_before_collection_is_reported() {
+    let shipments = link_shipments(
+        vec![
+            leg("P1", "H1", DispoMode::Pickup, "2024-03-04 14:00", "2024-03-04 16:00"),
+            leg("D1", "H1", DispoMode::Delivery, "2024-03-04 08:00", "2024-03-04 10:00"),
+        ],
+        &LinkOptions::default(),
+    )
+    .expect("the options are valid");
+
+    assert_eq!(
+        shipments[0].issues,
+        [ChainIssue::DeliveryBeforeCollection {
+            collection: "P1".to_string(),
+            delivery: "D1".to_string(),
+        }]
+    );
+}
+
+#[test]
+fn unreachable_deliveries_use_the_transit_time_of_the_route() {
+    let legs = vec![
+        leg("P1", "H1", DispoMode::Pickup, "2024-03-04 08:00", "2024-03-04 10:00"),
+        ShipmentLeg {
+            country: "FR".to_string(),
+            ..leg("D1", "H1", DispoMode::Delivery, "2024-03-04 09:00", "2024-03-04 11:00")
+        },
+    ];
+
+    // The default transit time of 120 minutes arrives at 10:00, inside the delivery window
+    assert!(
+        link_shipments(legs.clone(), &LinkOptions::default()).expect("the options are valid")[0]
+            .issues
+            .is_empty()
+    );
+
+    let options = LinkOptions {
+        routes: vec![TransitTime {
+            from_country: "DE".to_string(),
+            to_country: "FR".to_string(),
+            minutes: 360,
+        }],
+        ..LinkOptions::default()
+    };
+    assert_eq!(
+        link_shipments(legs, &options).expect("the options are valid")[0].issues,
+        [ChainIssue::Unreachable {
+            collection: "P1".to_string(),
+            delivery: "D1".to_string(),
+            earliest_arrival: date("2024-03-04 14:00"),
+        }]
+    );
+}
+
+#[test]
+fn broken_shipments_come_first() {
+    let shipments = link_shipments(
+        vec![
+            leg("P1", "A1", DispoMode::Pickup, "2024-03-04 08:00", "2024-03-04 10:00"),
+            leg("D1", "A1", DispoMode::Delivery, "2024-03-04 14:00", "2024-03-04 16:00"),
+            leg("P2", "B1", DispoMode::Pickup, "2024-03-04 14:00", "2024-03-04 16:00"),
+            leg("D2", "B1", DispoMode::Delivery, "2024-03-04 08:00", "2024-03-04 10:00"),
+        ],
+        &LinkOptions::default(),
+    )
+    .expect("the options are valid");
+
+    assert_eq!(shipments[0].hawb_number, "B1");
+    assert!(shipments[0].is_broken());
+    assert_eq!(shipments[1].hawb_number, "A1");
+    assert!(!shipments[1].is_broken());
+}
+
+#[test]
+fn transit_times_out_of_range_are_rejected() {
+    let legs = vec![
+        leg("P1", "H1", DispoMode::Pickup, "2024-03-04 08:00", "2024-03-04 10:00"),
+        leg("D1", "H1", DispoMode::Delivery, "2024-03-04 14:00", "2024-03-04 16:00"),
+    ];
+
+    let options = LinkOptions {
+        transit_minutes: i64::MAX,
+        ..LinkOptions::default()
+    };
+    assert!(matches!(
+        link_shipments(legs.clone(), &options),
+        Err(LinkOptionsError::TransitOutOfRange((_, i64::MAX)))
+    ));
+
+    let options = LinkOptions {
+        routes: vec![TransitTime {
+            from_country: "DE".to_string(),
+            to_country: "FR".to_string(),
+            minutes: -30,
+        }],
+        ..LinkOptions::default()
+    };
+    assert_eq!(
+        options.validate(),
+        Err(LinkOptionsError::TransitOutOfRange(("the route DE to FR".to_string(), -30)))
+    );
+    assert!(link_shipments(legs, &options).is_err());
+}
+
+#[test]
+fn transit_beyond_the_last_date_is_unreachable() {
+    // The transit is valid, but starting at the last representable day there is no arrival
+    let legs = vec![
+        ShipmentLeg {
+            early_date: NaiveDateTime::MAX - chrono::Duration::minutes(1),
+            late_date: NaiveDateTime::MAX,
+            ..leg("P1", "H1", DispoMode::Pickup, "2024-03-04 08:00", "2024-03-04 10:00")
+        },
+        ShipmentLeg {
+            late_date: NaiveDateTime::MAX - chrono::Duration::minutes(1),
+            ..leg("D1", "H1", DispoMode::Delivery, "2024-03-04 14:00", "2024-03-04 16:00")
+        },
+    ];
+
+    let shipments = link_shipments(legs, &LinkOptions::default()).expect("the options are valid");
+    assert_eq!(
+        shipments[0].issues,
+        [ChainIssue::Unreachable {
+            collection: "P1".to_string(),
+            delivery: "D1".to_string(),
+            earliest_arrival: NaiveDateTime::MAX,
+        }]
+    );
+}
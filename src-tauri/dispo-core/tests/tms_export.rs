@@ -0,0 +1,198 @@
+// Copyright 2023 Felix Kahle. All rights reserved.
+
+//! Exporting the planning status to TMS
+
+mod common;
+
+use common::date;
+use dispo_core::{
+    export::{ExportError, PlannedJob},
+    tms_export::{export_status, TmsExportLayout, TmsExportState, TmsExportSummary},
+};
+use std::path::{Path, PathBuf};
+
+/// Export the status of jobs as CSV with the default layout
+///
+/// # Arguments
+/// * `path` - The path of the file to write
+/// * `state_path` - The path of the export state file
+/// * `jobs` - The jobs of the dispatch plan
+/// * `full` - Whether all jobs are exported
+///
+/// # Returns
+/// * The summary of the export and the job numbers in the written file
+fn export(path: &Path, state_path: &Path, jobs: &[PlannedJob], full: bool) -> (TmsExportSummary, Vec<String>) {
+    let _ = std::fs::remove_file(path);
+    let layout = TmsExportLayout::default();
+    let summary = export_status(path.to_str().expect("the path is valid UTF-8"), state_path, jobs, &layout, full)
+        .expect("the status can be exported");
+
+    let job_numbers = match path.exists() {
+        true => csv::ReaderBuilder::new()
+            .delimiter(layout.delimiter as u8)
+            .from_path(path)
+            .expect("the export can be read")
+            .records()
+            .map(|record| record.expect("the export is valid CSV")[0].to_string())
+            .collect(),
+        false => Vec::new(),
+    };
+    (summary, job_numbers)
+}
+
+/// Get the paths of an export and its state file
+///
+/// # Arguments
+/// * `name` - The name of the export
+///
+/// # Returns
+/// * The path of the export and the path of the state file
+fn paths(name: &str) -> (PathBuf, PathBuf) {
+    (
+        common::temp_path(&format!("{}.csv", name)),
+        common::temp_path(&format!("{}-state.json", name)),
+    )
+}
+
+/// Remove the files of an export
+///
+/// # Arguments
+/// * `paths` - The path of the export and the path of the state file
+fn remove((path, state_path): (PathBuf, PathBuf)) {
+    let _ = std::fs::remove_file(path);
+    let _ = std::fs::remove_file(state_path);
+}
+
+#[test]
+fn planned_date_uses_the_date_format() {
+    let layout = TmsExportLayout::default();
+
+    let record = layout
+        .record(&common::planned_job("L1001", "Anna", "Truck 1"))
+        .expect("the job can be exported");
+    assert_eq!(record, ["L1001", "H-L1001", "Anna", "Truck 1", "", "03/04/2024 10:00", "PLANNED"]);
+}
+
+#[test]
+fn invalid_date_format_is_an_error() {
+    let layout = TmsExportLayout {
+        date_format: "%Q".to_string(),
+        ..TmsExportLayout::default()
+    };
+    let (path, state_path) = (common::temp_path("invalid.csv"), common::temp_path("invalid-state.json"));
+
+    let result = export_status(
+        path.to_str().expect("the path is valid UTF-8"),
+        &state_path,
+        &[common::planned_job("L1001", "Anna", "Truck 1")],
+        &layout,
+        false,
+    );
+    assert!(matches!(result, Err(ExportError::InvalidDateFormat(format)) if format == "%Q"));
+    assert!(!path.exists());
+    assert!(!state_path.exists());
+}
+
+#[test]
+fn unchanged_jobs_are_not_exported_again() {
+    let jobs = vec![
+        common::planned_job("L1001", "Anna", "Truck 1"),
+        common::planned_job("L1002", "", ""),
+    ];
+    let (path, state_path) = paths("unchanged");
+
+    let (summary, job_numbers) = export(&path, &state_path, &jobs, false);
+    assert_eq!(
+        summary,
+        TmsExportSummary {
+            exported: 2,
+            unchanged: 0,
+            written: true
+        }
+    );
+    assert_eq!(job_numbers, ["L1001", "L1002"]);
+
+    let (summary, job_numbers) = export(&path, &state_path, &jobs, false);
+    remove((path, state_path));
+    assert_eq!(
+        summary,
+        TmsExportSummary {
+            exported: 0,
+            unchanged: 2,
+            written: false
+        }
+    );
+    assert!(job_numbers.is_empty());
+}
+
+#[test]
+fn only_changed_jobs_are_exported() {
+    let mut jobs = vec![
+        common::planned_job("L1001", "Anna", "Truck 1"),
+        common::planned_job("L1002", "Anna", "Truck 1"),
+        common::planned_job("L1003", "Anna", "Truck 1"),
+    ];
+    let (path, state_path) = paths("changed");
+    export(&path, &state_path, &jobs, false);
+
+    jobs[0].driver = "Ben".to_string();
+    let (summary, job_numbers) = export(&path, &state_path, &jobs, false);
+    assert_eq!((summary.exported, summary.unchanged), (1, 2));
+    assert_eq!(job_numbers, ["L1001"]);
+
+    jobs[2].job.calculated_date = date("2024-03-04 11:00");
+    let (summary, job_numbers) = export(&path, &state_path, &jobs, false);
+    remove((path, state_path));
+    assert_eq!((summary.exported, summary.unchanged), (1, 2));
+    assert_eq!(job_numbers, ["L1003"]);
+}
+
+#[test]
+fn full_export_contains_every_job() {
+    let jobs = vec![
+        common::planned_job("L1001", "Anna", "Truck 1"),
+        common::planned_job("L1002", "", ""),
+    ];
+    let (path, state_path) = paths("full");
+    export(&path, &state_path, &jobs, false);
+
+    let (summary, job_numbers) = export(&path, &state_path, &jobs, true);
+    remove((path, state_path));
+    assert_eq!(
+        summary,
+        TmsExportSummary {
+            exported: 2,
+            unchanged: 0,
+            written: true
+        }
+    );
+    assert_eq!(job_numbers, ["L1001", "L1002"]);
+}
+
+#[test]
+fn failed_write_keeps_the_export_state() {
+    let mut jobs = vec![common::planned_job("L1001", "Anna", "Truck 1")];
+    let (path, state_path) = paths("failed");
+    export(&path, &state_path, &jobs, false);
+    let state = TmsExportState::load(&state_path).expect("the state can be loaded");
+
+    // The directory of the export does not exist, so the file can not be written
+    jobs[0].driver = "Ben".to_string();
+    let missing = common::temp_path("missing").join("status.csv");
+    let result = export_status(
+        missing.to_str().expect("the path is valid UTF-8"),
+        &state_path,
+        &jobs,
+        &TmsExportLayout::default(),
+        false,
+    );
+    let after = TmsExportState::load(&state_path).expect("the state can be loaded");
+    assert!(result.is_err());
+    assert_eq!(after, state);
+
+    // The change is still exported by the next export
+    let (summary, job_numbers) = export(&path, &state_path, &jobs, false);
+    remove((path, state_path));
+    assert_eq!(summary.exported, 1);
+    assert_eq!(job_numbers, ["L1001"]);
+}
@@ -0,0 +1,209 @@
+// Copyright 2023 Felix Kahle. All rights reserved.
+
+//! Rounding tolerances and resolving the tolerance policy of a job
+
+mod common;
+
+use chrono::{Duration, NaiveDateTime};
+use common::date;
+use dispo_core::{
+    tolerance::{BucketOverflow, TolerancePolicy, TolerancePolicySet, ToleranceRounding, LEGACY_POLICY_NAME},
+    DispoMode,
+};
+
+/// Create a policy that only applies to the given customer, country and mode
+///
+/// # Arguments
+/// * `name` - The name of the policy
+/// * `customer` - The customer the policy applies to
+/// * `country` - The country the policy applies to
+/// * `mode` - The mode the policy applies to
+///
+/// # Returns
+/// * The policy, rounding both sides exactly
+fn policy(name: &str, customer: Option<&str>, country: Option<&str>, mode: Option<DispoMode>) -> TolerancePolicy {
+    TolerancePolicy {
+        name: name.to_string(),
+        customer: customer.map(str::to_string),
+        country: country.map(str::to_string),
+        mode,
+        before: ToleranceRounding::Exact,
+        after: ToleranceRounding::Exact,
+    }
+}
+
+/// The tolerance of the fixed buckets that were used before tolerance policies existed
+///
+/// # Arguments
+/// * `early_date` - The early date of the window
+/// * `late_date` - The late date of the window
+///
+/// # Returns
+/// * The tolerance in minutes
+fn baseline_tolerance(early_date: NaiveDateTime, late_date: NaiveDateTime) -> i32 {
+    let middle_date = early_date + (late_date - early_date) / 2;
+    let difference = (middle_date - early_date).num_minutes().abs();
+
+    if difference <= 0 {
+        0
+    } else if difference <= 15 {
+        15
+    } else if difference <= 30 {
+        30
+    } else if difference <= 60 {
+        60
+    } else {
+        120
+    }
+}
+
+#[test]
+fn buckets_round_up_to_the_next_bucket() {
+    let rounding = ToleranceRounding::Buckets {
+        buckets: vec![60, 0, 30],
+        overflow: BucketOverflow::LargestBucket,
+    };
+
+    assert_eq!(rounding.apply(0), 0);
+    assert_eq!(rounding.apply(1), 30);
+    assert_eq!(rounding.apply(30), 30);
+    assert_eq!(rounding.apply(31), 60);
+    assert_eq!(rounding.apply(60), 60);
+    // Negative differences are treated as no difference
+    assert_eq!(rounding.apply(-45), 0);
+    assert_eq!(ToleranceRounding::Exact.apply(47), 47);
+    assert_eq!(ToleranceRounding::Exact.apply(-5), 0);
+}
+
+#[test]
+fn overflow_keeps_the_largest_bucket_or_the_exact_difference() {
+    let largest = ToleranceRounding::Buckets {
+        buckets: vec![15, 30],
+        overflow: BucketOverflow::LargestBucket,
+    };
+    let exact = ToleranceRounding::Buckets {
+        buckets: vec![15, 30],
+        overflow: BucketOverflow::Exact,
+    };
+    let empty = ToleranceRounding::Buckets {
+        buckets: vec![],
+        overflow: BucketOverflow::LargestBucket,
+    };
+
+    assert_eq!(largest.apply(31), 30);
+    assert_eq!(largest.apply(500), 30);
+    assert_eq!(exact.apply(31), 31);
+    assert_eq!(exact.apply(500), 500);
+    // Without buckets there is no largest bucket, the exact difference is kept
+    assert_eq!(empty.apply(42), 42);
+}
+
+#[test]
+fn before_and_after_are_rounded_separately() {
+    let policy = TolerancePolicy {
+        before: ToleranceRounding::Buckets {
+            buckets: vec![0, 15, 30],
+            overflow: BucketOverflow::LargestBucket,
+        },
+        after: ToleranceRounding::Exact,
+        ..policy("Asymmetric", None, None, None)
+    };
+
+    let tolerance = policy.calculate(date("2024-03-04 08:00"), date("2024-03-04 10:00"), date("2024-03-04 08:20"));
+    assert_eq!(tolerance.before, 30);
+    assert_eq!(tolerance.after, 100);
+    assert_eq!(tolerance.policy, "Asymmetric");
+    assert_eq!(tolerance.symmetric(), 100);
+
+    // A planned date outside the window gives no tolerance on the side it lies beyond
+    let tolerance = policy.calculate(date("2024-03-04 08:00"), date("2024-03-04 10:00"), date("2024-03-04 07:00"));
+    assert_eq!(tolerance.before, 0);
+    assert_eq!(tolerance.after, 180);
+}
+
+#[test]
+fn exact_tolerance_of_a_huge_window_is_clamped() {
+    let policy = policy("Exact", None, None, None);
+
+    let tolerance = policy.calculate(date("0001-01-01 00:00"), date("9999-12-31 23:59"), date("5000-01-01 00:00"));
+    assert_eq!(tolerance.before, i32::MAX);
+    assert_eq!(tolerance.after, i32::MAX);
+}
+
+#[test]
+fn legacy_policy_reproduces_the_fixed_buckets() {
+    let policy = TolerancePolicy::legacy();
+    assert_eq!(policy.name, LEGACY_POLICY_NAME);
+
+    let early_date = date("2024-03-04 08:00");
+    for window in 0..=600 {
+        let late_date = early_date + Duration::minutes(window);
+        let middle_date = early_date + (late_date - early_date) / 2;
+        let tolerance = policy.calculate(early_date, late_date, middle_date);
+
+        let expected = baseline_tolerance(early_date, late_date);
+        assert_eq!(tolerance.before, expected, "window of {window} minutes");
+        assert_eq!(tolerance.after, expected, "window of {window} minutes");
+    }
+}
+
+#[test]
+fn the_most_specific_policy_wins() {
+    let set = TolerancePolicySet {
+        policies: vec![
+            policy("Mode", None, None, Some(DispoMode::Delivery)),
+            policy("Country", None, Some("DE"), None),
+            policy("Customer", Some("ACME"), None, None),
+            policy("Customer and country", Some("ACME"), Some("DE"), None),
+        ],
+        ..TolerancePolicySet::default()
+    };
+
+    assert_eq!(set.resolve(&["acme"], "de", DispoMode::Delivery).name, "Customer and country");
+    assert_eq!(set.resolve(&["ACME"], "FR", DispoMode::Delivery).name, "Customer");
+    assert_eq!(set.resolve(&["Other"], "DE", DispoMode::Delivery).name, "Country");
+    assert_eq!(set.resolve(&["Other"], "FR", DispoMode::Delivery).name, "Mode");
+    assert_eq!(set.resolve(&["Other"], "FR", DispoMode::Pickup).name, LEGACY_POLICY_NAME);
+}
+
+#[test]
+fn a_customer_policy_wins_over_a_more_specific_policy_without_customer() {
+    let set = TolerancePolicySet {
+        policies: vec![
+            policy("Country and mode", None, Some("DE"), Some(DispoMode::Delivery)),
+            policy("Customer", Some("ACME"), None, None),
+        ],
+        ..TolerancePolicySet::default()
+    };
+
+    assert_eq!(set.resolve(&["ACME"], "DE", DispoMode::Delivery).name, "Customer");
+    assert_eq!(set.resolve(&[], "DE", DispoMode::Delivery).name, "Country and mode");
+}
+
+#[test]
+fn customer_keys_are_tried_in_order() {
+    let set = TolerancePolicySet {
+        policies: vec![
+            policy("By name", Some("Acme GmbH"), None, None),
+            policy("By code", Some("ACM01"), None, None),
+        ],
+        ..TolerancePolicySet::default()
+    };
+
+    assert_eq!(set.resolve(&["ACM01", "Acme GmbH"], "DE", DispoMode::Delivery).name, "By code");
+    assert_eq!(set.resolve(&["XYZ99", "Acme GmbH"], "DE", DispoMode::Delivery).name, "By name");
+}
+
+#[test]
+fn equally_specific_policies_keep_the_first() {
+    let set = TolerancePolicySet {
+        policies: vec![
+            policy("First", None, Some("DE"), None),
+            policy("Second", None, None, Some(DispoMode::Delivery)),
+        ],
+        default: policy("Default", None, None, None),
+    };
+
+    assert_eq!(set.resolve(&[], "DE", DispoMode::Delivery).name, "First");
+    assert_eq!(set.resolve(&[], "FR", DispoMode::Pickup).name, "Default");
+}
@@ -0,0 +1,188 @@
+// Copyright 2023 Felix Kahle. All rights reserved.
+
+//! Caching parsed workbooks in a directory
+
+mod common;
+
+use dispo_core::{
+    file_parsing::{parse_xls_file_tms_with_task, EQUIPMENT_CODES_COLUMN_NAME, JOB_NUMBER_COLUMN_NAME},
+    parse_task::ParseTask,
+    workbook_cache::{self, CacheKey, WorkbookCache, PARSER_VERSION},
+};
+use polars::prelude::{DataFrame, NamedFrom, Series};
+use std::path::Path;
+
+/// The headers of the test workbooks
+const HEADERS: [&str; 3] = ["Load #", "Equipment Codes", "Consignee"];
+
+/// The headers of the test workbooks
+///
+/// # Returns
+/// * The headers
+fn headers() -> Vec<String> {
+    HEADERS.iter().map(|header| header.to_string()).collect()
+}
+
+/// A parsed workbook with the given columns
+///
+/// # Arguments
+/// * `load_number` - The Load # of its only row
+/// * `columns` - The headers of the parsed columns
+///
+/// # Returns
+/// * The DataFrame of the workbook
+fn workbook(load_number: &str, columns: &[&str]) -> DataFrame {
+    let series = columns
+        .iter()
+        .map(|column| Series::new(column, [format!("{} {}", column, load_number)]))
+        .collect();
+    DataFrame::new(series).expect("the columns are valid")
+}
+
+/// Get the size of the cached DataFrames in a directory
+///
+/// # Arguments
+/// * `directory` - The directory of the cache
+///
+/// # Returns
+/// * The size of the Arrow IPC files
+fn cached_bytes(directory: &Path) -> u64 {
+    std::fs::read_dir(directory)
+        .expect("the directory exists")
+        .map(|file| file.expect("the file can be read").path())
+        .filter(|path| path.extension().is_some_and(|extension| extension == "arrow"))
+        .map(|path| std::fs::metadata(path).expect("the file exists").len())
+        .sum()
+}
+
+#[test]
+fn stored_workbook_is_loaded() {
+    let directory = common::temp_path("cache-hit");
+    let cache = WorkbookCache::new(directory.clone(), u64::MAX);
+    let key = CacheKey::of(b"workbook");
+    let df = workbook("L1001", &HEADERS);
+
+    assert!(cache.load(&key, None).is_none());
+    cache.store(&key, &headers(), &df).expect("the workbook can be stored");
+    let loaded = cache.load(&key, None);
+    let _ = cache.clear();
+
+    assert_eq!(loaded, Some(df));
+}
+
+#[test]
+fn other_contents_are_a_miss() {
+    let directory = common::temp_path("cache-miss");
+    let cache = WorkbookCache::new(directory.clone(), u64::MAX);
+    cache
+        .store(&CacheKey::of(b"workbook"), &headers(), &workbook("L1001", &HEADERS))
+        .expect("the workbook can be stored");
+
+    let loaded = cache.load(&CacheKey::of(b"workbook, saved again"), None);
+    let _ = cache.clear();
+
+    assert!(loaded.is_none());
+}
+
+#[test]
+fn projection_is_loaded_from_the_cached_columns() {
+    let directory = common::temp_path("cache-projection");
+    let cache = WorkbookCache::new(directory.clone(), u64::MAX);
+    let key = CacheKey::of(b"workbook");
+    cache
+        .store(&key, &headers(), &workbook("L1001", &["Load #", "Consignee"]))
+        .expect("the workbook can be stored");
+
+    let projected = cache.load(&key, Some(&["Consignee"]));
+    // Not all wanted columns are cached, so the workbook has to be parsed again
+    let missing = cache.load(&key, Some(&["Load #", "Equipment Codes"]));
+    let all = cache.load(&key, None);
+    let cached_columns = cache.cached_columns(&key);
+    let _ = cache.clear();
+
+    assert_eq!(projected, Some(workbook("L1001", &["Consignee"])));
+    assert!(missing.is_none());
+    assert!(all.is_none());
+    assert_eq!(cached_columns, ["Load #", "Consignee"]);
+}
+
+#[test]
+fn parsed_columns_are_added_to_the_cached_columns() {
+    let directory = common::temp_path("cache-union");
+    workbook_cache::configure(directory.clone(), u64::MAX);
+    let cache = workbook_cache::global().expect("the cache is configured");
+    let path = common::fixture_path("cl_view.xlsx");
+    let path = path.to_str().expect("the path is valid UTF-8");
+    let key = CacheKey::of(&std::fs::read(path).expect("the fixture exists"));
+
+    let job_numbers =
+        parse_xls_file_tms_with_task(path, Some(&[JOB_NUMBER_COLUMN_NAME]), &ParseTask::silent()).expect("the fixture can be parsed");
+    let equipment_codes =
+        parse_xls_file_tms_with_task(path, Some(&[EQUIPMENT_CODES_COLUMN_NAME]), &ParseTask::silent()).expect("the fixture can be parsed");
+    let cached_columns = cache.cached_columns(&key);
+    // Both columns are cached now, so the first projection is a hit
+    let loaded = cache.load(&key, Some(&[JOB_NUMBER_COLUMN_NAME]));
+    let _ = cache.clear();
+
+    assert_eq!(job_numbers.get_column_names(), [JOB_NUMBER_COLUMN_NAME]);
+    // The columns cached before were parsed again
+    assert_eq!(
+        equipment_codes.get_column_names(),
+        [JOB_NUMBER_COLUMN_NAME, EQUIPMENT_CODES_COLUMN_NAME]
+    );
+    assert!(cached_columns.contains(&JOB_NUMBER_COLUMN_NAME.to_string()));
+    assert!(cached_columns.contains(&EQUIPMENT_CODES_COLUMN_NAME.to_string()));
+    assert_eq!(loaded, Some(job_numbers));
+}
+
+#[test]
+fn entries_of_another_parser_version_are_not_used() {
+    let directory = common::temp_path("cache-version");
+    let cache = WorkbookCache::new(directory.clone(), u64::MAX);
+    let old_key = CacheKey::versioned(b"workbook", PARSER_VERSION - 1);
+    cache
+        .store(&old_key, &headers(), &workbook("L1001", &HEADERS))
+        .expect("the workbook can be stored");
+
+    let loaded = cache.load(&CacheKey::of(b"workbook"), None);
+    let cached_columns = cache.cached_columns(&CacheKey::of(b"workbook"));
+    let _ = cache.clear();
+
+    assert_eq!(CacheKey::of(b"workbook"), CacheKey::versioned(b"workbook", PARSER_VERSION));
+    assert!(loaded.is_none());
+    assert!(cached_columns.is_empty());
+}
+
+#[test]
+fn least_recently_used_entry_is_evicted() {
+    // All workbooks have the same shape, so their entries have the same size
+    let directory = common::temp_path("cache-size");
+    let cache = WorkbookCache::new(directory.clone(), u64::MAX);
+    cache
+        .store(&CacheKey::of(b"size"), &headers(), &workbook("L1000", &HEADERS))
+        .expect("the workbook can be stored");
+    let entry_bytes = cached_bytes(&directory);
+    let _ = cache.clear();
+
+    let directory = common::temp_path("cache-eviction");
+    let cache = WorkbookCache::new(directory.clone(), entry_bytes * 2 + entry_bytes / 2);
+    let keys = [CacheKey::of(b"first"), CacheKey::of(b"second"), CacheKey::of(b"third")];
+    cache
+        .store(&keys[0], &headers(), &workbook("L1001", &HEADERS))
+        .expect("the workbook can be stored");
+    cache
+        .store(&keys[1], &headers(), &workbook("L1002", &HEADERS))
+        .expect("the workbook can be stored");
+    // Loading the first workbook makes the second one the least recently used
+    assert!(cache.load(&keys[0], None).is_some());
+    cache
+        .store(&keys[2], &headers(), &workbook("L1003", &HEADERS))
+        .expect("the workbook can be stored");
+
+    let loaded: Vec<bool> = keys.iter().map(|key| cache.load(key, None).is_some()).collect();
+    let bytes = cached_bytes(&directory);
+    let _ = cache.clear();
+
+    assert_eq!(loaded, [true, false, true]);
+    assert_eq!(bytes, entry_bytes * 2);
+}
@@ -0,0 +1,58 @@
+// Copyright 2023 Felix Kahle. All rights reserved.
+
+use dispo_core::{
+    audit_log::{self, AuditAction, FieldChange},
+    export::PlannedJob,
+    job_row::JobRow,
+};
+
+/// Record an action in the audit log.
+/// The action already happened, so a failed entry does not fail the command, it is reported on the console.
+///
+/// # Arguments
+/// * `action` - What was done
+/// * `job_ids` - The ids of the jobs the action was about
+/// * `changes` - The fields the action changed
+pub fn record(action: AuditAction, job_ids: Vec<String>, changes: Vec<FieldChange>) {
+    if let Err(error) = audit_log::record(action, job_ids, changes) {
+        eprintln!("Could not write the audit log: {}", error);
+    }
+}
+
+/// Record a change of jobs in the audit log, with the fields that changed
+///
+/// # Arguments
+/// * `action` - What was done
+/// * `before` - The jobs before the action
+/// * `after` - The jobs after the action
+pub fn record_job_changes(action: AuditAction, before: &[JobRow], after: &[JobRow]) {
+    let (job_ids, changes) = audit_log::diff_jobs(before, after, JobRow::id);
+    record(action, job_ids, changes);
+}
+
+/// Record changed assignments in the audit log, with the fields that changed.
+/// Nothing is recorded if no field changed.
+///
+/// # Arguments
+/// * `before` - The jobs before the change
+/// * `after` - The jobs after the change
+pub fn record_assignments(before: &[PlannedJob], after: &[PlannedJob]) {
+    let (job_ids, changes) = audit_log::diff_jobs(before, after, PlannedJob::id);
+    if !changes.is_empty() {
+        record(AuditAction::Assignment, job_ids, changes);
+    }
+}
+
+/// Record an export in the audit log
+///
+/// # Arguments
+/// * `kind` - The name of the export
+/// * `path` - The path of the written file or directory
+/// * `job_ids` - The ids of the exported jobs
+pub fn record_export(kind: &str, path: &str, job_ids: Vec<String>) {
+    let action = AuditAction::Export {
+        kind: kind.to_string(),
+        path: path.to_string(),
+    };
+    record(action, job_ids, Vec::new());
+}
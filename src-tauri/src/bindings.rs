@@ -0,0 +1,426 @@
+// Copyright 2023 Felix Kahle. All rights reserved.
+
+use crate::import_watcher::{ImportBatch, ImportFailure, ImportWatcherError};
+use dispo_core::{
+    audit_log::AuditLogError,
+    calendar::CalendarError,
+    dry_ice::DryIcePlan,
+    export::ExportError,
+    lifecycle::StatusChangeError,
+    manual_job::{JobEditError, JobEditRejection},
+    parse_error::ParseFilesError,
+    parse_task::ParseProgress,
+    plan_file::PlanFileError,
+    planning_workbook::PlanningWorkbookError,
+    shipment::LinkOptionsError,
+    workbook_cache::WorkbookCacheError,
+};
+use std::{
+    any::TypeId,
+    collections::HashSet,
+    path::{Path, PathBuf},
+};
+use ts_rs::{TypeVisitor, TS};
+
+/// The file the bindings are written to, relative to the directory of the app crate
+pub const BINDINGS_PATH: &str = "../src/tauri-api/bindings.ts";
+
+/// The first lines of the bindings
+const BINDINGS_HEADER: &str = "// This file is generated from the Rust types and commands of the app, do not edit it.
+// Debug builds of the app write it when they start, `cargo test --test bindings` checks it, see src-tauri/src/bindings.rs.
+// Failed commands reject with the message of their error.
+
+import { invoke } from \"@tauri-apps/api/tauri\";
+";
+
+/// Define the Tauri commands of the app, each one together with its client function in the bindings.
+/// The client function is derived from the command itself, so it can not drift from it:
+/// its docs are the summary of the doc comment, its parameters are the arguments of the command
+/// and what it resolves or rejects with follows from the return type.
+/// Arguments injected by Tauri, the AppHandle, the Window and managed State, are not passed by the frontend and left out.
+///
+/// Besides the commands the macro defines
+/// * COMMAND_NAMES: The names of all commands
+/// * invoke_handler: The invoke handler of all commands, registered by main.rs
+/// * bind_commands: Adds the client functions of all commands to the bindings
+macro_rules! commands {
+    // All commands are defined
+    (@command [$($names:ident)*] [$($binds:tt)*]) => {
+        /// The names of all commands, as the frontend invokes them
+        pub const COMMAND_NAMES: &[&str] = &[$(stringify!($names)),*];
+
+        /// Create the invoke handler of all commands
+        ///
+        /// # Returns
+        /// * The invoke handler
+        pub fn invoke_handler() -> impl Fn(tauri::Invoke) + Send + Sync + 'static {
+            tauri::generate_handler![$($names),*]
+        }
+
+        /// Add the client functions of all commands to the bindings
+        ///
+        /// # Arguments
+        /// * `bindings` - The bindings
+        pub fn bind_commands(bindings: &mut $crate::bindings::Bindings) {
+            $($crate::bindings::commands!(@bind bindings $binds);)*
+        }
+    };
+    (@command [$($names:ident)*] [$($binds:tt)*]
+        $(#[doc = $docs:literal])*
+        #[tauri::command]
+        async fn $name:ident($($arguments:tt)*) $(-> $returns:ty)? { $($body:tt)* }
+        $($rest:tt)*
+    ) => {
+        $(#[doc = $docs])*
+        #[tauri::command]
+        pub async fn $name($($arguments)*) $(-> $returns)? { $($body)* }
+
+        $crate::bindings::commands!(@command [$($names)* $name] [$($binds)* [$name [$($docs),*] [$($arguments)*] [$($returns)?]]] $($rest)*);
+    };
+    (@command [$($names:ident)*] [$($binds:tt)*]
+        $(#[doc = $docs:literal])*
+        #[tauri::command]
+        fn $name:ident($($arguments:tt)*) $(-> $returns:ty)? { $($body:tt)* }
+        $($rest:tt)*
+    ) => {
+        $(#[doc = $docs])*
+        #[tauri::command]
+        pub fn $name($($arguments)*) $(-> $returns)? { $($body)* }
+
+        $crate::bindings::commands!(@command [$($names)* $name] [$($binds)* [$name [$($docs),*] [$($arguments)*] [$($returns)?]]] $($rest)*);
+    };
+    // The client function of a command
+    (@bind $bindings:ident [$name:ident [$($docs:literal),*] [$($arguments:tt)*] []]) => {
+        $crate::bindings::commands!(@bind $bindings [$name [$($docs),*] [$($arguments)*] [()]])
+    };
+    (@bind $bindings:ident [$name:ident [$($docs:literal),*] [$($arguments:tt)*] [$returns:ty]]) => {
+        $crate::bindings::commands!(@argument [$bindings.command(stringify!($name), &[$($docs),*])] $($arguments)*)
+            .returns::<$returns>()
+    };
+    // The arguments of a command, the ones injected by Tauri are skipped
+    (@argument [$command:expr]) => {
+        $command
+    };
+    (@argument [$command:expr] $argument:ident: AppHandle $(, $($rest:tt)*)?) => {
+        $crate::bindings::commands!(@argument [$command] $($($rest)*)?)
+    };
+    (@argument [$command:expr] $argument:ident: Window $(, $($rest:tt)*)?) => {
+        $crate::bindings::commands!(@argument [$command] $($($rest)*)?)
+    };
+    (@argument [$command:expr] $argument:ident: State<$lifetime:lifetime, $state:ty> $(, $($rest:tt)*)?) => {
+        $crate::bindings::commands!(@argument [$command] $($($rest)*)?)
+    };
+    (@argument [$command:expr] $argument:ident: State<$state:ty> $(, $($rest:tt)*)?) => {
+        $crate::bindings::commands!(@argument [$command] $($($rest)*)?)
+    };
+    (@argument [$command:expr] $argument:ident: $type:ty $(, $($rest:tt)*)?) => {
+        $crate::bindings::commands!(@argument [$command.argument::<$type>(stringify!($argument))] $($($rest)*)?)
+    };
+    ($($commands:tt)*) => {
+        $crate::bindings::commands!(@command [] [] $($commands)*);
+    };
+}
+pub(crate) use commands;
+
+/// The TypeScript bindings of the app: the declarations of all types the frontend sees and a typed function per command.
+/// * visited: The types that were already visited
+/// * declarations: The names and declarations of the visited types
+/// * commands: The client functions of the commands
+#[derive(Default)]
+pub struct Bindings {
+    visited: HashSet<TypeId>,
+    declarations: Vec<(String, String)>,
+    commands: Vec<String>,
+}
+
+impl TypeVisitor for Bindings {
+    fn visit<T: TS + 'static + ?Sized>(&mut self) {
+        if !self.visited.insert(TypeId::of::<T>()) {
+            return;
+        }
+        // Only derived types have a declaration, primitives and containers are inlined
+        if T::output_path().is_some() {
+            self.declarations
+                .push((T::ident(), format!("{}export {}", T::DOCS.unwrap_or_default(), T::decl())));
+        }
+        T::visit_dependencies(self);
+        T::visit_generics(self);
+    }
+}
+
+impl Bindings {
+    /// Get the TypeScript type of a Rust type, declaring it and all types it depends on
+    ///
+    /// # Returns
+    /// * The TypeScript type
+    pub fn type_of<T: TS + 'static>(&mut self) -> String {
+        self.visit::<T>();
+        T::name()
+    }
+
+    /// Start the client function of a command
+    ///
+    /// # Arguments
+    /// * `name` - The name of the command, as registered in the invoke handler
+    /// * `docs` - The lines of the doc comment of the command
+    ///
+    /// # Returns
+    /// * The builder of the client function
+    pub fn command(&mut self, name: &'static str, docs: &[&str]) -> CommandBinding<'_> {
+        CommandBinding {
+            bindings: self,
+            name,
+            docs: summary(docs),
+            arguments: Vec::new(),
+        }
+    }
+
+    /// Render the bindings.
+    /// The declarations are sorted by name, ts-rs finds the dependencies of a type in a different order on every build.
+    ///
+    /// # Returns
+    /// * The content of the bindings file
+    pub fn render(&self) -> String {
+        let mut declarations: Vec<&(String, String)> = self.declarations.iter().collect();
+        declarations.sort();
+        let declarations = declarations.into_iter().map(|(_, declaration)| declaration);
+
+        let mut content = BINDINGS_HEADER.to_string();
+        for section in declarations.chain(self.commands.iter()) {
+            content.push('\n');
+            content.push_str(section);
+            content.push('\n');
+        }
+        content
+    }
+}
+
+/// The client function of a command, finished by returns
+/// * bindings: The bindings the function is added to
+/// * name: The name of the command
+/// * docs: What the command does
+/// * arguments: The names and TypeScript types of the arguments, in camelCase like Tauri expects them
+pub struct CommandBinding<'a> {
+    bindings: &'a mut Bindings,
+    name: &'static str,
+    docs: String,
+    arguments: Vec<(String, String)>,
+}
+
+impl CommandBinding<'_> {
+    /// Add an argument the frontend passes.
+    /// Arguments injected by Tauri, like the app or managed state, are left out.
+    ///
+    /// # Arguments
+    /// * `name` - The name of the argument in snake_case
+    ///
+    /// # Returns
+    /// * The builder
+    pub fn argument<T: TS + 'static>(mut self, name: &str) -> Self {
+        let ts_type = self.bindings.type_of::<T>();
+        self.arguments.push((camel_case(name), ts_type));
+        self
+    }
+
+    /// Finish the client function with the type the command returns
+    pub fn returns<T: CommandReturn>(self) {
+        let returns = match T::resolves_with(self.bindings).as_str() {
+            "null" => "void".to_string(),
+            returns => returns.to_string(),
+        };
+
+        let mut docs = format!("/**\n * {}\n", self.docs);
+        if let Some(error) = T::rejects_with(self.bindings) {
+            docs.push_str(&format!(" *\n * @throws {}\n", error));
+        }
+        docs.push_str(" */\n");
+
+        let parameters: Vec<String> = self
+            .arguments
+            .iter()
+            .map(|(name, ts_type)| format!("{}: {}", name, ts_type))
+            .collect();
+        let arguments: Vec<&str> = self.arguments.iter().map(|(name, _)| name.as_str()).collect();
+        let arguments = match arguments.is_empty() {
+            true => String::new(),
+            false => format!(", {{ {} }}", arguments.join(", ")),
+        };
+
+        self.bindings.commands.push(format!(
+            "{}export async function {}({}): Promise<{}> {{\n  return await invoke(\"{}\"{});\n}}",
+            docs,
+            camel_case(self.name),
+            parameters.join(", "),
+            returns,
+            self.name,
+            arguments
+        ));
+    }
+}
+
+/// Get the summary of a doc comment, its first paragraph in one line
+///
+/// # Arguments
+/// * `docs` - The lines of the doc comment
+///
+/// # Returns
+/// * The summary
+fn summary(docs: &[&str]) -> String {
+    let lines: Vec<&str> = docs.iter().map(|line| line.trim()).take_while(|line| !line.is_empty()).collect();
+    lines.join(" ")
+}
+
+/// Convert a snake_case name of a command or argument into the camelCase name the frontend uses
+///
+/// # Arguments
+/// * `name` - The name in snake_case
+///
+/// # Returns
+/// * The name in camelCase
+fn camel_case(name: &str) -> String {
+    let mut words = name.split('_');
+    let mut result = words.next().unwrap_or_default().to_string();
+    for word in words {
+        let mut characters = word.chars();
+        if let Some(first) = characters.next() {
+            result.extend(first.to_uppercase());
+            result.push_str(characters.as_str());
+        }
+    }
+    result
+}
+
+/// What the client function of a command resolves with, derived from the return type of the command
+pub trait CommandReturn {
+    /// Get the TypeScript type the promise resolves with
+    ///
+    /// # Arguments
+    /// * `bindings` - The bindings the type is declared in
+    ///
+    /// # Returns
+    /// * The TypeScript type
+    fn resolves_with(bindings: &mut Bindings) -> String;
+
+    /// Get what the promise rejects with
+    ///
+    /// # Arguments
+    /// * `bindings` - The bindings the type is declared in
+    ///
+    /// # Returns
+    /// * What the promise rejects with, None if the command can not fail
+    fn rejects_with(_bindings: &mut Bindings) -> Option<String> {
+        None
+    }
+}
+
+/// The error of a command, the client function rejects with what Tauri serializes it to
+pub trait CommandError {
+    /// Get what the promise rejects with
+    ///
+    /// # Arguments
+    /// * `bindings` - The bindings the type is declared in
+    ///
+    /// # Returns
+    /// * What the promise rejects with
+    fn rejected_with(bindings: &mut Bindings) -> String;
+}
+
+impl<T: TS + 'static, E: CommandError> CommandReturn for Result<T, E> {
+    fn resolves_with(bindings: &mut Bindings) -> String {
+        bindings.type_of::<T>()
+    }
+
+    fn rejects_with(bindings: &mut Bindings) -> Option<String> {
+        Some(E::rejected_with(bindings))
+    }
+}
+
+impl<T: TS + 'static> CommandReturn for Vec<T> {
+    fn resolves_with(bindings: &mut Bindings) -> String {
+        bindings.type_of::<Self>()
+    }
+}
+
+/// Commands that return one of these types can not fail
+macro_rules! infallible_returns {
+    ($($type:ty),* $(,)?) => {
+        $(impl CommandReturn for $type {
+            fn resolves_with(bindings: &mut Bindings) -> String {
+                bindings.type_of::<Self>()
+            }
+        })*
+    };
+}
+
+infallible_returns!((), bool, usize, DryIcePlan);
+
+/// Errors that are serialized as their message
+macro_rules! message_errors {
+    ($($error:ident),* $(,)?) => {
+        $(impl CommandError for $error {
+            fn rejected_with(_bindings: &mut Bindings) -> String {
+                format!("The message of the {}.", stringify!($error))
+            }
+        })*
+    };
+}
+
+message_errors!(
+    AuditLogError,
+    CalendarError,
+    ExportError,
+    ImportWatcherError,
+    LinkOptionsError,
+    ParseFilesError,
+    PlanFileError,
+    PlanningWorkbookError,
+    StatusChangeError,
+    WorkbookCacheError,
+);
+
+// A JobEditError is serialized as a JobEditRejection, so the frontend can show the errors next to their fields
+impl CommandError for JobEditError {
+    fn rejected_with(bindings: &mut Bindings) -> String {
+        format!("A {}.", bindings.type_of::<JobEditRejection>())
+    }
+}
+
+/// Create the bindings of all commands and events of the app.
+///
+/// # Returns
+/// * The bindings
+pub fn bindings() -> Bindings {
+    let mut bindings = Bindings::default();
+
+    // The payloads of the events
+    bindings.type_of::<ParseProgress>();
+    bindings.type_of::<ImportBatch>();
+    bindings.type_of::<ImportFailure>();
+
+    crate::commands::bind_commands(&mut bindings);
+    bindings
+}
+
+/// Get the path of the bindings file in the source tree the app was built from
+///
+/// # Returns
+/// * The path of the bindings file
+pub fn bindings_path() -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR")).join(BINDINGS_PATH)
+}
+
+/// Write the bindings of the app
+///
+/// # Arguments
+/// * `path` - The path of the bindings file
+///
+/// # Returns
+/// * Result containing nothing or an error
+pub fn export(path: &Path) -> Result<(), std::io::Error> {
+    let content = bindings().render();
+    // Rewriting an unchanged file would make the dev server reload the frontend
+    if std::fs::read_to_string(path).is_ok_and(|existing| existing == content) {
+        return Ok(());
+    }
+    std::fs::write(path, content)
+}
@@ -0,0 +1,497 @@
+// Copyright 2023 Felix Kahle. All rights reserved.
+
+//! The Tauri commands of the app.
+//! Each command is defined inside commands!, which also derives its client function in the bindings from its signature.
+
+use crate::{
+    audit,
+    bindings::commands,
+    import_watcher::{self, ImportWatchOptions, ImportWatcherError, ImportWatcherState},
+};
+use dispo_core::{
+    audit_log::{self, AuditAction, AuditEntries, AuditLogError, AuditQuery},
+    calendar::{CalendarError, HolidaySet},
+    dry_ice::{self, DryIceConfig, DryIcePlan},
+    export::{self, ExportError, PlannedJob},
+    file_parsing::{create_job_rows_with_files, ImportOptions},
+    icalendar::{self, CalendarGrouping},
+    job_row::{DispoMode, JobRow},
+    lifecycle::{self, JobStatus, StatusChangeError, StatusReason},
+    manual_job::{self, JobEditError, ManualJob},
+    parse_error::ParseFilesError,
+    parse_task::{ParseTask, ParseTasks, PARSE_PROGRESS_EVENT},
+    plan_file::{self, PlanFile, PlanFileError},
+    planning_workbook::{self, ChangeSet, JobChange, PlanningWorkbookError},
+    shipment::{self, LinkOptions, LinkOptionsError, Shipment, ShipmentLeg},
+    tms_export::{self, TmsExportLayout, TmsExportSummary},
+    workbook_cache::{self, WorkbookCacheError},
+};
+use tauri::{AppHandle, Manager, State, Window};
+
+commands! {
+
+    /// Returns a list of all printers available on the system
+    /// as a vector of strings
+    ///
+    /// # Returns
+    /// The list of printers as a vector of strings
+    #[tauri::command]
+    fn get_printer_names() -> Vec<String> {
+        printers::get_printers().iter().map(|printer| printer.name.clone()).collect()
+    }
+
+    /// Parses the CL View and the Shipper Site into jobs in a background task.
+    /// Both workbooks are parsed concurrently.
+    /// The progress is emitted as "parse-progress" events and the task can be cancelled with cancel_parse.
+    ///
+    /// # Arguments
+    /// * `app` - The app, used to emit the progress
+    /// * `tasks` - The running parse tasks
+    /// * `task_id` - The id of the task, chosen by the caller
+    /// * `cl_view` - The path to the CL View .xls file
+    /// * `shipper_site` - The path to the Shipper Site .xls file
+    /// * `mode` - The mode of the jobs
+    /// * `options` - The import options, or None for the defaults
+    ///
+    /// # Returns
+    /// The imported jobs
+    #[tauri::command]
+    async fn parse_files(
+        app: AppHandle,
+        tasks: State<'_, ParseTasks>,
+        task_id: String,
+        cl_view: String,
+        shipper_site: String,
+        mode: DispoMode,
+        options: Option<ImportOptions>,
+    ) -> Result<Vec<JobRow>, ParseFilesError> {
+        let cancelled = tasks.register(&task_id)?;
+        let task = ParseTask::new(&task_id, cancelled, move |progress| {
+            // The import goes on if nobody listens to the progress
+            let _ = app.emit_all(PARSE_PROGRESS_EVENT, progress);
+        });
+
+        let result = tauri::async_runtime::spawn_blocking(move || -> Result<Vec<JobRow>, ParseFilesError> {
+            let (jobs, files) = create_job_rows_with_files(&cl_view, &shipper_site, mode, &options.unwrap_or_default(), &task)?;
+            audit::record(
+                AuditAction::Import {
+                    mode,
+                    files,
+                    watched: false,
+                },
+                jobs.iter().map(JobRow::id).collect(),
+                Vec::new(),
+            );
+            Ok(jobs)
+        })
+        .await;
+        tasks.finish(&task_id);
+
+        result.map_err(|error| ParseFilesError::TaskFailed(error.to_string()))?
+    }
+
+    /// Cancels a running parse task.
+    /// The task stops after its current step and parse_files returns a Cancelled error.
+    ///
+    /// # Arguments
+    /// * `tasks` - The running parse tasks
+    /// * `task_id` - The id of the task
+    ///
+    /// # Returns
+    /// Whether the task was running
+    #[tauri::command]
+    fn cancel_parse(tasks: State<ParseTasks>, task_id: String) -> bool {
+        tasks.cancel(&task_id)
+    }
+
+    /// Removes all parsed workbooks from the cache, so the next imports parse them from scratch
+    ///
+    /// # Returns
+    /// Nothing, or an error if the cache could not be removed
+    #[tauri::command]
+    fn clear_workbook_cache() -> Result<(), WorkbookCacheError> {
+        match workbook_cache::global() {
+            Some(cache) => cache.clear(),
+            None => Ok(()),
+        }
+    }
+
+    /// Starts watching a folder for new CL View and Shipper Site exports.
+    /// Every recognised pair is imported and emitted as an "import-batch" event, failed imports as an "import-failed" event.
+    /// A folder that is already watched is replaced.
+    ///
+    /// # Arguments
+    /// * `app` - The app, used to emit the events
+    /// * `state` - The folder watcher of the app
+    /// * `directory` - The folder to watch
+    /// * `options` - The mode and options of the imports
+    #[tauri::command]
+    fn start_import_watcher(
+        app: AppHandle,
+        state: State<ImportWatcherState>,
+        directory: String,
+        options: ImportWatchOptions,
+    ) -> Result<(), ImportWatcherError> {
+        let watcher = import_watcher::watch(app, &directory, options)?;
+        *state.0.lock().unwrap_or_else(|poisoned| poisoned.into_inner()) = Some(watcher);
+        Ok(())
+    }
+
+    /// Stops watching the folder for new exports
+    ///
+    /// # Arguments
+    /// * `state` - The folder watcher of the app
+    #[tauri::command]
+    fn stop_import_watcher(state: State<ImportWatcherState>) {
+        state.0.lock().unwrap_or_else(|poisoned| poisoned.into_inner()).take();
+    }
+
+    /// Reads a set of public holidays from a CSV file.
+    /// Every line of the file must have the format `YYYY-MM-DD,Name`.
+    ///
+    /// # Arguments
+    /// * `path` - The path of the CSV file
+    /// * `country` - The country code the holidays belong to
+    /// * `region` - The region the holidays belong to, or None for the whole country
+    ///
+    /// # Returns
+    /// The holiday set, to be sent back as part of the import options
+    #[tauri::command]
+    fn import_holidays(path: String, country: String, region: Option<String>) -> Result<HolidaySet, CalendarError> {
+        HolidaySet::from_csv_file(&path, &country, region.as_deref())
+    }
+
+    /// Writes the dispatch plan to a formatted .xlsx file.
+    /// The file has a sheet with all jobs and one sheet per driver.
+    ///
+    /// # Arguments
+    /// * `path` - The path of the .xlsx file
+    /// * `jobs` - The jobs of the dispatch plan
+    #[tauri::command]
+    fn export_plan(path: String, jobs: Vec<PlannedJob>) -> Result<(), ExportError> {
+        export::write_plan_xlsx(&path, &jobs)?;
+        audit::record_export("plan", &path, jobs.iter().map(PlannedJob::id).collect());
+        Ok(())
+    }
+
+    /// Creates a job manually, e.g. for an order taken by phone.
+    /// The job is validated and planned like an imported job and marked as manual.
+    ///
+    /// # Arguments
+    /// * `jobs` - The current jobs
+    /// * `job` - The job as entered by the dispatcher
+    /// * `options` - The import options, or None for the defaults
+    ///
+    /// # Returns
+    /// The jobs with the new job at the end
+    #[tauri::command]
+    fn create_job(jobs: Vec<JobRow>, job: ManualJob, options: Option<ImportOptions>) -> Result<Vec<JobRow>, JobEditError> {
+        let before = jobs.clone();
+        let jobs = manual_job::create_job(jobs, &job, &options.unwrap_or_default())?;
+        audit::record_job_changes(AuditAction::JobCreated, &before, &jobs);
+        Ok(jobs)
+    }
+
+    /// Replaces a job with an edited version, the job keeps its origin.
+    ///
+    /// # Arguments
+    /// * `jobs` - The current jobs
+    /// * `id` - The id of the job to edit, the mode and the job number like "Delivery:L1001"
+    /// * `job` - The edited job
+    /// * `options` - The import options, or None for the defaults
+    ///
+    /// # Returns
+    /// The jobs with the edited job
+    #[tauri::command]
+    fn update_job(jobs: Vec<JobRow>, id: String, job: ManualJob, options: Option<ImportOptions>) -> Result<Vec<JobRow>, JobEditError> {
+        let before = jobs.clone();
+        let jobs = manual_job::update_job(jobs, &id, &job, &options.unwrap_or_default())?;
+        audit::record_job_changes(AuditAction::JobEdited, &before, &jobs);
+        Ok(jobs)
+    }
+
+    /// Removes a job.
+    ///
+    /// # Arguments
+    /// * `jobs` - The current jobs
+    /// * `id` - The id of the job to remove, the mode and the job number like "Delivery:L1001"
+    ///
+    /// # Returns
+    /// The remaining jobs
+    #[tauri::command]
+    fn delete_job(jobs: Vec<JobRow>, id: String) -> Result<Vec<JobRow>, JobEditError> {
+        let before = jobs.clone();
+        let jobs = manual_job::delete_job(jobs, &id)?;
+        audit::record_job_changes(AuditAction::JobDeleted, &before, &jobs);
+        Ok(jobs)
+    }
+
+    /// Changes the status of a job, if its current status allows it.
+    /// The change is recorded with the current time in the status history of the job.
+    ///
+    /// # Arguments
+    /// * `jobs` - The current jobs
+    /// * `id` - The id of the job, the mode and the job number like "Delivery:L1001"
+    /// * `status` - The new status
+    /// * `reason` - Why the status is changed, required for Exception and Cancelled
+    /// * `note` - A free text explanation, may be empty
+    ///
+    /// # Returns
+    /// The jobs with the changed job
+    #[tauri::command]
+    fn change_job_status(
+        jobs: Vec<JobRow>,
+        id: String,
+        status: JobStatus,
+        reason: Option<StatusReason>,
+        note: String,
+    ) -> Result<Vec<JobRow>, StatusChangeError> {
+        let before = jobs.clone();
+        let jobs = lifecycle::change_job_status(jobs, &id, status, reason, &note)?;
+        let change = jobs
+            .iter()
+            .find(|job| job.id() == id)
+            .and_then(|job| job.status_history.last())
+            .cloned();
+        if let Some(change) = change {
+            let action = AuditAction::StatusChanged {
+                from: change.from,
+                to: change.to,
+                reason: change.reason,
+                note: change.note,
+            };
+            audit::record_job_changes(action, &before, &jobs);
+        }
+        Ok(jobs)
+    }
+
+    /// Saves the jobs to a versioned JSON plan file, so they can be loaded again later.
+    ///
+    /// # Arguments
+    /// * `path` - The path of the .json file
+    /// * `jobs` - The jobs to save, with the driver, vehicle and notes assigned to them
+    #[tauri::command]
+    fn export_plan_file(path: String, jobs: Vec<PlannedJob>) -> Result<(), PlanFileError> {
+        let job_ids = jobs.iter().map(PlannedJob::id).collect();
+        plan_file::write_plan_file(&path, jobs)?;
+        audit::record_export("planFile", &path, job_ids);
+        Ok(())
+    }
+
+    /// Loads the jobs of a plan file.
+    /// Files written by older versions of the app are migrated to the current version.
+    ///
+    /// # Arguments
+    /// * `path` - The path of the .json file
+    ///
+    /// # Returns
+    /// The plan file with the loaded jobs
+    #[tauri::command]
+    fn import_plan_file(path: String) -> Result<PlanFile, PlanFileError> {
+        plan_file::read_plan_file(&path)
+    }
+
+    /// Writes one iCalendar file per driver or vehicle into a directory.
+    ///
+    /// # Arguments
+    /// * `directory` - The directory to write to
+    /// * `jobs` - The jobs of the dispatch plan
+    /// * `grouping` - Whether one calendar per driver or per vehicle is created
+    ///
+    /// # Returns
+    /// The paths of the written files
+    #[tauri::command]
+    fn export_calendars(directory: String, jobs: Vec<PlannedJob>, grouping: CalendarGrouping) -> Result<Vec<String>, ExportError> {
+        let paths = icalendar::write_calendars(&directory, &jobs, grouping)?;
+        audit::record_export("calendars", &directory, jobs.iter().map(PlannedJob::id).collect());
+        Ok(paths)
+    }
+
+    /// Writes the planning status of the jobs to a file that can be imported into TMS.
+    /// Only jobs that changed since the last export are written, unless a full export is requested.
+    ///
+    /// # Arguments
+    /// * `app` - The app, used to find the data directory the export state is kept in
+    /// * `path` - The path of the file to write
+    /// * `jobs` - The jobs of the dispatch plan
+    /// * `layout` - The layout of the file, or None for the default layout
+    /// * `full` - Whether all jobs are exported, regardless of what was exported before
+    ///
+    /// # Returns
+    /// The number of exported and unchanged jobs
+    #[tauri::command]
+    fn export_tms_status(
+        app: AppHandle,
+        path: String,
+        jobs: Vec<PlannedJob>,
+        layout: Option<TmsExportLayout>,
+        full: bool,
+    ) -> Result<TmsExportSummary, ExportError> {
+        let state_path = app
+            .path_resolver()
+            .app_data_dir()
+            .ok_or(ExportError::NoDataDirectory)?
+            .join(tms_export::TMS_EXPORT_STATE_FILE_NAME);
+        let summary = tms_export::export_status(&path, &state_path, &jobs, &layout.unwrap_or_default(), full)?;
+        if summary.written {
+            audit::record_export("tmsStatus", &path, jobs.iter().map(PlannedJob::id).collect());
+        }
+        Ok(summary)
+    }
+
+    /// Writes the jobs to a planning workbook that can be edited in Excel and imported again.
+    ///
+    /// # Arguments
+    /// * `path` - The path of the .xlsx file
+    /// * `jobs` - The jobs to plan
+    #[tauri::command]
+    fn export_planning_workbook(path: String, jobs: Vec<PlannedJob>) -> Result<(), PlanningWorkbookError> {
+        planning_workbook::write_planning_workbook(&path, &jobs)?;
+        audit::record_export("planningWorkbook", &path, jobs.iter().map(PlannedJob::id).collect());
+        Ok(())
+    }
+
+    /// Reads an edited planning workbook and compares it with the current jobs.
+    /// Nothing is applied, the change set is meant to be reviewed first.
+    ///
+    /// # Arguments
+    /// * `path` - The path of the edited .xlsx file
+    /// * `jobs` - The current jobs
+    ///
+    /// # Returns
+    /// The change set of the workbook
+    #[tauri::command]
+    fn read_planning_workbook(path: String, jobs: Vec<PlannedJob>) -> Result<ChangeSet, PlanningWorkbookError> {
+        planning_workbook::read_planning_workbook(&path, &jobs)
+    }
+
+    /// Applies a reviewed change set to the current jobs.
+    ///
+    /// # Arguments
+    /// * `jobs` - The current jobs
+    /// * `change_set` - The reviewed change set
+    /// * `include_conflicts` - Whether the conflicting changes are applied as well
+    ///
+    /// # Returns
+    /// The changed jobs
+    #[tauri::command]
+    fn apply_change_set(jobs: Vec<PlannedJob>, change_set: ChangeSet, include_conflicts: bool) -> Vec<PlannedJob> {
+        let before = jobs.clone();
+        let jobs = planning_workbook::apply_change_set(jobs, &change_set, include_conflicts);
+        audit::record_assignments(&before, &jobs);
+        jobs
+    }
+
+    /// Changes the driver, vehicle, planned date or notes of a job, as edited by the dispatcher in the app.
+    /// The change is recorded in the audit log like the changes of a planning workbook.
+    /// A job that gets a driver and a vehicle becomes Assigned.
+    ///
+    /// # Arguments
+    /// * `jobs` - The current jobs
+    /// * `change` - The change of one job
+    ///
+    /// # Returns
+    /// The changed jobs
+    #[tauri::command]
+    fn change_assignment(jobs: Vec<PlannedJob>, change: JobChange) -> Result<Vec<PlannedJob>, JobEditError> {
+        let before = jobs.clone();
+        let jobs = planning_workbook::apply_job_change(jobs, &change)?;
+        audit::record_assignments(&before, &jobs);
+        Ok(jobs)
+    }
+
+    /// Records that jobs were printed.
+    /// Printing happens in the frontend, so it reports each print here for the audit log.
+    ///
+    /// # Arguments
+    /// * `printer` - The name of the printer
+    /// * `job_ids` - The ids of the printed jobs
+    #[tauri::command]
+    fn record_print(printer: String, job_ids: Vec<String>) {
+        audit::record(AuditAction::Print { printer }, job_ids, Vec::new());
+    }
+
+    /// Reads the entries of the audit log.
+    ///
+    /// # Arguments
+    /// * `query` - Which entries are read, or None for all entries
+    ///
+    /// # Returns
+    /// The matching entries, oldest first, and the lines that are not entries
+    #[tauri::command]
+    fn read_audit_log(query: Option<AuditQuery>) -> Result<AuditEntries, AuditLogError> {
+        audit_log::global()
+            .ok_or(AuditLogError::NotConfigured)?
+            .entries(&query.unwrap_or_default())
+    }
+
+    /// Writes the entries of the audit log to a CSV file, e.g. for a quality investigation.
+    /// Every changed field is a row of its own.
+    ///
+    /// # Arguments
+    /// * `path` - The path of the .csv file
+    /// * `query` - Which entries are written, or None for all entries
+    ///
+    /// # Returns
+    /// The number of written entries
+    #[tauri::command]
+    fn export_audit_log(path: String, query: Option<AuditQuery>) -> Result<usize, AuditLogError> {
+        audit_log::export_csv(&path, &query.unwrap_or_default())
+    }
+
+    /// Links the jobs of all imports into shipments and validates their chains of legs.
+    ///
+    /// # Arguments
+    /// * `legs` - The legs of all known jobs
+    /// * `options` - The options used for the transit times, or None for the defaults
+    ///
+    /// # Returns
+    /// The shipments with at least two legs, the broken ones first, or an error if a transit time can not be used
+    #[tauri::command]
+    fn link_shipments(legs: Vec<ShipmentLeg>, options: Option<LinkOptions>) -> Result<Vec<Shipment>, LinkOptionsError> {
+        shipment::link_shipments(legs, &options.unwrap_or_default())
+    }
+
+    /// Estimates the dry ice of the dry ice shipments of the plan and totals it per vehicle.
+    /// The result also contains the UN1845 declarations for printing.
+    ///
+    /// # Arguments
+    /// * `jobs` - The jobs of the dispatch plan
+    /// * `config` - The packaging profiles, replenishment rule and vehicle limit, or None for the defaults
+    ///
+    /// # Returns
+    /// The dry ice of the plan
+    #[tauri::command]
+    fn plan_dry_ice(jobs: Vec<PlannedJob>, config: Option<DryIceConfig>) -> DryIcePlan {
+        dry_ice::plan_dry_ice(&jobs, &config.unwrap_or_default())
+    }
+
+    /// Shows the splashscreen window
+    ///
+    /// # Arguments
+    /// * `window` - The window manager
+    #[tauri::command]
+    async fn show_splashscreen(window: Window) {
+        window
+            .get_window("splashscreen")
+            .expect("no window labeled 'splashscreen' found")
+            .show()
+            .unwrap();
+    }
+
+    /// Closes the splashscreen window and shows the main window
+    ///
+    /// # Arguments
+    /// * `window` - The window manager
+    #[tauri::command]
+    async fn close_splashscreen(window: Window) {
+        // Close splashscreen
+        window
+            .get_window("splashscreen")
+            .expect("no window labeled 'splashscreen' found")
+            .close()
+            .unwrap();
+        // Show main window
+        window.get_window("main").expect("no window labeled 'main' found").show().unwrap();
+    }
+}
@@ -0,0 +1,438 @@
+// Copyright 2023 Felix Kahle. All rights reserved.
+
+use crate::audit;
+use dispo_core::{
+    audit_log::AuditAction,
+    diagnostics::Severity,
+    file_parsing::{
+        create_job_rows_with_files, parse_xls_file_tms_with_task, ImportOptions, EQUIPMENT_CODES_COLUMN_NAME, HAWB_COLUMN_NAME,
+        JOB_NUMBER_COLUMN_NAME, TEMPERATURE_RANGE_COLUMN_NAME,
+    },
+    job_row::{extract_column_as_string, DispoMode, JobRow},
+    parse_error::ParseFilesError,
+    parse_task::ParseTask,
+};
+use notify::{EventKind, RecursiveMode, Watcher};
+use std::{
+    collections::HashSet,
+    fmt::{self},
+    path::{Path, PathBuf},
+    sync::{
+        mpsc::{self, RecvTimeoutError},
+        Mutex,
+    },
+    time::{Duration, SystemTime},
+};
+use tauri::{AppHandle, InvokeError, Manager};
+
+/// The event emitted for every batch imported from the watched folder
+pub const IMPORT_BATCH_EVENT: &str = "import-batch";
+
+/// The event emitted if a pair of workbooks from the watched folder could not be imported
+pub const IMPORT_FAILED_EVENT: &str = "import-failed";
+
+/// The time pairs of workbooks may be apart if no other pairing window is configured
+pub const DEFAULT_PAIRING_WINDOW_MINUTES: u64 = 120;
+
+/// Changed files are only read once no event arrived for this long, so files are not read while they are still being written
+const SETTLE_DELAY: Duration = Duration::from_millis(1500);
+
+/// The columns needed to recognise and pair an export, with their default headers
+const WATCHED_COLUMNS: [&str; 4] = [
+    JOB_NUMBER_COLUMN_NAME,
+    EQUIPMENT_CODES_COLUMN_NAME,
+    HAWB_COLUMN_NAME,
+    TEMPERATURE_RANGE_COLUMN_NAME,
+];
+
+/// The WorkbookKind enum represents the TMS exports the watcher recognises
+/// * ClView: The CL View export
+/// * ShipperSite: The Shipper Site export
+#[derive(serde::Serialize, serde::Deserialize, Debug, Copy, Clone, PartialEq, Eq)]
+pub enum WorkbookKind {
+    ClView,
+    ShipperSite,
+}
+
+impl fmt::Display for WorkbookKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            WorkbookKind::ClView => write!(f, "CL View"),
+            WorkbookKind::ShipperSite => write!(f, "Shipper Site"),
+        }
+    }
+}
+
+impl WorkbookKind {
+    /// Recognise a TMS export by its headers.
+    /// Both exports have a Load # column, only the CL View has the equipment codes
+    /// and only the Shipper Site has the hawb and temperature range.
+    ///
+    /// # Arguments
+    /// * `headers` - The headers of the first sheet
+    /// * `options` - The import options, they may configure other headers for the columns
+    ///
+    /// # Returns
+    /// * The kind of the export or None if it is neither
+    pub fn classify(headers: &[&str], options: &ImportOptions) -> Option<Self> {
+        let has = |column| headers.contains(&options.column_header(column));
+        if !has(JOB_NUMBER_COLUMN_NAME) {
+            return None;
+        }
+        let equipment = has(EQUIPMENT_CODES_COLUMN_NAME);
+        let hawb = has(HAWB_COLUMN_NAME) && has(TEMPERATURE_RANGE_COLUMN_NAME);
+        match (equipment, hawb) {
+            (true, false) => Some(WorkbookKind::ClView),
+            (false, true) => Some(WorkbookKind::ShipperSite),
+            _ => None,
+        }
+    }
+}
+
+/// The options of the folder watcher
+/// * mode: The mode of the jobs of all imports
+/// * options: The options used for every import
+/// * pairing_window_minutes: How far apart the modification times of a CL View and a Shipper Site may be to be paired
+#[derive(serde::Serialize, serde::Deserialize, ts_rs::TS, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct ImportWatchOptions {
+    pub mode: DispoMode,
+    #[serde(default)]
+    #[ts(as = "Option<ImportOptions>", optional)]
+    pub options: ImportOptions,
+    #[serde(default = "default_pairing_window_minutes")]
+    #[ts(as = "Option<u32>", optional)]
+    pub pairing_window_minutes: u64,
+}
+
+/// The pairing window used if none is configured
+///
+/// # Returns
+/// * DEFAULT_PAIRING_WINDOW_MINUTES
+fn default_pairing_window_minutes() -> u64 {
+    DEFAULT_PAIRING_WINDOW_MINUTES
+}
+
+/// A recognised workbook in the watched folder that was not imported yet
+/// * path: The path of the workbook
+/// * kind: Whether it is a CL View or a Shipper Site
+/// * modified: The modification time of the file
+/// * load_numbers: The Load # of all rows
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WatchedWorkbook {
+    pub path: PathBuf,
+    pub kind: WorkbookKind,
+    pub modified: SystemTime,
+    pub load_numbers: HashSet<String>,
+}
+
+impl WatchedWorkbook {
+    /// Read a workbook of the watched folder.
+    /// Only the columns needed to recognise and pair it are converted.
+    ///
+    /// # Arguments
+    /// * `path` - The path of the workbook
+    /// * `options` - The import options, they may configure other headers for the columns
+    ///
+    /// # Returns
+    /// * Result containing the workbook, None if it is not a TMS export, or an error
+    pub fn read(path: &Path, options: &ImportOptions) -> Result<Option<Self>, ParseFilesError> {
+        let projection = options.column_headers_of(&WATCHED_COLUMNS);
+        let df = parse_xls_file_tms_with_task(&path.to_string_lossy(), Some(&projection), &ParseTask::silent())?;
+        let kind = match WorkbookKind::classify(&df.get_column_names(), options) {
+            Some(kind) => kind,
+            None => return Ok(None),
+        };
+        let load_numbers = extract_column_as_string(&df, options.column_header(JOB_NUMBER_COLUMN_NAME))?
+            .into_iter()
+            .map(|load_number| load_number.trim().to_string())
+            .filter(|load_number| !load_number.is_empty())
+            .collect();
+        let modified = std::fs::metadata(path)
+            .and_then(|metadata| metadata.modified())
+            .unwrap_or_else(|_| SystemTime::now());
+
+        Ok(Some(Self {
+            path: path.to_path_buf(),
+            kind,
+            modified,
+            load_numbers,
+        }))
+    }
+
+    /// Get how far apart the modification times of two workbooks are
+    ///
+    /// # Arguments
+    /// * `other` - The other workbook
+    ///
+    /// # Returns
+    /// * The time between both modifications
+    fn gap(&self, other: &WatchedWorkbook) -> Duration {
+        match self.modified.duration_since(other.modified) {
+            Ok(gap) => gap,
+            Err(error) => error.duration(),
+        }
+    }
+}
+
+/// Check whether a file is a workbook the watcher should read.
+/// TMS exports .xls, but a workbook saved again by Excel is .xlsx or .xlsm.
+/// The lock files Excel creates next to open workbooks are skipped.
+///
+/// # Arguments
+/// * `path` - The path of the file
+///
+/// # Returns
+/// * True for .xls, .xlsx and .xlsm files
+pub fn is_workbook(path: &Path) -> bool {
+    let extension = path.extension().map(|extension| extension.to_string_lossy().to_lowercase());
+    let lock_file = path.file_name().is_none_or(|name| name.to_string_lossy().starts_with("~$"));
+    matches!(extension.as_deref(), Some("xls" | "xlsx" | "xlsm")) && !lock_file
+}
+
+/// Pair the CL Views and Shipper Sites of the watched folder.
+/// The pair sharing the most Load # is taken first, a smaller gap between the modification times breaks ties.
+/// Workbooks without any shared Load # or further apart than the window are never paired.
+/// Paired workbooks are removed from the pending workbooks.
+///
+/// # Arguments
+/// * `pending` - The workbooks that were not imported yet
+/// * `window` - How far apart the modification times of a pair may be
+///
+/// # Returns
+/// * The pairs as (CL View, Shipper Site)
+pub fn pair_workbooks(pending: &mut Vec<WatchedWorkbook>, window: Duration) -> Vec<(WatchedWorkbook, WatchedWorkbook)> {
+    let mut pairs = Vec::new();
+
+    loop {
+        let mut best: Option<(usize, usize, usize, Duration)> = None;
+        for (cl_view_index, cl_view) in pending.iter().enumerate() {
+            if cl_view.kind != WorkbookKind::ClView {
+                continue;
+            }
+            for (shipper_site_index, shipper_site) in pending.iter().enumerate() {
+                if shipper_site.kind != WorkbookKind::ShipperSite {
+                    continue;
+                }
+                let overlap = cl_view.load_numbers.intersection(&shipper_site.load_numbers).count();
+                let gap = cl_view.gap(shipper_site);
+                if overlap == 0 || gap > window {
+                    continue;
+                }
+                let better = match best {
+                    Some((_, _, best_overlap, best_gap)) => overlap > best_overlap || (overlap == best_overlap && gap < best_gap),
+                    None => true,
+                };
+                if better {
+                    best = Some((cl_view_index, shipper_site_index, overlap, gap));
+                }
+            }
+        }
+
+        match best {
+            Some((cl_view_index, shipper_site_index, _, _)) => {
+                // Remove the higher index first, so the lower one stays valid
+                let (first, second) = (cl_view_index.max(shipper_site_index), cl_view_index.min(shipper_site_index));
+                let first = pending.remove(first);
+                let second = pending.remove(second);
+                match first.kind {
+                    WorkbookKind::ClView => pairs.push((first, second)),
+                    WorkbookKind::ShipperSite => pairs.push((second, first)),
+                }
+            }
+            None => break,
+        }
+    }
+
+    pairs
+}
+
+/// The jobs imported from a pair of workbooks of the watched folder
+/// * mode: The mode of the jobs
+/// * cl_view: The path of the CL View
+/// * shipper_site: The path of the Shipper Site
+/// * jobs: The imported jobs, each with its diagnostics
+/// * highest_severity: The highest severity of all diagnostics, None if there are none
+#[derive(serde::Serialize, ts_rs::TS, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct ImportBatch {
+    pub mode: DispoMode,
+    pub cl_view: String,
+    pub shipper_site: String,
+    pub jobs: Vec<JobRow>,
+    pub highest_severity: Option<Severity>,
+}
+
+/// A pair of workbooks of the watched folder that could not be imported
+/// * cl_view: The path of the CL View
+/// * shipper_site: The path of the Shipper Site
+/// * error: Why the import failed
+#[derive(serde::Serialize, ts_rs::TS, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct ImportFailure {
+    pub cl_view: String,
+    pub shipper_site: String,
+    pub error: String,
+}
+
+/// Import a pair of workbooks and emit the result to all windows
+///
+/// # Arguments
+/// * `app` - The app to emit the events with
+/// * `cl_view` - The CL View
+/// * `shipper_site` - The Shipper Site
+/// * `options` - The options of the watcher
+fn import_pair(app: &AppHandle, cl_view: &WatchedWorkbook, shipper_site: &WatchedWorkbook, options: &ImportWatchOptions) {
+    let cl_view = cl_view.path.to_string_lossy().to_string();
+    let shipper_site = shipper_site.path.to_string_lossy().to_string();
+
+    // Nobody is listening if emitting fails, so there is nothing left to report to
+    let _ = match create_job_rows_with_files(&cl_view, &shipper_site, options.mode, &options.options, &ParseTask::silent()) {
+        Ok((jobs, files)) => {
+            audit::record(
+                AuditAction::Import {
+                    mode: options.mode,
+                    files,
+                    watched: true,
+                },
+                jobs.iter().map(JobRow::id).collect(),
+                Vec::new(),
+            );
+            let highest_severity = jobs
+                .iter()
+                .flat_map(|job| job.diagnostics.iter().map(|diagnostic| diagnostic.severity))
+                .max();
+            app.emit_all(
+                IMPORT_BATCH_EVENT,
+                ImportBatch {
+                    mode: options.mode,
+                    cl_view,
+                    shipper_site,
+                    jobs,
+                    highest_severity,
+                },
+            )
+        }
+        Err(error) => app.emit_all(
+            IMPORT_FAILED_EVENT,
+            ImportFailure {
+                cl_view,
+                shipper_site,
+                error: error.to_string(),
+            },
+        ),
+    };
+}
+
+/// This error includes all errors that can occur while starting the folder watcher
+///
+/// # Variants
+/// * `Notify` - The folder could not be watched
+#[derive(Debug)]
+pub enum ImportWatcherError {
+    Notify(notify::Error),
+}
+
+impl From<notify::Error> for ImportWatcherError {
+    fn from(error: notify::Error) -> Self {
+        ImportWatcherError::Notify(error)
+    }
+}
+
+impl fmt::Display for ImportWatcherError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ImportWatcherError::Notify(error) => write!(f, "NotifyError: {}", error),
+        }
+    }
+}
+
+impl std::error::Error for ImportWatcherError {}
+
+impl From<ImportWatcherError> for InvokeError {
+    fn from(error: ImportWatcherError) -> Self {
+        InvokeError::from(error.to_string())
+    }
+}
+
+/// A running folder watcher.
+/// The folder is watched until the watcher is dropped.
+/// * directory: The watched folder
+/// * watcher: The watcher of the file system
+pub struct ImportWatcher {
+    pub directory: String,
+    // Only kept alive, dropping it stops the watcher
+    #[allow(dead_code)]
+    watcher: notify::RecommendedWatcher,
+}
+
+/// The folder watcher of the app, managed by Tauri
+#[derive(Default)]
+pub struct ImportWatcherState(pub Mutex<Option<ImportWatcher>>);
+
+/// Start watching a folder for new TMS exports.
+/// New workbooks are recognised as CL View or Shipper Site by their headers and paired by their Load #.
+/// Every pair is imported and emitted as IMPORT_BATCH_EVENT, or as IMPORT_FAILED_EVENT if the import failed.
+/// Workbooks already in the folder are not imported.
+///
+/// # Arguments
+/// * `app` - The app to emit the events with
+/// * `directory` - The folder to watch
+/// * `options` - The options of the watcher
+///
+/// # Returns
+/// * Result containing the watcher or an error
+pub fn watch(app: AppHandle, directory: &str, options: ImportWatchOptions) -> Result<ImportWatcher, ImportWatcherError> {
+    let (sender, receiver) = mpsc::channel();
+    let mut watcher = notify::recommended_watcher(sender)?;
+    watcher.watch(Path::new(directory), RecursiveMode::NonRecursive)?;
+
+    std::thread::spawn(move || {
+        // A window too large to represent pairs workbooks regardless of their modification times
+        let window = Duration::from_secs(options.pairing_window_minutes.saturating_mul(60));
+        let mut changed: HashSet<PathBuf> = HashSet::new();
+        let mut pending: Vec<WatchedWorkbook> = Vec::new();
+
+        loop {
+            match receiver.recv_timeout(SETTLE_DELAY) {
+                Ok(Ok(event)) => {
+                    let paths = event.paths.into_iter().filter(|path| is_workbook(path));
+                    match event.kind {
+                        EventKind::Create(_) | EventKind::Modify(_) => changed.extend(paths),
+                        EventKind::Remove(_) => {
+                            for path in paths {
+                                changed.remove(&path);
+                                pending.retain(|workbook| workbook.path != path);
+                            }
+                        }
+                        _ => {}
+                    }
+                }
+                // A single failed event does not stop the watcher
+                Ok(Err(_)) => {}
+                Err(RecvTimeoutError::Timeout) => {
+                    if changed.is_empty() {
+                        continue;
+                    }
+                    for path in changed.drain() {
+                        pending.retain(|workbook| workbook.path != path);
+                        // Unreadable files are skipped, they are read again with their next change
+                        if let Ok(Some(workbook)) = WatchedWorkbook::read(&path, &options.options) {
+                            pending.push(workbook);
+                        }
+                    }
+                    for (cl_view, shipper_site) in pair_workbooks(&mut pending, window) {
+                        import_pair(&app, &cl_view, &shipper_site, &options);
+                    }
+                }
+                // The watcher was dropped
+                Err(RecvTimeoutError::Disconnected) => break,
+            }
+        }
+    });
+
+    Ok(ImportWatcher {
+        directory: directory.to_string(),
+        watcher,
+    })
+}
@@ -0,0 +1,9 @@
+// Copyright 2023 Felix Kahle. All rights reserved.
+
+//! The Tauri commands of the Dispo Master app and the modules they use together with dispo-core.
+//! main.rs only sets up the app and registers the commands.
+
+pub mod audit;
+pub mod bindings;
+pub mod commands;
+pub mod import_watcher;
@@ -3,70 +3,42 @@
 // Prevents additional console window on Windows in release, DO NOT REMOVE!!
 #![cfg_attr(not(debug_assertions), windows_subsystem = "windows")]
 
-mod file_parsing;
-mod job_row;
-mod parse_error;
-
-use file_parsing::create_job_rows;
-use job_row::{DispoMode, JobRow};
-use parse_error::ParseFilesError;
-use tauri::{Manager, Window};
-
-/// Returns a list of all printers available on the system
-/// as a vector of strings
-///
-/// # Returns
-/// The list of printers as a vector of strings
-#[tauri::command]
-fn get_printer_names() -> Vec<String> {
-    printers::get_printers().iter().map(|printer| printer.name.clone()).collect()
-}
-
-#[tauri::command]
-fn parse_files(cl_view: String, shipper_site: String, mode: String) -> Result<Vec<JobRow>, ParseFilesError> {
-    let mode: DispoMode = DispoMode::from_str(&mode)?;
-    let rows = create_job_rows(&cl_view, &shipper_site, mode)?;
-    Ok(rows)
-}
-
-/// Shows the splashscreen window
-///
-/// # Arguments
-/// * `window` - The window manager
-#[tauri::command]
-async fn show_splashscreen(window: Window) {
-    window
-        .get_window("splashscreen")
-        .expect("no window labeled 'splashscreen' found")
-        .show()
-        .unwrap();
-}
-
-/// Closes the splashscreen window and shows the main window
-///
-/// # Arguments
-/// * `window` - The window manager
-#[tauri::command]
-async fn close_splashscreen(window: Window) {
-    // Close splashscreen
-    window
-        .get_window("splashscreen")
-        .expect("no window labeled 'splashscreen' found")
-        .close()
-        .unwrap();
-    // Show main window
-    window.get_window("main").expect("no window labeled 'main' found").show().unwrap();
-}
+use dispo_core::{
+    audit_log::{self, AuditLogError},
+    parse_task::ParseTasks,
+    workbook_cache,
+};
+use dispo_master::import_watcher::ImportWatcherState;
 
 // The main function that runs the application
 fn main() {
+    // `tauri dev` runs debug builds, so the bindings of the frontend follow every change of the commands
+    #[cfg(debug_assertions)]
+    if let Err(error) = dispo_master::bindings::export(&dispo_master::bindings::bindings_path()) {
+        eprintln!("Could not write the bindings: {}", error);
+    }
+
     tauri::Builder::default()
-        .invoke_handler(tauri::generate_handler![
-            get_printer_names,
-            parse_files,
-            show_splashscreen,
-            close_splashscreen
-        ])
+        .setup(|app| {
+            // Without a cache directory every import parses the workbooks from scratch
+            if let Some(directory) = app.path_resolver().app_cache_dir() {
+                workbook_cache::configure(directory.join("workbooks"), workbook_cache::DEFAULT_CACHE_LIMIT_BYTES);
+            }
+            // Without a data directory nothing is recorded, the app still works
+            if let Some(directory) = app.path_resolver().app_data_dir() {
+                let configured = std::fs::create_dir_all(&directory)
+                    .map_err(AuditLogError::from)
+                    .and_then(|_| audit_log::configure(directory.join(audit_log::AUDIT_LOG_FILE_NAME), &audit_log::current_user()));
+                if let Err(error) = configured {
+                    eprintln!("Could not open the audit log: {}", error);
+                }
+            }
+            Ok(())
+        })
+        .manage(ImportWatcherState::default())
+        .manage(ParseTasks::default())
+        // The same commands the bindings of the frontend are generated for
+        .invoke_handler(dispo_master::commands::invoke_handler())
         .run(tauri::generate_context!())
         .expect("error while running tauri application");
 }
@@ -0,0 +1,31 @@
+// Copyright 2023 Felix Kahle. All rights reserved.
+
+//! The TypeScript bindings of the frontend
+
+use dispo_master::{
+    bindings::{bindings, bindings_path, BINDINGS_PATH},
+    commands::COMMAND_NAMES,
+};
+
+#[test]
+fn checked_in_bindings_are_up_to_date() {
+    let content = std::fs::read_to_string(bindings_path()).expect("the bindings can be read");
+    assert!(
+        content == bindings().render(),
+        "{} is stale, start a debug build of the app, e.g. with `npm run tauri dev`, to write it",
+        BINDINGS_PATH
+    );
+}
+
+#[test]
+fn every_command_has_one_client_function() {
+    let content = bindings().render();
+    for name in COMMAND_NAMES {
+        assert_eq!(
+            content.matches(&format!("invoke(\"{}\"", name)).count(),
+            1,
+            "{} needs exactly one client function",
+            name
+        );
+    }
+}
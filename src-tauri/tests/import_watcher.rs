@@ -0,0 +1,222 @@
+// Copyright 2023 Felix Kahle. All rights reserved.
+
+//! Recognising and pairing the workbooks of the watched folder
+
+use dispo_core::file_parsing::{
+    ImportOptions, EQUIPMENT_CODES_COLUMN_NAME, HAWB_COLUMN_NAME, JOB_NUMBER_COLUMN_NAME, TEMPERATURE_RANGE_COLUMN_NAME,
+};
+use dispo_master::import_watcher::{is_workbook, pair_workbooks, WatchedWorkbook, WorkbookKind};
+use rust_xlsxwriter::Workbook;
+use std::{
+    collections::HashSet,
+    path::{Path, PathBuf},
+    time::{Duration, SystemTime},
+};
+
+/// The header the Load # is renamed to in the tests
+const RENAMED_JOB_NUMBER: &str = "Load Number";
+
+/// Import options that map the Load # to RENAMED_JOB_NUMBER
+///
+/// # Returns
+/// * The import options
+fn renamed_options() -> ImportOptions {
+    let mut options = ImportOptions::default();
+    options
+        .column_headers
+        .insert(JOB_NUMBER_COLUMN_NAME.to_string(), RENAMED_JOB_NUMBER.to_string());
+    options
+}
+
+/// Write a workbook with a header row and one row per Load #
+///
+/// # Arguments
+/// * `name` - The file name of the workbook in the temporary directory
+/// * `headers` - The headers, the first column gets the Load #
+/// * `load_numbers` - The Load # of the rows
+///
+/// # Returns
+/// * The path of the workbook
+fn write_workbook(name: &str, headers: &[&str], load_numbers: &[&str]) -> PathBuf {
+    let path = std::env::temp_dir().join(format!("dispo-master-{}-{}", std::process::id(), name));
+    let mut workbook = Workbook::new();
+    let worksheet = workbook.add_worksheet();
+    for (column, header) in headers.iter().enumerate() {
+        worksheet
+            .write_string(0, column as u16, *header)
+            .expect("the header can be written");
+    }
+    for (row, load_number) in load_numbers.iter().enumerate() {
+        for column in 0..headers.len() {
+            let value = if column == 0 { *load_number } else { "" };
+            worksheet
+                .write_string(row as u32 + 1, column as u16, value)
+                .expect("the cell can be written");
+        }
+    }
+    workbook.save(&path).expect("the workbook can be saved");
+    path
+}
+
+/// A workbook of the watched folder
+///
+/// # Arguments
+/// * `name` - The file name of the workbook
+/// * `kind` - Whether it is a CL View or a Shipper Site
+/// * `minutes` - The minutes after the start of the test it was modified
+/// * `load_numbers` - The Load # of its rows
+///
+/// # Returns
+/// * The workbook
+fn workbook(name: &str, kind: WorkbookKind, minutes: u64, load_numbers: &[&str]) -> WatchedWorkbook {
+    WatchedWorkbook {
+        path: PathBuf::from(name),
+        kind,
+        modified: SystemTime::UNIX_EPOCH + Duration::from_secs(minutes * 60),
+        load_numbers: load_numbers.iter().map(|load_number| load_number.to_string()).collect(),
+    }
+}
+
+/// Get the file names of pairs of workbooks
+///
+/// # Arguments
+/// * `pairs` - The pairs as (CL View, Shipper Site)
+///
+/// # Returns
+/// * The file names of each pair
+fn names(pairs: &[(WatchedWorkbook, WatchedWorkbook)]) -> Vec<(String, String)> {
+    pairs
+        .iter()
+        .map(|(cl_view, shipper_site)| (cl_view.path.display().to_string(), shipper_site.path.display().to_string()))
+        .collect()
+}
+
+#[test]
+fn exports_are_recognised_by_their_headers() {
+    let options = ImportOptions::default();
+    let cl_view = [JOB_NUMBER_COLUMN_NAME, EQUIPMENT_CODES_COLUMN_NAME];
+    let shipper_site = [JOB_NUMBER_COLUMN_NAME, HAWB_COLUMN_NAME, TEMPERATURE_RANGE_COLUMN_NAME];
+    assert_eq!(WorkbookKind::classify(&cl_view, &options), Some(WorkbookKind::ClView));
+    assert_eq!(WorkbookKind::classify(&shipper_site, &options), Some(WorkbookKind::ShipperSite));
+
+    // Without a Load # or with the columns of both exports the workbook is neither
+    assert_eq!(WorkbookKind::classify(&[EQUIPMENT_CODES_COLUMN_NAME], &options), None);
+    let both = [
+        JOB_NUMBER_COLUMN_NAME,
+        EQUIPMENT_CODES_COLUMN_NAME,
+        HAWB_COLUMN_NAME,
+        TEMPERATURE_RANGE_COLUMN_NAME,
+    ];
+    assert_eq!(WorkbookKind::classify(&both, &options), None);
+    // The hawb alone does not make a Shipper Site
+    assert_eq!(WorkbookKind::classify(&[JOB_NUMBER_COLUMN_NAME, HAWB_COLUMN_NAME], &options), None);
+}
+
+#[test]
+fn renamed_headers_are_recognised_through_the_options() {
+    let options = renamed_options();
+    let cl_view = [RENAMED_JOB_NUMBER, EQUIPMENT_CODES_COLUMN_NAME];
+    let shipper_site = [RENAMED_JOB_NUMBER, HAWB_COLUMN_NAME, TEMPERATURE_RANGE_COLUMN_NAME];
+    assert_eq!(WorkbookKind::classify(&cl_view, &options), Some(WorkbookKind::ClView));
+    assert_eq!(WorkbookKind::classify(&shipper_site, &options), Some(WorkbookKind::ShipperSite));
+
+    // The default header is not the Load # anymore, and without the options the renamed one is not either
+    let default_cl_view = [JOB_NUMBER_COLUMN_NAME, EQUIPMENT_CODES_COLUMN_NAME];
+    assert_eq!(WorkbookKind::classify(&default_cl_view, &options), None);
+    assert_eq!(WorkbookKind::classify(&cl_view, &ImportOptions::default()), None);
+}
+
+#[test]
+fn renamed_load_numbers_are_read_through_the_options() {
+    let path = write_workbook(
+        "renamed_cl_view.xlsx",
+        &[RENAMED_JOB_NUMBER, EQUIPMENT_CODES_COLUMN_NAME, "Consignee Name"],
+        &["L1001", "L1002"],
+    );
+
+    let workbook = WatchedWorkbook::read(&path, &renamed_options())
+        .expect("the workbook can be read")
+        .expect("the workbook is a CL View");
+    assert_eq!(workbook.kind, WorkbookKind::ClView);
+    assert_eq!(workbook.load_numbers, HashSet::from(["L1001".to_string(), "L1002".to_string()]));
+
+    let unmapped = WatchedWorkbook::read(&path, &ImportOptions::default()).expect("the workbook can be read");
+    assert_eq!(unmapped, None);
+    let _ = std::fs::remove_file(&path);
+}
+
+#[test]
+fn workbooks_saved_by_excel_are_watched() {
+    assert!(is_workbook(Path::new("exports/cl_view.xls")));
+    assert!(is_workbook(Path::new("exports/cl_view.XLSX")));
+    assert!(is_workbook(Path::new("exports/shipper_site.xlsm")));
+    assert!(!is_workbook(Path::new("exports/shipper_site.csv")));
+    assert!(!is_workbook(Path::new("exports/~$cl_view.xlsx")));
+}
+
+#[test]
+fn largest_overlap_is_paired_first() {
+    let mut pending = vec![
+        workbook("cl_view.xls", WorkbookKind::ClView, 0, &["L1", "L2", "L3"]),
+        workbook("shipper_site_a.xls", WorkbookKind::ShipperSite, 1, &["L1"]),
+        workbook("shipper_site_b.xls", WorkbookKind::ShipperSite, 30, &["L1", "L2", "L3"]),
+    ];
+
+    let pairs = pair_workbooks(&mut pending, Duration::from_secs(120 * 60));
+    // The closer Shipper Site shares fewer loads, so it is left for a later CL View
+    assert_eq!(names(&pairs), [("cl_view.xls".to_string(), "shipper_site_b.xls".to_string())]);
+    assert_eq!(pending.len(), 1);
+    assert_eq!(pending[0].path, PathBuf::from("shipper_site_a.xls"));
+}
+
+#[test]
+fn smaller_gap_breaks_ties() {
+    let mut pending = vec![
+        workbook("shipper_site_old.xls", WorkbookKind::ShipperSite, 0, &["L1", "L2"]),
+        workbook("cl_view.xls", WorkbookKind::ClView, 60, &["L1", "L2"]),
+        workbook("shipper_site_new.xls", WorkbookKind::ShipperSite, 50, &["L1", "L2"]),
+    ];
+
+    let pairs = pair_workbooks(&mut pending, Duration::from_secs(120 * 60));
+    assert_eq!(names(&pairs), [("cl_view.xls".to_string(), "shipper_site_new.xls".to_string())]);
+    assert_eq!(pending[0].path, PathBuf::from("shipper_site_old.xls"));
+}
+
+#[test]
+fn workbooks_outside_the_window_are_not_paired() {
+    let window = Duration::from_secs(120 * 60);
+    let mut pending = vec![
+        workbook("cl_view.xls", WorkbookKind::ClView, 0, &["L1"]),
+        workbook("shipper_site.xls", WorkbookKind::ShipperSite, 121, &["L1"]),
+        workbook("unrelated.xls", WorkbookKind::ShipperSite, 5, &["L9"]),
+    ];
+
+    assert!(pair_workbooks(&mut pending, window).is_empty());
+    assert_eq!(pending.len(), 3);
+
+    // Exactly at the window the workbooks still belong together
+    pending[1].modified = SystemTime::UNIX_EPOCH + window;
+    let pairs = pair_workbooks(&mut pending, window);
+    assert_eq!(names(&pairs), [("cl_view.xls".to_string(), "shipper_site.xls".to_string())]);
+    assert_eq!(pending.len(), 1);
+}
+
+#[test]
+fn every_workbook_is_paired_at_most_once() {
+    let mut pending = vec![
+        workbook("cl_view_a.xls", WorkbookKind::ClView, 0, &["L1", "L2"]),
+        workbook("cl_view_b.xls", WorkbookKind::ClView, 10, &["L3"]),
+        workbook("shipper_site_b.xls", WorkbookKind::ShipperSite, 11, &["L3"]),
+        workbook("shipper_site_a.xls", WorkbookKind::ShipperSite, 1, &["L1", "L2", "L3"]),
+    ];
+
+    let pairs = pair_workbooks(&mut pending, Duration::from_secs(120 * 60));
+    assert_eq!(
+        names(&pairs),
+        [
+            ("cl_view_a.xls".to_string(), "shipper_site_a.xls".to_string()),
+            ("cl_view_b.xls".to_string(), "shipper_site_b.xls".to_string())
+        ]
+    );
+    assert!(pending.is_empty());
+}
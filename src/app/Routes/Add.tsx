@@ -28,8 +28,11 @@ const ModeSelector: React.FC<ModeSelectorProps> = ({ mode, onModeChange }) => {
 
   return (
     <Select value={mode} onChange={handleChange} placeholder="Select a mode" sx={{ width: "200px" }}>
-      <Option value={DispoMode.PICKUP}>Pickup</Option>
-      <Option value={DispoMode.DELIVERY}>Delivery</Option>
+      <Option value={"Pickup"}>Pickup</Option>
+      <Option value={"Delivery"}>Delivery</Option>
+      <Option value={"Transfer"}>Transfer</Option>
+      <Option value={"Recovery"}>Recovery</Option>
+      <Option value={"Return"}>Return</Option>
     </Select>
   );
 };
@@ -105,7 +108,7 @@ export default function Add() {
     // This happens on a different thread in the backend.
     // When finished, the data is returned and added to the redux store.
     // If an error occurs, an error message is shown.
-    parseFiles(clViewFile, shipperSiteFile, mode)
+    parseFiles(crypto.randomUUID(), clViewFile, shipperSiteFile, mode, null)
       .then((rows) => {
         const jobDataArray = rows.map((r) => {
           return fromImportedJobRow(r);
@@ -1,30 +1,12 @@
 // Copyright 2023 Felix Kahle. All rights reserved.
 
-import { DispoMode, ImportedJobRow, TemperatureRange } from "../../tauri-api/types";
+import { ImportedJobRow, PlannedJob } from "../../tauri-api/types";
 import { PayloadAction, createSlice } from "@reduxjs/toolkit";
 
 /**
- * The data of a job row.
+ * The data of a job row, the imported job with the assignments of the dispatcher.
  */
-export interface JobRow {
-  mode: DispoMode;
-  address: string;
-  calculatedDate: string;
-  city: string;
-  contactName: string;
-  country: string;
-  earlyDate: string;
-  equipment: string;
-  hawbNumber: string;
-  jobNumber: string;
-  lateDate: string;
-  postalCode: string;
-  quantities: number;
-  temperatureRanges: TemperatureRange[];
-  tolerance: number;
-  driver: string;
-  vehicle: string;
-}
+export type JobRow = PlannedJob;
 
 /**
  * Creates a job data row from an imported job row.
@@ -34,23 +16,10 @@ export interface JobRow {
  */
 export function fromImportedJobRow(row: ImportedJobRow): JobRow {
   return {
-    mode: row.mode,
-    address: row.address,
-    calculatedDate: row.calculatedDate,
-    city: row.city,
-    contactName: row.contactName,
-    country: row.country,
-    earlyDate: row.earlyDate,
-    equipment: row.equipment,
-    hawbNumber: row.hawbNumber,
-    jobNumber: row.jobNumber,
-    lateDate: row.lateDate,
-    postalCode: row.postalCode,
-    quantities: row.quantities,
-    temperatureRanges: row.temperatureRanges,
-    tolerance: row.tolerance,
+    ...row,
     driver: "",
     vehicle: "",
+    notes: "",
   };
 }
 
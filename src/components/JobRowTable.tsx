@@ -3,7 +3,6 @@
 import { Sheet, Table, Typography } from "@mui/joy";
 import { useSelector } from "react-redux";
 import { RootState } from "../app/redux/store";
-import { DispoMode } from "../tauri-api/types";
 
 export default function JobRowTable() {
   const rows = useSelector((state: RootState) => state.jobData);
@@ -50,7 +49,7 @@ export default function JobRowTable() {
         <tbody>
           {rows
             .filter((row) => {
-              return row.mode === DispoMode.PICKUP;
+              return row.mode === "Pickup";
             })
             .map((row) => (
               <tr key={row.jobNumber}>
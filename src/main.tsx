@@ -3,12 +3,12 @@
 import React from "react";
 import ReactDOM from "react-dom/client";
 import App from "./app/App";
-import { closeSplashScreen } from "./tauri-api/dispoMasterTauriApi";
+import { closeSplashscreen } from "./tauri-api/dispoMasterTauriApi";
 
 // Close the splash screen when the DOM is loaded.
 document.addEventListener("DOMContentLoaded", async () => {
   await setTimeout(() => {
-    closeSplashScreen();
+    closeSplashscreen();
   }, 5000);
 });
 
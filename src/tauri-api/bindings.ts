@@ -0,0 +1,1016 @@
+// This file is generated from the Rust types and commands of the app, do not edit it.
+// Debug builds of the app write it when they start, `cargo test --test bindings` checks it, see src-tauri/src/bindings.rs.
+// Failed commands reject with the message of their error.
+
+import { invoke } from "@tauri-apps/api/tauri";
+
+/**
+ * The AuditAction enum represents what a dispatcher did
+ * * Import: Jobs were imported from the exports of TMS, watched is true for imports of the watched folder
+ * * JobCreated: A job was created manually
+ * * JobEdited: A job was edited manually
+ * * JobDeleted: A job was deleted
+ * * Assignment: Drivers, vehicles, planned times or notes were changed in the app or by a change set
+ * * StatusChanged: The status of a job was changed
+ * * Export: Jobs were written to a file, kind is the name of the export like "planFile"
+ * * Print: Jobs were printed
+ */
+export type AuditAction = { "type": "import", mode: DispoMode, files: Array<ImportedFile>, watched: boolean, } | { "type": "jobCreated" } | { "type": "jobEdited" } | { "type": "jobDeleted" } | { "type": "assignment" } | { "type": "statusChanged", from: JobStatus, to: JobStatus, reason: StatusReason | null, note: string, } | { "type": "export", kind: string, path: string, } | { "type": "print", printer: string, };
+
+/**
+ * The entries of the audit log that matched a query
+ * * entries: The matching entries, oldest first
+ * * invalid_lines: The lines that are not entries, they are skipped
+ */
+export type AuditEntries = { entries: Array<AuditEntry>, invalidLines: Array<InvalidLine>, };
+
+/**
+ * One line of the audit log
+ * * sequence: The number of the entry, starting at 1
+ * * at: When the action happened, in UTC
+ * * user: The user of the operating system the app runs as
+ * * action: What was done
+ * * job_ids: The ids of the jobs the action was about
+ * * changes: The fields the action changed
+ */
+export type AuditEntry = { sequence: number, at: string, user: string, action: AuditAction, jobIds: Array<string>, changes: Array<FieldChange>, };
+
+/**
+ * Which entries of the audit log are read, every filter that is None matches all entries
+ * * job_id: Only entries about this job
+ * * from: Only entries at or after this time, in UTC
+ * * to: Only entries before this time, in UTC
+ */
+export type AuditQuery = { jobId: string | null, from: string | null, to: string | null, };
+
+/**
+ * Decides what happens with a difference that is larger than the largest bucket
+ * * LargestBucket: The difference is rounded down to the largest bucket
+ * * Exact: The exact difference is kept
+ */
+export type BucketOverflow = "LargestBucket" | "Exact";
+
+/**
+ * The business calendar used to check the target windows of jobs
+ * * holiday_sets: The known public holidays
+ * * bundled_holidays: Whether the bundled German public holidays apply, they are created for the year of each checked day
+ * * weekend: The weekdays that are not worked
+ * * opening_hours: The receiving hours of customers. Customers without an entry receive all day.
+ */
+export type BusinessCalendar = { holidaySets: Array<HolidaySet>, bundledHolidays: boolean, weekend: Array<string>, openingHours: Array<OpeningHours>, };
+
+/**
+ * The result of the calendar check of a job
+ * * issues: The issues that were found
+ * * suggested_date: The nearest date inside working time, if the calculated date is outside working time
+ */
+export type CalendarCheck = { issues: Array<CalendarIssue>, suggestedDate: string | null, };
+
+/**
+ * The CalendarGrouping enum decides for whom the calendars are created
+ * * Driver: One calendar per driver
+ * * Vehicle: One calendar per vehicle
+ */
+export type CalendarGrouping = "Driver" | "Vehicle";
+
+/**
+ * A finding of the calendar check of a job
+ * * WindowOutsideWorkingTime: The whole target window lies outside working time
+ * * CalculatedDateOutsideWorkingTime: The calculated date lies outside working time
+ */
+export type CalendarIssue = { "type": "windowOutsideWorkingTime", "reason": NonWorkingReason } | { "type": "calculatedDateOutsideWorkingTime", "reason": NonWorkingReason };
+
+/**
+ * A problem with the chain of legs of a shipment
+ * * DuplicateLeg: The shipment has more than one leg of the same mode
+ * * MissingCollection: The shipment is delivered more than once, but never collected
+ * * DeliveryBeforeCollection: The delivery window ends before the collection window starts
+ * * Unreachable: The delivery window can not be reached after the collection window plus transit time
+ */
+export type ChainIssue = { "type": "duplicateLeg", mode: DispoMode, jobNumbers: Array<string>, } | { "type": "missingCollection" } | { "type": "deliveryBeforeCollection", collection: string, delivery: string, } | { "type": "unreachable", collection: string, delivery: string, earliestArrival: string, };
+
+/**
+ * The changes found in an edited planning workbook, to be reviewed before they are applied
+ * * changes: The changes of jobs that were not changed since the export
+ * * conflicts: The changes of jobs that were changed since the export, they are not applied by default
+ * * unknown_jobs: The ids of jobs that are no longer known
+ * * invalid_rows: The rows that could not be read
+ */
+export type ChangeSet = { changes: Array<JobChange>, conflicts: Array<JobChange>, unknownJobs: Array<string>, invalidRows: Array<InvalidRow>, };
+
+/**
+ * The strategy to use for the jobs of one customer
+ * * customer: The customer, compared case insensitive
+ * * strategy: The strategy to use for this customer
+ */
+export type CustomerPlannedTime = { customer: string, strategy: PlannedTimeStrategy, };
+
+/**
+ * The data printed on the dangerous goods declaration of a dry ice shipment
+ * * un_number: Always UN1845
+ * * proper_shipping_name: The proper shipping name of dry ice
+ * * hazard_class: The hazard class of dry ice
+ * * packing_instruction: The packing instruction for dry ice
+ * * job_number: The job number of the shipment
+ * * hawb_number: The hawb number of the shipment
+ * * contact_name: The name of the shipper or consignee
+ * * address: The address of the stop
+ * * postal_code: The postal code of the stop
+ * * city: The city of the stop
+ * * country: The country of the stop
+ * * packaging: The packaging used
+ * * number_of_packages: The number of packages containing dry ice
+ * * net_kg_per_package: The net dry ice in kilograms per package
+ * * total_net_kg: The net dry ice in kilograms of the whole shipment
+ * * gross_weight_kg: The gross weight of the shipment, None if unknown
+ */
+export type DangerousGoodsDeclaration = { unNumber: string, properShippingName: string, hazardClass: string, packingInstruction: string, jobNumber: string, hawbNumber: string, contactName: string, address: string, postalCode: string, city: string, country: string, packaging: PackagingType, numberOfPackages: number, netKgPerPackage: number, totalNetKg: number, grossWeightKg: number | null, };
+
+/**
+ * A problem found while importing a job.
+ * Diagnostics never stop an import, they are attached to the job they belong to.
+ * * severity: How serious the problem is
+ * * kind: What the problem is
+ */
+export type Diagnostic = { severity: Severity, kind: DiagnosticKind, };
+
+/**
+ * The DiagnosticKind enum represents the different problems found while importing a job
+ * * UnknownEquipmentCode: An equipment code is not in the equipment catalogue
+ * * InvalidQuantity: A quantity, weight or volume could not be parsed or is not plausible
+ * * CutOffBeforeWindow: The airline cut-off can not be met inside the target window
+ * * MultipleHawbs: The load has more than one HAWB in the Shipper Site, they were merged into one job
+ * * ConflictingTemperatureRanges: The merged HAWBs of the load need different temperature ranges
+ * * DuplicateHawb: The same HAWB is listed more than once for the load
+ * * MissingShipperSiteRow: The load has no row in the Shipper Site
+ * * MissingFlightDetails: A Transfer or Recovery has no MAWB, airline or cut-off, fields names the missing details
+ * * MissingTargetDate: A date of the target window is empty or not a date, field names the column
+ * * InvertedWindow: The early date of the target window is after the late date
+ */
+export type DiagnosticKind = { "type": "unknownEquipmentCode", code: string, } | { "type": "invalidQuantity", field: string, value: string, reason: string, } | { "type": "cutOffBeforeWindow", cutOff: string, } | { "type": "multipleHawbs", hawbs: Array<string>, } | { "type": "conflictingTemperatureRanges", ranges: Array<TemperatureRange>, } | { "type": "duplicateHawb", hawb: string, } | { "type": "missingShipperSiteRow" } | { "type": "missingFlightDetails", fields: Array<string>, } | { "type": "missingTargetDate", field: string, } | { "type": "invertedWindow", earlyDate: string, lateDate: string, };
+
+/**
+ * The DispoMode enum represents the different modes of a dispo operation
+ * * Delivery: The dispo operation is a delivery
+ * * Pickup: The dispo operation is a pickup
+ * * Transfer: The dispo operation brings export freight from the station to the airport
+ * * Recovery: The dispo operation recovers import freight from the airline at the airport
+ * * Return: The dispo operation returns empty packaging to the shipper
+ */
+export type DispoMode = "Delivery" | "Pickup" | "Transfer" | "Recovery" | "Return";
+
+/**
+ * The configuration used to estimate dry ice requirements
+ * * profiles: The packaging profiles that are known
+ * * default_packaging: The packaging used if none is specified for a job
+ * * replenishment: The replenishment rule to apply
+ * * vehicle_limit_kg: The maximum net dry ice in kilograms allowed on one vehicle
+ * * rounding_step_kg: Every estimate is rounded up to a multiple of this value
+ */
+export type DryIceConfig = { profiles: Array<PackagingProfile>, defaultPackaging: PackagingType, replenishment: ReplenishmentRule, vehicleLimitKg: number, roundingStepKg: number, };
+
+/**
+ * The estimated dry ice requirement of a single shipment
+ * * job_number: The job number of the shipment
+ * * hawb_number: The hawb number of the shipment
+ * * packaging: The packaging used
+ * * pieces: The number of packages
+ * * transit_hours: The number of hours the shipment has to be kept frozen
+ * * replenishments: The number of times the packages are refilled during transit
+ * * initial_kg_per_piece: The dry ice in kilograms filled into each package before loading
+ * * net_kg: The net dry ice in kilograms loaded on the vehicle for this shipment
+ * * total_kg: The dry ice in kilograms needed over the whole transit, including replenishments
+ */
+export type DryIceEstimate = { jobNumber: string, hawbNumber: string, packaging: PackagingType, pieces: number, transitHours: number, replenishments: number, initialKgPerPiece: number, netKg: number, totalKg: number, };
+
+/**
+ * The dry ice of a dispatch plan
+ * * estimates: The estimates of all dry ice shipments that could be estimated
+ * * vehicles: The net dry ice per vehicle, shipments without a vehicle are not counted
+ * * declarations: The dangerous goods declarations of the estimated shipments, ready for printing
+ * * problems: The dry ice shipments that could not be estimated
+ */
+export type DryIcePlan = { estimates: Array<DryIceEstimate>, vehicles: Array<VehicleDryIceTotal>, declarations: Array<DangerousGoodsDeclaration>, problems: Array<string>, };
+
+/**
+ * A recognised piece of equipment of a job
+ * * code: The code as defined in the catalogue
+ * * kind: The kind of equipment
+ * * description: A human readable description
+ * * quantity: The number of units
+ * * capabilities: The capabilities a vehicle needs to carry the equipment
+ * * prep_minutes: The minutes needed to prepare all units
+ * * volume_liters: The volume of all units in liters
+ */
+export type Equipment = { code: string, kind: EquipmentKind, description: string, quantity: number, capabilities: Array<VehicleCapability>, prepMinutes: number, volumeLiters: number, };
+
+/**
+ * The catalogue of all known equipment codes
+ * * definitions: The known equipment
+ */
+export type EquipmentCatalogue = { definitions: Array<EquipmentDefinition>, };
+
+/**
+ * An entry of the equipment catalogue
+ * * code: The code as it appears in the Equipment Codes column, compared case insensitive
+ * * kind: The kind of equipment
+ * * description: A human readable description
+ * * capabilities: The capabilities a vehicle needs to carry the equipment
+ * * prep_minutes: The minutes needed to prepare one unit before the job
+ * * volume_liters: The volume of one unit in liters
+ */
+export type EquipmentDefinition = { code: string, kind: EquipmentKind, description: string, capabilities: Array<VehicleCapability>, prepMinutes: number, volumeLiters: number, };
+
+/**
+ * The EquipmentKind enum represents the different kinds of equipment
+ * * PackagingSystem: An insulated or active packaging system
+ * * DataLogger: A temperature data logger
+ * * DryShipper: A dry shipper for cryogenic shipments
+ * * TailLift: A tail-lift is needed to load or unload the shipment
+ * * Other: Any other equipment
+ */
+export type EquipmentKind = "PackagingSystem" | "DataLogger" | "DryShipper" | "TailLift" | "Other";
+
+/**
+ * A changed field of a job
+ * * job_id: The id of the job, see JobRow::id
+ * * field: The name of the field in camelCase
+ * * before: The value before the change, empty for new jobs
+ * * after: The value after the change, empty for deleted jobs
+ */
+export type FieldChange = { jobId: string, field: string, before: string, after: string, };
+
+/**
+ * A field of a manual job that did not pass the validation
+ * * field: The path of the field in camelCase, like "consignee.postalCode" or "hawbs.0.number"
+ * * message: Why the value is not valid
+ */
+export type FieldError = { field: string, message: string, };
+
+/**
+ * The air freight details of a Transfer or Recovery
+ * * mawb_number: The master air waybill number
+ * * airline: The airline
+ * * cut_off: The latest time the freight can be handed over to the airline
+ */
+export type FlightDetails = { mawbNumber: string, airline: string, cutOff: string | null, };
+
+/**
+ * A house air waybill of a job and the temperature ranges listed for it
+ * * number: The hawb number
+ * * temperature_ranges: The temperature ranges of the hawb
+ */
+export type Hawb = { number: string, temperatureRanges: Array<TemperatureRange>, };
+
+/**
+ * A single public holiday
+ * * date: The date of the holiday
+ * * name: The name of the holiday
+ */
+export type Holiday = { date: string, name: string, };
+
+/**
+ * The public holidays of a country or of a region inside a country
+ * * country: The country code, e.g. DE
+ * * aliases: Other spellings of the country as they appear in TMS, e.g. Germany
+ * * region: The region, e.g. HE. If None, the holidays apply to the whole country.
+ * * holidays: The holidays
+ */
+export type HolidaySet = { country: string, aliases: Array<string>, region: string | null, holidays: Array<Holiday>, };
+
+/**
+ * The jobs imported from a pair of workbooks of the watched folder
+ * * mode: The mode of the jobs
+ * * cl_view: The path of the CL View
+ * * shipper_site: The path of the Shipper Site
+ * * jobs: The imported jobs, each with its diagnostics
+ * * highest_severity: The highest severity of all diagnostics, None if there are none
+ */
+export type ImportBatch = { mode: DispoMode, clView: string, shipperSite: string, jobs: Array<JobRow>, highestSeverity: Severity | null, };
+
+/**
+ * A pair of workbooks of the watched folder that could not be imported
+ * * cl_view: The path of the CL View
+ * * shipper_site: The path of the Shipper Site
+ * * error: Why the import failed
+ */
+export type ImportFailure = { clView: string, shipperSite: string, error: string, };
+
+/**
+ * Options that influence how the rows of an import are turned into jobs.
+ * Every field has a default, so the frontend only has to send what it wants to change.
+ * * tolerance_policies: The tolerance policies used to calculate the tolerance of each job
+ * * planned_time: The strategies used to calculate the planned date of each job
+ * * calendar: The business calendar the jobs are checked against
+ * * equipment_catalogue: The catalogue used to recognise the codes of the Equipment Codes column
+ * * volumetric_kg_per_m3: The factor used to calculate the volumetric weight from the volume
+ * * column_headers: The headers of exports that differ from the TMS defaults, by the default header they replace
+ */
+export type ImportOptions = { tolerancePolicies: TolerancePolicySet, plannedTime: PlannedTimeSettings, calendar: BusinessCalendar, equipmentCatalogue: EquipmentCatalogue, volumetricKgPerM3: number, columnHeaders: { [key in string]?: string }, };
+
+/**
+ * The options of the folder watcher
+ * * mode: The mode of the jobs of all imports
+ * * options: The options used for every import
+ * * pairing_window_minutes: How far apart the modification times of a CL View and a Shipper Site may be to be paired
+ */
+export type ImportWatchOptions = { mode: DispoMode, options?: ImportOptions, pairingWindowMinutes?: number, };
+
+/**
+ * A file read by an import
+ * * path: The path of the file
+ * * hash: The FNV-1a hash of the content of the file as it was parsed
+ */
+export type ImportedFile = { path: string, hash: string, };
+
+/**
+ * A line of the audit log that is not an entry, e.g. the half entry of a crash
+ * * line: The line number, starting at 1
+ * * reason: Why the line could not be read
+ */
+export type InvalidLine = { line: number, reason: string, };
+
+/**
+ * A row of the edited workbook that could not be read
+ * * row: The row number as shown by Excel
+ * * reason: Why the row could not be read
+ */
+export type InvalidRow = { row: number, reason: string, };
+
+/**
+ * A change of one job, read from the edited planning workbook.
+ * Fields that were not changed are None.
+ * * job_id: The id of the job
+ * * job_number: The job number of the job
+ * * driver: The new driver
+ * * vehicle: The new vehicle
+ * * calculated_date: The new planned date
+ * * notes: The new notes
+ */
+export type JobChange = { jobId: string, jobNumber: string, driver: string | null, vehicle: string | null, calculatedDate: string | null, notes: string | null, };
+
+/**
+ * What a failed job edit sends to the frontend.
+ * Unlike other errors it is not only a message, the field errors let the form mark the invalid fields.
+ * * message: The message of the error
+ * * field_errors: The fields that did not pass the validation, empty if the error is not about a field
+ */
+export type JobEditRejection = { message: string, fieldErrors: Array<FieldError>, };
+
+/**
+ * The JobOrigin enum represents where a job comes from
+ * * Imported: The job was imported from the exports of TMS
+ * * Manual: The job was created by a dispatcher, e.g. for an order taken by phone
+ */
+export type JobOrigin = "Imported" | "Manual";
+
+/**
+ * The JobRow struct represents a single row of a dispo operation
+ * * mode: The mode of the dispo operation
+ * * job_number: The job number of the dispo operation
+ * * hawb_number: The hawb numbers of the dispo operation, separated by a comma
+ * * hawbs: The hawbs of the dispo operation with their temperature ranges
+ * * temperature_range: The temperature ranges of all hawbs of the dispo operation
+ * * quantity: The pieces, weight and volume of the dispo operation
+ * * shipper: The party the freight comes from
+ * * consignee: The party the freight goes to
+ * * stop: The party that is visited, decided by the mode
+ * * address: The address of the stop
+ * * postal_code: The postal code of the stop
+ * * city: The city of the stop
+ * * country: The country of the stop
+ * * equipment: The equipment of the dispo operation, as found in the Equipment Codes column
+ * * equipment_items: The recognised equipment of the dispo operation
+ * * tolerance: The tolerance of the dispo operation
+ * * tolerance_before: The tolerance before the calculated date
+ * * tolerance_after: The tolerance after the calculated date
+ * * tolerance_policy: The name of the tolerance policy that produced the tolerance
+ * * early_date: The early date of the dispo operation
+ * * late_date: The late date of the dispo operation
+ * * calculated_date: The calculated date of the dispo operation.
+ * * planned_time_strategy: The strategy that produced the calculated date
+ * * contact_name: The name of the stop
+ * * calendar: The result of checking the window and calculated date against the business calendar
+ * * diagnostics: The problems found while importing the dispo operation
+ * * flight: The air freight details, only set for Transfer and Recovery
+ * * origin: Where the dispo operation comes from
+ * * status: Where the dispo operation is in its lifecycle, only changed through JobRow::change_status
+ * * status_history: The status changes of the dispo operation, oldest first
+ */
+export type JobRow = { mode: DispoMode, jobNumber: string, hawbNumber: string, hawbs: Array<Hawb>, temperatureRanges: Array<TemperatureRange>, quantity: Quantity, shipper: Party, consignee: Party, stop: PartyRole, address: string, postalCode: string, city: string, country: string, equipment: string, equipmentItems: Array<Equipment>, 
+/**
+ * The tolerance of the dispo operation
+ * This is the larger of tolerance_before and tolerance_after.
+ */
+tolerance: number, toleranceBefore: number, toleranceAfter: number, tolerancePolicy: string, earlyDate: string, lateDate: string, 
+/**
+ * The calculated date of the dispo operation.
+ * This is being calculated from the early and late date using the planned_time_strategy.
+ */
+calculatedDate: string, plannedTimeStrategy: PlannedTimeStrategy, contactName: string, calendar: CalendarCheck, diagnostics: Array<Diagnostic>, flight: FlightDetails | null, origin: JobOrigin, status: JobStatus, statusHistory: Array<StatusChange>, };
+
+/**
+ * The JobStatus enum represents where a job is in its lifecycle
+ * * Imported: The job is known, but not planned yet. Manually created jobs start here as well.
+ * * Planned: The job has a planned date
+ * * Assigned: The job is assigned to a driver and vehicle
+ * * Dispatched: The driver is on the way
+ * * PickedUp: The freight was collected
+ * * Delivered: The freight was handed over, the job is done
+ * * Exception: Something went wrong and the job needs a dispatcher
+ * * Cancelled: The job will not be done
+ */
+export type JobStatus = "Imported" | "Planned" | "Assigned" | "Dispatched" | "PickedUp" | "Delivered" | "Exception" | "Cancelled";
+
+/**
+ * Options for linking the legs of shipments
+ * * transit_minutes: The transit time used if no route specific transit time is configured
+ * * routes: The route specific transit times
+ */
+export type LinkOptions = { transitMinutes: number, routes: Array<TransitTime>, };
+
+/**
+ * The air freight details of a manual Transfer or Recovery
+ * * mawb_number: The master air waybill number
+ * * airline: The airline
+ * * cut_off: The latest time the freight can be handed over to the airline, empty if unknown
+ */
+export type ManualFlight = { mawbNumber: string, airline: string, cutOff: string, };
+
+/**
+ * A job as entered by a dispatcher, e.g. for an order taken by phone that never goes through TMS.
+ * The values are kept as entered, so invalid ones can be reported field by field instead of failing the whole request.
+ * * mode: The mode of the job
+ * * job_number: The job number of the job, unique per mode
+ * * hawbs: The hawbs of the job with their temperature ranges
+ * * shipper: The party the freight comes from
+ * * consignee: The party the freight goes to
+ * * early_date: The early date, like "2024-03-04T08:00"
+ * * late_date: The late date, like "2024-03-04T12:00"
+ * * equipment: The equipment codes, like in the Equipment Codes column of TMS
+ * * quantity: The pieces, weight and volume as entered, units are parsed like in the imports
+ * * flight: The air freight details, needed for Transfer and Recovery
+ */
+export type ManualJob = { mode: DispoMode, jobNumber: string, hawbs: Array<Hawb>, shipper: Party, consignee: Party, earlyDate: string, lateDate: string, equipment?: string, quantity?: QuantityCells, flight: ManualFlight | null, };
+
+/**
+ * The strategy to use for the jobs of one mode
+ * * mode: The mode
+ * * strategy: The strategy to use for this mode
+ */
+export type ModePlannedTime = { mode: DispoMode, strategy: PlannedTimeStrategy, };
+
+/**
+ * The reason why a date is not inside working time
+ * * Holiday: The date is a public holiday
+ * * Weekend: The date is on a weekend
+ * * Closed: The customer does not receive at that time
+ */
+export type NonWorkingReason = { "type": "holiday", "name": string } | { "type": "weekend" } | { "type": "closed" };
+
+/**
+ * The receiving hours of a customer on one weekday
+ * * weekday: The weekday
+ * * open: The time the customer opens
+ * * close: The time the customer closes
+ */
+export type OpeningDay = { weekday: string, open: string, close: string, };
+
+/**
+ * The receiving hours of a customer.
+ * Weekdays without an entry are closed.
+ * * customer: The customer, compared case insensitive
+ * * days: The opening hours per weekday
+ */
+export type OpeningHours = { customer: string, days: Array<OpeningDay>, };
+
+/**
+ * Describes how a packaging type behaves when filled with dry ice
+ * * packaging: The packaging type this profile belongs to
+ * * sublimation_kg_per_day: The amount of dry ice in kilograms one package loses per 24 hours
+ * * capacity_kg: The maximum amount of dry ice in kilograms one package can hold
+ */
+export type PackagingProfile = { packaging: PackagingType, sublimationKgPerDay: number, capacityKg: number, };
+
+/**
+ * The PackagingType enum represents the different insulated packagings used for dry ice shipments
+ * * EpsBox: Expanded polystyrene box
+ * * PurBox: Polyurethane box
+ * * VacuumInsulated: Vacuum insulated panel box
+ */
+export type PackagingType = "EpsBox" | "PurBox" | "VacuumInsulated";
+
+/**
+ * The progress of a parse task
+ * * task_id: The id of the task
+ * * stage: The step that was finished
+ * * file: The workbook the step belongs to, None for the steps that use both workbooks
+ * * rows: The number of rows after the step, 0 for Opened
+ */
+export type ParseProgress = { taskId: string, stage: ParseStage, file: string | null, rows: number, };
+
+/**
+ * The ParseStage enum represents the steps of an import
+ * * Opened: A workbook was opened
+ * * RowsParsed: The rows of a workbook were parsed
+ * * Joined: The CL View and the Shipper Site were joined
+ * * Converted: The joined rows were converted into jobs
+ */
+export type ParseStage = "Opened" | "RowsParsed" | "Joined" | "Converted";
+
+/**
+ * The record of a shipper or consignee
+ * * code: The party code as used by TMS
+ * * name: The name of the party
+ * * address: The street address of the party
+ * * city: The city of the party
+ * * state: The state of the party
+ * * postal_code: The postal code of the party
+ * * country: The country of the party
+ */
+export type Party = { code: string, name: string, address: string, city: string, state: string, postalCode: string, country: string, };
+
+/**
+ * The PartyRole enum represents the role of a party of a job
+ * * Shipper: The party the freight comes from
+ * * Consignee: The party the freight goes to
+ */
+export type PartyRole = "Shipper" | "Consignee";
+
+/**
+ * A saved set of jobs.
+ * The JSON is an object like `{"format": "dispo-master-plan", "version": 1, "savedAt": "2024-03-04T08:00:00", "jobs": [...]}`,
+ * every job is a PlannedJob, the JobRow as the app sends it to the frontend with the assignments of the dispatcher.
+ * Files of older versions are migrated when they are read, files of newer versions are rejected.
+ * * format: Always PLAN_FILE_FORMAT
+ * * version: The version of the file, PLAN_FILE_VERSION for new files
+ * * saved_at: When the file was written, in UTC. None for files migrated from version 0.
+ * * jobs: The saved jobs
+ */
+export type PlanFile = { format: string, version: number, savedAt: string | null, jobs: Array<PlannedJob>, };
+
+/**
+ * A job of the dispatch plan, as it is kept by the frontend and saved in plan files.
+ * The JSON is the JSON of the JobRow with the assignments as additional fields.
+ * The assignments default to empty, so jobs without them can be read as well.
+ * * job: The job
+ * * driver: The driver the job is assigned to, empty if unassigned
+ * * vehicle: The vehicle the job is assigned to, empty if unassigned
+ * * notes: The notes of the dispatcher
+ */
+export type PlannedJob = { driver: string, vehicle: string, notes: string, mode: DispoMode, jobNumber: string, hawbNumber: string, hawbs: Array<Hawb>, temperatureRanges: Array<TemperatureRange>, quantity: Quantity, shipper: Party, consignee: Party, stop: PartyRole, address: string, postalCode: string, city: string, country: string, equipment: string, equipmentItems: Array<Equipment>, 
+/**
+ * The tolerance of the dispo operation
+ * This is the larger of tolerance_before and tolerance_after.
+ */
+tolerance: number, toleranceBefore: number, toleranceAfter: number, tolerancePolicy: string, earlyDate: string, lateDate: string, 
+/**
+ * The calculated date of the dispo operation.
+ * This is being calculated from the early and late date using the planned_time_strategy.
+ */
+calculatedDate: string, plannedTimeStrategy: PlannedTimeStrategy, contactName: string, calendar: CalendarCheck, diagnostics: Array<Diagnostic>, flight: FlightDetails | null, origin: JobOrigin, status: JobStatus, statusHistory: Array<StatusChange>, };
+
+/**
+ * The planned time strategies of an import
+ * A customer specific strategy wins over a mode specific strategy, which wins over the default strategy.
+ * * default: The strategy used for all jobs without a customer or mode specific strategy
+ * * modes: The mode specific strategies
+ * * customers: The customer specific strategies
+ * * cut_off_buffer_minutes: The minutes a transfer has to arrive before the airline cut-off
+ */
+export type PlannedTimeSettings = { default: PlannedTimeStrategy, modes: Array<ModePlannedTime>, customers: Array<CustomerPlannedTime>, cutOffBufferMinutes: number, };
+
+/**
+ * The PlannedTimeStrategy enum decides how the calculated date of a job is derived from its target window.
+ * The result is always kept inside the window.
+ * * Earliest: The early date
+ * * Latest: The late date minus a safety margin
+ * * Midpoint: The middle between the early and the late date
+ * * FixedOffset: A fixed number of minutes after the early date or before the late date
+ * * CustomerPreference: The preferred time of day of the customer on the first day of the window where it fits
+ * * QuarterHour: The middle between the early and the late date, rounded to the nearest quarter hour
+ */
+export type PlannedTimeStrategy = { "type": "earliest" } | { "type": "latest", safetyMinutes: number, } | { "type": "midpoint" } | { "type": "fixedOffset", minutes: number, from: WindowEdge, } | { "type": "customerPreference", time: string, } | { "type": "quarterHour" };
+
+/**
+ * The quantity of a dispo operation.
+ * All weights are in kilograms and all volumes in cubic meters, regardless of the units in TMS.
+ * A field is None if it was not present in TMS or could not be parsed.
+ * * pieces: The number of pieces
+ * * gross_weight_kg: The gross weight
+ * * chargeable_weight_kg: The chargeable weight, from TMS or calculated from gross weight and volume
+ * * volume_m3: The volume
+ */
+export type Quantity = { pieces: number | null, grossWeightKg: number | null, chargeableWeightKg: number | null, volumeM3: number | null, };
+
+/**
+ * The raw cells a Quantity is built from.
+ * Every cell is None if the column is missing or the cell is empty.
+ * * pieces: The Actual Quantity cell
+ * * gross_weight: The weight cell, optionally with a unit suffix like "12.5 kg"
+ * * weight_unit: The weight unit cell
+ * * chargeable_weight: The chargeable weight cell, optionally with a unit suffix
+ * * volume: The volume cell, optionally with a unit suffix like "0.2 m3"
+ * * volume_unit: The volume unit cell
+ */
+export type QuantityCells = { pieces: string | null, grossWeight: string | null, weightUnit: string | null, chargeableWeight: string | null, volume: string | null, volumeUnit: string | null, };
+
+/**
+ * Describes when and how dry ice is replenished during transit
+ * * interval_hours: The number of hours after which a package is refilled. A value of 0 disables replenishment.
+ * * safety_margin: The additional fraction of dry ice added on top of the calculated amount, e.g. 0.2 for 20%
+ * * handling_buffer_hours: Hours added to the transit duration to cover packing and handover
+ */
+export type ReplenishmentRule = { intervalHours: number, safetyMargin: number, handlingBufferHours: number, };
+
+/**
+ * The Severity enum represents how serious a diagnostic is
+ * * Info: Something worth knowing, no action needed
+ * * Warning: The job was imported, but should be checked by a dispatcher
+ * * Error: The job was imported, but parts of it are known to be wrong
+ */
+export type Severity = "Info" | "Warning" | "Error";
+
+/**
+ * A shipment made of the linked legs of several jobs
+ * * hawb_number: The hawb number of the shipment, the job number of the first leg if no leg has one
+ * * legs: The legs, ordered by their early date
+ * * issues: The problems with the chain of legs, empty if the chain is valid
+ */
+export type Shipment = { hawbNumber: string, legs: Array<ShipmentLeg>, issues: Array<ChainIssue>, };
+
+/**
+ * One job of a shipment, as sent by the frontend for linking.
+ * Only the fields needed for linking are used, so jobs of older imports can be linked as well.
+ * * job_number: The job number of the job
+ * * hawb_numbers: The hawb numbers of the job, a load can have several
+ * * mode: The mode of the job
+ * * country: The country of the stop
+ * * early_date: The early date of the job
+ * * late_date: The late date of the job
+ */
+export type ShipmentLeg = { jobNumber: string, hawbNumbers: Array<string>, mode: DispoMode, country: string, earlyDate: string, lateDate: string, };
+
+/**
+ * A change of the status of a job
+ * * from: The status before the change
+ * * to: The status after the change
+ * * at: When the status was changed, in UTC
+ * * reason: Why the status was changed, always set for Exception and Cancelled
+ * * note: A free text explanation, empty if there is none
+ */
+export type StatusChange = { from: JobStatus, to: JobStatus, at: string, reason: StatusReason | null, note?: string, };
+
+/**
+ * The StatusReason enum represents why the status of a job was changed
+ * * CustomerRequest: The shipper or consignee asked for the change
+ * * CustomerUnavailable: Nobody was there to hand over or take over the freight
+ * * AddressNotFound: The driver could not find the address
+ * * FreightNotReady: The freight was not ready for pickup
+ * * Refused: The consignee refused the freight
+ * * Damaged: The freight or its packaging is damaged
+ * * TemperatureExcursion: The freight left its temperature range
+ * * VehicleBreakdown: The vehicle broke down
+ * * MissedCutOff: The freight did not reach the airline before the cut-off
+ * * Other: Any other reason, explained in the note
+ */
+export type StatusReason = "CustomerRequest" | "CustomerUnavailable" | "AddressNotFound" | "FreightNotReady" | "Refused" | "Damaged" | "TemperatureExcursion" | "VehicleBreakdown" | "MissedCutOff" | "Other";
+
+/**
+ * The TemperatureRange enum represents the different temperature ranges of a dispo operation
+ */
+export type TemperatureRange = "DryIce" | "DryShipper" | "Refrigerated" | "ControlledAmbient" | "Frozen" | "Ambient" | "NonSOP" | "Invalid";
+
+/**
+ * A column of the status export
+ * * header: The header of the column, as expected by TMS
+ * * field: The value written into the column
+ */
+export type TmsColumn = { header: string, field: TmsField, };
+
+/**
+ * The layout of the status export, it has to match the import definition in TMS
+ * * format: The file format
+ * * delimiter: The delimiter of CSV files
+ * * date_format: The chrono format of the planned dates
+ * * planned_status: The status of jobs with a driver
+ * * unplanned_status: The status of jobs without a driver
+ * * columns: The columns, in the order they are written
+ */
+export type TmsExportLayout = { format: TmsFileFormat, delimiter: string, dateFormat: string, plannedStatus: string, unplannedStatus: string, columns: Array<TmsColumn>, };
+
+/**
+ * The result of a status export
+ * * exported: The number of jobs written to the file
+ * * unchanged: The number of jobs left out, because they were exported before without changes
+ * * written: Whether a file was written, no file is written if nothing changed
+ */
+export type TmsExportSummary = { exported: number, unchanged: number, written: boolean, };
+
+/**
+ * The TmsField enum represents the values that can be written into a column of the status export
+ * * LoadNumber: The Load # of the job
+ * * Hawb: The hawb number of the job
+ * * Mode: The mode of the job
+ * * Driver: The assigned driver
+ * * Vehicle: The assigned vehicle
+ * * PlannedPickup: The planned date, only set for jobs that stop at the shipper
+ * * PlannedDelivery: The planned date, only set for jobs that stop at the consignee
+ * * Status: The planning status of the job
+ */
+export type TmsField = "LoadNumber" | "Hawb" | "Mode" | "Driver" | "Vehicle" | "PlannedPickup" | "PlannedDelivery" | "Status";
+
+/**
+ * The file format of the status export
+ * * Csv: A CSV file with the configured delimiter
+ * * Xlsx: An .xlsx file with one sheet
+ */
+export type TmsFileFormat = "Csv" | "Xlsx";
+
+/**
+ * A tolerance policy as agreed with a customer or for a country
+ * The optional customer, country and mode fields decide which jobs the policy applies to.
+ * A field that is None matches every job.
+ * * name: The name of the policy, reported on every job it produced the tolerance for
+ * * customer: The customer the policy applies to
+ * * country: The country the policy applies to
+ * * mode: The mode the policy applies to
+ * * before: The rounding of the time between the early date and the planned date
+ * * after: The rounding of the time between the planned date and the late date
+ */
+export type TolerancePolicy = { name: string, customer: string | null, country: string | null, mode: DispoMode | null, before: ToleranceRounding, after: ToleranceRounding, };
+
+/**
+ * The set of all configured tolerance policies
+ * * policies: The configured policies
+ * * default: The policy used if no configured policy applies
+ */
+export type TolerancePolicySet = { policies: Array<TolerancePolicy>, default: TolerancePolicy, };
+
+/**
+ * Decides how the difference between the planned date and a window edge becomes a tolerance
+ * * Exact: The exact difference in minutes is used
+ * * Buckets: The difference is rounded up to the next bucket
+ */
+export type ToleranceRounding = { "type": "exact" } | { "type": "buckets", buckets: number[], overflow: BucketOverflow, };
+
+/**
+ * The transit time between two countries
+ * * from_country: The country the freight is collected in
+ * * to_country: The country the freight is delivered to
+ * * minutes: The minutes between collecting and delivering
+ */
+export type TransitTime = { fromCountry: string, toCountry: string, minutes: number, };
+
+/**
+ * The VehicleCapability enum represents what a vehicle must offer to carry a piece of equipment
+ * * TailLift: The vehicle has a tail-lift
+ * * TemperatureControlled: The vehicle has a temperature controlled cargo area
+ * * DangerousGoods: The vehicle and driver may carry dangerous goods
+ * * PowerSupply: The vehicle can power active packaging systems
+ */
+export type VehicleCapability = "TailLift" | "TemperatureControlled" | "DangerousGoods" | "PowerSupply";
+
+/**
+ * The net dry ice loaded on one vehicle
+ * * vehicle: The vehicle identifier
+ * * job_numbers: The job numbers of the dry ice shipments on the vehicle
+ * * net_kg: The total net dry ice in kilograms on the vehicle
+ * * limit_kg: The configured limit for one vehicle
+ * * exceeds_limit: True if the net dry ice is above the limit
+ */
+export type VehicleDryIceTotal = { vehicle: string, jobNumbers: Array<string>, netKg: number, limitKg: number, exceedsLimit: boolean, };
+
+/**
+ * The edge of a target window
+ * * Early: The early date of the window
+ * * Late: The late date of the window
+ */
+export type WindowEdge = "Early" | "Late";
+
+/**
+ * Returns a list of all printers available on the system as a vector of strings
+ */
+export async function getPrinterNames(): Promise<Array<string>> {
+  return await invoke("get_printer_names");
+}
+
+/**
+ * Parses the CL View and the Shipper Site into jobs in a background task. Both workbooks are parsed concurrently. The progress is emitted as "parse-progress" events and the task can be cancelled with cancel_parse.
+ *
+ * @throws The message of the ParseFilesError.
+ */
+export async function parseFiles(taskId: string, clView: string, shipperSite: string, mode: DispoMode, options: ImportOptions | null): Promise<Array<JobRow>> {
+  return await invoke("parse_files", { taskId, clView, shipperSite, mode, options });
+}
+
+/**
+ * Cancels a running parse task. The task stops after its current step and parse_files returns a Cancelled error.
+ */
+export async function cancelParse(taskId: string): Promise<boolean> {
+  return await invoke("cancel_parse", { taskId });
+}
+
+/**
+ * Removes all parsed workbooks from the cache, so the next imports parse them from scratch
+ *
+ * @throws The message of the WorkbookCacheError.
+ */
+export async function clearWorkbookCache(): Promise<void> {
+  return await invoke("clear_workbook_cache");
+}
+
+/**
+ * Starts watching a folder for new CL View and Shipper Site exports. Every recognised pair is imported and emitted as an "import-batch" event, failed imports as an "import-failed" event. A folder that is already watched is replaced.
+ *
+ * @throws The message of the ImportWatcherError.
+ */
+export async function startImportWatcher(directory: string, options: ImportWatchOptions): Promise<void> {
+  return await invoke("start_import_watcher", { directory, options });
+}
+
+/**
+ * Stops watching the folder for new exports
+ */
+export async function stopImportWatcher(): Promise<void> {
+  return await invoke("stop_import_watcher");
+}
+
+/**
+ * Reads a set of public holidays from a CSV file. Every line of the file must have the format `YYYY-MM-DD,Name`.
+ *
+ * @throws The message of the CalendarError.
+ */
+export async function importHolidays(path: string, country: string, region: string | null): Promise<HolidaySet> {
+  return await invoke("import_holidays", { path, country, region });
+}
+
+/**
+ * Writes the dispatch plan to a formatted .xlsx file. The file has a sheet with all jobs and one sheet per driver.
+ *
+ * @throws The message of the ExportError.
+ */
+export async function exportPlan(path: string, jobs: Array<PlannedJob>): Promise<void> {
+  return await invoke("export_plan", { path, jobs });
+}
+
+/**
+ * Creates a job manually, e.g. for an order taken by phone. The job is validated and planned like an imported job and marked as manual.
+ *
+ * @throws A JobEditRejection.
+ */
+export async function createJob(jobs: Array<JobRow>, job: ManualJob, options: ImportOptions | null): Promise<Array<JobRow>> {
+  return await invoke("create_job", { jobs, job, options });
+}
+
+/**
+ * Replaces a job with an edited version, the job keeps its origin.
+ *
+ * @throws A JobEditRejection.
+ */
+export async function updateJob(jobs: Array<JobRow>, id: string, job: ManualJob, options: ImportOptions | null): Promise<Array<JobRow>> {
+  return await invoke("update_job", { jobs, id, job, options });
+}
+
+/**
+ * Removes a job.
+ *
+ * @throws A JobEditRejection.
+ */
+export async function deleteJob(jobs: Array<JobRow>, id: string): Promise<Array<JobRow>> {
+  return await invoke("delete_job", { jobs, id });
+}
+
+/**
+ * Changes the status of a job, if its current status allows it. The change is recorded with the current time in the status history of the job.
+ *
+ * @throws The message of the StatusChangeError.
+ */
+export async function changeJobStatus(jobs: Array<JobRow>, id: string, status: JobStatus, reason: StatusReason | null, note: string): Promise<Array<JobRow>> {
+  return await invoke("change_job_status", { jobs, id, status, reason, note });
+}
+
+/**
+ * Saves the jobs to a versioned JSON plan file, so they can be loaded again later.
+ *
+ * @throws The message of the PlanFileError.
+ */
+export async function exportPlanFile(path: string, jobs: Array<PlannedJob>): Promise<void> {
+  return await invoke("export_plan_file", { path, jobs });
+}
+
+/**
+ * Loads the jobs of a plan file. Files written by older versions of the app are migrated to the current version.
+ *
+ * @throws The message of the PlanFileError.
+ */
+export async function importPlanFile(path: string): Promise<PlanFile> {
+  return await invoke("import_plan_file", { path });
+}
+
+/**
+ * Writes one iCalendar file per driver or vehicle into a directory.
+ *
+ * @throws The message of the ExportError.
+ */
+export async function exportCalendars(directory: string, jobs: Array<PlannedJob>, grouping: CalendarGrouping): Promise<Array<string>> {
+  return await invoke("export_calendars", { directory, jobs, grouping });
+}
+
+/**
+ * Writes the planning status of the jobs to a file that can be imported into TMS. Only jobs that changed since the last export are written, unless a full export is requested.
+ *
+ * @throws The message of the ExportError.
+ */
+export async function exportTmsStatus(path: string, jobs: Array<PlannedJob>, layout: TmsExportLayout | null, full: boolean): Promise<TmsExportSummary> {
+  return await invoke("export_tms_status", { path, jobs, layout, full });
+}
+
+/**
+ * Writes the jobs to a planning workbook that can be edited in Excel and imported again.
+ *
+ * @throws The message of the PlanningWorkbookError.
+ */
+export async function exportPlanningWorkbook(path: string, jobs: Array<PlannedJob>): Promise<void> {
+  return await invoke("export_planning_workbook", { path, jobs });
+}
+
+/**
+ * Reads an edited planning workbook and compares it with the current jobs. Nothing is applied, the change set is meant to be reviewed first.
+ *
+ * @throws The message of the PlanningWorkbookError.
+ */
+export async function readPlanningWorkbook(path: string, jobs: Array<PlannedJob>): Promise<ChangeSet> {
+  return await invoke("read_planning_workbook", { path, jobs });
+}
+
+/**
+ * Applies a reviewed change set to the current jobs.
+ */
+export async function applyChangeSet(jobs: Array<PlannedJob>, changeSet: ChangeSet, includeConflicts: boolean): Promise<Array<PlannedJob>> {
+  return await invoke("apply_change_set", { jobs, changeSet, includeConflicts });
+}
+
+/**
+ * Changes the driver, vehicle, planned date or notes of a job, as edited by the dispatcher in the app. The change is recorded in the audit log like the changes of a planning workbook. A job that gets a driver and a vehicle becomes Assigned.
+ *
+ * @throws A JobEditRejection.
+ */
+export async function changeAssignment(jobs: Array<PlannedJob>, change: JobChange): Promise<Array<PlannedJob>> {
+  return await invoke("change_assignment", { jobs, change });
+}
+
+/**
+ * Records that jobs were printed. Printing happens in the frontend, so it reports each print here for the audit log.
+ */
+export async function recordPrint(printer: string, jobIds: Array<string>): Promise<void> {
+  return await invoke("record_print", { printer, jobIds });
+}
+
+/**
+ * Reads the entries of the audit log.
+ *
+ * @throws The message of the AuditLogError.
+ */
+export async function readAuditLog(query: AuditQuery | null): Promise<AuditEntries> {
+  return await invoke("read_audit_log", { query });
+}
+
+/**
+ * Writes the entries of the audit log to a CSV file, e.g. for a quality investigation. Every changed field is a row of its own.
+ *
+ * @throws The message of the AuditLogError.
+ */
+export async function exportAuditLog(path: string, query: AuditQuery | null): Promise<number> {
+  return await invoke("export_audit_log", { path, query });
+}
+
+/**
+ * Links the jobs of all imports into shipments and validates their chains of legs.
+ *
+ * @throws The message of the LinkOptionsError.
+ */
+export async function linkShipments(legs: Array<ShipmentLeg>, options: LinkOptions | null): Promise<Array<Shipment>> {
+  return await invoke("link_shipments", { legs, options });
+}
+
+/**
+ * Estimates the dry ice of the dry ice shipments of the plan and totals it per vehicle. The result also contains the UN1845 declarations for printing.
+ */
+export async function planDryIce(jobs: Array<PlannedJob>, config: DryIceConfig | null): Promise<DryIcePlan> {
+  return await invoke("plan_dry_ice", { jobs, config });
+}
+
+/**
+ * Shows the splashscreen window
+ */
+export async function showSplashscreen(): Promise<void> {
+  return await invoke("show_splashscreen");
+}
+
+/**
+ * Closes the splashscreen window and shows the main window
+ */
+export async function closeSplashscreen(): Promise<void> {
+  return await invoke("close_splashscreen");
+}
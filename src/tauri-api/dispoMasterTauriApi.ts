@@ -1,63 +1,47 @@
 // Copyright 2023 Felix Kahle. All rights reserved.
 
+import { UnlistenFn, listen } from "@tauri-apps/api/event";
 import { invoke } from "@tauri-apps/api/tauri";
-import { DispoMode, TemperatureRange } from "./types";
+import { ImportBatch, ImportFailure, ParseProgress } from "./bindings";
 
-export interface ImportedJobRow {
-  mode: DispoMode;
-  address: string;
-  calculatedDate: string;
-  city: string;
-  contactName: string;
-  country: string;
-  earlyDate: string;
-  equipment: string;
-  hawbNumber: string;
-  jobNumber: string;
-  lateDate: string;
-  postalCode: string;
-  quantities: number;
-  tolerance: number;
-  temperatureRanges: TemperatureRange[];
-}
+// The typed clients of all commands are generated from the Rust commands.
+export * from "./bindings";
 
 /**
- * Calls the `get_printer_names` function of the Tauri API.
+ * Returns the current mouse position.
  *
- * @returns A promise that resolves to an array of printer names.
+ * @returns The current mouse position.
  */
-export function getPrinterNames(): Promise<string[]> {
-  return invoke("get_printer_names");
+export async function getMousePosition(): Promise<{ x: number; y: number }> {
+  return await invoke("get_mouse_position");
 }
 
 /**
- * Close the splash screen.
+ * Listens for the progress of all parse tasks.
  *
- * @returns Resolves when the splash screen is closed.
+ * @param callback Called after every step of a task.
+ * @returns A function that stops listening.
  */
-export function closeSplashScreen(): Promise<void> {
-  return invoke("close_splashscreen");
+export async function onParseProgress(callback: (progress: ParseProgress) => void): Promise<UnlistenFn> {
+  return await listen<ParseProgress>("parse-progress", (event) => callback(event.payload));
 }
 
 /**
- * Returns the current mouse position.
+ * Listens for batches imported from the watched folder.
  *
- * @returns The current mouse position.
+ * @param callback Called with every imported batch.
+ * @returns A function that stops listening.
  */
-export async function getMousePosition(): Promise<{ x: number; y: number }> {
-  return await invoke("get_mouse_position");
+export async function onImportBatch(callback: (batch: ImportBatch) => void): Promise<UnlistenFn> {
+  return await listen<ImportBatch>("import-batch", (event) => callback(event.payload));
 }
 
 /**
- * Parses the given files and returns the parsed data.
- * The files must be in the format of the CL View and the Shipper Site downloaded from TMS.
+ * Listens for pairs of workbooks of the watched folder that could not be imported.
  *
- * @param clViewFile The path to the CL View file.
- * @param shipperSiteFile The path to the Shipper Site file.
- * @param mode The mode of the shipment.
- * @returns Array of parsed job rows.
+ * @param callback Called with every failed import.
+ * @returns A function that stops listening.
  */
-export async function parseFiles(clViewFile: string, shipperSiteFile: string, mode: DispoMode): Promise<ImportedJobRow[]> {
-  const result: Promise<ImportedJobRow[]> = invoke("parse_files", { clView: clViewFile, shipperSite: shipperSiteFile, mode: mode });
-  return result;
+export async function onImportFailed(callback: (failure: ImportFailure) => void): Promise<UnlistenFn> {
+  return await listen<ImportFailure>("import-failed", (event) => callback(event.payload));
 }